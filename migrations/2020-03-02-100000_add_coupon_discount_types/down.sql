@@ -0,0 +1,3 @@
+ALTER TABLE coupons DROP COLUMN IF EXISTS currency;
+ALTER TABLE coupons DROP COLUMN IF EXISTS amount;
+ALTER TABLE coupons DROP COLUMN IF EXISTS discount_type;
@@ -0,0 +1,3 @@
+ALTER TABLE coupons ADD COLUMN discount_type VARCHAR NOT NULL DEFAULT 'Percent';
+ALTER TABLE coupons ADD COLUMN amount DOUBLE PRECISION;
+ALTER TABLE coupons ADD COLUMN currency VARCHAR;
@@ -0,0 +1,5 @@
+ALTER TABLE used_coupons DROP COLUMN IF EXISTS times_used;
+
+ALTER TABLE coupons DROP COLUMN IF EXISTS active_from;
+ALTER TABLE coupons DROP COLUMN IF EXISTS min_subtotal;
+ALTER TABLE coupons DROP COLUMN IF EXISTS per_user_limit;
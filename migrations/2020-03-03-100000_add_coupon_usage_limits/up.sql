@@ -0,0 +1,5 @@
+ALTER TABLE coupons ADD COLUMN per_user_limit INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE coupons ADD COLUMN min_subtotal DOUBLE PRECISION;
+ALTER TABLE coupons ADD COLUMN active_from TIMESTAMP;
+
+ALTER TABLE used_coupons ADD COLUMN times_used INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS coupons_campaign_id_idx;
+
+ALTER TABLE coupons DROP COLUMN IF EXISTS campaign_id;
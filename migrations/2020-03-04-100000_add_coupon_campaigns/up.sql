@@ -0,0 +1,3 @@
+ALTER TABLE coupons ADD COLUMN campaign_id UUID;
+
+CREATE INDEX IF NOT EXISTS coupons_campaign_id_idx ON coupons (campaign_id);
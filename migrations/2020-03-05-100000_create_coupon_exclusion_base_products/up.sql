@@ -0,0 +1,7 @@
+CREATE TABLE coupon_exclusion_base_products (
+    id SERIAL PRIMARY KEY,
+    coupon_id INTEGER NOT NULL REFERENCES coupons (id) ON DELETE CASCADE,
+    base_product_id INTEGER NOT NULL REFERENCES base_products (id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS coupon_exclusion_base_products_unique_idx ON coupon_exclusion_base_products (coupon_id, base_product_id);
@@ -0,0 +1,37 @@
+CREATE TABLE product_price_history (
+    id SERIAL PRIMARY KEY,
+    product_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+    price DOUBLE PRECISION NOT NULL,
+    discount DOUBLE PRECISION,
+    cashback DOUBLE PRECISION,
+    currency VARCHAR NOT NULL,
+    user_id INTEGER,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS product_price_history_product_id_idx ON product_price_history (product_id, created_at);
+
+CREATE TABLE scheduled_price_changes (
+    id SERIAL PRIMARY KEY,
+    product_id INTEGER NOT NULL REFERENCES products (id) ON DELETE CASCADE,
+    price DOUBLE PRECISION,
+    discount DOUBLE PRECISION,
+    cashback DOUBLE PRECISION,
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'Pending',
+    previous_price DOUBLE PRECISION,
+    previous_discount DOUBLE PRECISION,
+    previous_cashback DOUBLE PRECISION,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    CHECK (starts_at < ends_at)
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_price_changes_product_id_idx ON scheduled_price_changes (product_id);
+CREATE INDEX IF NOT EXISTS scheduled_price_changes_status_idx ON scheduled_price_changes (status, starts_at, ends_at);
+
+SELECT diesel_manage_updated_at('scheduled_price_changes');
+
+INSERT INTO product_price_history (product_id, price, discount, cashback, currency, created_at)
+SELECT id, price, discount, cashback, currency, updated_at FROM products;
@@ -0,0 +1 @@
+ALTER TABLE products ADD COLUMN stock_status VARCHAR;
@@ -0,0 +1,10 @@
+DROP TRIGGER IF EXISTS touch_product ON prod_attr_values;
+DROP FUNCTION IF EXISTS touch_product_of_prod_attr_value();
+
+DROP INDEX IF EXISTS products_updated_at_idx;
+DROP INDEX IF EXISTS base_products_updated_at_idx;
+DROP INDEX IF EXISTS stores_updated_at_idx;
+DROP INDEX IF EXISTS categories_updated_at_idx;
+
+DROP TRIGGER IF EXISTS set_updated_at ON categories;
+ALTER TABLE categories DROP COLUMN IF EXISTS updated_at;
@@ -0,0 +1,23 @@
+ALTER TABLE categories ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp;
+
+SELECT diesel_manage_updated_at('categories');
+
+CREATE INDEX IF NOT EXISTS categories_updated_at_idx ON categories (updated_at);
+CREATE INDEX IF NOT EXISTS stores_updated_at_idx ON stores (updated_at);
+CREATE INDEX IF NOT EXISTS base_products_updated_at_idx ON base_products (updated_at);
+CREATE INDEX IF NOT EXISTS products_updated_at_idx ON products (updated_at);
+
+-- Attribute values have no timestamps, changing them marks the product as updated
+CREATE OR REPLACE FUNCTION touch_product_of_prod_attr_value() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        UPDATE products SET updated_at = current_timestamp WHERE id = OLD.prod_id;
+    ELSE
+        UPDATE products SET updated_at = current_timestamp WHERE id = NEW.prod_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER touch_product AFTER INSERT OR UPDATE OR DELETE ON prod_attr_values
+    FOR EACH ROW EXECUTE PROCEDURE touch_product_of_prod_attr_value();
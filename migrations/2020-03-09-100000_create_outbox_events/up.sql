@@ -0,0 +1,15 @@
+CREATE TABLE outbox_events (
+    id BIGSERIAL PRIMARY KEY,
+    aggregate_type VARCHAR NOT NULL,
+    aggregate_id INTEGER NOT NULL,
+    event_type VARCHAR NOT NULL,
+    kafka_update_no INTEGER,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    published_at TIMESTAMP,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_unpublished_idx ON outbox_events (id) WHERE published_at IS NULL;
+CREATE INDEX IF NOT EXISTS outbox_events_aggregate_idx ON outbox_events (aggregate_type, aggregate_id);
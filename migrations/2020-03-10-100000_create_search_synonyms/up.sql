@@ -0,0 +1,11 @@
+CREATE TABLE search_synonyms (
+    id SERIAL PRIMARY KEY,
+    terms VARCHAR[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    CHECK (array_length(terms, 1) > 1)
+);
+
+CREATE INDEX IF NOT EXISTS search_synonyms_terms_idx ON search_synonyms USING GIN (terms);
+
+SELECT diesel_manage_updated_at('search_synonyms');
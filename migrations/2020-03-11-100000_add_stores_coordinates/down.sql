@@ -0,0 +1,3 @@
+ALTER TABLE stores DROP CONSTRAINT IF EXISTS stores_coordinates_check;
+ALTER TABLE stores DROP COLUMN IF EXISTS longitude;
+ALTER TABLE stores DROP COLUMN IF EXISTS latitude;
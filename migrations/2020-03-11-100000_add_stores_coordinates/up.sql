@@ -0,0 +1,7 @@
+ALTER TABLE stores ADD COLUMN latitude DOUBLE PRECISION;
+ALTER TABLE stores ADD COLUMN longitude DOUBLE PRECISION;
+
+ALTER TABLE stores ADD CONSTRAINT stores_coordinates_check CHECK (
+    (latitude IS NULL AND longitude IS NULL)
+    OR (latitude BETWEEN -90 AND 90 AND longitude BETWEEN -180 AND 180)
+);
@@ -0,0 +1,11 @@
+DROP INDEX IF EXISTS stores_search_vector_idx;
+DROP TRIGGER IF EXISTS stores_search_vector_update ON stores;
+DROP FUNCTION IF EXISTS stores_search_vector();
+ALTER TABLE stores DROP COLUMN IF EXISTS search_vector;
+
+DROP INDEX IF EXISTS base_products_search_vector_idx;
+DROP TRIGGER IF EXISTS base_products_search_vector_update ON base_products;
+DROP FUNCTION IF EXISTS base_products_search_vector();
+ALTER TABLE base_products DROP COLUMN IF EXISTS search_vector;
+
+DROP FUNCTION IF EXISTS translations_text(JSONB);
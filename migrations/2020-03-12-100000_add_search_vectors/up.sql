@@ -0,0 +1,48 @@
+-- Texts of translations array joined with spaces, translations are `[{"lang": "en", "text": "..."}]`
+CREATE OR REPLACE FUNCTION translations_text(translations JSONB) RETURNS TEXT AS $$
+    SELECT coalesce(string_agg(translation->>'text', ' '), '')
+    FROM jsonb_array_elements(
+        CASE WHEN jsonb_typeof(translations) = 'array' THEN translations ELSE '[]'::JSONB END
+    ) AS translation
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Search vectors are not in diesel schema, they are only used in raw sql of postgres search backend
+ALTER TABLE base_products ADD COLUMN search_vector TSVECTOR;
+
+CREATE OR REPLACE FUNCTION base_products_search_vector() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector :=
+        setweight(to_tsvector('simple', translations_text(NEW.name)), 'A') ||
+        setweight(to_tsvector('simple', translations_text(NEW.short_description)), 'B') ||
+        setweight(to_tsvector('simple', translations_text(NEW.long_description)), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER base_products_search_vector_update
+    BEFORE INSERT OR UPDATE OF name, short_description, long_description ON base_products
+    FOR EACH ROW EXECUTE PROCEDURE base_products_search_vector();
+
+UPDATE base_products SET search_vector =
+    setweight(to_tsvector('simple', translations_text(name)), 'A') ||
+    setweight(to_tsvector('simple', translations_text(short_description)), 'B') ||
+    setweight(to_tsvector('simple', translations_text(long_description)), 'C');
+
+CREATE INDEX base_products_search_vector_idx ON base_products USING GIN (search_vector);
+
+ALTER TABLE stores ADD COLUMN search_vector TSVECTOR;
+
+CREATE OR REPLACE FUNCTION stores_search_vector() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector := setweight(to_tsvector('simple', translations_text(NEW.name)), 'A');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stores_search_vector_update
+    BEFORE INSERT OR UPDATE OF name ON stores
+    FOR EACH ROW EXECUTE PROCEDURE stores_search_vector();
+
+UPDATE stores SET search_vector = setweight(to_tsvector('simple', translations_text(name)), 'A');
+
+CREATE INDEX stores_search_vector_idx ON stores USING GIN (search_vector);
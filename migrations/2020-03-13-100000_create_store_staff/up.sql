@@ -0,0 +1,13 @@
+CREATE TABLE store_staff (
+    id SERIAL PRIMARY KEY,
+    store_id INTEGER NOT NULL REFERENCES stores (id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL,
+    role VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    UNIQUE (store_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS store_staff_user_id_idx ON store_staff (user_id);
+
+SELECT diesel_manage_updated_at('store_staff');
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS moderation_transitions;
+DROP TABLE IF EXISTS moderation_queue;
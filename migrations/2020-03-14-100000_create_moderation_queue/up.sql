@@ -0,0 +1,32 @@
+CREATE TABLE moderation_queue (
+    id SERIAL PRIMARY KEY,
+    entity_type VARCHAR NOT NULL,
+    entity_id INTEGER NOT NULL,
+    assignee_id INTEGER,
+    enqueued_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    assigned_at TIMESTAMP,
+    UNIQUE (entity_type, entity_id)
+);
+
+CREATE INDEX IF NOT EXISTS moderation_queue_assignee_id_idx ON moderation_queue (assignee_id);
+
+CREATE TABLE moderation_transitions (
+    id SERIAL PRIMARY KEY,
+    entity_type VARCHAR NOT NULL,
+    entity_id INTEGER NOT NULL,
+    user_id INTEGER,
+    from_status VARCHAR NOT NULL,
+    to_status VARCHAR NOT NULL,
+    reason VARCHAR NOT NULL,
+    comment VARCHAR,
+    time_in_queue_sec BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS moderation_transitions_entity_idx ON moderation_transitions (entity_type, entity_id);
+
+INSERT INTO moderation_queue (entity_type, entity_id, enqueued_at)
+SELECT 'Store', id, updated_at FROM stores WHERE status = 'moderation' AND is_active;
+
+INSERT INTO moderation_queue (entity_type, entity_id, enqueued_at)
+SELECT 'BaseProduct', id, updated_at FROM base_products WHERE status = 'moderation' AND is_active;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS moderation_feedback;
+DROP TABLE IF EXISTS decline_reasons;
@@ -0,0 +1,32 @@
+CREATE TABLE decline_reasons (
+    id SERIAL PRIMARY KEY,
+    code VARCHAR NOT NULL UNIQUE,
+    name JSONB NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+SELECT diesel_manage_updated_at('decline_reasons');
+
+INSERT INTO decline_reasons (code, name) VALUES
+    ('prohibited_item', '[{"lang": "en", "text": "Prohibited item"}]'),
+    ('bad_photo', '[{"lang": "en", "text": "Bad photo"}]'),
+    ('wrong_category', '[{"lang": "en", "text": "Wrong category"}]'),
+    ('missing_translation', '[{"lang": "en", "text": "Missing translation"}]'),
+    ('incomplete_description', '[{"lang": "en", "text": "Incomplete description"}]'),
+    ('misleading_information', '[{"lang": "en", "text": "Misleading information"}]');
+
+CREATE TABLE moderation_feedback (
+    id SERIAL PRIMARY KEY,
+    transition_id INTEGER NOT NULL REFERENCES moderation_transitions (id) ON DELETE CASCADE,
+    entity_type VARCHAR NOT NULL,
+    entity_id INTEGER NOT NULL,
+    reason_code VARCHAR NOT NULL REFERENCES decline_reasons (code) ON UPDATE CASCADE,
+    field VARCHAR,
+    comment VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS moderation_feedback_transition_id_idx ON moderation_feedback (transition_id);
+CREATE INDEX IF NOT EXISTS moderation_feedback_reason_code_idx ON moderation_feedback (reason_code);
@@ -0,0 +1,2 @@
+ALTER TABLE moderation_queue DROP COLUMN IF EXISTS review_notes;
+ALTER TABLE moderation_queue DROP COLUMN IF EXISTS needs_review;
@@ -0,0 +1,2 @@
+ALTER TABLE moderation_queue ADD COLUMN needs_review BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE moderation_queue ADD COLUMN review_notes JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS stores_rating_update ON stores;
+DROP FUNCTION IF EXISTS stores_rating_to_base_products();
+
+DROP TRIGGER IF EXISTS base_products_store_rating_update ON base_products;
+DROP FUNCTION IF EXISTS base_products_store_rating();
+ALTER TABLE base_products DROP COLUMN IF EXISTS store_rating;
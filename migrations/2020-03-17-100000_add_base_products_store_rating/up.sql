@@ -0,0 +1,28 @@
+-- Store rating is denormalized into base products so that it reaches products index with every base product change,
+-- it is not in diesel schema, stores rating is updated outside of this service
+ALTER TABLE base_products ADD COLUMN store_rating DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+UPDATE base_products SET store_rating=bp_store.rating FROM (SELECT id, rating FROM stores) AS bp_store WHERE bp_store.id=base_products.store_id;
+
+CREATE OR REPLACE FUNCTION base_products_store_rating() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.store_rating := coalesce((SELECT rating FROM stores WHERE id = NEW.store_id), 0);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER base_products_store_rating_update
+    BEFORE INSERT OR UPDATE OF store_id ON base_products
+    FOR EACH ROW EXECUTE PROCEDURE base_products_store_rating();
+
+CREATE OR REPLACE FUNCTION stores_rating_to_base_products() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE base_products SET store_rating = NEW.rating WHERE store_id = NEW.id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER stores_rating_update
+    AFTER UPDATE OF rating ON stores
+    FOR EACH ROW WHEN (OLD.rating IS DISTINCT FROM NEW.rating)
+    EXECUTE PROCEDURE stores_rating_to_base_products();
@@ -0,0 +1,32 @@
+#[macro_use]
+extern crate log;
+extern crate stores_lib;
+extern crate stq_logging;
+
+use std::env;
+use std::process;
+
+use stores_lib::loaders::reindex::ReindexArgs;
+
+fn main() {
+    let config = stores_lib::config::Config::new().expect("Can't load app config!");
+
+    // Prepare sentry integration
+    let _sentry = stores_lib::sentry_integration::init(config.sentry.as_ref());
+
+    // Prepare logger
+    stq_logging::init(config.graylog.as_ref());
+
+    let args = match ReindexArgs::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            error!("{}", err);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = stores_lib::start_reindex(config, &args) {
+        error!("Reindexing failed: {:?}", err);
+        process::exit(1);
+    }
+}
@@ -22,6 +22,19 @@ pub struct Config {
     pub rocket_retail: Option<RocketRetail>,
     pub s3: Option<S3>,
     pub ticker: Option<Ticker>,
+    pub price_scheduler: Option<PriceScheduler>,
+    pub outbox_relay: Option<OutboxRelay>,
+    pub reindex: Option<Reindex>,
+    #[serde(default)]
+    pub search_ranking: SearchRanking,
+    #[serde(default)]
+    pub search: Search,
+    #[serde(default)]
+    pub acl: Acl,
+    #[serde(default)]
+    pub moderation: Moderation,
+    #[serde(default)]
+    pub pre_moderation: PreModeration,
 }
 
 /// Common server settings
@@ -61,6 +74,145 @@ pub struct Ticker {
     pub thread_count: usize,
 }
 
+/// Price scheduler settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct PriceScheduler {
+    pub interval_s: u64,
+    pub thread_count: usize,
+}
+
+/// Outbox relay settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutboxRelay {
+    pub interval_s: u64,
+    pub thread_count: usize,
+    pub batch_size: i64,
+    pub sink: OutboxRelaySink,
+    /// Required for `webhook` sink
+    pub webhook_url: Option<String>,
+    /// Required for `file` sink
+    pub file_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboxRelaySink {
+    /// Batches of events are posted to `webhook_url` as JSON array
+    Webhook,
+    /// Events are appended to `file_path` in JSON Lines format
+    File,
+}
+
+/// Reindexing settings
+#[derive(Debug, Deserialize, Clone)]
+pub struct Reindex {
+    /// Count of entities loaded from db and indexed in one bulk request
+    pub batch_size: i64,
+}
+
+/// Weights of relevance signals added to text score of products search, zero weight turns signal off
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SearchRanking {
+    /// Weight of logarithm of product views
+    pub views_weight: f64,
+    /// Weight of logarithm of product rating
+    pub rating_weight: f64,
+    /// Weight of product recency, decaying to half in `recency_scale_days` after creation
+    pub recency_weight: f64,
+    pub recency_scale_days: u32,
+    /// Weight of logarithm of store rating
+    pub store_rating_weight: f64,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self {
+            views_weight: 0.5,
+            rating_weight: 1.0,
+            recency_weight: 0.5,
+            recency_scale_days: 30,
+            store_rating_weight: 0.5,
+        }
+    }
+}
+
+/// Search backend settings
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Search {
+    pub backend: SearchBackend,
+    /// Search in Postgres when request to Elastic fails, used with `elastic` backend only
+    pub fallback_to_postgres: bool,
+    /// Max count of best matching base products or stores loaded from Postgres, filters on variants,
+    /// sorting and facets are applied to them
+    pub postgres_candidates_limit: i64,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            backend: SearchBackend::Elastic,
+            fallback_to_postgres: true,
+            postgres_candidates_limit: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackend {
+    Elastic,
+    /// Full text search over translations in Postgres, relevance is worse than in Elastic
+    Postgres,
+}
+
+/// Access control settings
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Acl {
+    /// JSON file with permissions of roles, built-in policy is used when not set
+    pub policy_file: Option<String>,
+}
+
+/// Moderation settings
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Moderation {
+    /// Hours moderator has to make decision on entity sent to moderation
+    pub sla_hours: u64,
+}
+
+impl Default for Moderation {
+    fn default() -> Self {
+        Self { sla_hours: 24 }
+    }
+}
+
+/// Automated checks of base products sent to moderation
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PreModeration {
+    pub enabled: bool,
+    /// Words base product can not be published with, compared case insensitive
+    pub forbidden_words: Vec<String>,
+    /// Heavier base products are marked as needing moderator review
+    pub max_weight_g: i32,
+    /// Base products with longer length, width or height are marked as needing moderator review
+    pub max_dimension_cm: i32,
+}
+
+impl Default for PreModeration {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            forbidden_words: vec![],
+            max_weight_g: 1_000_000,
+            max_dimension_cm: 1_000,
+        }
+    }
+}
+
 /// AWS S3 credentials
 #[derive(Debug, Deserialize, Clone)]
 pub struct S3 {
@@ -1,19 +1,25 @@
 //! `Context` is a top level module containg static context and dynamic context for each request
+use std::str::FromStr;
 use std::sync::Arc;
 
 use diesel::connection::AnsiTransactionManager;
 use diesel::pg::Pg;
 use diesel::Connection;
+use failure::Error as FailureError;
 use futures_cpupool::CpuPool;
+use hyper::header::{Authorization, Cookie};
+use hyper::server::Request;
 use r2d2::{ManageConnection, Pool};
 
 use stq_http::client::ClientHandle;
+use stq_http::request_util::{self, Currency as CurrencyHeader, FiatCurrency as FiatCurrencyHeader};
 use stq_router::RouteParser;
 use stq_static_resources::Currency;
 use stq_types::UserId;
 
 use super::routes::*;
 use config::Config;
+use errors::Error;
 use repos::repo_factory::*;
 
 /// Static context for all app
@@ -89,6 +95,36 @@ impl DynamicContext {
         }
     }
 
+    /// Create a new dynamic context from request headers
+    pub fn from_request(req: &Request) -> Result<Self, FailureError> {
+        let headers = req.headers();
+        let auth_header = headers.get::<Authorization<String>>();
+        let user_id = auth_header
+            .map(|auth| auth.0.clone())
+            .and_then(|id| i32::from_str(&id).ok())
+            .map(UserId);
+
+        let uuid_header = headers.get::<Cookie>();
+        let uuid = uuid_header.and_then(|cookie| cookie.get("UUID"));
+        debug!("User with id = '{:?}' and uuid = {:?} is requesting {}", user_id, uuid, req.path());
+
+        let currency = headers
+            .get::<CurrencyHeader>()
+            .ok_or(format_err!("Missing Currency header"))
+            .and_then(|sid| Currency::from_code(sid).ok_or(format_err!("Invalid currency: {}", sid)))
+            .map_err(|e| e.context(Error::Parse))?;
+
+        let fiat_currency = headers
+            .get::<FiatCurrencyHeader>()
+            .ok_or(format_err!("Missing FiatCurrency header"))
+            .and_then(|sid| Currency::from_code(sid).ok_or(format_err!("Invalid fiat currency: {}", sid)))
+            .map_err(|e| e.context(Error::Parse))?;
+
+        let correlation_token = request_util::get_correlation_token(req);
+
+        Ok(Self::new(user_id, currency, fiat_currency, correlation_token))
+    }
+
     pub fn is_super_admin(&self) -> bool {
         self.user_id == Some(UserId(1))
     }
@@ -6,28 +6,24 @@
 pub mod context;
 pub mod responses;
 pub mod routes;
+pub mod streaming;
 pub mod utils;
 
-use std::str::FromStr;
-
+use chrono::{DateTime, Utc};
 use diesel::{connection::AnsiTransactionManager, pg::Pg, Connection};
 use failure::Fail;
 use futures::{future, Future, IntoFuture};
-use hyper::{
-    header::{Authorization, Cookie},
-    server::Request,
-    Delete, Get, Post, Put,
-};
+use hyper::{server::Request, Delete, Get, Post, Put};
 use r2d2::ManageConnection;
 use validator::Validate;
 
 use stq_http::{
     controller::{Controller, ControllerFuture},
     errors::ErrorMessageWrapper,
-    request_util::{self, parse_body, read_body, serialize_future, Currency as CurrencyHeader, FiatCurrency as FiatCurrencyHeader},
+    request_util::{parse_body, read_body, serialize_future},
 };
 
-use stq_static_resources::{Currency, ModerationStatus};
+use stq_static_resources::ModerationStatus;
 use stq_types::*;
 
 use self::routes::Route;
@@ -37,6 +33,7 @@ use models::*;
 use repos::repo_factory::*;
 use repos::CouponSearch;
 use sentry_integration::log_and_capture_error;
+use services::acl::AclService;
 use services::attribute_values::{AttributeValuesService, NewAttributeValuePayload};
 use services::attributes::AttributesService;
 use services::base_products::BaseProductsService;
@@ -45,8 +42,12 @@ use services::categories::CategoriesService;
 use services::coupons::CouponsService;
 use services::currency_exchange::CurrencyExchangeService;
 use services::custom_attributes::CustomAttributesService;
+use services::decline_reasons::DeclineReasonsService;
+use services::moderation::ModerationService;
 use services::moderator_comments::ModeratorCommentsService;
 use services::products::ProductsService;
+use services::search_synonyms::SearchSynonymsService;
+use services::store_staff::StoreStaffService;
 use services::stores::StoresService;
 use services::user_roles::UserRolesService;
 use services::wizard_stores::WizardStoresService;
@@ -82,45 +83,13 @@ impl<
 {
     /// Handle a request and get future response
     fn call(&self, req: Request) -> ControllerFuture {
-        let headers = req.headers().clone();
-        let auth_header = headers.get::<Authorization<String>>();
-        let user_id = auth_header
-            .map(|auth| auth.0.clone())
-            .and_then(|id| i32::from_str(&id).ok())
-            .map(UserId);
-
-        let uuid_header = headers.get::<Cookie>();
-        let uuid = uuid_header.and_then(|cookie| cookie.get("UUID"));
-        debug!("User with id = '{:?}' and uuid = {:?} is requesting {}", user_id, uuid, req.path());
-
-        let currency = match headers
-            .get::<CurrencyHeader>()
-            .ok_or(format_err!("Missing Currency header"))
-            .and_then(|sid| Currency::from_code(sid).ok_or(format_err!("Invalid currency: {}", sid)))
-            .map_err(|e| e.context(Error::Parse).into())
-        {
-            Ok(v) => v,
-            Err(e) => {
-                return Box::new(future::err(e));
-            }
-        };
-
-        let fiat_currency = match headers
-            .get::<FiatCurrencyHeader>()
-            .ok_or(format_err!("Missing FiatCurrency header"))
-            .and_then(|sid| Currency::from_code(sid).ok_or(format_err!("Invalid fiat currency: {}", sid)))
-            .map_err(|e| e.context(Error::Parse).into())
-        {
+        let dynamic_context = match DynamicContext::from_request(&req) {
             Ok(v) => v,
             Err(e) => {
                 return Box::new(future::err(e));
             }
         };
 
-        let correlation_token = request_util::get_correlation_token(&req);
-
-        let dynamic_context = DynamicContext::new(user_id, currency, fiat_currency, correlation_token);
-
         let service = Service::new(self.static_context.clone(), dynamic_context);
 
         let path = req.path().to_string();
@@ -186,6 +155,35 @@ impl<
                 serialize_future(service.get_store_products_count(store_id, visibility))
             }
 
+            // POST /stores/<store_id>/base_products/import
+            (&Post, Some(Route::StoreBaseProductsImport(store_id))) => {
+                let dry_run = parse_query!(req.query().unwrap_or_default(), "dry_run" => bool).unwrap_or(false);
+                serialize_future(
+                    read_body(req.body())
+                        .map_err(|e| e.context("Parsing body failed, target: String").context(Error::Parse).into())
+                        .and_then(move |payload| service.import_base_products(store_id, payload, dry_run)),
+                )
+            }
+
+            // GET /stores/<store_id>/staff
+            (&Get, Some(Route::StoreStaff(store_id))) => serialize_future(service.get_store_staff(store_id)),
+
+            // POST /stores/<store_id>/staff
+            (&Post, Some(Route::StoreStaff(store_id))) => serialize_future(
+                parse_body::<NewStoreStaffMemberPayload>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: NewStoreStaffMemberPayload")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| service.invite_store_staff_member(store_id, payload)),
+            ),
+
+            // DELETE /stores/<store_id>/staff/<user_id>
+            (&Delete, Some(Route::StoreStaffMember { store_id, user_id })) => {
+                serialize_future(service.remove_store_staff_member(store_id, user_id))
+            }
+
             // GET /stores/slug_exists route
             (&Get, Some(Route::StoresSlugExists)) => {
                 if let Some(slug) = parse_query!(req.query().unwrap_or_default(), "slug" => String) {
@@ -214,7 +212,25 @@ impl<
             (&Post, Some(Route::StoreModerate)) => serialize_future(
                 parse_body::<StoreModerate>(req.body())
                     .map_err(|e| e.context("Parsing body failed, target: StoreModerate").context(Error::Parse).into())
-                    .and_then(move |store_moderate| service.set_store_moderation_status(store_moderate.store_id, store_moderate.status)),
+                    .and_then(move |store_moderate| {
+                        service.set_store_moderation_status(
+                            store_moderate.store_id,
+                            store_moderate.status,
+                            store_moderate.comment,
+                            store_moderate.feedback,
+                        )
+                    }),
+            ),
+
+            // POST /stores/moderate/bulk
+            (&Post, Some(Route::StoresBulkModerate)) => serialize_future(
+                parse_body::<BulkModeration<StoreId>>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: BulkModeration<StoreId>")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| service.bulk_moderate_stores(payload)),
             ),
 
             // POST /stores/validate_change_moderation_status
@@ -232,13 +248,34 @@ impl<
             // POST /stores/moderation
             (&Post, Some(Route::StoreModeration(store_id))) => serialize_future(service.send_store_to_moderation(store_id)),
 
+            // GET /stores/<store_id>/moderation_history
+            (&Get, Some(Route::StoreModerationHistory(store_id))) => serialize_future(service.get_store_moderation_history(store_id)),
+
+            // GET /stores/<store_id>/moderation_feedback
+            (&Get, Some(Route::StoreModerationFeedback(store_id))) => serialize_future(service.get_store_moderation_feedback(store_id)),
+
+            // GET /stores/<store_id>/moderation_transitions
+            (&Get, Some(Route::StoreModerationTransitions(store_id))) => {
+                serialize_future(service.get_store_moderation_transitions(store_id))
+            }
+
             // POST /stores/search
             (&Post, Some(Route::StoresSearch)) => {
                 if let (Some(offset), Some(count)) = parse_query!(req.query().unwrap_or_default(), "offset" => i32, "count" => i32) {
                     serialize_future(
                         parse_body::<SearchStore>(req.body())
                             .map_err(|e| e.context("Parsing body failed, target: SearchStore").context(Error::Parse).into())
-                            .and_then(move |store_search| service.find_store_by_name(store_search, count, offset)),
+                            .and_then(move |store_search| {
+                                store_search
+                                    .validate()
+                                    .map_err(|e| {
+                                        format_err!("Validation failed, target: SearchStore")
+                                            .context(Error::Validate(e))
+                                            .into()
+                                    })
+                                    .into_future()
+                                    .and_then(move |_| service.find_store_by_name(store_search, count, offset))
+                            }),
                     )
                 } else {
                     Box::new(future::err(
@@ -253,21 +290,68 @@ impl<
             (&Post, Some(Route::StoresSearchFiltersCount)) => serialize_future(
                 parse_body::<SearchStore>(req.body())
                     .map_err(|e| e.context("Parsing body failed, target: SearchStore").context(Error::Parse).into())
-                    .and_then(move |search_store| service.search_store_filters_count(search_store)),
+                    .and_then(move |search_store| {
+                        search_store
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: SearchStore")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.search_store_filters_count(search_store))
+                    }),
             ),
 
             // POST /stores/search/filters/country
             (&Post, Some(Route::StoresSearchFiltersCountry)) => serialize_future(
                 parse_body::<SearchStore>(req.body())
                     .map_err(|e| e.context("Parsing body failed, target: SearchStore").context(Error::Parse).into())
-                    .and_then(move |search_store| service.search_store_filters_country(search_store)),
+                    .and_then(move |search_store| {
+                        search_store
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: SearchStore")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.search_store_filters_country(search_store))
+                    }),
+            ),
+
+            // POST /stores/search/filters/locality
+            (&Post, Some(Route::StoresSearchFiltersLocality)) => serialize_future(
+                parse_body::<SearchStore>(req.body())
+                    .map_err(|e| e.context("Parsing body failed, target: SearchStore").context(Error::Parse).into())
+                    .and_then(move |search_store| {
+                        search_store
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: SearchStore")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.search_store_filters_locality(search_store))
+                    }),
             ),
 
             // POST /stores/search/filters/category
             (&Post, Some(Route::StoresSearchFiltersCategory)) => serialize_future(
                 parse_body::<SearchStore>(req.body())
                     .map_err(|e| e.context("Parsing body failed, target: SearchStore").context(Error::Parse).into())
-                    .and_then(move |search_store| service.search_store_filters_category(search_store)),
+                    .and_then(move |search_store| {
+                        search_store
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: SearchStore")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.search_store_filters_category(search_store))
+                    }),
             ),
 
             // POST /stores/auto_complete
@@ -348,7 +432,7 @@ impl<
 
             // POST /stores/<store_id>/publish
             (&Post, Some(Route::StorePublish(store_id))) => {
-                serialize_future(service.set_store_moderation_status(store_id, ModerationStatus::Published))
+                serialize_future(service.set_store_moderation_status(store_id, ModerationStatus::Published, None, vec![]))
             }
 
             // POST /stores/<store_id>/draft
@@ -460,6 +544,42 @@ impl<
             // DELETE /products/<product_id>
             (&Delete, Some(Route::Product(product_id))) => serialize_future(service.deactivate_product(product_id)),
 
+            // GET /products/<product_id>/price_history
+            (&Get, Some(Route::ProductPriceHistory(product_id))) => serialize_future(service.get_product_price_history(product_id)),
+
+            // GET /products/<product_id>/scheduled_prices
+            (&Get, Some(Route::ProductScheduledPrices(product_id))) => serialize_future(service.get_scheduled_price_changes(product_id)),
+
+            // POST /products/<product_id>/scheduled_prices
+            (&Post, Some(Route::ProductScheduledPrices(product_id))) => serialize_future(
+                parse_body::<NewScheduledPriceChangePayload>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: NewScheduledPriceChangePayload")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| {
+                        payload
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: NewScheduledPriceChangePayload")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.create_scheduled_price_change(product_id, payload))
+                    }),
+            ),
+
+            // DELETE /products/<product_id>/scheduled_prices/<scheduled_price_change_id>
+            (
+                &Delete,
+                Some(Route::ProductScheduledPrice {
+                    product_id,
+                    scheduled_price_change_id,
+                }),
+            ) => serialize_future(service.delete_scheduled_price_change(product_id, scheduled_price_change_id)),
+
             // GET /base_products/<base_product_id>
             (&Get, Some(Route::BaseProduct(base_product_id))) => {
                 let visibility = parse_query!(req.query().unwrap_or_default(), "visibility" => Visibility);
@@ -579,10 +699,26 @@ impl<
                             .into()
                     })
                     .and_then(move |base_product_moderate| {
-                        service.set_moderation_status_base_product(base_product_moderate.base_product_id, base_product_moderate.status)
+                        service.set_moderation_status_base_product(
+                            base_product_moderate.base_product_id,
+                            base_product_moderate.status,
+                            base_product_moderate.comment,
+                            base_product_moderate.feedback,
+                        )
                     }),
             ),
 
+            // POST /base_products/moderate/bulk
+            (&Post, Some(Route::BaseProductsBulkModerate)) => serialize_future(
+                parse_body::<BulkModeration<BaseProductId>>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: BulkModeration<BaseProductId>")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| service.bulk_moderate_base_products(payload)),
+            ),
+
             // POST /base_products/validate_change_moderation_status
             (&Post, Some(Route::BaseProductValidateChangeModerationStatus)) => serialize_future(
                 parse_body::<BaseProductModerate>(req.body())
@@ -782,6 +918,27 @@ impl<
                     .and_then(move |search_prod| service.search_base_products_filters_count(search_prod)),
             ),
 
+            // POST /base_products/search/faceted
+            (&Post, Some(Route::BaseProductsSearchFaceted)) => {
+                if let (Some(offset), Some(count)) = parse_query!(req.query().unwrap_or_default(), "offset" => i32, "count" => i32) {
+                    serialize_future(
+                        parse_body::<SearchProductsByName>(req.body())
+                            .map_err(|e| {
+                                e.context("Parsing body failed, target: SearchProductsByName")
+                                    .context(Error::Parse)
+                                    .into()
+                            })
+                            .and_then(move |prod| service.search_base_products_faceted(prod, count, offset)),
+                    )
+                } else {
+                    Box::new(future::err(
+                        format_err!("Parsing query parameters failed, action: faceted search base products")
+                            .context(Error::Parse)
+                            .into(),
+                    ))
+                }
+            }
+
             // POST /base_products/publish
             (&Post, Some(Route::BaseProductPublish)) => serialize_future(
                 parse_body::<Vec<BaseProductId>>(req.body())
@@ -800,6 +957,90 @@ impl<
                 serialize_future(service.set_base_product_moderation_status_draft(base_product_id))
             }
 
+            // GET /base_products/<base_product_id>/moderation_history
+            (&Get, Some(Route::BaseProductModerationHistory(base_product_id))) => {
+                serialize_future(service.get_base_product_moderation_history(base_product_id))
+            }
+
+            // GET /base_products/<base_product_id>/moderation_feedback
+            (&Get, Some(Route::BaseProductModerationFeedback(base_product_id))) => {
+                serialize_future(service.get_base_product_moderation_feedback(base_product_id))
+            }
+
+            // GET /base_products/<base_product_id>/moderation_transitions
+            (&Get, Some(Route::BaseProductModerationTransitions(base_product_id))) => {
+                serialize_future(service.get_base_product_moderation_transitions(base_product_id))
+            }
+
+            // POST /moderation_queue/search
+            (&Post, Some(Route::ModerationQueueSearch)) => serialize_future(
+                parse_body::<ModerationQueueSearch>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: ModerationQueueSearch")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |search| service.search_moderation_queue(search)),
+            ),
+
+            // POST /moderation_queue/<item_id>/claim
+            (&Post, Some(Route::ModerationQueueItemClaim(item_id))) => serialize_future(service.claim_moderation_queue_item(item_id)),
+
+            // POST /moderation_queue/<item_id>/release
+            (&Post, Some(Route::ModerationQueueItemRelease(item_id))) => serialize_future(service.release_moderation_queue_item(item_id)),
+
+            // GET /decline_reasons
+            (&Get, Some(Route::DeclineReasons)) => serialize_future(service.list_decline_reasons()),
+
+            // POST /decline_reasons
+            (&Post, Some(Route::DeclineReasons)) => serialize_future(
+                parse_body::<NewDeclineReason>(req.body())
+                    .map_err(|e| e.context("Parsing body failed, target: NewDeclineReason").context(Error::Parse).into())
+                    .and_then(move |payload| {
+                        payload
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: NewDeclineReason")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.create_decline_reason(payload))
+                    }),
+            ),
+
+            // PUT /decline_reasons/:id
+            (&Put, Some(Route::DeclineReason(decline_reason_id))) => serialize_future(
+                parse_body::<UpdateDeclineReason>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: UpdateDeclineReason")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| {
+                        payload
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: UpdateDeclineReason")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.update_decline_reason(decline_reason_id, payload))
+                    }),
+            ),
+
+            // POST /decline_reasons/report
+            (&Post, Some(Route::DeclineReasonsReport)) => serialize_future(
+                parse_body::<ModerationFeedbackSearch>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: ModerationFeedbackSearch")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |search| service.get_decline_reasons_report(search)),
+            ),
+
             // POST /custom_attributes
             (&Post, Some(Route::CustomAttributes)) => serialize_future(
                 parse_body::<NewCustomAttribute>(req.body())
@@ -824,6 +1065,59 @@ impl<
                 serialize_future({ service.delete_custom_attribute(custom_attributes_id) })
             }
 
+            // GET /search_synonyms
+            (&Get, Some(Route::SearchSynonyms)) => serialize_future(service.list_search_synonyms()),
+
+            // POST /search_synonyms
+            (&Post, Some(Route::SearchSynonyms)) => serialize_future(
+                parse_body::<NewSearchSynonym>(req.body())
+                    .map_err(|e| e.context("Parsing body failed, target: NewSearchSynonym").context(Error::Parse).into())
+                    .and_then(move |payload| {
+                        payload
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: NewSearchSynonym")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.create_search_synonym(payload))
+                    }),
+            ),
+
+            // PUT /search_synonyms/:id
+            (&Put, Some(Route::SearchSynonym(search_synonym_id))) => serialize_future(
+                parse_body::<UpdateSearchSynonym>(req.body())
+                    .map_err(|e| {
+                        e.context("Parsing body failed, target: UpdateSearchSynonym")
+                            .context(Error::Parse)
+                            .into()
+                    })
+                    .and_then(move |payload| {
+                        payload
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: UpdateSearchSynonym")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.update_search_synonym(search_synonym_id, payload))
+                    }),
+            ),
+
+            // DELETE /search_synonyms/:id
+            (&Delete, Some(Route::SearchSynonym(search_synonym_id))) => {
+                serialize_future(service.delete_search_synonym(search_synonym_id))
+            }
+
+            // POST /acl/explain
+            (&Post, Some(Route::AclExplain)) => serialize_future(
+                parse_body::<AclExplainRequest>(req.body())
+                    .map_err(|e| e.context("Parsing body failed, target: AclExplainRequest").context(Error::Parse).into())
+                    .and_then(move |payload| service.explain_acl_decision(payload)),
+            ),
+
             // POST /coupons
             (&Post, Some(Route::Coupons)) => serialize_future(
                 parse_body::<NewCoupon>(req.body())
@@ -847,6 +1141,32 @@ impl<
             // GET /coupons/generate_code
             (&Get, Some(Route::CouponsGenerateCode)) => serialize_future(service.generate_coupon_code()),
 
+            // POST /coupons/batches
+            (&Post, Some(Route::CouponsBatches)) => serialize_future(
+                parse_body::<NewCouponsBatch>(req.body())
+                    .map_err(|e| e.context("Parsing body failed, target: NewCouponsBatch").context(Error::Parse).into())
+                    .and_then(move |new_coupons_batch| {
+                        new_coupons_batch
+                            .validate()
+                            .map_err(|e| {
+                                format_err!("Validation failed, target: NewCouponsBatch")
+                                    .context(Error::Validate(e))
+                                    .into()
+                            })
+                            .into_future()
+                            .and_then(move |_| service.create_coupons_batch(new_coupons_batch))
+                    }),
+            ),
+
+            // GET /coupons/batches/:campaign_id
+            (&Get, Some(Route::CouponsBatch(campaign_id))) => {
+                let search = CouponSearch::Campaign(campaign_id);
+                serialize_future(service.find_coupons(search))
+            }
+
+            // GET /coupons/batches/:campaign_id/csv
+            (&Get, Some(Route::CouponsBatchCsv(campaign_id))) => service.export_coupons_batch(campaign_id),
+
             // POST /coupons/search/code
             (&Post, Some(Route::CouponsSearchCode)) => serialize_future(
                 parse_body::<CouponsSearchCodePayload>(req.body())
@@ -870,7 +1190,10 @@ impl<
             ),
 
             // GET /coupons/:id/validate
-            (&Get, Some(Route::CouponValidate(coupon_id))) => serialize_future(service.validate_coupon(coupon_id)),
+            (&Get, Some(Route::CouponValidate(coupon_id))) => {
+                let subtotal = parse_query!(req.query().unwrap_or_default(), "subtotal" => f64);
+                serialize_future(service.validate_coupon(coupon_id, subtotal))
+            }
 
             // POST /coupons/:coupon_id/base_products/:base_product_id
             (
@@ -899,6 +1222,42 @@ impl<
             // GET /coupons/:coupon_id/base_products
             (&Get, Some(Route::BaseProductsByCoupon(coupon_id))) => serialize_future(service.find_base_products_by_coupon(coupon_id)),
 
+            // POST /coupons/:coupon_id/categories/:category_id
+            (&Post, Some(Route::CouponScopeCategories { coupon_id, category_id })) => {
+                serialize_future(service.add_category_coupon(coupon_id, category_id))
+            }
+
+            // DELETE /coupons/:coupon_id/categories/:category_id
+            (&Delete, Some(Route::CouponScopeCategories { coupon_id, category_id })) => {
+                serialize_future(service.delete_category_from_coupon(coupon_id, category_id))
+            }
+
+            // GET /coupons/:coupon_id/categories
+            (&Get, Some(Route::CategoriesByCoupon(coupon_id))) => serialize_future(service.find_categories_by_coupon(coupon_id)),
+
+            // POST /coupons/:coupon_id/excluded_base_products/:base_product_id
+            (
+                &Post,
+                Some(Route::CouponExclusionBaseProducts {
+                    coupon_id,
+                    base_product_id,
+                }),
+            ) => serialize_future(service.add_excluded_base_product_coupon(coupon_id, base_product_id)),
+
+            // DELETE /coupons/:coupon_id/excluded_base_products/:base_product_id
+            (
+                &Delete,
+                Some(Route::CouponExclusionBaseProducts {
+                    coupon_id,
+                    base_product_id,
+                }),
+            ) => serialize_future(service.delete_excluded_base_product_from_coupon(coupon_id, base_product_id)),
+
+            // GET /coupons/:coupon_id/excluded_base_products
+            (&Get, Some(Route::ExcludedBaseProductsByCoupon(coupon_id))) => {
+                serialize_future(service.find_excluded_base_products_by_coupon(coupon_id))
+            }
+
             // PUT /coupons/:id
             (&Put, Some(Route::Coupon(coupon_id))) => serialize_future(
                 parse_body::<UpdateCoupon>(req.body())
@@ -1059,6 +1418,18 @@ impl<
 
             (&Get, Some(Route::Catalog)) => serialize_future(service.get_catalog()),
 
+            // GET /catalog/feed?cursor=<cursor>&limit=<limit>, GET /catalog/feed?updated_since=<rfc3339 timestamp>&limit=<limit>
+            (&Get, Some(Route::CatalogFeed)) => {
+                let (cursor, updated_since, limit) = parse_query!(
+                    req.query().unwrap_or_default(),
+                    "cursor" => CatalogFeedCursor, "updated_since" => DateTime<Utc>, "limit" => i64
+                );
+                let cursor = cursor
+                    .or_else(|| updated_since.map(|updated_since| CatalogFeedCursor::since(updated_since.into())))
+                    .unwrap_or_default();
+                serialize_future(service.get_catalog_feed(cursor, limit))
+            }
+
             // GET /categories/<category_id>
             (&Get, Some(Route::Category(category_id))) => serialize_future(service.get_category(category_id)),
 
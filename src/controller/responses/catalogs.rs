@@ -6,9 +6,9 @@ use stq_types::*;
 
 use models::attributes::attribute::Attribute;
 use models::attributes::attribute_product::ProdAttr;
-use models::base_product::BaseProduct;
+use models::base_product::{BaseProduct, CatalogWithAttributes};
 use models::category::RawCategory;
-use models::product::RawProduct;
+use models::product::{ProductWithAttributes, RawProduct, StockStatus};
 use models::store::Store;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -21,6 +21,33 @@ pub struct CatalogResponse {
     pub prod_attrs: Vec<CatalogResponseProdAttr>,
 }
 
+/// Catalog rows changed since feed cursor
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogFeedResponse {
+    pub categories: Vec<CatalogResponseCategory>,
+    pub stores: Vec<CatalogResponseStore>,
+    pub base_products: Vec<CatalogResponseBaseProduct>,
+    pub products: Vec<CatalogResponseProduct>,
+    /// All attribute values of every product in `products`
+    pub prod_attrs: Vec<CatalogResponseProdAttr>,
+    pub deleted: CatalogFeedDeleted,
+    /// Cursor for the next request, may repeat some of the rows
+    pub next_cursor: String,
+    /// Some rows did not fit into the page, next page has to be requested right away
+    pub has_more: bool,
+}
+
+/// Ids of rows that were deactivated or hidden from catalog since feed cursor
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogFeedDeleted {
+    pub category_ids: Vec<CategoryId>,
+    pub store_ids: Vec<StoreId>,
+    pub base_product_ids: Vec<BaseProductId>,
+    pub product_ids: Vec<ProductId>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CatalogResponseCategory {
@@ -32,6 +59,7 @@ pub struct CatalogResponseCategory {
     pub is_active: bool,
     pub uuid: Uuid,
     pub slug: CategorySlug,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -68,6 +96,7 @@ pub struct CatalogResponseStore {
     pub street_number: Option<String>,
     pub country_code: Option<Alpha3>,
     pub uuid: Uuid,
+    pub kafka_update_no: i32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -94,6 +123,7 @@ pub struct CatalogResponseBaseProduct {
     pub width_cm: Option<i32>,
     pub height_cm: Option<i32>,
     pub weight_g: Option<i32>,
+    pub kafka_update_no: i32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -111,6 +141,7 @@ pub struct CatalogResponseProduct {
     pub price: ProductPrice,
     pub currency: Currency,
     pub vendor_code: String,
+    pub kafka_update_no: i32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -121,6 +152,35 @@ pub struct CatalogResponseProdAttr {
     pub value: AttributeValueCode,
 }
 
+/// Line of store catalog export in JSON Lines format
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogExportBaseProduct {
+    #[serde(flatten)]
+    pub base_product: CatalogResponseBaseProduct,
+    pub variants: Vec<CatalogExportProduct>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogExportProduct {
+    #[serde(flatten)]
+    pub product: CatalogResponseProduct,
+    pub pre_order: bool,
+    pub pre_order_days: i32,
+    pub stock_status: Option<StockStatus>,
+    pub attributes: Vec<CatalogExportProdAttr>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogExportProdAttr {
+    pub attr_id: AttributeId,
+    pub name: serde_json::Value,
+    pub value: AttributeValueCode,
+    pub meta_field: Option<String>,
+}
+
 impl From<RawCategory> for CatalogResponseCategory {
     fn from(category: RawCategory) -> Self {
         Self {
@@ -132,6 +192,7 @@ impl From<RawCategory> for CatalogResponseCategory {
             is_active: category.is_active,
             uuid: category.uuid,
             slug: category.slug,
+            updated_at: category.updated_at.into(),
         }
     }
 }
@@ -170,6 +231,7 @@ impl From<Store> for CatalogResponseStore {
             street_number: store.street_number,
             country_code: store.country_code,
             uuid: store.uuid,
+            kafka_update_no: store.kafka_update_no,
         }
     }
 }
@@ -198,6 +260,7 @@ impl From<BaseProduct> for CatalogResponseBaseProduct {
             width_cm: base_product.width_cm,
             height_cm: base_product.height_cm,
             weight_g: base_product.weight_g,
+            kafka_update_no: base_product.kafka_update_no,
         }
     }
 }
@@ -217,6 +280,7 @@ impl From<RawProduct> for CatalogResponseProduct {
             price: product.price,
             currency: product.currency,
             vendor_code: product.vendor_code,
+            kafka_update_no: product.kafka_update_no,
         }
     }
 }
@@ -232,3 +296,39 @@ impl From<(ProdAttr, Attribute)> for CatalogResponseProdAttr {
         }
     }
 }
+
+impl From<CatalogWithAttributes> for CatalogExportBaseProduct {
+    fn from(catalog: CatalogWithAttributes) -> Self {
+        Self {
+            base_product: catalog.base_product.into(),
+            variants: catalog.variants.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+impl From<ProductWithAttributes> for CatalogExportProduct {
+    fn from(variant: ProductWithAttributes) -> Self {
+        let ProductWithAttributes { product, attributes } = variant;
+
+        Self {
+            pre_order: product.pre_order,
+            pre_order_days: product.pre_order_days,
+            stock_status: product.stock_status,
+            product: product.into(),
+            attributes: attributes.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+impl From<(ProdAttr, Attribute)> for CatalogExportProdAttr {
+    fn from(tuple: (ProdAttr, Attribute)) -> Self {
+        let (prod_attr, attr) = tuple;
+
+        Self {
+            attr_id: prod_attr.attr_id,
+            name: attr.name,
+            value: prod_attr.value,
+            meta_field: prod_attr.meta_field,
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use stq_router::RouteParser;
 use stq_types::*;
+use uuid::Uuid;
 
 /// List of all routes with params for the app
 #[derive(Clone, Debug, PartialEq)]
 pub enum Route {
     Healthcheck,
+    AclExplain,
     Attributes,
     Attribute(AttributeId),
     AttributeValue(AttributeValueId),
@@ -21,6 +23,7 @@ pub enum Route {
     BaseProductsSearchFiltersCategory,
     BaseProductsSearchFiltersAttributes,
     BaseProductsSearchFiltersCount,
+    BaseProductsSearchFaceted,
     BaseProduct(BaseProductId),
     BaseProductWithoutFilters(BaseProductId),
     BaseProductBySlug(StoreSlug, BaseProductSlug),
@@ -31,6 +34,7 @@ pub enum Route {
     BaseProductCustomAttributes(BaseProductId),
     BaseProductPublish,
     Catalog,
+    CatalogFeed,
     Categories,
     CategoriesWithProducts,
     Category(CategoryId),
@@ -47,6 +51,9 @@ pub enum Route {
     CouponsValidateCode,
     CouponValidate(CouponId),
     CouponsGenerateCode,
+    CouponsBatches,
+    CouponsBatch(Uuid),
+    CouponsBatchCsv(Uuid),
     CouponsSearchFiltersStore(StoreId),
     CouponScopeBaseProducts {
         coupon_id: CouponId,
@@ -57,6 +64,16 @@ pub enum Route {
         coupon_id: CouponId,
     },
     BaseProductsByCoupon(CouponId),
+    CouponScopeCategories {
+        coupon_id: CouponId,
+        category_id: CategoryId,
+    },
+    CategoriesByCoupon(CouponId),
+    CouponExclusionBaseProducts {
+        coupon_id: CouponId,
+        base_product_id: BaseProductId,
+    },
+    ExcludedBaseProductsByCoupon(CouponId),
     ModeratorProductComments,
     ModeratorBaseProductComment(BaseProductId),
     ModeratorBaseProductSearch,
@@ -70,14 +87,23 @@ pub enum Route {
     ProductWithoutFilters(ProductId),
     ProductValidateUpdate(ProductId),
     ProductAttributes(ProductId),
+    ProductPriceHistory(ProductId),
+    ProductScheduledPrices(ProductId),
+    ProductScheduledPrice {
+        product_id: ProductId,
+        scheduled_price_change_id: i32,
+    },
     ProductsByBaseProduct(BaseProductId),
     ProductsByStore(StoreId),
     SellerProductPrice(ProductId),
+    SearchSynonyms,
+    SearchSynonym(i32),
     Stores,
     StoresSearch,
     StoresAutoComplete,
     StoresSearchFiltersCount,
     StoresSearchFiltersCountry,
+    StoresSearchFiltersLocality,
     StoresSearchFiltersCategory,
     StoresCart,
     StoresSlugExists,
@@ -89,14 +115,29 @@ pub enum Route {
     StoreByUser(UserId),
     StoreProducts(StoreId),
     StoreProductsCount(StoreId),
+    StoreBaseProductsImport(StoreId),
+    StoreCatalogExport(StoreId),
+    StoreStaff(StoreId),
+    StoreStaffMember {
+        store_id: StoreId,
+        user_id: UserId,
+    },
     StorePublish(StoreId),
     StoreDraft(StoreId),
     StoreValidateChangeModerationStatus,
     StoreValidateUpdate(StoreId),
     StoreModerate,
+    StoresBulkModerate,
     StoreModeration(StoreId),
+    StoreModerationHistory(StoreId),
+    StoreModerationFeedback(StoreId),
+    StoreModerationTransitions(StoreId),
     BaseProductModerate,
+    BaseProductsBulkModerate,
     BaseProductModeration(BaseProductId),
+    BaseProductModerationHistory(BaseProductId),
+    BaseProductModerationFeedback(BaseProductId),
+    BaseProductModerationTransitions(BaseProductId),
     BaseProductDraft(BaseProductId),
     BaseProductValidateChangeModerationStatus,
     BaseProductValidateUpdate(BaseProductId),
@@ -111,6 +152,12 @@ pub enum Route {
         role: StoresRole,
     },
     WizardStores,
+    ModerationQueueSearch,
+    ModerationQueueItemClaim(i32),
+    ModerationQueueItemRelease(i32),
+    DeclineReasons,
+    DeclineReason(i32),
+    DeclineReasonsReport,
 }
 
 pub fn create_route_parser() -> RouteParser<Route> {
@@ -119,6 +166,9 @@ pub fn create_route_parser() -> RouteParser<Route> {
     // Healthcheck
     router.add_route(r"^/healthcheck$", || Route::Healthcheck);
 
+    // Acl explain route
+    router.add_route(r"^/acl/explain$", || Route::AclExplain);
+
     // Stores Routes
     router.add_route(r"^/stores$", || Route::Stores);
 
@@ -177,6 +227,41 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::StoreProductsCount)
     });
 
+    // Stores/:id/base_products/import route
+    router.add_route_with_params(r"^/stores/(\d+)/base_products/import$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(StoreId)
+            .map(Route::StoreBaseProductsImport)
+    });
+
+    // Stores/:id/catalog/export route
+    router.add_route_with_params(r"^/stores/(\d+)/catalog/export$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(StoreId)
+            .map(Route::StoreCatalogExport)
+    });
+
+    // Stores/:id/staff route
+    router.add_route_with_params(r"^/stores/(\d+)/staff$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(StoreId)
+            .map(Route::StoreStaff)
+    });
+
+    // Stores/:id/staff/:user_id route
+    router.add_route_with_params(r"^/stores/(\d+)/staff/(\d+)$", |params| {
+        let store_id = params.get(0)?.parse().ok().map(StoreId)?;
+        let user_id = params.get(1)?.parse().ok().map(UserId)?;
+
+        Some(Route::StoreStaffMember { store_id, user_id })
+    });
+
     // Stores count route
     router.add_route(r"^/stores/count$", || Route::StoreCount);
 
@@ -195,6 +280,9 @@ pub fn create_route_parser() -> RouteParser<Route> {
     // Stores Search filter country route
     router.add_route(r"^/stores/search/filters/country$", || Route::StoresSearchFiltersCountry);
 
+    // Stores Search filter locality route
+    router.add_route(r"^/stores/search/filters/locality$", || Route::StoresSearchFiltersLocality);
+
     // Stores Search filter  route
     router.add_route(r"^/stores/search/filters/category$", || Route::StoresSearchFiltersCategory);
 
@@ -204,6 +292,9 @@ pub fn create_route_parser() -> RouteParser<Route> {
     // Change moderation status by moderator
     router.add_route(r"^/stores/moderate$", || Route::StoreModerate);
 
+    // Change moderation status of several stores by moderator
+    router.add_route(r"^/stores/moderate/bulk$", || Route::StoresBulkModerate);
+
     // Check that you can change the moderation status
     router.add_route(r"^/stores/validate_change_moderation_status$", || {
         Route::StoreValidateChangeModerationStatus
@@ -223,6 +314,27 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::StoreModeration)
     });
 
+    router.add_route_with_params(r"^/stores/(\d+)/moderation_history$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<StoreId>().ok())
+            .map(Route::StoreModerationHistory)
+    });
+
+    router.add_route_with_params(r"^/stores/(\d+)/moderation_feedback$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<StoreId>().ok())
+            .map(Route::StoreModerationFeedback)
+    });
+
+    router.add_route_with_params(r"^/stores/(\d+)/moderation_transitions$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<StoreId>().ok())
+            .map(Route::StoreModerationTransitions)
+    });
+
     // Products Routes
     router.add_route(r"^/products$", || Route::Products);
 
@@ -291,6 +403,33 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::ProductValidateUpdate)
     });
 
+    // Products/:id/price_history route
+    router.add_route_with_params(r"^/products/(\d+)/price_history$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<ProductId>().ok())
+            .map(Route::ProductPriceHistory)
+    });
+
+    // Products/:id/scheduled_prices route
+    router.add_route_with_params(r"^/products/(\d+)/scheduled_prices$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<ProductId>().ok())
+            .map(Route::ProductScheduledPrices)
+    });
+
+    // Products/:id/scheduled_prices/:id route
+    router.add_route_with_params(r"^/products/(\d+)/scheduled_prices/(\d+)$", |params| {
+        let product_id = params.get(0)?.parse().ok().map(ProductId)?;
+        let scheduled_price_change_id = params.get(1)?.parse().ok()?;
+
+        Some(Route::ProductScheduledPrice {
+            product_id,
+            scheduled_price_change_id,
+        })
+    });
+
     // Base products routes
     router.add_route(r"^/base_products$", || Route::BaseProducts);
 
@@ -397,9 +536,15 @@ pub fn create_route_parser() -> RouteParser<Route> {
     // BaseProducts search filters count route
     router.add_route(r"^/base_products/search/filters/count$", || Route::BaseProductsSearchFiltersCount);
 
+    // BaseProducts search with all filters route
+    router.add_route(r"^/base_products/search/faceted$", || Route::BaseProductsSearchFaceted);
+
     // Change moderation status by moderator
     router.add_route(r"^/base_products/moderate$", || Route::BaseProductModerate);
 
+    // Change moderation status of several base products by moderator
+    router.add_route(r"^/base_products/moderate/bulk$", || Route::BaseProductsBulkModerate);
+
     // Check that you can change the moderation status
     router.add_route(r"^/base_products/validate_change_moderation_status$", || {
         Route::BaseProductValidateChangeModerationStatus
@@ -419,6 +564,27 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::BaseProductModeration)
     });
 
+    router.add_route_with_params(r"^/base_products/(\d+)/moderation_history$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<BaseProductId>().ok())
+            .map(Route::BaseProductModerationHistory)
+    });
+
+    router.add_route_with_params(r"^/base_products/(\d+)/moderation_feedback$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<BaseProductId>().ok())
+            .map(Route::BaseProductModerationFeedback)
+    });
+
+    router.add_route_with_params(r"^/base_products/(\d+)/moderation_transitions$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<BaseProductId>().ok())
+            .map(Route::BaseProductModerationTransitions)
+    });
+
     router.add_route_with_params(r"^/base_products/(\d+)/draft$", |params| {
         params
             .get(0)
@@ -443,12 +609,40 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::CustomAttribute)
     });
 
+    // Search synonyms Routes
+    router.add_route(r"^/search_synonyms$", || Route::SearchSynonyms);
+
+    // Search synonyms/:id route
+    router.add_route_with_params(r"^/search_synonyms/(\d+)$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(Route::SearchSynonym)
+    });
+
     // Coupons Routes
     router.add_route(r"^/coupons$", || Route::Coupons);
 
     // Generate code coupon
     router.add_route(r"^/coupons/generate_code$", || Route::CouponsGenerateCode);
 
+    // Batch of coupons with generated codes
+    router.add_route(r"^/coupons/batches$", || Route::CouponsBatches);
+
+    router.add_route_with_params(r"^/coupons/batches/([a-zA-Z0-9-]+)$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<Uuid>().ok())
+            .map(Route::CouponsBatch)
+    });
+
+    router.add_route_with_params(r"^/coupons/batches/([a-zA-Z0-9-]+)/csv$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<Uuid>().ok())
+            .map(Route::CouponsBatchCsv)
+    });
+
     // Coupons/:id route
     router.add_route_with_params(r"^/coupons/(\d+)$", |params| {
         params
@@ -505,6 +699,41 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::BaseProductsByCoupon)
     });
 
+    // Add category to coupon
+    router.add_route_with_params(r"^/coupons/(\d+)/categories/(\d+)$", |params| {
+        let coupon_id = params.get(0)?.parse().ok().map(CouponId)?;
+        let category_id = params.get(1)?.parse().ok().map(CategoryId)?;
+
+        Some(Route::CouponScopeCategories { coupon_id, category_id })
+    });
+
+    // Getting categories by coupon_id
+    router.add_route_with_params(r"^/coupons/(\d+)/categories$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<CouponId>().ok())
+            .map(Route::CategoriesByCoupon)
+    });
+
+    // Exclude base product from coupon
+    router.add_route_with_params(r"^/coupons/(\d+)/excluded_base_products/(\d+)$", |params| {
+        let coupon_id = params.get(0)?.parse().ok().map(CouponId)?;
+        let base_product_id = params.get(1)?.parse().ok().map(BaseProductId)?;
+
+        Some(Route::CouponExclusionBaseProducts {
+            coupon_id,
+            base_product_id,
+        })
+    });
+
+    // Getting excluded base_products by coupon_id
+    router.add_route_with_params(r"^/coupons/(\d+)/excluded_base_products$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<CouponId>().ok())
+            .map(Route::ExcludedBaseProductsByCoupon)
+    });
+
     // Attributes/:id route
     router.add_route_with_params(r"^/attributes/(\d+)$", |params| {
         params
@@ -589,6 +818,39 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(Route::ModeratorStoreComment)
     });
 
+    // Moderation queue Routes
+    router.add_route(r"^/moderation_queue/search$", || Route::ModerationQueueSearch);
+
+    // Moderation queue/:id/claim Route
+    router.add_route_with_params(r"^/moderation_queue/(\d+)/claim$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(Route::ModerationQueueItemClaim)
+    });
+
+    // Moderation queue/:id/release Route
+    router.add_route_with_params(r"^/moderation_queue/(\d+)/release$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(Route::ModerationQueueItemRelease)
+    });
+
+    // Decline reasons Routes
+    router.add_route(r"^/decline_reasons$", || Route::DeclineReasons);
+
+    // Decline reasons/:id Route
+    router.add_route_with_params(r"^/decline_reasons/(\d+)$", |params| {
+        params
+            .get(0)
+            .and_then(|string_id| string_id.parse::<i32>().ok())
+            .map(Route::DeclineReason)
+    });
+
+    // Decline reasons report Route
+    router.add_route(r"^/decline_reasons/report$", || Route::DeclineReasonsReport);
+
     // Moderator Store search
     router.add_route(r"^/stores/moderator_search$", || Route::ModeratorStoreSearch);
 
@@ -637,6 +899,7 @@ pub fn create_route_parser() -> RouteParser<Route> {
             .map(|id| Route::RoleById { id })
     });
     router.add_route(r"^/catalog$", || Route::Catalog);
+    router.add_route(r"^/catalog/feed$", || Route::CatalogFeed);
 
     router
 }
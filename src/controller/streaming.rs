@@ -0,0 +1,143 @@
+//! Routes with response body sent to the client chunk by chunk.
+//! They are served before `Application`, which buffers the whole response body.
+use std::io;
+use std::sync::Arc;
+
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use failure::Error as FailureError;
+use futures::sync::mpsc;
+use futures::{future, Future, Sink, Stream};
+use hyper::header::ContentType;
+use hyper::server::{Request, Response, Service as HyperService};
+use hyper::{self, Body, Chunk, Get, StatusCode};
+use r2d2::ManageConnection;
+use serde_json;
+use tokio_core::reactor::Handle;
+
+use stq_http::controller::Application;
+use stq_http::errors::{Codeable, ErrorMessageWrapper};
+use stq_types::StoreId;
+
+use super::context::{DynamicContext, StaticContext};
+use super::routes::Route;
+use errors::Error;
+use models::CatalogExportFormat;
+use repos::repo_factory::ReposFactory;
+use sentry_integration::log_and_capture_error;
+use services::catalogs::{CatalogExportStream, CatalogService};
+use services::Service;
+
+/// Application serving streamed routes itself and passing other requests to `Application`
+pub struct StreamingApplication<T, M, F>
+where
+    T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+    M: ManageConnection<Connection = T>,
+    F: ReposFactory<T>,
+{
+    app: Application<Error>,
+    static_context: StaticContext<T, M, F>,
+    /// Streamed response bodies are sent from the reactor, so waiting for slow clients takes no threads of cpu pool
+    handle: Arc<Handle>,
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > StreamingApplication<T, M, F>
+{
+    pub fn new(app: Application<Error>, static_context: StaticContext<T, M, F>, handle: Arc<Handle>) -> Self {
+        Self {
+            app,
+            static_context,
+            handle,
+        }
+    }
+
+    /// GET /stores/<store_id>/catalog/export?format=<csv|jsonl>
+    fn export_store_catalog(&self, req: Request, store_id: StoreId) -> Box<Future<Item = Response, Error = hyper::Error>> {
+        let format = parse_query!(req.query().unwrap_or_default(), "format" => CatalogExportFormat).unwrap_or_default();
+        let dynamic_context = match DynamicContext::from_request(&req) {
+            Ok(v) => v,
+            Err(e) => {
+                return Box::new(future::ok(error_response(e)));
+            }
+        };
+
+        let handle = self.handle.clone();
+        let service = Service::new(self.static_context.clone(), dynamic_context);
+
+        Box::new(service.export_store_catalog(store_id, format).then(move |result| {
+            Ok(match result {
+                Ok(stream) => streamed_response(&handle, stream, format.content_type()),
+                Err(e) => error_response(e),
+            })
+        }))
+    }
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > HyperService for StreamingApplication<T, M, F>
+{
+    type Request = Request;
+    type Response = Response;
+    type Error = hyper::Error;
+    type Future = Box<Future<Item = Response, Error = hyper::Error>>;
+
+    fn call(&self, req: Request) -> Self::Future {
+        let is_get = *req.method() == Get;
+        match self.static_context.route_parser.test(req.path()) {
+            // GET /stores/<store_id>/catalog/export
+            Some(Route::StoreCatalogExport(store_id)) if is_get => self.export_store_catalog(req, store_id),
+            _ => Box::new(self.app.call(req)),
+        }
+    }
+}
+
+/// Response with body sent to the client as `stream` yields chunks,
+/// the body is cut short if the stream fails
+fn streamed_response(handle: &Handle, stream: CatalogExportStream, content_type: &str) -> Response {
+    let (sender, body) = Body::pair();
+    let chunks = stream.then(|chunk| {
+        let chunk = chunk.map(Chunk::from).map_err(|e| {
+            log_and_capture_error(&e);
+            hyper::Error::Io(io::Error::new(io::ErrorKind::Other, e.to_string()))
+        });
+        Ok::<_, mpsc::SendError<Result<Chunk, hyper::Error>>>(chunk)
+    });
+    handle.spawn(sender.send_all(chunks).map(|_| ()).map_err(|_| {
+        warn!("Client has gone away before streamed response was sent.");
+    }));
+
+    let mut response = Response::new().with_body(body);
+    if let Ok(mime) = content_type.parse() {
+        response.headers_mut().set(ContentType(mime));
+    }
+
+    response
+}
+
+/// Response with error serialized the same way `Application` does it
+fn error_response(err: FailureError) -> Response {
+    let wrapper = ErrorMessageWrapper::<Error>::from(&err);
+    if wrapper.inner.code == 500 {
+        log_and_capture_error(&err);
+    }
+
+    let status = err
+        .iter_chain()
+        .filter_map(|cause| cause.downcast_ref::<Error>())
+        .next()
+        .map(|e| e.code())
+        .unwrap_or(StatusCode::InternalServerError);
+
+    Response::new()
+        .with_status(status)
+        .with_header(ContentType::json())
+        .with_body(serde_json::to_string(&wrapper.inner).unwrap_or_default())
+}
@@ -0,0 +1,175 @@
+//! Text analysis of search indices: language analyzers of translations, edge n-gram autocompletion
+//! and queries built on top of them
+use serde_json;
+
+use models::{AutoCompleteSuggestion, Hit};
+
+/// Languages of translations analyzed with stemming and stop words, paired with names of elastic built-in analyzers
+pub const LANGUAGE_ANALYZERS: &[(&str, &str)] = &[
+    ("en", "english"),
+    ("ru", "russian"),
+    ("de", "german"),
+    ("fr", "french"),
+    ("es", "spanish"),
+    ("pt", "portuguese"),
+    ("it", "italian"),
+    ("zh", "cjk"),
+    ("ja", "cjk"),
+    ("ko", "cjk"),
+];
+
+/// Analyzer splitting words into prefixes for search-as-you-type
+pub const AUTOCOMPLETE_ANALYZER: &str = "autocomplete";
+
+/// Analyzer of text typed by user, it must not be split into prefixes
+pub const AUTOCOMPLETE_SEARCH_ANALYZER: &str = "autocomplete_search";
+
+/// Synonyms are matched with lower score than the text typed by user
+const SYNONYM_BOOST: f64 = 0.8;
+
+/// Analysis settings of products and stores indices
+pub fn index_analysis_settings() -> serde_json::Value {
+    json!({
+        "filter": {
+            "autocomplete_edge_ngram": {
+                "type": "edge_ngram",
+                "min_gram": 1,
+                "max_gram": 20
+            }
+        },
+        "analyzer": {
+            AUTOCOMPLETE_ANALYZER: {
+                "type": "custom",
+                "tokenizer": "standard",
+                "filter": ["lowercase", "asciifolding", "autocomplete_edge_ngram"]
+            },
+            AUTOCOMPLETE_SEARCH_ANALYZER: {
+                "type": "custom",
+                "tokenizer": "standard",
+                "filter": ["lowercase", "asciifolding"]
+            }
+        }
+    })
+}
+
+/// Mapping of nested translations field, text is indexed by each language analyzer and for autocompletion
+pub fn translation_field_mapping() -> serde_json::Value {
+    let mut fields = serde_json::Map::<String, serde_json::Value>::new();
+    for &(_, analyzer) in LANGUAGE_ANALYZERS {
+        fields.insert(analyzer.to_string(), json!({ "type": "text", "analyzer": analyzer }));
+    }
+    fields.insert(
+        AUTOCOMPLETE_ANALYZER.to_string(),
+        json!({
+            "type": "text",
+            "analyzer": AUTOCOMPLETE_ANALYZER,
+            "search_analyzer": AUTOCOMPLETE_SEARCH_ANALYZER
+        }),
+    );
+
+    json!({
+        "type": "nested",
+        "properties": {
+            "lang": { "type": "keyword" },
+            "text": { "type": "text", "fields": fields }
+        }
+    })
+}
+
+/// Query over nested translations field. Each translation is matched by analyzer of its language,
+/// `synonyms` are variants of `text` taken from synonyms dictionary
+pub fn translated_text_query(path: &str, text: &str, synonyms: &[String], fuzzy: bool) -> serde_json::Value {
+    let mut should = translated_text_clauses(path, text, 1.0, fuzzy);
+    for synonym in synonyms {
+        should.extend(translated_text_clauses(path, synonym, SYNONYM_BOOST, fuzzy));
+    }
+
+    json!({
+        "nested": {
+            "path": path,
+            "score_mode": "max",
+            "query": {
+                "bool": { "should": should }
+            }
+        }
+    })
+}
+
+fn translated_text_clauses(path: &str, text: &str, boost: f64, fuzzy: bool) -> Vec<serde_json::Value> {
+    let mut plain_match = json!({ "query": text, "boost": boost });
+    if fuzzy {
+        plain_match["fuzziness"] = json!("AUTO");
+        plain_match["prefix_length"] = json!(1);
+    }
+
+    let mut clauses = vec![json!({ "match": { format!("{}.text", path): plain_match } })];
+    for &(lang, analyzer) in LANGUAGE_ANALYZERS {
+        clauses.push(json!({
+            "bool": {
+                "filter": { "term": { format!("{}.lang", path): lang } },
+                "must": { "match": { format!("{}.text.{}", path, analyzer): { "query": text, "boost": boost } } }
+            }
+        }));
+    }
+    clauses
+}
+
+/// Search-as-you-type query over nested translations field, best matching translation is returned
+/// in inner hits with highlighted fragments. Autocomplete subfields exist only in indices created
+/// with `translation_field_mapping`, indices created before it have to be rebuilt with `reindex` binary
+pub fn autocomplete_query(path: &str, text: &str) -> serde_json::Value {
+    let field = format!("{}.text.{}", path, AUTOCOMPLETE_ANALYZER);
+    json!({
+        "nested": {
+            "path": path,
+            "score_mode": "max",
+            "query": {
+                "match": {
+                    field.clone(): {
+                        "query": text,
+                        "operator": "and",
+                        "fuzziness": "AUTO",
+                        "prefix_length": 1
+                    }
+                }
+            },
+            "inner_hits": {
+                "size": 1,
+                "highlight": {
+                    "fields": {
+                        field: { "number_of_fragments": 0 }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Suggestion of search hit found by `autocomplete_query` over `path`
+pub fn autocomplete_suggestion<T>(path: &str, hit: &Hit<T>) -> Option<AutoCompleteSuggestion> {
+    let inner_hit = hit.inner_hits().as_ref()?.get(path)?["hits"]["hits"].get(0)?.clone();
+    let text = inner_hit["_source"]["text"].as_str()?.to_string();
+    let highlighted = inner_hit["highlight"][format!("{}.text.{}", path, AUTOCOMPLETE_ANALYZER)][0]
+        .as_str()
+        .map(|highlighted| highlighted.to_string())
+        .unwrap_or_else(|| text.clone());
+
+    Some(AutoCompleteSuggestion {
+        text,
+        highlighted,
+        score: hit.score().unwrap_or_default(),
+    })
+}
+
+/// Ranked suggestions without duplicated texts
+pub fn autocomplete_suggestions<T, I: Iterator<Item = Hit<T>>>(path: &str, hits: I) -> Vec<AutoCompleteSuggestion> {
+    let mut suggestions: Vec<AutoCompleteSuggestion> = vec![];
+    for hit in hits {
+        if let Some(suggestion) = autocomplete_suggestion(path, &hit) {
+            if !suggestions.iter().any(|s| s.text == suggestion.text) {
+                suggestions.push(suggestion);
+            }
+        }
+    }
+    suggestions
+}
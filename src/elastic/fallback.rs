@@ -0,0 +1,308 @@
+//! Searches falling back to another backend when request to Elastic fails, so that search keeps working
+//! with degraded relevance while Elastic is unavailable
+use std::fmt::Display;
+
+use futures::Future;
+
+use stq_types::CategoryId;
+
+use super::{ProductsElastic, StoresElastic};
+use models::*;
+use repos::types::RepoFuture;
+
+pub struct ProductsSearchWithFallback<E, F> {
+    pub elastic: E,
+    pub fallback: F,
+}
+
+pub struct StoresSearchWithFallback<E, F> {
+    pub elastic: E,
+    pub fallback: F,
+}
+
+impl<E, F> ProductsSearchWithFallback<E, F> {
+    pub fn new(elastic: E, fallback: F) -> Self {
+        Self { elastic, fallback }
+    }
+}
+
+impl<E, F> StoresSearchWithFallback<E, F> {
+    pub fn new(elastic: E, fallback: F) -> Self {
+        Self { elastic, fallback }
+    }
+}
+
+fn log_fallback<T: Display>(error: &T) {
+    warn!("Elastic search request failed, falling back to search in db. Error: {}", error);
+}
+
+impl<E, F> ProductsElastic for ProductsSearchWithFallback<E, F>
+where
+    E: ProductsElastic,
+    F: ProductsElastic + Clone + Send + 'static,
+{
+    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.auto_complete(name.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.auto_complete(name, count, offset)
+        }))
+    }
+
+    fn search_by_name(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.search_by_name(prod.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.search_by_name(prod, count, offset)
+        }))
+    }
+
+    fn search_faceted(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<ElasticProductsFacetedSearch> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.search_faceted(prod.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.search_faceted(prod, count, offset)
+        }))
+    }
+
+    fn search_most_viewed(&self, prod: MostViewedProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.search_most_viewed(prod.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.search_most_viewed(prod, count, offset)
+        }))
+    }
+
+    fn search_most_discount(&self, prod: MostDiscountProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.search_most_discount(prod.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.search_most_discount(prod, count, offset)
+        }))
+    }
+
+    fn aggregate_categories(&self, name: String) -> RepoFuture<Vec<CategoryId>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.aggregate_categories(name.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.aggregate_categories(name)
+        }))
+    }
+
+    fn aggregate_price(&self, prod: SearchProductsByName) -> RepoFuture<RangeFilter> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.aggregate_price(prod.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.aggregate_price(prod)
+        }))
+    }
+
+    fn aggregate_attributes(&self, prod: SearchProductsByName) -> RepoFuture<Vec<AttributeFilter>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.aggregate_attributes(prod.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.aggregate_attributes(prod)
+        }))
+    }
+
+    fn count(&self, prod: SearchProductsByName) -> RepoFuture<i32> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.count(prod.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.count(prod)
+        }))
+    }
+}
+
+impl<E, F> StoresElastic for StoresSearchWithFallback<E, F>
+where
+    E: StoresElastic,
+    F: StoresElastic + Clone + Send + 'static,
+{
+    fn find_by_name(&self, search_store: SearchStore, count: i32, offset: i32) -> RepoFuture<Vec<ElasticStore>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.find_by_name(search_store.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.find_by_name(search_store, count, offset)
+        }))
+    }
+
+    fn search_count(&self, search_store: SearchStore) -> RepoFuture<i32> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.search_count(search_store.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.search_count(search_store)
+        }))
+    }
+
+    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<CountryLocalities>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.aggregate_countries(search_store.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.aggregate_countries(search_store)
+        }))
+    }
+
+    fn aggregate_categories(&self, search_store: SearchStore) -> RepoFuture<Vec<CategoryId>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.aggregate_categories(search_store.clone()).or_else(move |e| {
+            log_fallback(&e);
+            fallback.aggregate_categories(search_store)
+        }))
+    }
+
+    fn auto_complete(&self, name: String, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+        let fallback = self.fallback.clone();
+        Box::new(self.elastic.auto_complete(name.clone(), count, offset).or_else(move |e| {
+            log_fallback(&e);
+            fallback.auto_complete(name, count, offset)
+        }))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use futures::future;
+
+    use errors::Error;
+    use failure::Fail;
+
+    use elastic::fallback::*;
+
+    /// Search backend answering with `count` in counts and categories, or failing if there is no `count`
+    #[derive(Clone)]
+    struct SearchMock {
+        count: Option<i32>,
+    }
+
+    impl SearchMock {
+        fn respond<T: Send + 'static>(&self, value: T) -> RepoFuture<T> {
+            match self.count {
+                Some(_) => Box::new(future::ok(value)),
+                None => Box::new(future::err(
+                    format_err!("Search backend is unavailable").context(Error::ElasticSearch).into(),
+                )),
+            }
+        }
+
+        fn categories(&self) -> RepoFuture<Vec<CategoryId>> {
+            self.respond(self.count.map(|count| vec![CategoryId(count)]).unwrap_or_default())
+        }
+    }
+
+    impl ProductsElastic for SearchMock {
+        fn auto_complete(&self, _name: AutoCompleteProductName, _count: i32, _offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+            self.respond(vec![])
+        }
+
+        fn search_by_name(&self, _prod: SearchProductsByName, _count: i32, _offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+            self.respond(vec![])
+        }
+
+        fn search_faceted(&self, _prod: SearchProductsByName, _count: i32, _offset: i32) -> RepoFuture<ElasticProductsFacetedSearch> {
+            self.respond(ElasticProductsFacetedSearch::default())
+        }
+
+        fn search_most_viewed(&self, _prod: MostViewedProducts, _count: i32, _offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+            self.respond(vec![])
+        }
+
+        fn search_most_discount(&self, _prod: MostDiscountProducts, _count: i32, _offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+            self.respond(vec![])
+        }
+
+        fn aggregate_categories(&self, _name: String) -> RepoFuture<Vec<CategoryId>> {
+            self.categories()
+        }
+
+        fn aggregate_price(&self, _prod: SearchProductsByName) -> RepoFuture<RangeFilter> {
+            self.respond(RangeFilter::default())
+        }
+
+        fn aggregate_attributes(&self, _prod: SearchProductsByName) -> RepoFuture<Vec<AttributeFilter>> {
+            self.respond(vec![])
+        }
+
+        fn count(&self, _prod: SearchProductsByName) -> RepoFuture<i32> {
+            self.respond(self.count.unwrap_or_default())
+        }
+    }
+
+    impl StoresElastic for SearchMock {
+        fn find_by_name(&self, _search_store: SearchStore, _count: i32, _offset: i32) -> RepoFuture<Vec<ElasticStore>> {
+            self.respond(vec![])
+        }
+
+        fn search_count(&self, _search_store: SearchStore) -> RepoFuture<i32> {
+            self.respond(self.count.unwrap_or_default())
+        }
+
+        fn aggregate_countries(&self, _search_store: SearchStore) -> RepoFuture<Vec<CountryLocalities>> {
+            self.respond(vec![])
+        }
+
+        fn aggregate_categories(&self, _search_store: SearchStore) -> RepoFuture<Vec<CategoryId>> {
+            self.categories()
+        }
+
+        fn auto_complete(&self, _name: String, _count: i32, _offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+            self.respond(vec![])
+        }
+    }
+
+    fn products_search(elastic: Option<i32>, fallback: Option<i32>) -> ProductsSearchWithFallback<SearchMock, SearchMock> {
+        ProductsSearchWithFallback::new(SearchMock { count: elastic }, SearchMock { count: fallback })
+    }
+
+    fn stores_search(elastic: Option<i32>, fallback: Option<i32>) -> StoresSearchWithFallback<SearchMock, SearchMock> {
+        StoresSearchWithFallback::new(SearchMock { count: elastic }, SearchMock { count: fallback })
+    }
+
+    fn search_store() -> SearchStore {
+        SearchStore {
+            name: "store".to_string(),
+            options: None,
+            name_synonyms: vec![],
+        }
+    }
+
+    #[test]
+    fn test_products_search_passes_elastic_result() {
+        let search = products_search(Some(1), Some(2));
+        assert_eq!(search.count(SearchProductsByName::default()).wait().unwrap(), 1);
+        assert_eq!(
+            search.aggregate_categories("jacket".to_string()).wait().unwrap(),
+            vec![CategoryId(1)]
+        );
+    }
+
+    #[test]
+    fn test_products_search_falls_back_on_elastic_error() {
+        let search = products_search(None, Some(2));
+        assert_eq!(search.count(SearchProductsByName::default()).wait().unwrap(), 2);
+        assert_eq!(
+            search.aggregate_categories("jacket".to_string()).wait().unwrap(),
+            vec![CategoryId(2)]
+        );
+
+        let search = products_search(None, None);
+        assert!(search.count(SearchProductsByName::default()).wait().is_err());
+    }
+
+    #[test]
+    fn test_stores_search_passes_elastic_result() {
+        let search = stores_search(Some(1), Some(2));
+        assert_eq!(search.search_count(search_store()).wait().unwrap(), 1);
+        assert_eq!(search.aggregate_categories(search_store()).wait().unwrap(), vec![CategoryId(1)]);
+    }
+
+    #[test]
+    fn test_stores_search_falls_back_on_elastic_error() {
+        let search = stores_search(None, Some(2));
+        assert_eq!(search.search_count(search_store()).wait().unwrap(), 2);
+        assert_eq!(search.aggregate_categories(search_store()).wait().unwrap(), vec![CategoryId(2)]);
+
+        let search = stores_search(None, None);
+        assert!(search.search_count(search_store()).wait().is_err());
+    }
+}
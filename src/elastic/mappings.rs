@@ -0,0 +1,89 @@
+//! Settings and mappings of products and stores indices, indices are created with them by reindexing
+use serde_json;
+
+use elastic::analysis::{index_analysis_settings, translation_field_mapping};
+use models::ElasticIndex;
+
+/// Mapping type of documents, indices hold documents of single type
+pub const DOCUMENT_TYPE: &str = "_doc";
+
+/// Body of create index request
+pub fn index_definition(index: &ElasticIndex) -> serde_json::Value {
+    let properties = match *index {
+        ElasticIndex::Product => products_properties(),
+        ElasticIndex::Store => stores_properties(),
+    };
+
+    json!({
+        "settings": {
+            "analysis": index_analysis_settings()
+        },
+        "mappings": {
+            DOCUMENT_TYPE: {
+                "dynamic": "strict",
+                "properties": properties
+            }
+        }
+    })
+}
+
+fn products_properties() -> serde_json::Value {
+    json!({
+        "id": { "type": "integer" },
+        "store_id": { "type": "integer" },
+        "name": translation_field_mapping(),
+        "short_description": translation_field_mapping(),
+        "long_description": translation_field_mapping(),
+        "category_id": { "type": "integer" },
+        "views": { "type": "integer" },
+        "rating": { "type": "double" },
+        "status": { "type": "keyword" },
+        "store_status": { "type": "keyword" },
+        "created_at": { "type": "date", "format": "epoch_millis||strict_date_optional_time" },
+        "store_rating": { "type": "double" },
+        "variants": {
+            "type": "nested",
+            "properties": {
+                "prod_id": { "type": "integer" },
+                "discount": { "type": "double" },
+                "price": { "type": "double" },
+                "currency": { "type": "keyword" },
+                "stock_status": { "type": "keyword" },
+                "attrs": {
+                    "type": "nested",
+                    "properties": {
+                        "attr_id": { "type": "integer" },
+                        "str_val": { "type": "keyword" },
+                        "float_val": { "type": "double" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn stores_properties() -> serde_json::Value {
+    json!({
+        "id": { "type": "integer" },
+        "user_id": { "type": "integer" },
+        "name": translation_field_mapping(),
+        "short_description": translation_field_mapping(),
+        "rating": { "type": "double" },
+        "country": {
+            "type": "text",
+            "fields": {
+                "keyword": { "type": "keyword" }
+            }
+        },
+        "locality": { "type": "keyword" },
+        "location": { "type": "geo_point" },
+        "status": { "type": "keyword" },
+        "product_categories": {
+            "type": "nested",
+            "properties": {
+                "category_id": { "type": "integer" },
+                "count": { "type": "integer" }
+            }
+        }
+    })
+}
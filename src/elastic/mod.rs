@@ -1,7 +1,12 @@
 //! Elastic search modules
+pub mod analysis;
+pub mod fallback;
+pub mod mappings;
+pub mod postgres;
 pub mod products;
 pub mod stores;
 
+pub use self::fallback::*;
 pub use self::products::*;
 pub use self::stores::*;
 
@@ -0,0 +1,170 @@
+//! Search backend over Postgres full text search, used when Elastic is not configured or unavailable.
+//! Base products and stores are matched by `search_vector` columns kept by triggers, then filtered,
+//! sorted and aggregated in memory among at most `postgres_candidates_limit` best matching rows
+pub mod products;
+pub mod stores;
+
+pub use self::products::*;
+pub use self::stores::*;
+
+use std::fmt::Debug;
+
+use failure::Error as FailureError;
+use failure::Fail;
+use futures_cpupool::CpuPool;
+use r2d2::{ManageConnection, Pool, PooledConnection};
+use serde_json;
+
+use errors::Error;
+use models::AutoCompleteSuggestion;
+use repos::types::RepoFuture;
+
+/// Text search configuration of search vectors, translations of all languages are indexed without stemming
+pub const TEXT_SEARCH_CONFIG: &str = "simple";
+
+/// Runs `f` on connection from `db_pool` in `cpu_pool`
+pub fn spawn_on_pool<M, R, Func>(db_pool: &Pool<M>, cpu_pool: &CpuPool, f: Func) -> RepoFuture<R>
+where
+    M: ManageConnection,
+    Func: FnOnce(PooledConnection<M>) -> Result<R, FailureError> + Send + 'static,
+    R: Send + 'static,
+{
+    let db_pool = db_pool.clone();
+    Box::new(cpu_pool.spawn_fn(move || db_pool.get().map_err(|e| e.context(Error::Connection).into()).and_then(f)))
+}
+
+pub fn log_search_req<T: Debug>(item: &T) {
+    debug!("Searching in db {:?}.", item);
+}
+
+/// Lowercase words of `text`, punctuation is dropped
+pub fn query_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Argument of `to_tsquery` matching documents having all words of `text` or of one of `synonyms` as prefixes.
+/// With `names_only` words are matched in names only, that is in lexemes of weight `A`
+pub fn text_search_query(text: &str, synonyms: &[String], names_only: bool) -> Option<String> {
+    let weight = if names_only { "A" } else { "" };
+    let alternatives = Some(text)
+        .into_iter()
+        .chain(synonyms.iter().map(|synonym| synonym.as_str()))
+        .map(query_words)
+        .filter(|words| !words.is_empty())
+        .map(|words| {
+            let lexemes = words
+                .into_iter()
+                .map(|word| format!("{}:*{}", word, weight))
+                .collect::<Vec<String>>();
+            format!("({})", lexemes.join(" & "))
+        })
+        .collect::<Vec<String>>();
+
+    if alternatives.is_empty() {
+        None
+    } else {
+        Some(alternatives.join(" | "))
+    }
+}
+
+/// Suggestion of the first of `translations` having all words of `text` as prefixes of its words
+pub fn autocomplete_suggestion(translations: &serde_json::Value, text: &str, score: f32) -> Option<AutoCompleteSuggestion> {
+    let words = query_words(text);
+    if words.is_empty() {
+        return None;
+    }
+
+    translations
+        .as_array()?
+        .iter()
+        .filter_map(|translation| translation["text"].as_str())
+        .filter_map(|translation| {
+            highlight_prefixes(translation, &words).map(|highlighted| AutoCompleteSuggestion {
+                text: translation.to_string(),
+                highlighted,
+                score,
+            })
+        })
+        .next()
+}
+
+/// Adds suggestion unless suggestion with the same text is already added
+pub fn push_suggestion(suggestions: &mut Vec<AutoCompleteSuggestion>, suggestion: AutoCompleteSuggestion) {
+    if !suggestions.iter().any(|s| s.text == suggestion.text) {
+        suggestions.push(suggestion);
+    }
+}
+
+/// `text` with words starting with any of `words` wrapped in `<em>` tags, if each of `words` starts some word
+fn highlight_prefixes(text: &str, words: &[String]) -> Option<String> {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut matched = vec![false; words.len()];
+    let mut token = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            token.push(c);
+        } else {
+            push_token(&token, words, &mut matched, &mut highlighted);
+            token.clear();
+            highlighted.push(c);
+        }
+    }
+    push_token(&token, words, &mut matched, &mut highlighted);
+
+    if matched.into_iter().all(|matched| matched) {
+        Some(highlighted)
+    } else {
+        None
+    }
+}
+
+fn push_token(token: &str, words: &[String], matched: &mut [bool], highlighted: &mut String) {
+    let lowercase = token.to_lowercase();
+    let mut is_match = false;
+    for (word, matched) in words.iter().zip(matched.iter_mut()) {
+        if !lowercase.is_empty() && lowercase.starts_with(word.as_str()) {
+            *matched = true;
+            is_match = true;
+        }
+    }
+
+    if is_match {
+        highlighted.push_str("<em>");
+        highlighted.push_str(token);
+        highlighted.push_str("</em>");
+    } else {
+        highlighted.push_str(token);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use elastic::postgres::*;
+
+    #[test]
+    fn test_text_search_query() {
+        let synonyms = vec!["cell phone".to_string()];
+        assert_eq!(
+            text_search_query("Smart-Phone!", &synonyms, false),
+            Some("(smart:* & phone:*) | (cell:* & phone:*)".to_string())
+        );
+        assert_eq!(text_search_query("shoes", &[], true), Some("(shoes:*A)".to_string()));
+        assert_eq!(text_search_query(" ,.", &[], false), None);
+    }
+
+    #[test]
+    fn test_autocomplete_suggestion() {
+        let translations = json!([{"lang": "en", "text": "Red leather bag"}, {"lang": "ru", "text": "Красная сумка"}]);
+        let suggestion = autocomplete_suggestion(&translations, "le BA", 1.0).unwrap();
+        assert_eq!(suggestion.text, "Red leather bag");
+        assert_eq!(suggestion.highlighted, "Red <em>leather</em> <em>bag</em>");
+
+        let suggestion = autocomplete_suggestion(&translations, "сум", 1.0).unwrap();
+        assert_eq!(suggestion.highlighted, "Красная <em>сумка</em>");
+
+        assert!(autocomplete_suggestion(&translations, "red shoes", 1.0).is_none());
+    }
+}
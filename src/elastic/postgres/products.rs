@@ -0,0 +1,1027 @@
+//! Products search over Postgres full text search, implements the same trait as Elastic products search
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::SystemTime;
+
+use diesel::connection::AnsiTransactionManager;
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float, VarChar};
+use diesel::Connection;
+use failure::Error as FailureError;
+use failure::Fail;
+use futures::Future;
+use futures_cpupool::CpuPool;
+use r2d2::{ManageConnection, Pool};
+
+use stq_static_resources::{Currency, ModerationStatus};
+use stq_types::{CategoryId, ExchangeRate, ProductId, StoreId};
+
+use super::{autocomplete_suggestion, log_search_req, push_suggestion, spawn_on_pool, text_search_query, TEXT_SEARCH_CONFIG};
+use config::SearchRanking;
+use elastic::ProductsElastic;
+use errors::Error;
+use models::*;
+use repos::types::{RepoFuture, RepoResult};
+use schema::attributes::dsl as Attributes;
+use schema::base_products;
+use schema::base_products::dsl as BaseProducts;
+use schema::prod_attr_values::dsl as ProdAttrs;
+use schema::products::dsl as Products;
+use schema::stores::dsl as Stores;
+
+const MILLIS_IN_DAY: f64 = 86_400_000.0;
+
+/// Products search in Postgres, responsible for handling products when Elastic is not used
+pub struct ProductsPostgresImpl<M: ManageConnection> {
+    pub db_pool: Pool<M>,
+    pub cpu_pool: CpuPool,
+    pub candidates_limit: i64,
+    pub ranking: SearchRanking,
+}
+
+/// Active base products filters applied in db
+#[derive(Clone, Debug, Default)]
+struct CandidatesFilter {
+    text_query: Option<String>,
+    store_id: Option<StoreId>,
+    categories_ids: Option<Vec<CategoryId>>,
+    status: Option<ModerationStatus>,
+    store_status: Option<ModerationStatus>,
+    sort_by: Option<ProductsSorting>,
+}
+
+impl CandidatesFilter {
+    fn new(text: &str, synonyms: &[String], options: &Option<ProductsSearchOptions>) -> Self {
+        let options = options.clone().unwrap_or_default();
+        Self {
+            text_query: text_search_query(text, synonyms, false),
+            store_id: options.store_id,
+            categories_ids: options.categories_ids,
+            status: options.status,
+            store_status: options.status,
+            sort_by: options.sort_by,
+        }
+    }
+}
+
+/// Base product loaded from db with text rank of it
+struct Candidate {
+    document: ProductDocument,
+    text_rank: f32,
+}
+
+/// Candidate matching search with its variants matching search
+struct SearchMatch<'a> {
+    candidate: &'a Candidate,
+    matched_variants_ids: Vec<ProductId>,
+    score: f64,
+}
+
+impl<'a> SearchMatch<'a> {
+    fn to_elastic_product(&self) -> ElasticProduct {
+        let mut product = ElasticProduct::from(self.candidate.document.clone());
+        product.matched_variants_ids = Some(self.matched_variants_ids.clone());
+        product
+    }
+}
+
+impl<M: ManageConnection> Clone for ProductsPostgresImpl<M> {
+    fn clone(&self) -> Self {
+        Self {
+            db_pool: self.db_pool.clone(),
+            cpu_pool: self.cpu_pool.clone(),
+            candidates_limit: self.candidates_limit,
+            ranking: self.ranking.clone(),
+        }
+    }
+}
+
+impl<M> ProductsPostgresImpl<M>
+where
+    M: ManageConnection,
+    M::Connection: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    pub fn new(db_pool: Pool<M>, cpu_pool: CpuPool, candidates_limit: i64, ranking: SearchRanking) -> Self {
+        Self {
+            db_pool,
+            cpu_pool,
+            candidates_limit,
+            ranking,
+        }
+    }
+
+    fn spawn<R, Func>(&self, f: Func) -> RepoFuture<R>
+    where
+        Func: FnOnce(&M::Connection, i64) -> RepoResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let candidates_limit = self.candidates_limit;
+        spawn_on_pool(&self.db_pool, &self.cpu_pool, move |conn| f(&*conn, candidates_limit))
+    }
+}
+
+impl<M> ProductsElastic for ProductsPostgresImpl<M>
+where
+    M: ManageConnection,
+    M::Connection: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    /// Find specific products by name limited by `count` parameters
+    fn search_by_name(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        log_search_req(&prod);
+        let ranking = self.ranking.clone();
+        let filter = CandidatesFilter::new(&prod.name, &prod.name_synonyms, &prod.options);
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let matches = search_matches(&candidates, &prod.options, &ranking);
+                Ok(page(&matches, count, offset))
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Search product by name in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Find products by name limited by `count` parameters together with total count, price range, categories and attributes
+    fn search_faceted(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<ElasticProductsFacetedSearch> {
+        log_search_req(&prod);
+        let ranking = self.ranking.clone();
+        let filter = CandidatesFilter {
+            categories_ids: None,
+            ..CandidatesFilter::new(&prod.name, &prod.name_synonyms, &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let category_ids = categories_by_count(candidates.iter().map(|c| c.document.category_id));
+                let scope = facets_scope(&candidates, &prod.options);
+                let matches = search_matches(&candidates, &prod.options, &ranking);
+                let currency_map = prod.options.as_ref().and_then(|o| o.currency_map.clone());
+
+                Ok(ElasticProductsFacetedSearch {
+                    products: page(&matches, count, offset),
+                    total_count: matches.len() as u32,
+                    price_filter: price_range(&scope, &currency_map),
+                    category_ids,
+                    attribute_filters: attribute_filters(&scope, &candidates, &prod.options, &ranking),
+                })
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Faceted search product by name in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Find product by views limited by `count` and `offset` parameters
+    fn search_most_viewed(&self, prod: MostViewedProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        log_search_req(&prod);
+        let ranking = self.ranking.clone();
+        let filter = CandidatesFilter {
+            sort_by: Some(ProductsSorting::Views),
+            ..CandidatesFilter::new("", &[], &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let mut matches = search_matches(&candidates, &prod.options, &ranking);
+                sort_matches(&mut matches, Some(&ProductsSorting::Views));
+                Ok(page(&matches, count, offset))
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Search most viewed product in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Find product by dicount pattern limited by `count` and `offset` parameters
+    fn search_most_discount(&self, prod: MostDiscountProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        log_search_req(&prod);
+        let ranking = self.ranking.clone();
+        let in_stock_only = is_in_stock_only(&prod.options);
+        let filter = CandidatesFilter {
+            sort_by: None,
+            ..CandidatesFilter::new("", &[], &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let mut matches = candidates
+                    .iter()
+                    .filter_map(|candidate| {
+                        match_variants(candidate, &ranking, |variant| {
+                            variant.discount.map(|discount| discount > 0.0).unwrap_or(false) && (!in_stock_only || is_in_stock(variant))
+                        })
+                    })
+                    .collect::<Vec<SearchMatch>>();
+                sort_matches(&mut matches, Some(&ProductsSorting::Discount));
+                Ok(page(&matches, count, offset))
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Search most discount product in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+        log_search_req(&name);
+        let filter = CandidatesFilter {
+            text_query: text_search_query(&name.name, &[], true),
+            store_id: name.store_id,
+            status: name.status,
+            ..CandidatesFilter::default()
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                if filter.text_query.is_none() {
+                    return Ok(vec![]);
+                }
+
+                let mut suggestions = vec![];
+                for (base_product, text_rank) in load_base_products(conn, &filter, limit)? {
+                    if let Some(suggestion) = autocomplete_suggestion(&base_product.name, &name.name, text_rank) {
+                        push_suggestion(&mut suggestions, suggestion);
+                    }
+                }
+                Ok(suggestions
+                    .into_iter()
+                    .skip(offset.max(0) as usize)
+                    .take(count.max(0) as usize)
+                    .collect())
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Auto complete product name in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Find all categories ids where prod exist
+    fn aggregate_categories(&self, name: String) -> RepoFuture<Vec<CategoryId>> {
+        log_search_req(&name);
+        let filter = CandidatesFilter {
+            text_query: text_search_query(&name, &[], false),
+            status: Some(ModerationStatus::Published),
+            store_status: Some(ModerationStatus::Published),
+            ..CandidatesFilter::default()
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let base_products = load_base_products(conn, &filter, limit)?;
+                Ok(categories_by_count(
+                    base_products.into_iter().map(|(base_product, _)| base_product.category_id),
+                ))
+            })
+            .map_err(move |e| {
+                e.context(format!("Aggregate categories for products in db error occurred. Name: {:?}", name))
+                    .into()
+            }),
+        )
+    }
+
+    fn aggregate_price(&self, prod: SearchProductsByName) -> RepoFuture<RangeFilter> {
+        log_search_req(&prod);
+        let filter = CandidatesFilter {
+            store_id: None,
+            sort_by: None,
+            ..CandidatesFilter::new(&prod.name, &prod.name_synonyms, &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let scope = facets_scope(&candidates, &prod.options);
+                let currency_map = prod.options.as_ref().and_then(|o| o.currency_map.clone());
+                Ok(price_range(&scope, &currency_map))
+            })
+            .map_err(|e| e.context("Aggregate price in db error occurred.").into()),
+        )
+    }
+
+    /// Find attribute values with count of products having them
+    fn aggregate_attributes(&self, prod: SearchProductsByName) -> RepoFuture<Vec<AttributeFilter>> {
+        log_search_req(&prod);
+        let ranking = self.ranking.clone();
+        let filter = CandidatesFilter {
+            categories_ids: None,
+            sort_by: None,
+            ..CandidatesFilter::new(&prod.name, &prod.name_synonyms, &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let scope = facets_scope(&candidates, &prod.options);
+                Ok(attribute_filters(&scope, &candidates, &prod.options, &ranking))
+            })
+            .map_err(|e| e.context("Aggregate attributes in db error occurred.").into()),
+        )
+    }
+
+    fn count(&self, prod: SearchProductsByName) -> RepoFuture<i32> {
+        log_search_req(&prod);
+        let filter = CandidatesFilter {
+            store_status: Some(ModerationStatus::Published),
+            sort_by: None,
+            ..CandidatesFilter::new(&prod.name, &prod.name_synonyms, &prod.options)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let candidates = load_candidates(conn, &filter, limit)?;
+                let in_stock_only = is_in_stock_only(&prod.options);
+                Ok(candidates
+                    .iter()
+                    .filter(|candidate| !in_stock_only || candidate.document.variants.iter().any(is_in_stock))
+                    .count() as i32)
+            })
+            .map_err(|e| e.context("Search base product count in db error occurred.").into()),
+        )
+    }
+}
+
+fn text_rank(text_query: &Option<String>) -> Box<BoxableExpression<base_products::table, Pg, SqlType = Float>> {
+    match *text_query {
+        Some(ref text_query) => Box::new(
+            sql::<Float>(&format!("ts_rank(search_vector, to_tsquery('{}', ", TEXT_SEARCH_CONFIG))
+                .bind::<VarChar, _>(text_query.clone())
+                .sql("))"),
+        ),
+        None => Box::new(sql::<Float>("0")),
+    }
+}
+
+/// Query of active base products matching `filter` with their text ranks, the best matching go first
+fn base_products_query(filter: &CandidatesFilter) -> base_products::BoxedQuery<'static, Pg, (base_products::SqlType, Float)> {
+    let mut query = BaseProducts::base_products
+        .select((base_products::all_columns, text_rank(&filter.text_query)))
+        .filter(BaseProducts::is_active.eq(true))
+        .into_boxed();
+
+    if let Some(ref text_query) = filter.text_query {
+        query = query.filter(
+            sql::<Bool>(&format!("search_vector @@ to_tsquery('{}', ", TEXT_SEARCH_CONFIG))
+                .bind::<VarChar, _>(text_query.clone())
+                .sql(")"),
+        );
+    }
+    if let Some(store_id) = filter.store_id {
+        query = query.filter(BaseProducts::store_id.eq(store_id));
+    }
+    if let Some(ref categories_ids) = filter.categories_ids {
+        query = query.filter(BaseProducts::category_id.eq_any(categories_ids.clone()));
+    }
+    if let Some(status) = filter.status {
+        query = query.filter(BaseProducts::status.eq(status));
+    }
+    if let Some(store_status) = filter.store_status {
+        query = query.filter(BaseProducts::store_status.eq(store_status));
+    }
+
+    query = match filter.sort_by {
+        Some(ProductsSorting::Views) => query.order((BaseProducts::views.desc(), BaseProducts::id)),
+        Some(ProductsSorting::Rating) => query.order((BaseProducts::rating.desc(), BaseProducts::id)),
+        Some(ProductsSorting::Newest) => query.order((BaseProducts::created_at.desc(), BaseProducts::id)),
+        _ => query.order((text_rank(&filter.text_query).desc(), BaseProducts::views.desc(), BaseProducts::id)),
+    };
+
+    query
+}
+
+/// Active base products matching `filter` with their text ranks, the best matching go first
+fn load_base_products<C>(conn: &C, filter: &CandidatesFilter, limit: i64) -> RepoResult<Vec<(BaseProductRaw, f32)>>
+where
+    C: Connection<Backend = Pg>,
+{
+    base_products_query(filter)
+        .limit(limit)
+        .get_results::<(BaseProductRaw, f32)>(conn)
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context(format!("Searching base products in db by filter {:?}.", filter)).into())
+}
+
+/// Active base products matching `filter` with active variants, attributes and store ratings
+fn load_candidates<C>(conn: &C, filter: &CandidatesFilter, limit: i64) -> RepoResult<Vec<Candidate>>
+where
+    C: Connection<Backend = Pg>,
+{
+    let (raw_base_products, text_ranks): (Vec<BaseProductRaw>, Vec<f32>) = load_base_products(conn, filter, limit)?.into_iter().unzip();
+
+    let products = RawProduct::belonging_to(&raw_base_products)
+        .filter(Products::is_active.eq(true))
+        .order(Products::id)
+        .get_results::<RawProduct>(conn)
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context("Getting variants of found base products."))?;
+
+    let prod_ids = products.iter().map(|p| p.id).collect::<Vec<ProductId>>();
+    let mut attributes_by_product = HashMap::<ProductId, Vec<(ProdAttr, Attribute)>>::new();
+    for (prod_attr, attribute) in ProdAttrs::prod_attr_values
+        .filter(ProdAttrs::prod_id.eq_any(prod_ids))
+        .inner_join(Attributes::attributes)
+        .order(ProdAttrs::id)
+        .get_results::<(ProdAttr, Attribute)>(conn)
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context("Getting attributes of variants of found base products."))?
+    {
+        attributes_by_product
+            .entry(prod_attr.prod_id)
+            .or_insert_with(Vec::new)
+            .push((prod_attr, attribute));
+    }
+
+    let store_ids = raw_base_products.iter().map(|b| b.store_id).collect::<Vec<StoreId>>();
+    let store_ratings = Stores::stores
+        .filter(Stores::id.eq_any(store_ids))
+        .select((Stores::id, Stores::rating))
+        .get_results::<(StoreId, f64)>(conn)
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context("Getting ratings of stores of found base products."))?
+        .into_iter()
+        .collect::<HashMap<StoreId, f64>>();
+
+    let products = products.grouped_by(&raw_base_products);
+
+    Ok(raw_base_products
+        .into_iter()
+        .zip(products)
+        .zip(text_ranks)
+        .map(|((base_raw, variants), text_rank)| {
+            let variants = variants
+                .into_iter()
+                .map(|variant| {
+                    let attributes = attributes_by_product.remove(&variant.id).unwrap_or_default();
+                    ProductWithAttributes::new(variant, attributes)
+                })
+                .collect();
+            let store_rating = store_ratings.get(&base_raw.store_id).cloned().unwrap_or_default();
+            let catalog = CatalogWithAttributes::new(BaseProduct::from(base_raw), variants);
+
+            Candidate {
+                document: ProductDocument::new(catalog, store_rating),
+                text_rank,
+            }
+        })
+        .collect())
+}
+
+/// Candidates having variants matching search options, sorted by `sort_by` of options
+fn search_matches<'a>(
+    candidates: &'a [Candidate],
+    options: &Option<ProductsSearchOptions>,
+    ranking: &SearchRanking,
+) -> Vec<SearchMatch<'a>> {
+    let default_options = ProductsSearchOptions::default();
+    let options = options.as_ref().unwrap_or(&default_options);
+
+    let mut matches = candidates
+        .iter()
+        .filter(|candidate| in_categories(&candidate.document, options))
+        .filter_map(|candidate| match_variants(candidate, ranking, |variant| matches_options(variant, options)))
+        .collect::<Vec<SearchMatch>>();
+    sort_matches(&mut matches, options.sort_by.as_ref());
+    for search_match in &mut matches {
+        sort_matched_variants(search_match, &options.sort_by);
+    }
+    matches
+}
+
+/// Candidate with ids of its variants accepted by `predicate`, if there are any
+fn match_variants<'a, P>(candidate: &'a Candidate, ranking: &SearchRanking, predicate: P) -> Option<SearchMatch<'a>>
+where
+    P: Fn(&VariantDocument) -> bool,
+{
+    let matched_variants_ids = candidate
+        .document
+        .variants
+        .iter()
+        .filter(|variant| predicate(variant))
+        .map(|variant| variant.prod_id)
+        .collect::<Vec<ProductId>>();
+
+    if matched_variants_ids.is_empty() {
+        None
+    } else {
+        Some(SearchMatch {
+            candidate,
+            matched_variants_ids,
+            score: relevance_score(candidate, ranking),
+        })
+    }
+}
+
+/// Text rank summed with weighted relevance signals, the same as function score of Elastic backend
+fn relevance_score(candidate: &Candidate, ranking: &SearchRanking) -> f64 {
+    let document = &candidate.document;
+    let now = epoch_millis(SystemTime::now());
+    let age_days = now.saturating_sub(document.created_at) as f64 / MILLIS_IN_DAY;
+    let recency = 0.5f64.powf((age_days / f64::from(ranking.recency_scale_days.max(1))).powi(2));
+
+    f64::from(candidate.text_rank)
+        + ranking.views_weight * f64::from(document.views.max(0)).ln_1p()
+        + ranking.rating_weight * document.rating.max(0.0).ln_1p()
+        + ranking.recency_weight * recency
+        + ranking.store_rating_weight * document.store_rating.max(0.0).ln_1p()
+}
+
+fn sort_matches(matches: &mut Vec<SearchMatch>, sort_by: Option<&ProductsSorting>) {
+    matches.sort_by(|a, b| {
+        let (a_document, b_document) = (&a.candidate.document, &b.candidate.document);
+        let ordering = match sort_by {
+            Some(ProductsSorting::PriceAsc) => compare_missing_last(min_price(a_document), min_price(b_document), false),
+            Some(ProductsSorting::PriceDesc) => compare_missing_last(max_price(a_document), max_price(b_document), true),
+            Some(ProductsSorting::Views) => b_document.views.cmp(&a_document.views),
+            Some(ProductsSorting::Discount) => compare_missing_last(max_discount(a_document), max_discount(b_document), true),
+            Some(ProductsSorting::Rating) => compare_missing_last(Some(a_document.rating), Some(b_document.rating), true),
+            Some(ProductsSorting::Newest) => b_document.created_at.cmp(&a_document.created_at),
+            Some(ProductsSorting::Relevance) | None => Ordering::Equal,
+        };
+        ordering.then_with(|| compare_missing_last(Some(a.score), Some(b.score), true))
+    });
+}
+
+/// Matched variants are ordered by price or discount the products are sorted by
+fn sort_matched_variants(search_match: &mut SearchMatch, sort_by: &Option<ProductsSorting>) {
+    let (field, descending): (fn(&VariantDocument) -> Option<f64>, bool) = match *sort_by {
+        Some(ProductsSorting::PriceAsc) => (variant_price, false),
+        Some(ProductsSorting::PriceDesc) => (variant_price, true),
+        Some(ProductsSorting::Discount) => (variant_discount, true),
+        _ => return,
+    };
+
+    let candidate = search_match.candidate;
+    let value = |id: &ProductId| {
+        candidate
+            .document
+            .variants
+            .iter()
+            .find(|variant| variant.prod_id == *id)
+            .and_then(field)
+    };
+    search_match
+        .matched_variants_ids
+        .sort_by(|a, b| compare_missing_last(value(a), value(b), descending));
+}
+
+fn variant_price(variant: &VariantDocument) -> Option<f64> {
+    Some(variant.price.0)
+}
+
+fn variant_discount(variant: &VariantDocument) -> Option<f64> {
+    variant.discount
+}
+
+/// Missing values go last in both orders, as in sorting of Elastic
+fn compare_missing_last(a: Option<f64>, b: Option<f64>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        }
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn min_price(document: &ProductDocument) -> Option<f64> {
+    document
+        .variants
+        .iter()
+        .map(|variant| variant.price.0)
+        .fold(None, |min, price| match min {
+            Some(min) if min <= price => Some(min),
+            _ => Some(price),
+        })
+}
+
+fn max_price(document: &ProductDocument) -> Option<f64> {
+    document
+        .variants
+        .iter()
+        .map(|variant| variant.price.0)
+        .fold(None, |max, price| match max {
+            Some(max) if max >= price => Some(max),
+            _ => Some(price),
+        })
+}
+
+fn max_discount(document: &ProductDocument) -> Option<f64> {
+    document
+        .variants
+        .iter()
+        .filter_map(|variant| variant.discount)
+        .fold(None, |max, discount| match max {
+            Some(max) if max >= discount => Some(max),
+            _ => Some(discount),
+        })
+}
+
+fn page(matches: &[SearchMatch], count: i32, offset: i32) -> Vec<ElasticProduct> {
+    matches
+        .iter()
+        .skip(offset.max(0) as usize)
+        .take(count.max(0) as usize)
+        .map(SearchMatch::to_elastic_product)
+        .collect()
+}
+
+fn is_in_stock_only(options: &Option<ProductsSearchOptions>) -> bool {
+    options.as_ref().and_then(|o| o.in_stock_only).unwrap_or(false)
+}
+
+/// Variants without stock status are treated as in stock
+fn is_in_stock(variant: &VariantDocument) -> bool {
+    variant.stock_status != Some(StockStatus::OutOfStock)
+}
+
+fn in_categories(document: &ProductDocument, options: &ProductsSearchOptions) -> bool {
+    options
+        .categories_ids
+        .as_ref()
+        .map(|ids| ids.contains(&document.category_id))
+        .unwrap_or(true)
+}
+
+/// Variant matches attribute filters, price filter and stock filter of options.
+/// As in Elastic backend, matching any of attribute filters is enough
+fn matches_options(variant: &VariantDocument, options: &ProductsSearchOptions) -> bool {
+    let attrs_match = match options.attr_filters {
+        Some(ref attr_filters) if !attr_filters.is_empty() => attr_filters
+            .iter()
+            .any(|attr_filter| variant.attrs.iter().any(|attr| matches_attribute_filter(attr, attr_filter))),
+        _ => true,
+    };
+    let price_match = options
+        .price_filter
+        .as_ref()
+        .map(|price_filter| {
+            converted_price(variant, &options.currency_map)
+                .map(|price| in_range(price, price_filter))
+                .unwrap_or(false)
+        })
+        .unwrap_or(true);
+    let discount_match = options.sort_by != Some(ProductsSorting::Discount) || variant.discount.is_some();
+    let stock_match = !options.in_stock_only.unwrap_or(false) || is_in_stock(variant);
+
+    attrs_match && price_match && discount_match && stock_match
+}
+
+fn matches_attribute_filter(attr: &ElasticAttrValue, attr_filter: &AttributeFilter) -> bool {
+    if attr.attr_id != attr_filter.id {
+        return false;
+    }
+
+    if let Some(ref range) = attr_filter.range {
+        attr.float_val.map(|value| in_range(value, range)).unwrap_or(false)
+    } else if let Some(ref equal) = attr_filter.equal {
+        attr.str_val.as_ref().map(|value| equal.values.contains(value)).unwrap_or(false)
+    } else {
+        true
+    }
+}
+
+fn in_range(value: f64, range: &RangeFilter) -> bool {
+    range.min_value.map(|min| value >= min).unwrap_or(true) && range.max_value.map(|max| value <= max).unwrap_or(true)
+}
+
+/// Price of variant converted by `currency_map`, variants in currencies missing in it have no price
+fn converted_price(variant: &VariantDocument, currency_map: &Option<HashMap<Currency, ExchangeRate>>) -> Option<f64> {
+    match *currency_map {
+        Some(ref currency_map) => currency_map.get(&variant.currency).map(|rate| variant.price.0 / rate.0),
+        None => Some(variant.price.0),
+    }
+}
+
+/// Documents among which search facets are looked for, variant filters are not applied
+/// so that facet values stay visible when other values are selected
+fn facets_scope<'a>(candidates: &'a [Candidate], options: &Option<ProductsSearchOptions>) -> Vec<&'a ProductDocument> {
+    let default_options = ProductsSearchOptions::default();
+    let options = options.as_ref().unwrap_or(&default_options);
+    let in_stock_only = options.in_stock_only.unwrap_or(false);
+
+    candidates
+        .iter()
+        .map(|candidate| &candidate.document)
+        .filter(|document| in_categories(document, options))
+        .filter(|document| !in_stock_only || document.variants.iter().any(is_in_stock))
+        .collect()
+}
+
+/// Min and max price of all variants of documents
+fn price_range(documents: &[&ProductDocument], currency_map: &Option<HashMap<Currency, ExchangeRate>>) -> RangeFilter {
+    let mut range = RangeFilter::default();
+    for variant in documents.iter().flat_map(|document| document.variants.iter()) {
+        if let Some(price) = converted_price(variant, currency_map) {
+            range.add_value(price);
+        }
+    }
+    range
+}
+
+/// Distinct categories, the most frequent go first
+fn categories_by_count<I: Iterator<Item = CategoryId>>(categories_ids: I) -> Vec<CategoryId> {
+    let mut counts = HashMap::<CategoryId, usize>::new();
+    for category_id in categories_ids {
+        *counts.entry(category_id).or_insert(0) += 1;
+    }
+
+    let mut counts = counts.into_iter().collect::<Vec<(CategoryId, usize)>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| (a.0).0.cmp(&(b.0).0)));
+    counts.into_iter().map(|(category_id, _)| category_id).collect()
+}
+
+/// Attribute filters of facets scope documents with counts of values among documents matching search options,
+/// values of attribute having filters are counted without own filters of the attribute as in Elastic backend
+fn attribute_filters(
+    scope: &[&ProductDocument],
+    candidates: &[Candidate],
+    options: &Option<ProductsSearchOptions>,
+    ranking: &SearchRanking,
+) -> Vec<AttributeFilter> {
+    let mut values = BTreeMap::<i32, BTreeSet<String>>::new();
+    let mut ranges = BTreeMap::<i32, RangeFilter>::new();
+    for attr in scope
+        .iter()
+        .flat_map(|document| document.variants.iter().flat_map(|variant| variant.attrs.iter()))
+    {
+        if let Some(ref str_val) = attr.str_val {
+            values.entry(attr.attr_id).or_insert_with(BTreeSet::new).insert(str_val.clone());
+        }
+        if let Some(float_val) = attr.float_val {
+            ranges.entry(attr.attr_id).or_insert_with(RangeFilter::default).add_value(float_val);
+        }
+    }
+
+    let filtered_attributes_ids = options
+        .as_ref()
+        .map(|options| options.filtered_attributes_ids())
+        .unwrap_or_default();
+    let mut counts = attribute_values_counts(&search_matches(candidates, options, ranking), |id| {
+        !filtered_attributes_ids.contains(&id)
+    });
+    for filtered_id in &filtered_attributes_ids {
+        let options = options.as_ref().map(|options| options.without_attribute_filter(*filtered_id));
+        counts.extend(attribute_values_counts(&search_matches(candidates, &options, ranking), |id| {
+            id == *filtered_id
+        }));
+    }
+
+    let ids = values.keys().chain(ranges.keys()).cloned().collect::<BTreeSet<i32>>();
+    let mut attribute_filters = vec![];
+    for id in ids {
+        if let Some(values) = values.remove(&id) {
+            let values = values.into_iter().collect::<Vec<String>>();
+            let values_counts = values
+                .iter()
+                .map(|value| AttributeValueCount {
+                    value: value.clone(),
+                    count: counts.get(&(id, value.clone())).cloned().unwrap_or_default(),
+                })
+                .collect();
+            attribute_filters.push(AttributeFilter {
+                id,
+                equal: Some(EqualFilter {
+                    values,
+                    counts: Some(values_counts),
+                }),
+                range: None,
+            });
+        }
+        if let Some(range) = ranges.remove(&id) {
+            attribute_filters.push(AttributeFilter {
+                id,
+                equal: None,
+                range: Some(range),
+            });
+        }
+    }
+    attribute_filters
+}
+
+/// Counts of documents among `matches` having each value of attributes accepted by `predicate`
+fn attribute_values_counts<P>(matches: &[SearchMatch], predicate: P) -> HashMap<(i32, String), u64>
+where
+    P: Fn(i32) -> bool,
+{
+    let mut counts = HashMap::<(i32, String), u64>::new();
+    for search_match in matches {
+        let document_values = search_match
+            .candidate
+            .document
+            .variants
+            .iter()
+            .flat_map(|variant| variant.attrs.iter())
+            .filter(|attr| predicate(attr.attr_id))
+            .filter_map(|attr| attr.str_val.as_ref().map(|str_val| (attr.attr_id, str_val.as_str())))
+            .collect::<HashSet<(i32, &str)>>();
+        for (id, value) in document_values {
+            *counts.entry((id, value.to_string())).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+pub mod tests {
+    use diesel::debug_query;
+
+    use stq_types::{BaseProductId, ProductPrice};
+
+    use elastic::postgres::products::*;
+
+    fn variant(prod_id: i32, price: f64, stock_status: Option<StockStatus>, color: &str) -> VariantDocument {
+        VariantDocument {
+            prod_id: ProductId(prod_id),
+            discount: None,
+            price: ProductPrice(price),
+            currency: Currency::STQ,
+            stock_status,
+            attrs: vec![ElasticAttrValue {
+                attr_id: 1,
+                str_val: Some(color.to_string()),
+                float_val: None,
+            }],
+        }
+    }
+
+    fn candidate(id: i32, category_id: i32, created_at: u64, variants: Vec<VariantDocument>) -> Candidate {
+        Candidate {
+            document: ProductDocument {
+                id: BaseProductId(id),
+                store_id: StoreId(1),
+                name: json!([]),
+                short_description: json!([]),
+                long_description: None,
+                category_id: CategoryId(category_id),
+                views: 0,
+                rating: 0.0,
+                status: ModerationStatus::Published,
+                store_status: ModerationStatus::Published,
+                created_at,
+                store_rating: 0.0,
+                variants,
+            },
+            text_rank: 0.0,
+        }
+    }
+
+    fn candidates() -> Vec<Candidate> {
+        vec![
+            candidate(
+                1,
+                1,
+                1000,
+                vec![
+                    variant(11, 20.0, None, "red"),
+                    variant(12, 10.0, Some(StockStatus::OutOfStock), "blue"),
+                ],
+            ),
+            candidate(2, 1, 3000, vec![variant(21, 15.0, Some(StockStatus::InStock), "blue")]),
+            candidate(3, 2, 2000, vec![variant(31, 5.0, None, "red")]),
+        ]
+    }
+
+    /// Ids of matched base products with ids of their matched variants
+    fn matched_ids(matches: &[SearchMatch]) -> Vec<(i32, Vec<i32>)> {
+        matches
+            .iter()
+            .map(|search_match| {
+                (
+                    search_match.candidate.document.id.0,
+                    search_match.matched_variants_ids.iter().map(|id| id.0).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_base_products_query_filters() {
+        let filter = CandidatesFilter::new(
+            "phone",
+            &[],
+            &Some(ProductsSearchOptions {
+                store_id: Some(StoreId(1)),
+                categories_ids: Some(vec![CategoryId(2), CategoryId(3)]),
+                status: Some(ModerationStatus::Published),
+                ..Default::default()
+            }),
+        );
+        let query = debug_query::<Pg, _>(&base_products_query(&filter)).to_string();
+        let where_clause = query.split(" WHERE ").nth(1).unwrap();
+        assert!(where_clause.contains("\"base_products\".\"is_active\" = $"));
+        assert!(where_clause.contains("search_vector @@ to_tsquery('simple', $"));
+        assert!(where_clause.contains("\"base_products\".\"store_id\" = $"));
+        assert!(where_clause.contains("\"base_products\".\"category_id\""));
+        assert!(where_clause.contains("\"base_products\".\"status\" = $"));
+        assert!(where_clause.contains("\"base_products\".\"store_status\" = $"));
+
+        let query = debug_query::<Pg, _>(&base_products_query(&CandidatesFilter::default())).to_string();
+        let where_clause = query.split(" WHERE ").nth(1).unwrap();
+        assert!(!where_clause.contains("search_vector"));
+        assert!(!where_clause.contains("\"base_products\".\"store_id\""));
+        assert!(!where_clause.contains("\"base_products\".\"category_id\""));
+    }
+
+    #[test]
+    fn test_base_products_query_sorting() {
+        let query = |sort_by: Option<ProductsSorting>| {
+            let filter = CandidatesFilter {
+                sort_by,
+                ..Default::default()
+            };
+            debug_query::<Pg, _>(&base_products_query(&filter)).to_string()
+        };
+
+        assert!(query(Some(ProductsSorting::Newest)).contains("ORDER BY \"base_products\".\"created_at\" DESC, \"base_products\".\"id\""));
+        assert!(query(Some(ProductsSorting::Views)).contains("ORDER BY \"base_products\".\"views\" DESC, \"base_products\".\"id\""));
+        assert!(query(Some(ProductsSorting::Rating)).contains("ORDER BY \"base_products\".\"rating\" DESC, \"base_products\".\"id\""));
+        assert!(query(None).contains("ORDER BY 0 DESC, \"base_products\".\"views\" DESC, \"base_products\".\"id\""));
+    }
+
+    #[test]
+    fn test_search_matches_filters() {
+        let candidates = candidates();
+        let ranking = SearchRanking::default();
+
+        let options = Some(ProductsSearchOptions {
+            categories_ids: Some(vec![CategoryId(1)]),
+            ..Default::default()
+        });
+        let mut ids = matched_ids(&search_matches(&candidates, &options, &ranking));
+        ids.sort();
+        assert_eq!(ids, vec![(1, vec![11, 12]), (2, vec![21])]);
+
+        let options = Some(ProductsSearchOptions {
+            attr_filters: Some(vec![AttributeFilter {
+                id: 1,
+                equal: Some(EqualFilter {
+                    values: vec!["blue".to_string()],
+                    counts: None,
+                }),
+                range: None,
+            }]),
+            in_stock_only: Some(true),
+            ..Default::default()
+        });
+        assert_eq!(matched_ids(&search_matches(&candidates, &options, &ranking)), vec![(2, vec![21])]);
+
+        let options = Some(ProductsSearchOptions {
+            price_filter: Some(RangeFilter {
+                min_value: Some(10.0),
+                max_value: Some(15.0),
+            }),
+            ..Default::default()
+        });
+        let mut ids = matched_ids(&search_matches(&candidates, &options, &ranking));
+        ids.sort();
+        assert_eq!(ids, vec![(1, vec![12]), (2, vec![21])]);
+    }
+
+    #[test]
+    fn test_search_matches_sorting() {
+        let candidates = candidates();
+        let ranking = SearchRanking::default();
+        let sorted = |sort_by: ProductsSorting| {
+            let options = Some(ProductsSearchOptions {
+                sort_by: Some(sort_by),
+                ..Default::default()
+            });
+            matched_ids(&search_matches(&candidates, &options, &ranking))
+        };
+
+        assert_eq!(
+            sorted(ProductsSorting::PriceAsc),
+            vec![(3, vec![31]), (1, vec![12, 11]), (2, vec![21])]
+        );
+        assert_eq!(
+            sorted(ProductsSorting::PriceDesc),
+            vec![(1, vec![11, 12]), (2, vec![21]), (3, vec![31])]
+        );
+        assert_eq!(
+            sorted(ProductsSorting::Newest),
+            vec![(2, vec![21]), (3, vec![31]), (1, vec![11, 12])]
+        );
+    }
+}
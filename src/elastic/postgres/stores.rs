@@ -0,0 +1,360 @@
+//! Stores search over Postgres full text search, implements the same trait as Elastic stores search
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use diesel::connection::AnsiTransactionManager;
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float, Jsonb, VarChar};
+use diesel::Connection;
+use failure::Error as FailureError;
+use failure::Fail;
+use futures::Future;
+use futures_cpupool::CpuPool;
+use r2d2::{ManageConnection, Pool};
+use serde_json;
+
+use stq_static_resources::ModerationStatus;
+use stq_types::CategoryId;
+
+use super::{autocomplete_suggestion, log_search_req, push_suggestion, spawn_on_pool, text_search_query, TEXT_SEARCH_CONFIG};
+use elastic::StoresElastic;
+use errors::Error;
+use models::{
+    AutoCompleteSuggestion, CountryLocalities, ElasticStore, GeoPoint, ProductCategories, SearchStore, Store, StoresSearchOptions,
+    StoresSorting,
+};
+use repos::types::{RepoFuture, RepoResult};
+use schema::stores;
+use schema::stores::dsl as Stores;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+const KM_IN_LATITUDE_DEGREE: f64 = 111.2;
+
+/// Stores search in Postgres, responsible for handling stores when Elastic is not used
+pub struct StoresPostgresImpl<M: ManageConnection> {
+    pub db_pool: Pool<M>,
+    pub cpu_pool: CpuPool,
+    pub candidates_limit: i64,
+}
+
+/// Active published stores filters applied in db
+#[derive(Debug, Default)]
+struct CandidatesFilter {
+    text_query: Option<String>,
+    country: Option<String>,
+    locality: Option<String>,
+    category_id: Option<CategoryId>,
+    /// Location and radius in km, stores are looked for in the bounding box of the circle
+    geo_distance: Option<(GeoPoint, f64)>,
+}
+
+impl CandidatesFilter {
+    fn new(search_store: &SearchStore) -> Self {
+        Self {
+            text_query: text_search_query(&search_store.name, &search_store.name_synonyms, false),
+            geo_distance: geo_distance(&search_store.options),
+            ..CandidatesFilter::default()
+        }
+    }
+}
+
+impl<M: ManageConnection> Clone for StoresPostgresImpl<M> {
+    fn clone(&self) -> Self {
+        Self {
+            db_pool: self.db_pool.clone(),
+            cpu_pool: self.cpu_pool.clone(),
+            candidates_limit: self.candidates_limit,
+        }
+    }
+}
+
+impl<M> StoresPostgresImpl<M>
+where
+    M: ManageConnection,
+    M::Connection: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    pub fn new(db_pool: Pool<M>, cpu_pool: CpuPool, candidates_limit: i64) -> Self {
+        Self {
+            db_pool,
+            cpu_pool,
+            candidates_limit,
+        }
+    }
+
+    fn spawn<R, Func>(&self, f: Func) -> RepoFuture<R>
+    where
+        Func: FnOnce(&M::Connection, i64) -> RepoResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let candidates_limit = self.candidates_limit;
+        spawn_on_pool(&self.db_pool, &self.cpu_pool, move |conn| f(&*conn, candidates_limit))
+    }
+}
+
+impl<M> StoresElastic for StoresPostgresImpl<M>
+where
+    M: ManageConnection,
+    M::Connection: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    /// Find specific stores by name limited by `count` parameters
+    fn find_by_name(&self, search_store: SearchStore, count: i32, offset: i32) -> RepoFuture<Vec<ElasticStore>> {
+        log_search_req(&search_store);
+        let options = search_store.options.clone();
+        let filter = CandidatesFilter {
+            country: options.as_ref().and_then(|o| o.country.clone()),
+            locality: options.as_ref().and_then(|o| o.locality.clone()),
+            category_id: options.as_ref().and_then(|o| o.category_id),
+            ..CandidatesFilter::new(&search_store)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let mut stores = load_stores(conn, &filter, limit)?
+                    .into_iter()
+                    .filter(|&(ref store, _)| has_product_categories(store))
+                    .collect::<Vec<(Store, f32)>>();
+                sort_stores(&mut stores, &options);
+                Ok(stores
+                    .into_iter()
+                    .skip(offset.max(0) as usize)
+                    .take(count.max(0) as usize)
+                    .map(|(store, _)| ElasticStore::from(store))
+                    .collect())
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Search store by name in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Auto Complete
+    fn auto_complete(&self, name: String, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
+        log_search_req(&name);
+        let filter = CandidatesFilter {
+            text_query: text_search_query(&name, &[], false),
+            ..CandidatesFilter::default()
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                if filter.text_query.is_none() {
+                    return Ok(vec![]);
+                }
+
+                let mut suggestions = vec![];
+                for (store, text_rank) in load_stores(conn, &filter, limit)? {
+                    if let Some(suggestion) = autocomplete_suggestion(&store.name, &name, text_rank) {
+                        push_suggestion(&mut suggestions, suggestion);
+                    }
+                }
+                Ok(suggestions
+                    .into_iter()
+                    .skip(offset.max(0) as usize)
+                    .take(count.max(0) as usize)
+                    .collect())
+            })
+            .map_err(move |e| {
+                e.context(format!(
+                    "Auto complete store name in db error occurred. Count: {}, offset: {}",
+                    count, offset
+                ))
+                .into()
+            }),
+        )
+    }
+
+    /// Search count of stores by name
+    fn search_count(&self, search_store: SearchStore) -> RepoFuture<i32> {
+        log_search_req(&search_store);
+        let filter = CandidatesFilter::new(&search_store);
+        Box::new(
+            self.spawn(move |conn, limit| Ok(load_stores(conn, &filter, limit)?.len() as i32))
+                .map_err(|e| e.context("Search store count in db error occurred.").into()),
+        )
+    }
+
+    /// Aggregate countries with localities of them
+    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<CountryLocalities>> {
+        log_search_req(&search_store);
+        let filter = CandidatesFilter::new(&search_store);
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let mut countries = HashMap::<String, (usize, HashMap<String, usize>)>::new();
+                for (store, _) in load_stores(conn, &filter, limit)? {
+                    if let Some(country) = store.country {
+                        let country_counts = countries.entry(country).or_insert_with(Default::default);
+                        country_counts.0 += 1;
+                        if let Some(locality) = store.locality {
+                            *country_counts.1.entry(locality).or_insert(0) += 1;
+                        }
+                    }
+                }
+
+                let mut countries = countries.into_iter().collect::<Vec<(String, (usize, HashMap<String, usize>))>>();
+                countries.sort_by(|a, b| (b.1).0.cmp(&(a.1).0).then_with(|| a.0.cmp(&b.0)));
+                Ok(countries
+                    .into_iter()
+                    .map(|(country, (_, localities))| CountryLocalities {
+                        country,
+                        localities: by_count(localities.into_iter()),
+                    })
+                    .collect())
+            })
+            .map_err(|e| e.context("Aggregate countries for store in db error occurred.").into()),
+        )
+    }
+
+    /// Aggregate categories
+    fn aggregate_categories(&self, search_store: SearchStore) -> RepoFuture<Vec<CategoryId>> {
+        log_search_req(&search_store);
+        let filter = CandidatesFilter {
+            geo_distance: None,
+            ..CandidatesFilter::new(&search_store)
+        };
+        Box::new(
+            self.spawn(move |conn, limit| {
+                let mut categories = HashMap::<i32, usize>::new();
+                for (store, _) in load_stores(conn, &filter, limit)? {
+                    for product_categories in store_product_categories(&store) {
+                        *categories.entry(product_categories.category_id.0).or_insert(0) += 1;
+                    }
+                }
+                Ok(by_count(categories.into_iter()).into_iter().map(CategoryId).collect())
+            })
+            .map_err(|e| e.context("Aggregate categories for stores in db error occurred.").into()),
+        )
+    }
+}
+
+fn text_rank(text_query: &Option<String>) -> Box<BoxableExpression<stores::table, Pg, SqlType = Float>> {
+    match *text_query {
+        Some(ref text_query) => Box::new(
+            sql::<Float>(&format!("ts_rank(search_vector, to_tsquery('{}', ", TEXT_SEARCH_CONFIG))
+                .bind::<VarChar, _>(text_query.clone())
+                .sql("))"),
+        ),
+        None => Box::new(sql::<Float>("0")),
+    }
+}
+
+/// Active published stores matching `filter` with their text ranks, the best matching go first,
+/// stores found without text go by rating
+fn load_stores<C>(conn: &C, filter: &CandidatesFilter, limit: i64) -> RepoResult<Vec<(Store, f32)>>
+where
+    C: Connection<Backend = Pg>,
+{
+    let mut query = Stores::stores
+        .select((stores::all_columns, text_rank(&filter.text_query)))
+        .filter(Stores::is_active.eq(true))
+        .filter(Stores::status.eq(ModerationStatus::Published))
+        .into_boxed();
+
+    if let Some(ref text_query) = filter.text_query {
+        query = query.filter(
+            sql::<Bool>(&format!("search_vector @@ to_tsquery('{}', ", TEXT_SEARCH_CONFIG))
+                .bind::<VarChar, _>(text_query.clone())
+                .sql(")"),
+        );
+    }
+    if let Some(ref country) = filter.country {
+        query = query.filter(Stores::country.eq(country.clone()));
+    }
+    if let Some(ref locality) = filter.locality {
+        query = query.filter(Stores::locality.eq(locality.clone()));
+    }
+    if let Some(category_id) = filter.category_id {
+        query = query.filter(sql::<Bool>("product_categories @> ").bind::<Jsonb, _>(json!([{ "category_id": category_id }])));
+    }
+    if let Some((location, radius_km)) = filter.geo_distance {
+        let latitude_delta = radius_km / KM_IN_LATITUDE_DEGREE;
+        query = query.filter(Stores::latitude.between(location.latitude - latitude_delta, location.latitude + latitude_delta));
+        let longitude_delta = latitude_delta / location.latitude.to_radians().cos();
+        if location.longitude.abs() + longitude_delta < 180.0 {
+            query = query.filter(Stores::longitude.between(location.longitude - longitude_delta, location.longitude + longitude_delta));
+        }
+    }
+
+    query = match filter.text_query {
+        Some(_) => query.order((text_rank(&filter.text_query).desc(), Stores::rating.desc(), Stores::id)),
+        None => query.order((Stores::rating.desc(), Stores::id)),
+    };
+
+    let stores = query
+        .limit(limit)
+        .get_results::<(Store, f32)>(conn)
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context(format!("Searching stores in db by filter {:?}.", filter)))?;
+
+    Ok(match filter.geo_distance {
+        Some((location, radius_km)) => stores
+            .into_iter()
+            .filter(|&(ref store, _)| store_distance_km(store, location).map(|d| d <= radius_km).unwrap_or(false))
+            .collect(),
+        None => stores,
+    })
+}
+
+fn geo_distance(options: &Option<StoresSearchOptions>) -> Option<(GeoPoint, f64)> {
+    let options = options.as_ref()?;
+    Some((options.location?, options.radius_km?))
+}
+
+/// Stores are loaded sorted by text rank or by rating when there is no text, other sortings are applied to them
+fn sort_stores(stores: &mut Vec<(Store, f32)>, options: &Option<StoresSearchOptions>) {
+    let sort_by = options.as_ref().and_then(|options| options.sort_by);
+    let location = options.as_ref().and_then(|options| options.location);
+
+    match (sort_by, location) {
+        (Some(StoresSorting::Distance), Some(location)) => {
+            stores.sort_by(
+                |&(ref a, _), &(ref b, _)| match (store_distance_km(a, location), store_distance_km(b, location)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+            )
+        }
+        (Some(StoresSorting::Rating), _) => {
+            stores.sort_by(|&(ref a, _), &(ref b, _)| b.rating.partial_cmp(&a.rating).unwrap_or(Ordering::Equal))
+        }
+        _ => {}
+    }
+}
+
+fn store_distance_km(store: &Store, location: GeoPoint) -> Option<f64> {
+    store.location().map(|store_location| distance_km(store_location, location))
+}
+
+/// Great-circle distance by haversine formula
+pub fn distance_km(a: GeoPoint, b: GeoPoint) -> f64 {
+    let (a_latitude, b_latitude) = (a.latitude.to_radians(), b.latitude.to_radians());
+    let latitude_delta = b_latitude - a_latitude;
+    let longitude_delta = (b.longitude - a.longitude).to_radians();
+
+    let h = (latitude_delta / 2.0).sin().powi(2) + a_latitude.cos() * b_latitude.cos() * (longitude_delta / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().min(1.0).asin()
+}
+
+fn store_product_categories(store: &Store) -> Vec<ProductCategories> {
+    store
+        .product_categories
+        .clone()
+        .and_then(|product_categories| serde_json::from_value::<Vec<ProductCategories>>(product_categories).ok())
+        .unwrap_or_default()
+}
+
+fn has_product_categories(store: &Store) -> bool {
+    !store_product_categories(store).is_empty()
+}
+
+/// Distinct keys, the most frequent go first
+fn by_count<K: Ord, I: Iterator<Item = (K, usize)>>(counts: I) -> Vec<K> {
+    let mut counts = counts.collect::<Vec<(K, usize)>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts.into_iter().map(|(key, _)| key).collect()
+}
@@ -4,29 +4,40 @@ use failure::Fail;
 use futures::Future;
 use hyper::header::{ContentLength, ContentType, Headers};
 use hyper::Method;
+use std::collections::HashMap;
+
 use serde_json;
 
 use stq_http::client::ClientHandle;
-use stq_static_resources::ModerationStatus;
-use stq_types::{CategoryId, ProductId};
+use stq_static_resources::Currency;
+use stq_types::{CategoryId, ExchangeRate, ProductId};
 
+use super::analysis::{autocomplete_query, autocomplete_suggestions, translated_text_query};
 use super::{log_elastic_req, log_elastic_resp};
+use config::SearchRanking;
 use models::*;
 use repos::types::RepoFuture;
 
+/// Max count of buckets returned by facet aggregations
+const FACET_BUCKETS_COUNT: i32 = 1000;
+
 /// ProductsSearch repository, responsible for handling products
 pub struct ProductsElasticImpl {
     pub client_handle: ClientHandle,
     pub elastic_address: String,
+    pub ranking: SearchRanking,
 }
 
 pub trait ProductsElastic {
     /// Find specific product by name limited by `count` parameters
-    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> RepoFuture<Vec<String>>;
+    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>>;
 
     /// Find specific product by name limited by `count` parameters
     fn search_by_name(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>>;
 
+    /// Find products by name limited by `count` parameters together with total count, price range, categories and attributes
+    fn search_faceted(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<ElasticProductsFacetedSearch>;
+
     /// Find product by views limited by `count` and `offset` parameters
     fn search_most_viewed(&self, prod: MostViewedProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>>;
 
@@ -39,15 +50,19 @@ pub trait ProductsElastic {
     /// Find price range
     fn aggregate_price(&self, prod: SearchProductsByName) -> RepoFuture<RangeFilter>;
 
+    /// Find attribute values with count of products having them
+    fn aggregate_attributes(&self, prod: SearchProductsByName) -> RepoFuture<Vec<AttributeFilter>>;
+
     /// Find count
     fn count(&self, prod: SearchProductsByName) -> RepoFuture<i32>;
 }
 
 impl ProductsElasticImpl {
-    pub fn new(client_handle: ClientHandle, elastic_address: String) -> Self {
+    pub fn new(client_handle: ClientHandle, elastic_address: String, ranking: SearchRanking) -> Self {
         Self {
             client_handle,
             elastic_address,
+            ranking,
         }
     }
 
@@ -194,12 +209,41 @@ impl ProductsElasticImpl {
         if !variants_must.is_empty() {
             variants_map.insert("must".to_string(), serde_json::Value::Array(variants_must));
         }
+        if ProductsElasticImpl::is_in_stock_only(options) {
+            variants_map.insert("must_not".to_string(), ProductsElasticImpl::create_out_of_stock_variant_filter());
+        }
         if !variants_filters.is_empty() {
             variants_map.insert("filter".to_string(), serde_json::Value::Array(variants_filters));
         }
         variants_map
     }
 
+    fn is_in_stock_only(options: &Option<ProductsSearchOptions>) -> bool {
+        options.as_ref().and_then(|o| o.in_stock_only).unwrap_or(false)
+    }
+
+    /// Variants without stock status are treated as in stock
+    fn create_out_of_stock_variant_filter() -> serde_json::Value {
+        json!([{"match": {"variants.stock_status": StockStatus::OutOfStock}}])
+    }
+
+    fn create_in_stock_filter(options: &Option<ProductsSearchOptions>) -> Option<serde_json::Value> {
+        if ProductsElasticImpl::is_in_stock_only(options) {
+            Some(json!({
+                "nested": {
+                    "path": "variants",
+                    "query": {
+                        "bool": {
+                            "must_not": ProductsElasticImpl::create_out_of_stock_variant_filter()
+                        }
+                    }
+                }
+            }))
+        } else {
+            None
+        }
+    }
+
     fn create_category_filter(options: Option<ProductsSearchOptions>) -> Option<serde_json::Value> {
         options.and_then(|o| o.categories_ids).map(|ids| {
             json!({
@@ -259,20 +303,66 @@ impl ProductsElasticImpl {
                             }
                         }
                     }),
+                    ProductsSorting::Rating => json!({ "rating" : { "order" : "desc"} }),
+                    ProductsSorting::Newest => json!({ "created_at" : { "order" : "desc", "missing" : "_last"} }),
+                    ProductsSorting::Relevance => json!({ "_score" : { "order" : "desc"} }),
                 };
                 sorting.push(sort);
             }
         }
         sorting
     }
-}
 
-impl ProductsElastic for ProductsElasticImpl {
-    /// Find specific products by name limited by `count` parameters
-    fn search_by_name(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
-        log_elastic_req(&prod);
+    /// Scores products matched by `query_map` by text score summed with weighted relevance signals
+    fn create_ranked_query(query_map: serde_json::Map<String, serde_json::Value>, ranking: &SearchRanking) -> serde_json::Value {
+        let mut functions = vec![];
+        if ranking.views_weight > 0.0 {
+            functions.push(json!({
+                "field_value_factor": { "field": "views", "modifier": "log1p", "missing": 0 },
+                "weight": ranking.views_weight
+            }));
+        }
+        if ranking.rating_weight > 0.0 {
+            functions.push(json!({
+                "field_value_factor": { "field": "rating", "modifier": "log1p", "missing": 0 },
+                "weight": ranking.rating_weight
+            }));
+        }
+        if ranking.recency_weight > 0.0 {
+            // Decay of missing field scores as the origin, so products without creation date are not boosted
+            functions.push(json!({
+                "filter": { "exists": { "field": "created_at" } },
+                "gauss": {
+                    "created_at": { "origin": "now", "scale": format!("{}d", ranking.recency_scale_days), "decay": 0.5 }
+                },
+                "weight": ranking.recency_weight
+            }));
+        }
+        if ranking.store_rating_weight > 0.0 {
+            functions.push(json!({
+                "field_value_factor": { "field": "store_rating", "modifier": "log1p", "missing": 0 },
+                "weight": ranking.store_rating_weight
+            }));
+        }
+
+        if functions.is_empty() {
+            return json!({ "bool": query_map });
+        }
+
+        json!({
+            "function_score": {
+                "query": { "bool": query_map },
+                "functions": functions,
+                "score_mode": "sum",
+                "boost_mode": "sum"
+            }
+        })
+    }
+
+    /// Bool query of products search by name with all search options applied
+    fn create_search_by_name_query(prod: &SearchProductsByName) -> serde_json::Map<String, serde_json::Value> {
         let product_name = prod.name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&product_name);
+        let name_query = fuzzy_search_by_name_query(&product_name, &prod.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
         if !product_name.is_empty() {
@@ -293,10 +383,10 @@ impl ProductsElastic for ProductsElasticImpl {
                 ProductsSorting::PriceDesc => json!(
                 [{"variants.price" : "desc"}]
                 ),
-                ProductsSorting::Views => json!([]),
                 ProductsSorting::Discount => json!(
                     [{"variants.discount" : "desc"}]
                 ),
+                ProductsSorting::Views | ProductsSorting::Rating | ProductsSorting::Newest | ProductsSorting::Relevance => json!([]),
             })
             .unwrap_or_else(|| serde_json::Value::Array(vec![]));
 
@@ -335,14 +425,313 @@ impl ProductsElastic for ProductsElasticImpl {
         }
 
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
+        query_map
+    }
+
+    /// Bool query of products search by name without category, variant and price filters, facets are aggregated over it
+    fn create_facets_query(prod: &SearchProductsByName) -> serde_json::Map<String, serde_json::Value> {
+        let product_name = prod.name.to_lowercase();
+        let name_query = fuzzy_search_by_name_query(&product_name, &prod.name_synonyms);
 
+        let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
+        if !product_name.is_empty() {
+            query_map.insert("must".to_string(), name_query);
+        }
+
+        let mut filters: Vec<serde_json::Value> = vec![];
+
+        let store_filter = ProductsElasticImpl::create_store_filter(prod.options.clone());
+        if let Some(store_filter) = store_filter {
+            filters.push(store_filter);
+        }
+
+        let status_filter = ProductsElasticImpl::create_status_filter(prod.options.clone());
+        if let Some(status_filter) = status_filter {
+            filters.push(status_filter);
+        }
+
+        if let Some(status) = prod.options.as_ref().and_then(|o| o.status) {
+            filters.push(json!({ "term": {"store_status": status.to_string()}}));
+        }
+
+        query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
+        query_map
+    }
+
+    /// Min and max variant price aggregations, prices are converted by `currency_map` if it is present
+    fn create_price_aggregations(currency_map: Option<HashMap<Currency, ExchangeRate>>) -> serde_json::Value {
+        if let Some(currency_map) = currency_map {
+            json!({
+                "min_price" : {
+                    "min" : {
+                    "script": {
+                                "lang": "painless",
+                                "params": { "cur_map": currency_map },
+                                "source": r###"
+                                    def cur = doc['variants.currency'].value;
+                                    def koef = params.cur_map[cur];
+                                    return doc['variants.price'].value / koef;
+                                "###,
+                            }
+                        }
+                },
+                "max_price" : {
+                    "max" : {
+                    "script": {
+                                "lang": "painless",
+                                "params": { "cur_map": currency_map },
+                                "source": r###"
+                                    def cur = doc['variants.currency'].value;
+                                    def koef = params.cur_map[cur];
+                                    return doc['variants.price'].value / koef;
+                                "###,
+                            }
+                        }
+                    }
+            })
+        } else {
+            json!({
+                "min_price" : { "min" : { "field" : "variants.price" } },
+                "max_price" : { "max" : { "field" : "variants.price" } }
+            })
+        }
+    }
+
+    fn create_faceted_search_from_response(res: SearchResponse<ElasticProduct>) -> ElasticProductsFacetedSearch {
+        let total_count = res.total() as u32;
+        let mut price_filter = RangeFilter::default();
+        let mut category_ids = vec![];
+        let mut attribute_filters = vec![];
+
+        if let Some(aggs_raw) = res.aggs_raw() {
+            if let Some(max_price) = aggs_raw["facets"]["price"]["variants"]["max_price"]["value"].as_f64() {
+                price_filter.add_value(max_price);
+            };
+            if let Some(min_price) = aggs_raw["facets"]["price"]["variants"]["min_price"]["value"].as_f64() {
+                price_filter.add_value(min_price);
+            };
+
+            if let Some(buckets) = aggs_raw["facets"]["categories"]["category_ids"]["buckets"].as_array() {
+                category_ids = buckets
+                    .iter()
+                    .filter_map(|bucket| bucket["key"].as_i64())
+                    .map(|id| CategoryId(id as i32))
+                    .collect();
+            }
+
+            attribute_filters = ProductsElasticImpl::create_attribute_filters_from_aggregations(aggs_raw);
+        }
+
+        ElasticProductsFacetedSearch {
+            products: ProductsElasticImpl::create_products_from_search_response(res),
+            total_count,
+            price_filter,
+            category_ids,
+            attribute_filters,
+        }
+    }
+
+    /// Filters of products among which search facets are looked for, variant filters are not applied
+    /// so that facet values stay visible when other values are selected
+    fn create_facets_scope_filters(prod: &SearchProductsByName) -> Vec<serde_json::Value> {
+        let mut filters = vec![json!({ "bool": ProductsElasticImpl::create_facets_query(prod) })];
+        if let Some(category_filter) = ProductsElasticImpl::create_category_filter(prod.options.clone()) {
+            filters.push(category_filter);
+        }
+        if let Some(in_stock_filter) = ProductsElasticImpl::create_in_stock_filter(&prod.options) {
+            filters.push(in_stock_filter);
+        }
+        filters
+    }
+
+    /// Attribute values aggregations, each value is counted in base products
+    fn create_attributes_aggregation() -> serde_json::Value {
+        json!({
+            "nested": { "path": "variants" },
+            "aggs": {
+                "attrs": {
+                    "nested": { "path": "variants.attrs" },
+                    "aggs": {
+                        "attr_ids": {
+                            "terms": { "field": "variants.attrs.attr_id", "size": FACET_BUCKETS_COUNT },
+                            "aggs": {
+                                "str_values": {
+                                    "terms": { "field": "variants.attrs.str_val", "size": FACET_BUCKETS_COUNT },
+                                    "aggs": {
+                                        "products": { "reverse_nested": {} }
+                                    }
+                                },
+                                "min_float_value": { "min": { "field": "variants.attrs.float_val" } },
+                                "max_float_value": { "max": { "field": "variants.attrs.float_val" } }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Filter of products matching the query with all filters except filters of attribute `attr_id`
+    fn create_attribute_counts_filter(prod: &SearchProductsByName, attr_id: i32) -> serde_json::Value {
+        let options = prod.options.as_ref().map(|options| options.without_attribute_filter(attr_id));
+        let prod = SearchProductsByName { options, ..prod.clone() };
+
+        let mut filters = ProductsElasticImpl::create_facets_scope_filters(&prod);
+        filters.push(json!({
+            "nested": {
+                "path": "variants",
+                "query": { "bool": ProductsElasticImpl::create_variants_map_filters(&prod.options) }
+            }
+        }));
+        json!({ "bool": { "filter": filters } })
+    }
+
+    /// Aggregations of attribute facets: values are collected among all products of facets scope
+    /// and counted among products matching the query. Values of attribute having filters are counted
+    /// without its own filters, so that other values of it still can be selected together with selected ones
+    fn create_attributes_facets_aggregations(prod: &SearchProductsByName) -> serde_json::Value {
+        let mut aggregations = json!({
+            "facets": {
+                "global": {},
+                "aggs": {
+                    "attributes": {
+                        "filter": { "bool": { "filter": ProductsElasticImpl::create_facets_scope_filters(prod) } },
+                        "aggs": {
+                            "variants": ProductsElasticImpl::create_attributes_aggregation()
+                        }
+                    }
+                }
+            },
+            "attributes": ProductsElasticImpl::create_attributes_aggregation()
+        });
+
+        let filtered_attributes_ids = prod
+            .options
+            .as_ref()
+            .map(|options| options.filtered_attributes_ids())
+            .unwrap_or_default();
+        if !filtered_attributes_ids.is_empty() {
+            let counts_filters = filtered_attributes_ids
+                .into_iter()
+                .map(|id| (id.to_string(), ProductsElasticImpl::create_attribute_counts_filter(prod, id)))
+                .collect::<serde_json::Map<String, serde_json::Value>>();
+            aggregations["facets"]["aggs"]["attribute_counts"] = json!({
+                "filters": { "filters": counts_filters },
+                "aggs": {
+                    "variants": ProductsElasticImpl::create_attributes_aggregation()
+                }
+            });
+        }
+
+        aggregations
+    }
+
+    /// Adds counts of attribute values found in `aggregation`, only values of attribute `only_id` are added if it is present
+    fn add_attribute_values_counts(aggregation: &serde_json::Value, only_id: Option<i64>, counts: &mut HashMap<(i64, String), u64>) {
+        if let Some(buckets) = aggregation["attrs"]["attr_ids"]["buckets"].as_array() {
+            for bucket in buckets {
+                if let (Some(id), Some(values)) = (bucket["key"].as_i64(), bucket["str_values"]["buckets"].as_array()) {
+                    if only_id.map_or(false, |only_id| only_id != id) {
+                        continue;
+                    }
+                    for value in values {
+                        if let (Some(key), Some(count)) = (value["key"].as_str(), value["products"]["doc_count"].as_u64()) {
+                            counts.insert((id, key.to_string()), count);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn create_attribute_filters_from_aggregations(aggs_raw: &serde_json::Value) -> Vec<AttributeFilter> {
+        let mut counts = HashMap::<(i64, String), u64>::new();
+        ProductsElasticImpl::add_attribute_values_counts(&aggs_raw["attributes"], None, &mut counts);
+
+        // Values of filtered attributes are counted without own filters of the attribute
+        if let Some(buckets) = aggs_raw["facets"]["attribute_counts"]["buckets"].as_object() {
+            for (id, bucket) in buckets {
+                if let Ok(id) = id.parse::<i64>() {
+                    counts.retain(|&(counted_id, _), _| counted_id != id);
+                    ProductsElasticImpl::add_attribute_values_counts(&bucket["variants"], Some(id), &mut counts);
+                }
+            }
+        }
+
+        aggs_raw["facets"]["attributes"]["variants"]["attrs"]["attr_ids"]["buckets"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .flat_map(|bucket| ProductsElasticImpl::create_attribute_filters_from_bucket(bucket, &counts))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn create_attribute_filters_from_bucket(bucket: &serde_json::Value, counts: &HashMap<(i64, String), u64>) -> Vec<AttributeFilter> {
+        let mut attribute_filters = vec![];
+        let id = match bucket["key"].as_i64() {
+            Some(id) => id,
+            None => return attribute_filters,
+        };
+
+        let values = bucket["str_values"]["buckets"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .filter_map(|value| value["key"].as_str().map(|value| value.to_string()))
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        if !values.is_empty() {
+            let values_counts = values
+                .iter()
+                .map(|value| AttributeValueCount {
+                    value: value.clone(),
+                    count: counts.get(&(id, value.clone())).cloned().unwrap_or_default(),
+                })
+                .collect();
+            attribute_filters.push(AttributeFilter {
+                id: id as i32,
+                equal: Some(EqualFilter {
+                    values,
+                    counts: Some(values_counts),
+                }),
+                range: None,
+            });
+        }
+
+        let mut range = RangeFilter::default();
+        if let Some(min_value) = bucket["min_float_value"]["value"].as_f64() {
+            range.add_value(min_value);
+        }
+        if let Some(max_value) = bucket["max_float_value"]["value"].as_f64() {
+            range.add_value(max_value);
+        }
+        if range != RangeFilter::default() {
+            attribute_filters.push(AttributeFilter {
+                id: id as i32,
+                equal: None,
+                range: Some(range),
+            });
+        }
+
+        attribute_filters
+    }
+}
+
+impl ProductsElastic for ProductsElasticImpl {
+    /// Find specific products by name limited by `count` parameters
+    fn search_by_name(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
+        log_elastic_req(&prod);
+        let query_map = ProductsElasticImpl::create_search_by_name_query(&prod);
         let sorting = ProductsElasticImpl::create_sorting(prod.options.clone());
 
         let query = json!({
             "from" : offset, "size" : count,
-            "query": {
-                "bool" : query_map
-            },
+            "query": ProductsElasticImpl::create_ranked_query(query_map, &self.ranking),
             "sort" : sorting
         })
         .to_string();
@@ -368,6 +757,59 @@ impl ProductsElastic for ProductsElasticImpl {
         )
     }
 
+    /// Find products by name limited by `count` parameters together with total count, price range, categories and attributes
+    fn search_faceted(&self, prod: SearchProductsByName, count: i32, offset: i32) -> RepoFuture<ElasticProductsFacetedSearch> {
+        log_elastic_req(&prod);
+        let query_map = ProductsElasticImpl::create_search_by_name_query(&prod);
+        let sorting = ProductsElasticImpl::create_sorting(prod.options.clone());
+        let price_aggregations = ProductsElasticImpl::create_price_aggregations(prod.options.clone().and_then(|o| o.currency_map));
+
+        let mut aggregations = ProductsElasticImpl::create_attributes_facets_aggregations(&prod);
+        aggregations["facets"]["aggs"]["price"] = json!({
+            "filter": { "bool": { "filter": ProductsElasticImpl::create_facets_scope_filters(&prod) } },
+            "aggs": {
+                "variants": {
+                    "nested": { "path": "variants" },
+                    "aggs": price_aggregations
+                }
+            }
+        });
+        aggregations["facets"]["aggs"]["categories"] = json!({
+            "filter": { "bool": ProductsElasticImpl::create_facets_query(&prod) },
+            "aggs": {
+                "category_ids": { "terms": { "field": "category_id", "size": FACET_BUCKETS_COUNT } }
+            }
+        });
+
+        let query = json!({
+            "from" : offset, "size" : count,
+            "query": ProductsElasticImpl::create_ranked_query(query_map, &self.ranking),
+            "sort" : sorting,
+            "aggregations": aggregations
+        })
+        .to_string();
+
+        let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Product);
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set(ContentLength(query.len() as u64));
+        trace!("search_faceted query = '{}'", query);
+        Box::new(
+            self.client_handle
+                .request::<SearchResponse<ElasticProduct>>(Method::Post, url, Some(query), Some(headers))
+                .inspect(|ref res| log_elastic_resp(res))
+                .map(ProductsElasticImpl::create_faceted_search_from_response)
+                .map_err(move |e| {
+                    e.context(format!(
+                        "Faceted search product by name error occurred. Prod: {:?}, count: {:?}, offset: {:?}",
+                        prod, count, offset
+                    ))
+                    .context(Error::ElasticSearch)
+                    .into()
+                }),
+        )
+    }
+
     /// Find product by views limited by `count` and `offset` parameters
     fn search_most_viewed(&self, prod: MostViewedProducts, count: i32, offset: i32) -> RepoFuture<Vec<ElasticProduct>> {
         log_elastic_req(&prod);
@@ -465,22 +907,28 @@ impl ProductsElastic for ProductsElasticImpl {
         query_map.insert("must".to_string(), discount_exists);
 
         let mut filters: Vec<serde_json::Value> = vec![];
+        let mut variants_map = serde_json::Map::<String, serde_json::Value>::new();
+        variants_map.insert(
+            "filter".to_string(),
+            json!([
+                { "exists": {
+                    "field": "variants.discount"
+                }},
+                { "range": {
+                    "variants.discount": {
+                        "gt": 0
+                    }
+                }}
+            ]),
+        );
+        if ProductsElasticImpl::is_in_stock_only(&prod.options) {
+            variants_map.insert("must_not".to_string(), ProductsElasticImpl::create_out_of_stock_variant_filter());
+        }
         let variants = json!({
             "nested":{
                 "path":"variants",
                 "query":{
-                    "bool": {
-                        "filter": [
-                            { "exists": {
-                                "field": "variants.discount"
-                            }},
-                            { "range": {
-                                "variants.discount": {
-                                    "gt": 0
-                                }
-                            }}
-                        ]
-                    }
+                    "bool": variants_map
                 },
                 "inner_hits": {
                     "_source" : false,
@@ -549,45 +997,29 @@ impl ProductsElastic for ProductsElasticImpl {
         )
     }
 
-    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, _offset: i32) -> RepoFuture<Vec<String>> {
+    fn auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
         log_elastic_req(&name);
         let product_name = name.name.to_lowercase();
 
-        let store = if let Some(store_id) = name.store_id {
-            if let Some(status) = name.status {
-                json!([format!("{}_{}", store_id, status)]) // workaround because elastic doesn't afford to ANY contexts
-            } else {
-                let statuses: Vec<String> = ModerationStatus::enum_iter().map(|m| format!("{}_{}", store_id, m)).collect();
-                json!(statuses)
-            }
-        } else {
-            if let Some(status) = name.status {
-                let status = format!("{}", status);
-                json!([status])
-            } else {
-                json!([])
-            }
-        };
+        let mut filters: Vec<serde_json::Value> = vec![];
+        if let Some(store_id) = name.store_id {
+            filters.push(json!({ "term": {"store_id": store_id}}));
+        }
+        if let Some(status) = name.status {
+            filters.push(json!({ "term": {"status": status.to_string()}}));
+        }
 
-        let suggest = json!({
-            "name-suggest" : {
-                "prefix" : product_name,
-                "completion" : {
-                    "field" : "suggest",
-                    "size" : count,
-                    "skip_duplicates": true,
-                    "fuzzy": true,
-                    "contexts": {
-                        "store_and_status": store
-                    }
+        let query = json!({
+            "from" : offset, "size" : count,
+            "_source": false,
+            "query": {
+                "bool" : {
+                    "must": autocomplete_query("name", &product_name),
+                    "filter": filters
                 }
             }
-        });
-
-        let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
-        query_map.insert("_source".to_string(), serde_json::Value::Bool(false));
-        query_map.insert("suggest".to_string(), suggest);
-        let query = serde_json::Value::Object(query_map).to_string();
+        })
+        .to_string();
         trace!("auto_complete query = '{}'", query);
         let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Product);
         let mut headers = Headers::new();
@@ -597,11 +1029,11 @@ impl ProductsElastic for ProductsElasticImpl {
             self.client_handle
                 .request::<SearchResponse<ElasticProduct>>(Method::Post, url, Some(query), Some(headers))
                 .inspect(|ref res| log_elastic_resp(res))
-                .map(|res| res.suggested_texts())
+                .map(|res| autocomplete_suggestions("name", res.into_hits()))
                 .map_err(move |e| {
                     e.context(format!(
                         "Auto complete product name error occurred. Name: {:?}, count: {}, offset: {}",
-                        name, count, _offset
+                        name, count, offset
                     ))
                     .context(Error::ElasticSearch)
                     .into()
@@ -613,7 +1045,7 @@ impl ProductsElastic for ProductsElasticImpl {
     fn aggregate_categories(&self, name: String) -> RepoFuture<Vec<CategoryId>> {
         log_elastic_req(&name);
         let name = name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&name);
+        let name_query = fuzzy_search_by_name_query(&name, &[]);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
         if !name.is_empty() {
@@ -672,7 +1104,7 @@ impl ProductsElastic for ProductsElasticImpl {
         log_elastic_req(&prod);
         let product_name = prod.name.to_lowercase();
 
-        let name_query = fuzzy_search_by_name_query(&product_name);
+        let name_query = fuzzy_search_by_name_query(&product_name, &prod.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
         if !product_name.is_empty() {
@@ -699,73 +1131,29 @@ impl ProductsElastic for ProductsElasticImpl {
             filters.push(json!({ "term": {"store_status": status.to_string()}}));
         }
 
+        if let Some(in_stock_filter) = ProductsElasticImpl::create_in_stock_filter(&prod.options) {
+            filters.push(in_stock_filter);
+        }
+
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
 
-        let currency_map = prod.options.clone().and_then(|o| o.currency_map);
+        let price_aggregations = ProductsElasticImpl::create_price_aggregations(prod.options.clone().and_then(|o| o.currency_map));
 
-        let query = if let Some(currency_map) = currency_map {
-            json!({
-                "size": 0,
-                "query": {
-                        "bool" : query_map
-                    },
-                "aggregations": {
-                    "variants" : {
-                        "nested" : {
-                            "path" : "variants"
-                        },
-                        "aggs" : {
-                            "min_price" : {
-                                "min" : {
-                                "script": {
-                                            "lang": "painless",
-                                            "params": { "cur_map": currency_map },
-                                            "source": r###"
-                                                def cur = doc['variants.currency'].value;
-                                                def koef = params.cur_map[cur];
-                                                return doc['variants.price'].value / koef;
-                                            "###,
-                                        }
-                                    }
-                            },
-                            "max_price" : {
-                                "max" : {
-                                "script": {
-                                            "lang": "painless",
-                                            "params": { "cur_map": currency_map },
-                                            "source": r###"
-                                                def cur = doc['variants.currency'].value;
-                                                def koef = params.cur_map[cur];
-                                                return doc['variants.price'].value / koef;
-                                            "###,
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-            })
-            .to_string()
-        } else {
-            json!({
-                "size": 0,
-                "query": {
-                        "bool" : query_map
+        let query = json!({
+            "size": 0,
+            "query": {
+                    "bool" : query_map
+                },
+            "aggregations": {
+                "variants" : {
+                    "nested" : {
+                        "path" : "variants"
                     },
-                "aggregations": {
-                    "variants" : {
-                        "nested" : {
-                            "path" : "variants"
-                        },
-                        "aggs" : {
-                            "min_price" : { "min" : { "field" : "variants.price" } },
-                            "max_price" : { "max" : { "field" : "variants.price" } }
-                        }
-                    }
+                    "aggs" : price_aggregations
                 }
-            })
-            .to_string()
-        };
+            }
+        })
+        .to_string();
 
         let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Product);
         let mut headers = Headers::new();
@@ -796,11 +1184,48 @@ impl ProductsElastic for ProductsElasticImpl {
         )
     }
 
+    /// Find attribute values with count of products having them
+    fn aggregate_attributes(&self, prod: SearchProductsByName) -> RepoFuture<Vec<AttributeFilter>> {
+        log_elastic_req(&prod);
+        let query_map = ProductsElasticImpl::create_search_by_name_query(&prod);
+        let aggregations = ProductsElasticImpl::create_attributes_facets_aggregations(&prod);
+
+        let query = json!({
+            "size": 0,
+            "query": {
+                "bool" : query_map
+            },
+            "aggregations": aggregations
+        })
+        .to_string();
+
+        let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Product);
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set(ContentLength(query.len() as u64));
+        trace!("aggregate_attributes query = '{}'", query);
+        Box::new(
+            self.client_handle
+                .request::<SearchResponse<ElasticProduct>>(Method::Post, url, Some(query), Some(headers))
+                .inspect(|ref res| log_elastic_resp(res))
+                .map(|res| {
+                    res.aggs_raw()
+                        .map(ProductsElasticImpl::create_attribute_filters_from_aggregations)
+                        .unwrap_or_default()
+                })
+                .map_err(move |e| {
+                    e.context(format!("Aggregate attributes error occurred. Prod: {:?}", prod))
+                        .context(Error::ElasticSearch)
+                        .into()
+                }),
+        )
+    }
+
     fn count(&self, prod: SearchProductsByName) -> RepoFuture<i32> {
         log_elastic_req(&prod);
         let product_name = prod.name.to_lowercase();
 
-        let name_query = fuzzy_search_by_name_query(&product_name);
+        let name_query = fuzzy_search_by_name_query(&product_name, &prod.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
         if !product_name.is_empty() {
@@ -830,6 +1255,10 @@ impl ProductsElastic for ProductsElasticImpl {
 
         filters.push(json!({ "term": {"store_status": "published"}}));
 
+        if let Some(in_stock_filter) = ProductsElasticImpl::create_in_stock_filter(&prod.options) {
+            filters.push(in_stock_filter);
+        }
+
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
 
         let query = json!({
@@ -858,38 +1287,341 @@ impl ProductsElastic for ProductsElasticImpl {
     }
 }
 
-fn fuzzy_search_by_name_query(name: &str) -> serde_json::Value {
+fn fuzzy_search_by_name_query(name: &str, synonyms: &[String]) -> serde_json::Value {
     json!({
         "bool" : {
             "should" : [
-                {"nested": {
-                    "path": "name",
-                    "query": {
-                        "match": {
-                            "name.text":{
-                                "query":name,
-                                "fuzziness":"AUTO"
-                            }
+                translated_text_query("name", name, synonyms, true),
+                translated_text_query("short_description", name, synonyms, false),
+                translated_text_query("long_description", name, synonyms, false)
+            ]
+        }
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json;
+    use std::collections::HashMap;
+
+    use stq_types::{BaseProductId, CategoryId, ProductId};
+
+    use elastic::products::*;
+
+    fn value_count(value: &str, count: u64) -> AttributeValueCount {
+        AttributeValueCount {
+            value: value.to_string(),
+            count,
+        }
+    }
+
+    fn equal_attribute_filter(id: i32, values: &[&str]) -> AttributeFilter {
+        AttributeFilter {
+            id,
+            equal: Some(EqualFilter {
+                values: values.iter().map(|value| value.to_string()).collect(),
+                counts: None,
+            }),
+            range: None,
+        }
+    }
+
+    fn attr_ids_aggregation() -> serde_json::Value {
+        json!({
+            "attrs": {
+                "attr_ids": {
+                    "buckets": [
+                        {
+                            "key": 1,
+                            "str_values": {
+                                "buckets": [
+                                    { "key": "red", "products": { "doc_count": 3 } },
+                                    { "key": "blue", "products": { "doc_count": 1 } }
+                                ]
+                            },
+                            "min_float_value": { "value": null },
+                            "max_float_value": { "value": null }
+                        },
+                        {
+                            "key": 2,
+                            "str_values": { "buckets": [] },
+                            "min_float_value": { "value": 10.0 },
+                            "max_float_value": { "value": 42.5 }
                         }
+                    ]
+                }
+            }
+        })
+    }
+
+    fn search_response() -> SearchResponse<ElasticProduct> {
+        serde_json::from_value(json!({
+            "took": 1,
+            "timed_out": false,
+            "_shards": { "total": 1, "successful": 1, "failed": 0 },
+            "hits": {
+                "total": 12,
+                "max_score": 1.0,
+                "hits": [{
+                    "_index": "products",
+                    "_type": "_doc",
+                    "_score": 1.0,
+                    "_source": {
+                        "id": 5,
+                        "name": [{ "lang": "en", "text": "Jacket" }],
+                        "short_description": [{ "lang": "en", "text": "Warm jacket" }],
+                        "long_description": null,
+                        "views": 10,
+                        "rating": 4.5,
+                        "variants": [],
+                        "category_id": 3
+                    },
+                    "inner_hits": {
+                        "variants": { "hits": { "hits": [{ "fields": { "variants.prod_id": [7, 8] } }] } }
                     }
-                }},
-                {"nested": {
-                    "path": "short_description",
-                    "query": {
-                        "match": {
-                            "short_description.text": name
+                }]
+            },
+            "aggregations": {
+                "facets": {
+                    "price": { "variants": { "min_price": { "value": 15.0 }, "max_price": { "value": 120.0 } } },
+                    "categories": { "category_ids": { "buckets": [{ "key": 3, "doc_count": 12 }, { "key": 4, "doc_count": 2 }] } },
+                    "attributes": { "variants": attr_ids_aggregation() }
+                },
+                "attributes": {
+                    "attrs": {
+                        "attr_ids": {
+                            "buckets": [{ "key": 1, "str_values": { "buckets": [{ "key": "red", "products": { "doc_count": 2 } }] } }]
                         }
                     }
-                }},
-                {"nested": {
-                    "path": "long_description",
-                    "query": {
-                        "match": {
-                            "long_description.text": name
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_create_faceted_search_from_response() {
+        let search = ProductsElasticImpl::create_faceted_search_from_response(search_response());
+
+        assert_eq!(search.total_count, 12);
+        assert_eq!(search.products.len(), 1);
+        assert_eq!(search.products[0].id, BaseProductId(5));
+        assert_eq!(search.products[0].matched_variants_ids, Some(vec![ProductId(7), ProductId(8)]));
+        assert_eq!(
+            search.price_filter,
+            RangeFilter {
+                min_value: Some(15.0),
+                max_value: Some(120.0),
+            }
+        );
+        assert_eq!(search.category_ids, vec![CategoryId(3), CategoryId(4)]);
+
+        assert_eq!(search.attribute_filters.len(), 2);
+        assert_eq!(search.attribute_filters[0].id, 1);
+        assert_eq!(
+            search.attribute_filters[0].equal,
+            Some(EqualFilter {
+                values: vec!["red".to_string(), "blue".to_string()],
+                counts: Some(vec![value_count("red", 2), value_count("blue", 0)]),
+            })
+        );
+        assert_eq!(search.attribute_filters[1].id, 2);
+        assert_eq!(search.attribute_filters[1].equal, None);
+    }
+
+    #[test]
+    fn test_create_attribute_filters_from_bucket() {
+        let aggregation = attr_ids_aggregation();
+        let mut counts = HashMap::new();
+        counts.insert((1, "blue".to_string()), 5);
+
+        let filters = ProductsElasticImpl::create_attribute_filters_from_bucket(&aggregation["attrs"]["attr_ids"]["buckets"][0], &counts);
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].id, 1);
+        assert_eq!(
+            filters[0].equal,
+            Some(EqualFilter {
+                values: vec!["red".to_string(), "blue".to_string()],
+                counts: Some(vec![value_count("red", 0), value_count("blue", 5)]),
+            })
+        );
+        assert!(filters[0].range.is_none());
+
+        let filters = ProductsElasticImpl::create_attribute_filters_from_bucket(&aggregation["attrs"]["attr_ids"]["buckets"][1], &counts);
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].id, 2);
+        assert!(filters[0].equal.is_none());
+        assert_eq!(
+            filters[0].range,
+            Some(RangeFilter {
+                min_value: Some(10.0),
+                max_value: Some(42.5),
+            })
+        );
+
+        assert!(ProductsElasticImpl::create_attribute_filters_from_bucket(&json!({ "doc_count": 1 }), &counts).is_empty());
+    }
+
+    #[test]
+    fn test_create_attribute_filters_from_aggregations() {
+        let aggs_raw = json!({
+            "facets": {
+                "attributes": { "variants": attr_ids_aggregation() },
+                "attribute_counts": {
+                    "buckets": {
+                        "1": {
+                            "doc_count": 6,
+                            "variants": {
+                                "attrs": {
+                                    "attr_ids": {
+                                        "buckets": [
+                                            {
+                                                "key": 1,
+                                                "str_values": {
+                                                    "buckets": [
+                                                        { "key": "red", "products": { "doc_count": 4 } },
+                                                        { "key": "blue", "products": { "doc_count": 2 } }
+                                                    ]
+                                                }
+                                            },
+                                            { "key": 3, "str_values": { "buckets": [{ "key": "xl", "products": { "doc_count": 6 } }] } }
+                                        ]
+                                    }
+                                }
+                            }
                         }
                     }
-                }}
-            ]
+                }
+            },
+            "attributes": {
+                "attrs": {
+                    "attr_ids": {
+                        "buckets": [
+                            { "key": 1, "str_values": { "buckets": [{ "key": "red", "products": { "doc_count": 4 } }] } },
+                            { "key": 3, "str_values": { "buckets": [{ "key": "xl", "products": { "doc_count": 3 } }] } }
+                        ]
+                    }
+                }
+            }
+        });
+
+        let filters = ProductsElasticImpl::create_attribute_filters_from_aggregations(&aggs_raw);
+        assert_eq!(filters.len(), 2);
+        // Other values of filtered attribute are counted without its own filter
+        assert_eq!(
+            filters[0].equal,
+            Some(EqualFilter {
+                values: vec!["red".to_string(), "blue".to_string()],
+                counts: Some(vec![value_count("red", 4), value_count("blue", 2)]),
+            })
+        );
+        assert_eq!(
+            filters[1].range,
+            Some(RangeFilter {
+                min_value: Some(10.0),
+                max_value: Some(42.5),
+            })
+        );
+
+        assert!(ProductsElasticImpl::create_attribute_filters_from_aggregations(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_create_attributes_facets_aggregations() {
+        let mut prod = SearchProductsByName {
+            name: "jacket".to_string(),
+            ..Default::default()
+        };
+        let aggregations = ProductsElasticImpl::create_attributes_facets_aggregations(&prod);
+        assert!(aggregations["facets"]["aggs"]["attributes"].is_object());
+        assert!(aggregations["attributes"].is_object());
+        assert!(aggregations["facets"]["aggs"]["attribute_counts"].is_null());
+
+        prod.options = Some(ProductsSearchOptions {
+            attr_filters: Some(vec![equal_attribute_filter(2, &["xl"]), equal_attribute_filter(1, &["red"])]),
+            ..Default::default()
+        });
+        let aggregations = ProductsElasticImpl::create_attributes_facets_aggregations(&prod);
+        let counts_filters = aggregations["facets"]["aggs"]["attribute_counts"]["filters"]["filters"]
+            .as_object()
+            .unwrap();
+        assert_eq!(
+            counts_filters.keys().cloned().collect::<Vec<String>>(),
+            vec!["1".to_string(), "2".to_string()]
+        );
+
+        // Counts of attribute 1 are filtered by attribute 2 only
+        let counts_filter = counts_filters["1"].to_string();
+        assert!(counts_filter.contains("\"variants.attrs.attr_id\":2"));
+        assert!(!counts_filter.contains("\"variants.attrs.attr_id\":1"));
+    }
+
+    #[test]
+    fn test_create_sorting() {
+        assert!(ProductsElasticImpl::create_sorting(None).is_empty());
+
+        let options = ProductsSearchOptions {
+            sort_by: Some(ProductsSorting::Newest),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProductsElasticImpl::create_sorting(Some(options)),
+            vec![json!({ "created_at": { "order": "desc", "missing": "_last" } })]
+        );
+
+        let options = ProductsSearchOptions {
+            sort_by: Some(ProductsSorting::Relevance),
+            ..Default::default()
+        };
+        assert_eq!(
+            ProductsElasticImpl::create_sorting(Some(options)),
+            vec![json!({ "_score": { "order": "desc" } })]
+        );
+    }
+
+    #[test]
+    fn test_create_ranked_query() {
+        let mut query_map = serde_json::Map::new();
+        query_map.insert("filter".to_string(), json!([]));
+
+        let ranking = SearchRanking::default();
+        let query = ProductsElasticImpl::create_ranked_query(query_map.clone(), &ranking);
+        assert_eq!(query["function_score"]["query"], json!({ "bool": { "filter": [] } }));
+        assert_eq!(query["function_score"]["score_mode"], json!("sum"));
+
+        let functions = query["function_score"]["functions"].as_array().unwrap();
+        assert_eq!(functions.len(), 4);
+        for field in &["views", "rating", "store_rating"] {
+            let function = functions
+                .iter()
+                .find(|function| function["field_value_factor"]["field"] == json!(field))
+                .unwrap();
+            assert_eq!(function["field_value_factor"]["missing"], json!(0));
         }
-    })
+
+        // Products without creation date are not boosted by recency
+        let recency = functions.iter().find(|function| function["gauss"].is_object()).unwrap();
+        assert_eq!(recency["filter"], json!({ "exists": { "field": "created_at" } }));
+        assert_eq!(recency["gauss"]["created_at"]["scale"], json!("30d"));
+        assert_eq!(recency["weight"], json!(ranking.recency_weight));
+
+        let ranking = SearchRanking {
+            views_weight: 0.0,
+            rating_weight: 0.0,
+            recency_weight: 0.0,
+            store_rating_weight: 1.0,
+            ..Default::default()
+        };
+        let query = ProductsElasticImpl::create_ranked_query(query_map.clone(), &ranking);
+        let functions = query["function_score"]["functions"].as_array().unwrap();
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0]["field_value_factor"]["field"], json!("store_rating"));
+
+        let ranking = SearchRanking {
+            store_rating_weight: 0.0,
+            ..ranking
+        };
+        let query = ProductsElasticImpl::create_ranked_query(query_map, &ranking);
+        assert_eq!(query, json!({ "bool": { "filter": [] } }));
+    }
 }
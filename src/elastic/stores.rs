@@ -9,10 +9,17 @@ use stq_http::client::ClientHandle;
 
 use stq_types::CategoryId;
 
+use super::analysis::{autocomplete_query, autocomplete_suggestions, translated_text_query};
 use super::{log_elastic_req, log_elastic_resp};
-use models::{CountResponse, ElasticIndex, ElasticStore, SearchResponse, SearchStore, StoresSearchOptions};
+use models::{
+    AutoCompleteSuggestion, CountResponse, CountryLocalities, ElasticIndex, ElasticStore, SearchResponse, SearchStore, StoresSearchOptions,
+    StoresSorting,
+};
 use repos::types::RepoFuture;
 
+/// Max count of localities aggregated per country
+const LOCALITY_BUCKETS_COUNT: i32 = 100;
+
 /// StoresSearch repository, responsible for handling stores
 pub struct StoresElasticImpl {
     pub client_handle: ClientHandle,
@@ -24,12 +31,12 @@ pub trait StoresElastic {
     fn find_by_name(&self, search_store: SearchStore, count: i32, offset: i32) -> RepoFuture<Vec<ElasticStore>>;
     /// Search count of stores by name
     fn search_count(&self, search_store: SearchStore) -> RepoFuture<i32>;
-    /// Aggregate countries
-    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<String>>;
+    /// Aggregate countries with localities of them
+    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<CountryLocalities>>;
     /// Aggregate categories
     fn aggregate_categories(&self, search_store: SearchStore) -> RepoFuture<Vec<CategoryId>>;
     /// Auto complete
-    fn auto_complete(&self, name: String, count: i32, offset: i32) -> RepoFuture<Vec<String>>;
+    fn auto_complete(&self, name: String, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>>;
 }
 
 impl StoresElasticImpl {
@@ -42,10 +49,14 @@ impl StoresElasticImpl {
 
     fn create_elastic_filters(options: Option<StoresSearchOptions>) -> Vec<serde_json::Value> {
         let mut filters: Vec<serde_json::Value> = vec![];
-        let (category_id, country) = if let Some(options) = options {
-            (options.category_id, options.country)
+        if let Some(geo_distance) = StoresElasticImpl::create_geo_distance_filter(&options) {
+            filters.push(geo_distance);
+        }
+
+        let (category_id, country, locality) = if let Some(options) = options {
+            (options.category_id, options.country, options.locality)
         } else {
-            (None, None)
+            (None, None, None)
         };
 
         if let Some(country_name) = country {
@@ -65,8 +76,71 @@ impl StoresElasticImpl {
             filters.push(category);
         }
 
+        if let Some(locality) = locality {
+            filters.push(json!({
+                "term": {"locality": locality}
+            }));
+        }
+
         filters
     }
+
+    fn create_geo_distance_filter(options: &Option<StoresSearchOptions>) -> Option<serde_json::Value> {
+        let options = options.as_ref()?;
+        let location = options.location?;
+        let radius_km = options.radius_km?;
+
+        Some(json!({
+            "geo_distance": {
+                "distance": format!("{}km", radius_km),
+                "location": { "lat": location.latitude, "lon": location.longitude }
+            }
+        }))
+    }
+
+    /// Stores found by name are sorted by score unless other sorting is requested, others are sorted by rating
+    fn create_sorting(options: &Option<StoresSearchOptions>, by_name: bool) -> Vec<serde_json::Value> {
+        let sort_by = options.as_ref().and_then(|options| options.sort_by);
+        let location = options.as_ref().and_then(|options| options.location);
+
+        match (sort_by, location) {
+            (Some(StoresSorting::Distance), Some(location)) => vec![json!({
+                "_geo_distance": {
+                    "location": { "lat": location.latitude, "lon": location.longitude },
+                    "order": "asc",
+                    "unit": "km"
+                }
+            })],
+            (Some(StoresSorting::Rating), _) => vec![json!({ "rating" : { "order" : "desc"} })],
+            _ if !by_name => vec![json!({ "rating" : { "order" : "desc"} })],
+            _ => vec![],
+        }
+    }
+
+    fn create_countries_from_aggregations(aggs_raw: Option<&serde_json::Value>) -> Vec<CountryLocalities> {
+        let buckets = match aggs_raw.and_then(|aggs| aggs["countries"]["buckets"].as_array()) {
+            Some(buckets) => buckets,
+            None => return vec![],
+        };
+
+        buckets
+            .iter()
+            .filter_map(|bucket| {
+                let country = bucket["key"].as_str()?.to_string();
+                let localities = bucket["localities"]["buckets"]
+                    .as_array()
+                    .map(|localities| {
+                        localities
+                            .iter()
+                            .filter_map(|locality| locality["key"].as_str().map(|locality| locality.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Some(CountryLocalities { country, localities })
+            })
+            .collect()
+    }
 }
 
 impl StoresElastic for StoresElasticImpl {
@@ -74,7 +148,7 @@ impl StoresElastic for StoresElasticImpl {
     fn find_by_name(&self, search_store: SearchStore, count: i32, offset: i32) -> RepoFuture<Vec<ElasticStore>> {
         log_elastic_req(&search_store);
         let store_name = search_store.name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&store_name);
+        let name_query = fuzzy_search_by_name_query(&store_name, &search_store.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
 
@@ -101,26 +175,15 @@ impl StoresElastic for StoresElasticImpl {
         filters.push(product_categories);
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
 
-        let query = if store_name.is_empty() {
-            json!({
-                "from" : offset, "size" : count,
-                "query": {
-                    "bool" : query_map
-                },
-                "sort" : [
-                    { "rating" : { "order" : "desc"} }
-                ]
-            })
-            .to_string()
-        } else {
-            json!({
-                "from" : offset, "size" : count,
-                "query": {
-                    "bool" : query_map
-                }
-            })
-            .to_string()
-        };
+        let sorting = StoresElasticImpl::create_sorting(&search_store.options, !store_name.is_empty());
+        let query = json!({
+            "from" : offset, "size" : count,
+            "query": {
+                "bool" : query_map
+            },
+            "sort" : sorting
+        })
+        .to_string();
 
         let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Store);
         let mut headers = Headers::new();
@@ -145,29 +208,21 @@ impl StoresElastic for StoresElasticImpl {
     }
 
     /// Auto Complete
-    fn auto_complete(&self, name: String, count: i32, _offset: i32) -> RepoFuture<Vec<String>> {
+    fn auto_complete(&self, name: String, count: i32, offset: i32) -> RepoFuture<Vec<AutoCompleteSuggestion>> {
         log_elastic_req(&name);
         let name = name.to_lowercase();
 
-        let suggest = json!({
-            "name-suggest" : {
-                "prefix" : name,
-                "completion" : {
-                    "field" : "suggest",
-                    "size" : count,
-                    "skip_duplicates": true,
-                    "fuzzy": true,
-                    "contexts": {
-                        "status": "published"
-                    }
+        let query = json!({
+            "from" : offset, "size" : count,
+            "_source": false,
+            "query": {
+                "bool" : {
+                    "must": autocomplete_query("name", &name),
+                    "filter": { "term": {"status": "published"}}
                 }
             }
-        });
-
-        let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
-        query_map.insert("_source".to_string(), serde_json::Value::Bool(false));
-        query_map.insert("suggest".to_string(), suggest);
-        let query = serde_json::Value::Object(query_map).to_string();
+        })
+        .to_string();
 
         let url = format!("http://{}/{}/_search", self.elastic_address, ElasticIndex::Store);
         let mut headers = Headers::new();
@@ -178,11 +233,11 @@ impl StoresElastic for StoresElasticImpl {
             self.client_handle
                 .request::<SearchResponse<ElasticStore>>(Method::Post, url, Some(query), Some(headers))
                 .inspect(|ref res| log_elastic_resp(res))
-                .map(|res| res.suggested_texts())
+                .map(|res| autocomplete_suggestions("name", res.into_hits()))
                 .map_err(move |e| {
                     e.context(format!(
                         "Auto complete store name error occurred. Name: {:?}, count: {:?}, offset: {:?}",
-                        name, count, _offset
+                        name, count, offset
                     ))
                     .context(Error::ElasticSearch)
                     .into()
@@ -194,7 +249,7 @@ impl StoresElastic for StoresElasticImpl {
     fn search_count(&self, search_store: SearchStore) -> RepoFuture<i32> {
         log_elastic_req(&search_store);
         let store_name = search_store.name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&store_name);
+        let name_query = fuzzy_search_by_name_query(&store_name, &search_store.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
 
@@ -204,6 +259,9 @@ impl StoresElastic for StoresElasticImpl {
 
         let mut filters: Vec<serde_json::Value> = vec![];
         filters.push(json!({ "term": {"status": "published"}}));
+        if let Some(geo_distance) = StoresElasticImpl::create_geo_distance_filter(&search_store.options) {
+            filters.push(geo_distance);
+        }
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
 
         let query = json!({
@@ -231,11 +289,11 @@ impl StoresElastic for StoresElasticImpl {
         )
     }
 
-    /// Aggregate countries
-    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<String>> {
+    /// Aggregate countries with localities of them
+    fn aggregate_countries(&self, search_store: SearchStore) -> RepoFuture<Vec<CountryLocalities>> {
         log_elastic_req(&search_store);
         let store_name = search_store.name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&store_name);
+        let name_query = fuzzy_search_by_name_query(&store_name, &search_store.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
 
@@ -245,6 +303,9 @@ impl StoresElastic for StoresElasticImpl {
 
         let mut filters: Vec<serde_json::Value> = vec![];
         filters.push(json!({ "term": {"status": "published"}}));
+        if let Some(geo_distance) = StoresElasticImpl::create_geo_distance_filter(&search_store.options) {
+            filters.push(geo_distance);
+        }
         query_map.insert("filter".to_string(), serde_json::Value::Array(filters));
 
         let query = json!({
@@ -253,9 +314,14 @@ impl StoresElastic for StoresElasticImpl {
                 "bool" : query_map
             },
         "aggregations": {
-            "my_agg": {
+            "countries": {
                 "terms": {
                     "field": "country.keyword"
+                },
+                "aggs": {
+                    "localities": {
+                        "terms": { "field": "locality", "size": LOCALITY_BUCKETS_COUNT }
+                    }
                 }
             }
         }
@@ -271,17 +337,7 @@ impl StoresElastic for StoresElasticImpl {
             self.client_handle
                 .request::<SearchResponse<ElasticStore>>(Method::Post, url, Some(query), Some(headers))
                 .inspect(|ref res| log_elastic_resp(res))
-                .map(|res| {
-                    let mut countries = vec![];
-                    for ag in res.aggs() {
-                        if let Some(my_agg) = ag.get("my_agg") {
-                            if let Some(country) = my_agg.as_str() {
-                                countries.push(country.to_string());
-                            }
-                        }
-                    }
-                    countries
-                })
+                .map(|res| StoresElasticImpl::create_countries_from_aggregations(res.aggs_raw()))
                 .map_err(move |e| {
                     e.context(format!("Aggregate countries for store error occurred. Store: {:?}", search_store))
                         .context(Error::ElasticSearch)
@@ -294,7 +350,7 @@ impl StoresElastic for StoresElasticImpl {
     fn aggregate_categories(&self, search_store: SearchStore) -> RepoFuture<Vec<CategoryId>> {
         log_elastic_req(&search_store);
         let store_name = search_store.name.to_lowercase();
-        let name_query = fuzzy_search_by_name_query(&store_name);
+        let name_query = fuzzy_search_by_name_query(&store_name, &search_store.name_synonyms);
 
         let mut query_map = serde_json::Map::<String, serde_json::Value>::new();
 
@@ -370,18 +426,100 @@ impl StoresElastic for StoresElasticImpl {
     }
 }
 
-fn fuzzy_search_by_name_query(name: &str) -> serde_json::Value {
-    json!({
-        "nested" : {
-                "path" : "name",
-                "query" : {
-                    "match": {
-                        "name.text":{
-                            "query":name,
-                            "fuzziness":"AUTO"
-                        }
-                    }
+fn fuzzy_search_by_name_query(name: &str, synonyms: &[String]) -> serde_json::Value {
+    translated_text_query("name", name, synonyms, true)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use elastic::stores::*;
+    use models::GeoPoint;
+
+    fn search_options(location: Option<GeoPoint>, radius_km: Option<f64>, sort_by: Option<StoresSorting>) -> Option<StoresSearchOptions> {
+        Some(StoresSearchOptions {
+            category_id: None,
+            country: None,
+            locality: None,
+            location,
+            radius_km,
+            sort_by,
+        })
+    }
+
+    fn location() -> GeoPoint {
+        GeoPoint {
+            latitude: 55.75,
+            longitude: 37.62,
+        }
+    }
+
+    #[test]
+    fn test_create_elastic_filters_with_distance() {
+        let filters = StoresElasticImpl::create_elastic_filters(search_options(Some(location()), Some(10.5), None));
+        assert_eq!(
+            filters,
+            vec![json!({
+                "geo_distance": {
+                    "distance": "10.5km",
+                    "location": { "lat": 55.75, "lon": 37.62 }
                 }
+            })]
+        );
+
+        let filters = StoresElasticImpl::create_elastic_filters(search_options(Some(location()), None, None));
+        assert!(filters.is_empty());
+    }
+
+    #[test]
+    fn test_create_sorting_by_distance() {
+        let sorting = StoresElasticImpl::create_sorting(&search_options(Some(location()), None, Some(StoresSorting::Distance)), true);
+        assert_eq!(
+            sorting,
+            vec![json!({
+                "_geo_distance": {
+                    "location": { "lat": 55.75, "lon": 37.62 },
+                    "order": "asc",
+                    "unit": "km"
+                }
+            })]
+        );
+
+        let sorting = StoresElasticImpl::create_sorting(&None, true);
+        assert!(sorting.is_empty());
+
+        let sorting = StoresElasticImpl::create_sorting(&None, false);
+        assert_eq!(sorting, vec![json!({ "rating": { "order": "desc" } })]);
+    }
+
+    #[test]
+    fn test_create_countries_from_aggregations() {
+        let aggregations = json!({
+            "countries": {
+                "buckets": [
+                    {
+                        "key": "Russia",
+                        "doc_count": 3,
+                        "localities": { "buckets": [{ "key": "Moscow", "doc_count": 2 }, { "key": "Kazan", "doc_count": 1 }] }
+                    },
+                    { "key": "Latvia", "doc_count": 1, "localities": { "buckets": [] } }
+                ]
             }
-    })
+        });
+
+        let countries = StoresElasticImpl::create_countries_from_aggregations(Some(&aggregations));
+        assert_eq!(
+            countries,
+            vec![
+                CountryLocalities {
+                    country: "Russia".to_string(),
+                    localities: vec!["Moscow".to_string(), "Kazan".to_string()],
+                },
+                CountryLocalities {
+                    country: "Latvia".to_string(),
+                    localities: vec![],
+                },
+            ]
+        );
+        assert!(StoresElasticImpl::create_countries_from_aggregations(None).is_empty());
+    }
 }
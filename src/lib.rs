@@ -11,6 +11,7 @@
 #![recursion_limit = "128"]
 extern crate chrono;
 extern crate config as config_crate;
+extern crate csv;
 #[macro_use]
 extern crate diesel;
 #[macro_use]
@@ -28,6 +29,8 @@ extern crate log;
 extern crate num_traits;
 extern crate r2d2;
 extern crate r2d2_redis;
+#[cfg(test)]
+extern crate rand;
 extern crate regex;
 extern crate reqwest;
 extern crate rust_decimal;
@@ -86,11 +89,11 @@ use stq_cache::cache::{redis::RedisCache, Cache, NullCache, TypedCache};
 use stq_http::controller::Application;
 use tokio_core::reactor::Core;
 
-use config::{Config, ATTRIBUTE_CACHE_NAMESPACE, CATEGORY_CACHE_NAMESPACE, ROLES_CACHE_NAMESPACE};
+use config::{Config, OutboxRelaySink, ATTRIBUTE_CACHE_NAMESPACE, CATEGORY_CACHE_NAMESPACE, ROLES_CACHE_NAMESPACE};
 use controller::context::StaticContext;
 use errors::Error;
-use loaders::ticker;
-use repos::acl::RolesCacheImpl;
+use loaders::{outbox_relay, price_scheduler, reindex, ticker};
+use repos::acl::{AclPolicy, RolesCacheImpl};
 use repos::attributes::AttributeCacheImpl;
 use repos::categories::CategoryCacheImpl;
 use repos::repo_factory::ReposFactoryImpl;
@@ -161,18 +164,32 @@ pub fn start_server<F: FnOnce() + 'static>(config: Config, port: &Option<String>
         ),
     };
 
+    // Prepare ACL policy
+    let acl_policy = match config.acl.policy_file {
+        Some(ref path) => AclPolicy::from_file(path).unwrap_or_else(|why| {
+            error!("ACL Policy Initialization Error: {}", why);
+            process::exit(1);
+        }),
+        None => AclPolicy::default(),
+    };
+
     // Repo factory
-    let repo_factory = ReposFactoryImpl::new(roles_cache, category_cache, attribute_cache);
+    let repo_factory = ReposFactoryImpl::new(roles_cache, category_cache, attribute_cache, acl_policy);
 
     let context = StaticContext::new(db_pool, cpu_pool, client_handle, Arc::new(config), repo_factory);
 
+    let streaming_handle = handle.clone();
     let serve = Http::new()
         .serve_addr_handle(&address, &handle, move || {
             // Prepare application
             let controller = controller::ControllerImpl::new(context.clone());
             let app = Application::<Error>::new(controller);
 
-            Ok(app)
+            Ok(controller::streaming::StreamingApplication::new(
+                app,
+                context.clone(),
+                streaming_handle.clone(),
+            ))
         })
         .unwrap_or_else(|why| {
             error!("Http Server Initialization Error: {}", why);
@@ -255,3 +272,82 @@ pub fn start_ticker(config: Config) -> impl Future<Item = (), Error = FailureErr
 
     ticker::run(ctx)
 }
+
+pub fn start_price_scheduler(config: Config) -> impl Future<Item = (), Error = FailureError> {
+    let Config { server, price_scheduler, .. } = config;
+    let price_scheduler = price_scheduler.expect("Price scheduler config not found");
+
+    // Prepare database pool
+    let database_url = server.database.parse::<String>().expect("Failed to parse database URL");
+    let db_manager = ConnectionManager::<PgConnection>::new(database_url);
+    let db_pool = r2d2::Pool::builder().build(db_manager).expect("Failed to create connection pool");
+
+    let interval = Duration::from_secs(price_scheduler.interval_s);
+
+    let thread_pool = CpuPool::new(price_scheduler.thread_count);
+
+    let ctx = price_scheduler::PriceSchedulerContext {
+        db_pool,
+        interval,
+        thread_pool,
+    };
+
+    price_scheduler::run(ctx)
+}
+
+pub fn start_outbox_relay(config: Config) -> impl Future<Item = (), Error = FailureError> {
+    let Config { server, outbox_relay, .. } = config;
+    let outbox_relay = outbox_relay.expect("Outbox relay config not found");
+
+    // Prepare database pool
+    let database_url = server.database.parse::<String>().expect("Failed to parse database URL");
+    let db_manager = ConnectionManager::<PgConnection>::new(database_url);
+    let db_pool = r2d2::Pool::builder().build(db_manager).expect("Failed to create connection pool");
+
+    let sink: Arc<outbox_relay::OutboxSink> = match outbox_relay.sink {
+        OutboxRelaySink::Webhook => {
+            let url = outbox_relay.webhook_url.expect("Outbox relay webhook URL not found");
+            Arc::new(outbox_relay::WebhookSink::new(url))
+        }
+        OutboxRelaySink::File => {
+            let path = outbox_relay.file_path.expect("Outbox relay file path not found");
+            Arc::new(outbox_relay::FileSink::new(path))
+        }
+    };
+
+    let interval = Duration::from_secs(outbox_relay.interval_s);
+
+    let thread_pool = CpuPool::new(outbox_relay.thread_count);
+
+    let ctx = outbox_relay::OutboxRelayContext {
+        db_pool,
+        interval,
+        thread_pool,
+        batch_size: outbox_relay.batch_size,
+        sink,
+    };
+
+    outbox_relay::run(ctx)
+}
+
+pub fn start_reindex(config: Config, args: &reindex::ReindexArgs) -> Result<(), FailureError> {
+    let Config {
+        server,
+        reindex: reindex_config,
+        ..
+    } = config;
+    let reindex_config = reindex_config.expect("Reindex config not found");
+
+    // Prepare database pool
+    let database_url = server.database.parse::<String>().expect("Failed to parse database URL");
+    let db_manager = ConnectionManager::<PgConnection>::new(database_url);
+    let db_pool = r2d2::Pool::builder().build(db_manager).expect("Failed to create connection pool");
+
+    let ctx = reindex::ReindexContext {
+        db_pool,
+        elastic: reindex::ElasticIndexAdmin::new(server.elastic),
+        batch_size: reindex_config.batch_size,
+    };
+
+    reindex::run(&ctx, args)
+}
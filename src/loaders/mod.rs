@@ -1,3 +1,6 @@
+pub mod outbox_relay;
+pub mod price_scheduler;
+pub mod reindex;
 pub mod rocket_models;
 mod rocket_retail;
 pub mod services;
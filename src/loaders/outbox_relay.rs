@@ -0,0 +1,219 @@
+//! Relays catalog change events from outbox to the configured sink.
+//! Events are marked as published only after the sink has accepted them, so delivery is at-least-once
+//! and consumers are expected to deduplicate events by id.
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use diesel::{pg::PgConnection, r2d2::ConnectionManager};
+use failure::{Error as FailureError, Fail};
+use futures::{future, Future, Stream};
+use futures_cpupool::CpuPool;
+use r2d2::Pool;
+use reqwest;
+use serde_json;
+use tokio::timer::Interval;
+
+use models::OutboxEvent;
+use repos::acl::legacy_acl::SystemACL;
+use repos::outbox_events::{OutboxEventsRepo, OutboxEventsRepoImpl};
+use sentry::integrations::failure::capture_error;
+
+#[derive(Clone)]
+pub struct OutboxRelayContext {
+    pub db_pool: Pool<ConnectionManager<PgConnection>>,
+    pub interval: Duration,
+    pub thread_pool: CpuPool,
+    pub batch_size: i64,
+    pub sink: Arc<OutboxSink>,
+}
+
+/// Destination of relayed events, batch is either accepted as a whole or failed
+pub trait OutboxSink: Send + Sync {
+    fn send(&self, events: &[OutboxEvent]) -> Result<(), FailureError>;
+}
+
+/// Posts batches of events to HTTP endpoint as JSON array
+pub struct WebhookSink {
+    http_client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: String) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+impl OutboxSink for WebhookSink {
+    fn send(&self, events: &[OutboxEvent]) -> Result<(), FailureError> {
+        self.http_client.post(self.url.as_str()).json(events).send()?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Appends events to file in JSON Lines format
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutboxSink for FileSink {
+    fn send(&self, events: &[OutboxEvent]) -> Result<(), FailureError> {
+        let mut buf = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut buf, event)?;
+            buf.push(b'\n');
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&buf)?;
+        file.sync_data()?;
+
+        Ok(())
+    }
+}
+
+pub fn run(ctx: OutboxRelayContext) -> impl Future<Item = (), Error = FailureError> {
+    Interval::new(Instant::now(), ctx.interval)
+        .map_err(FailureError::from)
+        .fold(ctx, |ctx, _| {
+            debug!("Started relaying outbox events");
+            relay_outbox_events(ctx.clone()).then(|res| {
+                match res {
+                    Ok(relayed) => {
+                        debug!("Finished relaying outbox events: {} relayed", relayed);
+                    }
+                    Err(err) => {
+                        let err = FailureError::from(err.context("An error occurred while relaying outbox events"));
+                        error!("{:?}", &err);
+                        capture_error(&err);
+                    }
+                };
+
+                future::ok::<_, FailureError>(ctx)
+            })
+        })
+        .map(|_| ())
+}
+
+fn relay_outbox_events(ctx: OutboxRelayContext) -> impl Future<Item = usize, Error = FailureError> {
+    let OutboxRelayContext {
+        db_pool,
+        thread_pool,
+        batch_size,
+        sink,
+        ..
+    } = ctx;
+
+    thread_pool.spawn(future::lazy(move || {
+        let conn = db_pool.get().map_err(FailureError::from)?;
+        let outbox_repo = OutboxEventsRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+
+        relay_pending_events(&outbox_repo, &*sink, batch_size)
+    }))
+}
+
+/// Drains outbox batch by batch. Relaying stops at the first failed batch,
+/// so that events of the same entity never overtake each other.
+pub fn relay_pending_events(outbox_repo: &OutboxEventsRepo, sink: &OutboxSink, batch_size: i64) -> Result<usize, FailureError> {
+    let mut relayed = 0;
+
+    loop {
+        let events = outbox_repo.find_unpublished(batch_size)?;
+        if events.is_empty() {
+            return Ok(relayed);
+        }
+
+        let ids = events.iter().map(|event| event.id).collect::<Vec<_>>();
+        if let Err(err) = sink.send(&events) {
+            outbox_repo.mark_failed(ids.clone(), err.to_string())?;
+            return Err(err.context(format!("Relaying outbox events {:?} failed", ids)).into());
+        }
+
+        outbox_repo.mark_published(ids, SystemTime::now())?;
+        relayed += events.len();
+
+        if (events.len() as i64) < batch_size {
+            return Ok(relayed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use failure::Error as FailureError;
+
+    use loaders::outbox_relay::*;
+    use models::OutboxEvent;
+    use repos::repo_factory::tests::OutboxEventsRepoMock;
+
+    /// Sink recording ids of sent batches, fails batch number `fail_on_batch` counting from zero
+    #[derive(Default)]
+    struct OutboxSinkMock {
+        fail_on_batch: Option<usize>,
+        batches: Mutex<Vec<Vec<i64>>>,
+    }
+
+    impl OutboxSink for OutboxSinkMock {
+        fn send(&self, events: &[OutboxEvent]) -> Result<(), FailureError> {
+            let mut batches = self.batches.lock().unwrap();
+            batches.push(events.iter().map(|event| event.id).collect());
+            if Some(batches.len() - 1) == self.fail_on_batch {
+                return Err(format_err!("Sink is unavailable"));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_relay_pending_events() {
+        let outbox_repo = OutboxEventsRepoMock::with_unpublished(5);
+        let sink = OutboxSinkMock::default();
+
+        let relayed = relay_pending_events(&outbox_repo, &sink, 2).unwrap();
+        assert_eq!(relayed, 5);
+        assert_eq!(*sink.batches.lock().unwrap(), vec![vec![1, 2], vec![3, 4], vec![5]]);
+        assert_eq!(*outbox_repo.published.borrow(), vec![1, 2, 3, 4, 5]);
+        assert!(outbox_repo.failed.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_relay_pending_events_stops_on_first_failed_batch() {
+        let outbox_repo = OutboxEventsRepoMock::with_unpublished(5);
+        let sink = OutboxSinkMock {
+            fail_on_batch: Some(1),
+            ..Default::default()
+        };
+
+        let result = relay_pending_events(&outbox_repo, &sink, 2);
+        assert!(result.is_err());
+        assert_eq!(*sink.batches.lock().unwrap(), vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(*outbox_repo.published.borrow(), vec![1, 2]);
+        assert_eq!(*outbox_repo.failed.borrow(), vec![(vec![3, 4], "Sink is unavailable".to_string())]);
+    }
+
+    #[test]
+    fn test_relay_pending_events_with_empty_outbox() {
+        let outbox_repo = OutboxEventsRepoMock::default();
+        let sink = OutboxSinkMock::default();
+
+        let relayed = relay_pending_events(&outbox_repo, &sink, 2).unwrap();
+        assert_eq!(relayed, 0);
+        assert!(sink.batches.lock().unwrap().is_empty());
+    }
+}
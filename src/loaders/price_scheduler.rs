@@ -0,0 +1,117 @@
+use diesel::{pg::PgConnection, r2d2::ConnectionManager, Connection};
+use failure::{Error as FailureError, Fail};
+use futures::{future, Future, Stream};
+use futures_cpupool::CpuPool;
+use r2d2::Pool;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::timer::Interval;
+
+use models::ScheduledPriceChange;
+use repos::acl::legacy_acl::SystemACL;
+use repos::outbox_events::OutboxEventsRepoImpl;
+use repos::product_price_history::ProductPriceHistoryRepoImpl;
+use repos::products::ProductsRepoImpl;
+use repos::scheduled_price_changes::{ScheduledPriceChangesRepo, ScheduledPriceChangesRepoImpl};
+use sentry::integrations::failure::capture_error;
+use services::products::{apply_scheduled_price_change, revert_scheduled_price_change};
+
+#[derive(Clone)]
+pub struct PriceSchedulerContext {
+    pub db_pool: Pool<ConnectionManager<PgConnection>>,
+    pub interval: Duration,
+    pub thread_pool: CpuPool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ProcessedPriceChanges {
+    applied: usize,
+    reverted: usize,
+}
+
+pub fn run(ctx: PriceSchedulerContext) -> impl Future<Item = (), Error = FailureError> {
+    Interval::new(Instant::now(), ctx.interval)
+        .map_err(FailureError::from)
+        .fold(ctx, |ctx, _| {
+            debug!("Started processing scheduled price changes");
+            process_scheduled_price_changes(ctx.clone()).then(|res| {
+                match res {
+                    Ok(ProcessedPriceChanges { applied, reverted }) => {
+                        debug!(
+                            "Finished processing scheduled price changes: {} applied, {} reverted",
+                            applied, reverted
+                        );
+                    }
+                    Err(err) => {
+                        let err = FailureError::from(err.context("An error occurred while processing scheduled price changes"));
+                        error!("{:?}", &err);
+                        capture_error(&err);
+                    }
+                };
+
+                future::ok::<_, FailureError>(ctx)
+            })
+        })
+        .map(|_| ())
+}
+
+fn process_scheduled_price_changes(ctx: PriceSchedulerContext) -> impl Future<Item = ProcessedPriceChanges, Error = FailureError> {
+    let PriceSchedulerContext { db_pool, thread_pool, .. } = ctx;
+
+    thread_pool.spawn(future::lazy(move || {
+        let conn = db_pool.get().map_err(FailureError::from)?;
+        let products_repo = ProductsRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+        let price_history_repo = ProductPriceHistoryRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+        let scheduled_price_changes_repo = ScheduledPriceChangesRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+        let outbox_repo = OutboxEventsRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+
+        let now = SystemTime::now();
+        let mut processed = ProcessedPriceChanges::default();
+
+        // Reverting first lets a change that starts right after another one ends see the original prices
+        for scheduled_price_change in scheduled_price_changes_repo.find_due_to_revert(now)? {
+            let id = scheduled_price_change.id;
+            let res = conn.transaction::<ScheduledPriceChange, FailureError, _>(|| {
+                revert_scheduled_price_change(
+                    &products_repo,
+                    &price_history_repo,
+                    &scheduled_price_changes_repo,
+                    &outbox_repo,
+                    scheduled_price_change,
+                    None,
+                )
+            });
+
+            match res {
+                Ok(_) => processed.reverted += 1,
+                Err(err) => report_error(err.context(format!("Reverting scheduled price change {} failed", id))),
+            }
+        }
+
+        for scheduled_price_change in scheduled_price_changes_repo.find_due_to_apply(now)? {
+            let id = scheduled_price_change.id;
+            let res = conn.transaction::<ScheduledPriceChange, FailureError, _>(|| {
+                apply_scheduled_price_change(
+                    &products_repo,
+                    &price_history_repo,
+                    &scheduled_price_changes_repo,
+                    &outbox_repo,
+                    scheduled_price_change,
+                    now,
+                )
+            });
+
+            match res {
+                Ok(_) => processed.applied += 1,
+                Err(err) => report_error(err.context(format!("Applying scheduled price change {} failed", id))),
+            }
+        }
+
+        Ok(processed)
+    }))
+}
+
+fn report_error<E: Fail>(err: E) {
+    let err = FailureError::from(err);
+    error!("{:?}", &err);
+    capture_error(&err);
+}
@@ -0,0 +1,646 @@
+//! Rebuilds products and stores indices from db.
+//! Documents are bulk loaded into a new index, which then replaces the previous one behind the index alias
+//! in a single atomic alias update, so searching is never interrupted. Changes written through the alias
+//! while loading go to the previous index, so after the alias update entities updated since loading started
+//! are indexed once more into the new index.
+//! Verification compares documents of the index behind the alias with documents built from active entities of db.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime};
+
+use chrono::Utc;
+use diesel::{pg::PgConnection, r2d2::ConnectionManager};
+use failure::{err_msg, Error as FailureError, Fail};
+use r2d2::Pool;
+use reqwest;
+use serde::Serialize;
+use serde_json;
+
+use stq_types::{BaseProductId, StoreId};
+
+use elastic::mappings::{index_definition, DOCUMENT_TYPE};
+use models::{CatalogWithAttributes, ElasticIndex, ProductDocument, StoreDocument, Visibility};
+use repos::acl::legacy_acl::SystemACL;
+use repos::{BaseProductsRepo, BaseProductsRepoImpl, StoresRepo, StoresRepoImpl};
+
+const SCROLL_KEEP_ALIVE: &str = "1m";
+
+/// Transactions started before loading but committed after it have earlier `updated_at`,
+/// so entities updated this many seconds before loading are caught up as well
+const CATCH_UP_OVERLAP_SECS: u64 = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReindexCommand {
+    /// Load documents into new index and swap alias to it
+    Reindex,
+    /// Compare documents of the index behind alias with db
+    Verify,
+}
+
+/// Command line arguments: `[--verify] [products] [stores]`, both indices are processed when none is given
+#[derive(Debug)]
+pub struct ReindexArgs {
+    pub command: ReindexCommand,
+    pub indices: Vec<ElasticIndex>,
+}
+
+impl ReindexArgs {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, FailureError> {
+        let mut command = ReindexCommand::Reindex;
+        let mut indices = vec![];
+        for arg in args {
+            match arg.as_str() {
+                "--verify" => command = ReindexCommand::Verify,
+                "products" => indices.push(ElasticIndex::Product),
+                "stores" => indices.push(ElasticIndex::Store),
+                arg => return Err(format_err!("Unknown argument: {}. Usage: reindex [--verify] [products] [stores]", arg)),
+            }
+        }
+
+        if indices.is_empty() {
+            indices = vec![ElasticIndex::Product, ElasticIndex::Store];
+        }
+        indices.sort_by_key(|index| index.to_string());
+        indices.dedup();
+
+        Ok(Self { command, indices })
+    }
+}
+
+pub struct ReindexContext {
+    pub db_pool: Pool<ConnectionManager<PgConnection>>,
+    pub elastic: ElasticIndexAdmin,
+    pub batch_size: i64,
+}
+
+/// Differences between index and db found by verification
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    pub db_count: usize,
+    pub index_count: usize,
+    /// Ids of db entities without documents
+    pub missing: Vec<i32>,
+    /// Ids of documents without active db entities
+    pub unexpected: Vec<i32>,
+    /// Ids of documents differing from documents built from db entities
+    pub changed: Vec<i32>,
+}
+
+impl VerificationReport {
+    /// Compares hashes of documents by ids
+    pub fn new(db_hashes: BTreeMap<i32, u64>, index_hashes: BTreeMap<i32, u64>) -> Self {
+        Self {
+            db_count: db_hashes.len(),
+            index_count: index_hashes.len(),
+            missing: db_hashes.keys().filter(|id| !index_hashes.contains_key(*id)).cloned().collect(),
+            unexpected: index_hashes.keys().filter(|id| !db_hashes.contains_key(*id)).cloned().collect(),
+            changed: db_hashes
+                .iter()
+                .filter(|&(id, hash)| index_hashes.get(id).map_or(false, |index_hash| index_hash != hash))
+                .map(|(id, _)| *id)
+                .collect(),
+        }
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} in db, {} in index, missing in index: {:?}, unexpected in index: {:?}, changed in db: {:?}",
+            self.db_count, self.index_count, self.missing, self.unexpected, self.changed
+        )
+    }
+}
+
+pub fn run(ctx: &ReindexContext, args: &ReindexArgs) -> Result<(), FailureError> {
+    for index in &args.indices {
+        match args.command {
+            ReindexCommand::Reindex => reindex(ctx, index)?,
+            ReindexCommand::Verify => {
+                let report = verify(ctx, index)?;
+                if !report.is_consistent() {
+                    return Err(format_err!("Index {} is inconsistent with db: {}", index, report));
+                }
+                info!("Index {} is consistent with db: {}", index, report);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads documents into new index and points alias to it, previous indices behind alias are removed
+pub fn reindex(ctx: &ReindexContext, index: &ElasticIndex) -> Result<(), FailureError> {
+    let alias = index.to_string();
+    let new_index = format!("{}_{}", alias, Utc::now().format("%Y%m%d%H%M%S"));
+    let old_indices = ctx.elastic.aliased_indices(&alias)?;
+    if old_indices.is_empty() && ctx.elastic.index_exists(&alias)? {
+        return Err(format_err!(
+            "Index {} is not an alias, it has to be removed before the first reindexing",
+            alias
+        ));
+    }
+
+    info!("Reindexing {} into {}.", alias, new_index);
+    let load_started_at = SystemTime::now() - Duration::from_secs(CATCH_UP_OVERLAP_SECS);
+    let mut definition = index_definition(index);
+    // Refreshing is turned off during bulk loading and restored before swapping alias
+    definition["settings"]["refresh_interval"] = json!("-1");
+    ctx.elastic.create_index(&new_index, &definition)?;
+
+    let loaded = load_documents(ctx, index, &new_index)
+        .and_then(|loaded| {
+            ctx.elastic.update_settings(&new_index, &json!({ "refresh_interval": null }))?;
+            ctx.elastic.refresh(&new_index)?;
+            let indexed = ctx.elastic.count(&new_index)?;
+            if indexed != loaded as u64 {
+                return Err(format_err!("{} documents loaded, but {} indexed", loaded, indexed));
+            }
+            Ok(loaded)
+        })
+        .map_err(|e| e.context(format!("Loading documents into {} failed", new_index)));
+
+    let loaded = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            // Alias still points to previous index, so the half-loaded one is just dropped
+            if let Err(delete_err) = ctx.elastic.delete_index(&new_index) {
+                error!("Removing index {} failed: {}", new_index, delete_err);
+            }
+            return Err(e.into());
+        }
+    };
+
+    ctx.elastic.swap_alias(&alias, &new_index, &old_indices)?;
+    info!("Alias {} points to {} with {} documents.", alias, new_index, loaded);
+
+    for old_index in old_indices {
+        ctx.elastic.delete_index(&old_index)?;
+    }
+
+    let caught_up = catch_up(ctx, index, &new_index, load_started_at).map_err(|e| {
+        e.context(format!(
+            "Catching up changes made while loading {} failed, run reindexing again",
+            new_index
+        ))
+    })?;
+    info!(
+        "{} documents of entities updated while loading caught up in {}.",
+        caught_up, new_index
+    );
+
+    Ok(())
+}
+
+/// Compares documents behind alias with documents built from active db entities
+pub fn verify(ctx: &ReindexContext, index: &ElasticIndex) -> Result<VerificationReport, FailureError> {
+    let mut db_hashes = BTreeMap::new();
+    for_each_documents_batch(ctx, index, |documents| {
+        db_hashes.extend(documents.iter().map(|(id, document)| (*id, document_hash(document))));
+        Ok(())
+    })?;
+    let index_hashes = ctx.elastic.document_hashes(&index.to_string(), ctx.batch_size)?;
+
+    Ok(VerificationReport::new(db_hashes, index_hashes))
+}
+
+/// Hash of normalized document content, documents are compared by it in verification
+pub fn document_hash(document: &serde_json::Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    normalize_document(document).to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Elastic returns `_source` as it was indexed by any writer, so values equal for search are made equal here:
+/// numbers are compared as floats, null fields are dropped and arrays are sorted as sets
+fn normalize_document(document: &serde_json::Value) -> serde_json::Value {
+    match *document {
+        serde_json::Value::Object(ref fields) => serde_json::Value::Object(
+            fields
+                .iter()
+                .filter(|&(_, value)| !value.is_null())
+                .map(|(name, value)| (name.clone(), normalize_document(value)))
+                .collect(),
+        ),
+        serde_json::Value::Array(ref values) => {
+            let mut values = values.iter().map(normalize_document).collect::<Vec<_>>();
+            values.sort_by_key(|value| value.to_string());
+            serde_json::Value::Array(values)
+        }
+        serde_json::Value::Number(ref number) => number.as_f64().map_or(serde_json::Value::Null, |number| json!(number)),
+        ref value => value.clone(),
+    }
+}
+
+fn load_documents(ctx: &ReindexContext, index: &ElasticIndex, target: &str) -> Result<usize, FailureError> {
+    let mut loaded = 0;
+    for_each_documents_batch(ctx, index, |documents| {
+        loaded += ctx.elastic.bulk_index(target, &documents)?;
+        debug!("{} documents loaded into {}.", loaded, target);
+        Ok(())
+    })?;
+
+    Ok(loaded)
+}
+
+/// Builds documents of all active db entities and passes them to `f` by `batch_size`
+fn for_each_documents_batch<F>(ctx: &ReindexContext, index: &ElasticIndex, mut f: F) -> Result<(), FailureError>
+where
+    F: FnMut(Vec<(i32, serde_json::Value)>) -> Result<(), FailureError>,
+{
+    let conn = ctx.db_pool.get()?;
+
+    match *index {
+        ElasticIndex::Product => {
+            let base_products_repo = BaseProductsRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let stores_repo = StoresRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let store_ratings = load_store_ratings(&stores_repo)?;
+
+            let mut from = BaseProductId(0);
+            loop {
+                let catalog = base_products_repo.get_active_catalog(from, ctx.batch_size)?;
+                let last_id = match catalog.last() {
+                    Some(last) => last.base_product.id,
+                    None => break,
+                };
+
+                f(product_documents(catalog, &store_ratings)?)?;
+                from = BaseProductId(last_id.0 + 1);
+            }
+        }
+        ElasticIndex::Store => {
+            let stores_repo = StoresRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let mut from = StoreId(0);
+            loop {
+                let stores = stores_repo.list(from, ctx.batch_size as i32, Visibility::Active)?;
+                let last_id = match stores.last() {
+                    Some(last) => last.id,
+                    None => break,
+                };
+
+                f(documents(stores.into_iter().map(StoreDocument::from), |doc| doc.id.0)?)?;
+                from = StoreId(last_id.0 + 1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Indexes documents of entities updated after `since` into `target` and removes documents of deactivated ones
+fn catch_up(ctx: &ReindexContext, index: &ElasticIndex, target: &str, since: SystemTime) -> Result<usize, FailureError> {
+    let conn = ctx.db_pool.get()?;
+    let mut caught_up = 0;
+
+    match *index {
+        ElasticIndex::Product => {
+            let base_products_repo = BaseProductsRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let stores_repo = StoresRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let store_ratings = load_store_ratings(&stores_repo)?;
+
+            let ids = base_products_repo.find_ids_updated_since(since)?;
+            for ids in ids.chunks(ctx.batch_size as usize) {
+                let catalog = base_products_repo.get_active_catalog_by_ids(ids.to_vec())?;
+                let documents = product_documents(catalog, &store_ratings)?;
+                let deactivated = ids
+                    .iter()
+                    .map(|id| id.0)
+                    .filter(|id| documents.iter().all(|&(document_id, _)| document_id != *id))
+                    .collect::<Vec<i32>>();
+                caught_up += ctx.elastic.bulk_index(target, &documents)?;
+                caught_up += ctx.elastic.bulk_delete(target, &deactivated)?;
+            }
+        }
+        ElasticIndex::Store => {
+            let stores_repo = StoresRepoImpl::new(&*conn, Box::new(SystemACL::default()));
+            let mut from = StoreId(0);
+            loop {
+                let stores = stores_repo.list_updated_since(since, from, ctx.batch_size)?;
+                let last_id = match stores.last() {
+                    Some(last) => last.id,
+                    None => break,
+                };
+
+                let (active, deactivated): (Vec<_>, Vec<_>) = stores.into_iter().partition(|store| store.is_active);
+                let deactivated = deactivated.into_iter().map(|store| store.id.0).collect::<Vec<i32>>();
+                caught_up += ctx
+                    .elastic
+                    .bulk_index(target, &documents(active.into_iter().map(StoreDocument::from), |doc| doc.id.0)?)?;
+                caught_up += ctx.elastic.bulk_delete(target, &deactivated)?;
+                from = StoreId(last_id.0 + 1);
+            }
+        }
+    }
+
+    Ok(caught_up)
+}
+
+fn load_store_ratings(stores_repo: &StoresRepo) -> Result<HashMap<StoreId, f64>, FailureError> {
+    Ok(stores_repo
+        .all(Visibility::Active)?
+        .into_iter()
+        .map(|store| (store.id, store.rating))
+        .collect())
+}
+
+fn product_documents(
+    catalog: Vec<CatalogWithAttributes>,
+    store_ratings: &HashMap<StoreId, f64>,
+) -> Result<Vec<(i32, serde_json::Value)>, FailureError> {
+    let documents = catalog.into_iter().map(|catalog| {
+        let store_rating = store_ratings.get(&catalog.base_product.store_id).cloned().unwrap_or_default();
+        ProductDocument::new(catalog, store_rating)
+    });
+    self::documents(documents, |doc| doc.id.0)
+}
+
+/// Serialized documents paired with their ids
+fn documents<T, I, F>(documents: I, id: F) -> Result<Vec<(i32, serde_json::Value)>, FailureError>
+where
+    T: Serialize,
+    I: Iterator<Item = T>,
+    F: Fn(&T) -> i32,
+{
+    documents
+        .map(|document| {
+            serde_json::to_value(&document)
+                .map(|value| (id(&document), value))
+                .map_err(From::from)
+        })
+        .collect()
+}
+
+/// Blocking client of elastic index management api
+pub struct ElasticIndexAdmin {
+    http_client: reqwest::Client,
+    elastic_address: String,
+}
+
+impl ElasticIndexAdmin {
+    pub fn new(elastic_address: String) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            elastic_address,
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://{}/{}", self.elastic_address, path)
+    }
+
+    pub fn index_exists(&self, index: &str) -> Result<bool, FailureError> {
+        let response = self.http_client.head(&self.url(index)).send()?;
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            _ => response.error_for_status().map(|_| true).map_err(From::from),
+        }
+    }
+
+    /// Names of indices behind alias, empty when alias does not exist
+    pub fn aliased_indices(&self, alias: &str) -> Result<Vec<String>, FailureError> {
+        let response = self.http_client.get(&self.url(&format!("_alias/{}", alias))).send()?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(vec![]);
+        }
+
+        let indices = response.error_for_status()?.json::<serde_json::Map<String, serde_json::Value>>()?;
+        Ok(indices.keys().cloned().collect())
+    }
+
+    pub fn create_index(&self, index: &str, definition: &serde_json::Value) -> Result<(), FailureError> {
+        self.http_client.put(&self.url(index)).json(definition).send()?.error_for_status()?;
+        Ok(())
+    }
+
+    pub fn update_settings(&self, index: &str, settings: &serde_json::Value) -> Result<(), FailureError> {
+        self.http_client
+            .put(&self.url(&format!("{}/_settings", index)))
+            .json(&json!({ "index": settings }))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub fn delete_index(&self, index: &str) -> Result<(), FailureError> {
+        self.http_client.delete(&self.url(index)).send()?.error_for_status()?;
+        Ok(())
+    }
+
+    pub fn refresh(&self, index: &str) -> Result<(), FailureError> {
+        self.http_client
+            .post(&self.url(&format!("{}/_refresh", index)))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub fn count(&self, index: &str) -> Result<u64, FailureError> {
+        let mut response = self
+            .http_client
+            .get(&self.url(&format!("{}/_count", index)))
+            .send()?
+            .error_for_status()?;
+        let body = response.json::<serde_json::Value>()?;
+        body["count"].as_u64().ok_or_else(|| err_msg("Count of documents not found in response"))
+    }
+
+    /// Indexes documents with given ids in one bulk request, fails if any document is rejected
+    pub fn bulk_index<T: Serialize>(&self, index: &str, documents: &[(i32, T)]) -> Result<usize, FailureError> {
+        if documents.is_empty() {
+            return Ok(0);
+        }
+
+        let mut body = String::new();
+        for (id, document) in documents {
+            body.push_str(&json!({ "index": { "_id": id } }).to_string());
+            body.push('\n');
+            body.push_str(&serde_json::to_string(document)?);
+            body.push('\n');
+        }
+
+        self.bulk(index, body)
+            .map_err(|e| e.context(format!("Bulk indexing into {} failed", index)))?;
+        Ok(documents.len())
+    }
+
+    /// Removes documents with given ids in one bulk request, missing documents are skipped
+    pub fn bulk_delete(&self, index: &str, ids: &[i32]) -> Result<usize, FailureError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let mut body = String::new();
+        for id in ids {
+            body.push_str(&json!({ "delete": { "_id": id } }).to_string());
+            body.push('\n');
+        }
+
+        self.bulk(index, body)
+            .map_err(|e| e.context(format!("Bulk removing from {} failed", index)))?;
+        Ok(ids.len())
+    }
+
+    /// Sends bulk request, fails if any action is rejected
+    fn bulk(&self, index: &str, body: String) -> Result<(), FailureError> {
+        let mut response = self
+            .http_client
+            .post(&self.url(&format!("{}/{}/_bulk", index, DOCUMENT_TYPE)))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()?
+            .error_for_status()?;
+        let result = response.json::<serde_json::Value>()?;
+        if result["errors"].as_bool().unwrap_or(false) {
+            let failed = result["items"]
+                .as_array()
+                .and_then(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_object().and_then(|actions| actions.values().next()))
+                        .find(|action| !action["error"].is_null())
+                })
+                .cloned()
+                .unwrap_or_default();
+            return Err(format_err!("Bulk action failed: {}", failed));
+        }
+
+        Ok(())
+    }
+
+    /// Points alias to `new_index` instead of `old_indices` in one atomic update
+    pub fn swap_alias(&self, alias: &str, new_index: &str, old_indices: &[String]) -> Result<(), FailureError> {
+        let mut actions = old_indices
+            .iter()
+            .map(|old_index| json!({ "remove": { "index": old_index, "alias": alias } }))
+            .collect::<Vec<_>>();
+        actions.push(json!({ "add": { "index": new_index, "alias": alias } }));
+
+        self.http_client
+            .post(&self.url("_aliases"))
+            .json(&json!({ "actions": actions }))
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Hashes of all documents of index by their ids, read with scroll by `batch_size`
+    pub fn document_hashes(&self, index: &str, batch_size: i64) -> Result<BTreeMap<i32, u64>, FailureError> {
+        let mut hashes = BTreeMap::new();
+        let mut response = self
+            .http_client
+            .post(&self.url(&format!("{}/_search?scroll={}", index, SCROLL_KEEP_ALIVE)))
+            .json(&json!({ "size": batch_size, "sort": ["_doc"] }))
+            .send()?
+            .error_for_status()?
+            .json::<serde_json::Value>()?;
+
+        loop {
+            let scroll_id = response["_scroll_id"].clone();
+            let hits = response["hits"]["hits"].as_array().cloned().unwrap_or_default();
+            if hits.is_empty() {
+                self.http_client
+                    .delete(&self.url("_search/scroll"))
+                    .json(&json!({ "scroll_id": scroll_id }))
+                    .send()?;
+                return Ok(hashes);
+            }
+
+            for hit in hits {
+                let id = hit["_id"]
+                    .as_str()
+                    .and_then(|id| id.parse::<i32>().ok())
+                    .ok_or_else(|| format_err!("Document with invalid id in index {}: {}", index, hit["_id"]))?;
+                hashes.insert(id, document_hash(&hit["_source"]));
+            }
+
+            response = self
+                .http_client
+                .post(&self.url("_search/scroll"))
+                .json(&json!({ "scroll": SCROLL_KEEP_ALIVE, "scroll_id": scroll_id }))
+                .send()?
+                .error_for_status()?
+                .json::<serde_json::Value>()?;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use serde_json;
+    use std::collections::BTreeMap;
+
+    use models::ElasticIndex;
+
+    use loaders::reindex::*;
+
+    #[test]
+    fn test_parse_reindex_args() {
+        let args = ReindexArgs::parse(vec![]).unwrap();
+        assert_eq!(args.command, ReindexCommand::Reindex);
+        assert_eq!(args.indices, vec![ElasticIndex::Product, ElasticIndex::Store]);
+
+        let args = ReindexArgs::parse(vec!["--verify".to_string(), "stores".to_string()]).unwrap();
+        assert_eq!(args.command, ReindexCommand::Verify);
+        assert_eq!(args.indices, vec![ElasticIndex::Store]);
+
+        assert!(ReindexArgs::parse(vec!["orders".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_reindex_args_removes_duplicates() {
+        let args = ReindexArgs::parse(vec!["stores".to_string(), "products".to_string(), "stores".to_string()]).unwrap();
+        assert_eq!(args.indices, vec![ElasticIndex::Product, ElasticIndex::Store]);
+    }
+
+    #[test]
+    fn test_verification_report() {
+        let db_hashes = vec![(1, 10), (2, 20), (3, 30)].into_iter().collect();
+        let index_hashes = vec![(2, 20), (3, 31), (4, 40)].into_iter().collect();
+        let report = VerificationReport::new(db_hashes, index_hashes);
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing, vec![1]);
+        assert_eq!(report.unexpected, vec![4]);
+        assert_eq!(report.changed, vec![3]);
+
+        let hashes = vec![(1, 10)].into_iter().collect::<BTreeMap<i32, u64>>();
+        assert!(VerificationReport::new(hashes.clone(), hashes).is_consistent());
+    }
+
+    #[test]
+    fn test_document_hash_does_not_depend_on_indexed_representation() {
+        let document = json!({
+            "id": 1,
+            "rating": 5.0,
+            "location": null,
+            "name": [{ "lang": "en", "text": "Shop" }, { "lang": "de", "text": "Laden" }]
+        });
+        let indexed = json!({
+            "id": 1.0,
+            "rating": 5,
+            "name": [{ "text": "Laden", "lang": "de" }, { "text": "Shop", "lang": "en" }]
+        });
+        assert_eq!(document_hash(&document), document_hash(&indexed));
+        assert_ne!(
+            document_hash(&document),
+            document_hash(&json!({ "id": 1, "rating": 5.0, "location": { "lat": 1.0, "lon": 1.0 } }))
+        );
+    }
+
+    #[test]
+    fn test_document_hash_does_not_depend_on_formatting() {
+        let document = json!({ "id": 1, "name": "Shop", "rating": 4.5 });
+        let indexed = serde_json::from_str::<serde_json::Value>("{\"name\": \"Shop\", \"id\": 1, \"rating\": 4.5}").unwrap();
+        assert_eq!(document_hash(&document), document_hash(&indexed));
+        assert_ne!(
+            document_hash(&document),
+            document_hash(&json!({ "id": 1, "name": "Shop", "rating": 5.0 }))
+        );
+    }
+}
@@ -8,6 +8,15 @@ pub struct AttributeFilter {
 #[derive(Default, Serialize, Deserialize, Clone, Debug, Hash, PartialEq)]
 pub struct EqualFilter {
     pub values: Vec<String>,
+    /// Count of products having each of `values`, present in search facets only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counts: Option<Vec<AttributeValueCount>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq)]
+pub struct AttributeValueCount {
+    pub value: String,
+    pub count: u64,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone, PartialEq, Debug)]
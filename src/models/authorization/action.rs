@@ -7,7 +7,7 @@ use std::fmt;
 // Update - update resource with id.
 // Delete - delete resource with id.
 // Moderate - moderation resources
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Action {
     All,
     Read,
@@ -0,0 +1,59 @@
+//! Models for recording and explaining ACL decisions
+
+use stq_types::{StoresRole, UserId};
+
+use models::{Action, Permission, Resource, Rule, Scope};
+
+/// Check of one permission of user's role against requested resource, action and rule
+#[derive(Clone, Debug, Serialize)]
+pub struct PermissionCheck {
+    pub role: StoresRole,
+    pub permission: Permission,
+    pub resource_matches: bool,
+    pub action_matches: bool,
+    pub rule_matches: bool,
+    /// Scope is checked only when resource, action and rule match, it's `None` otherwise
+    pub in_scope: Option<bool>,
+}
+
+impl PermissionCheck {
+    pub fn is_match(&self) -> bool {
+        self.in_scope == Some(true)
+    }
+}
+
+/// Decision of ACL with checks of all permissions of user's roles it was made by
+#[derive(Clone, Debug, Serialize)]
+pub struct AclDecision {
+    /// `None` for unauthorized requests
+    pub user_id: Option<UserId>,
+    pub roles: Vec<StoresRole>,
+    pub resource: Resource,
+    pub action: Action,
+    pub rule: Option<Rule>,
+    pub allowed: bool,
+    pub checks: Vec<PermissionCheck>,
+}
+
+impl AclDecision {
+    /// Scopes of permissions which allowed the request
+    pub fn matched_scopes(&self) -> Vec<Scope> {
+        self.checks
+            .iter()
+            .filter(|check| check.is_match())
+            .map(|check| check.permission.scope)
+            .collect()
+    }
+}
+
+/// Payload for explaining why ACL allows or denies action on resource to user.
+/// With `object_id` scopes are checked against the object, otherwise as for requests without object
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AclExplainRequest {
+    pub user_id: UserId,
+    pub resource: Resource,
+    pub action: Action,
+    pub object_id: Option<i32>,
+    /// Defaults to moderation status of the object for stores and base products
+    pub rule: Option<Rule>,
+}
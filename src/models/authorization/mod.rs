@@ -1,12 +1,14 @@
 //! Models for working with authorization (acl - access control list)
 
 pub mod action;
+pub mod decision;
 pub mod permission;
 pub mod resource;
 pub mod rule;
 pub mod scope;
 
 pub use self::action::Action;
+pub use self::decision::{AclDecision, AclExplainRequest, PermissionCheck};
 pub use self::permission::Permission;
 pub use self::resource::Resource;
 pub use self::rule::Rule;
@@ -2,6 +2,7 @@
 
 use models::{Action, Resource, Rule, Scope};
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Permission {
     pub resource: Resource,
     pub action: Action,
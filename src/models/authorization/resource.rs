@@ -1,7 +1,7 @@
 //! Enum for resources available in ACLs
 use std::fmt;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Resource {
     Products,
     BaseProducts,
@@ -20,7 +20,18 @@ pub enum Resource {
     Coupons,
     CouponScopeBaseProducts,
     CouponScopeCategories,
+    CouponExclusionBaseProducts,
     UsedCoupons,
+    ProductPriceHistory,
+    ScheduledPriceChanges,
+    OutboxEvents,
+    SearchSynonyms,
+    StoreStaff,
+    AclDecisions,
+    ModerationQueue,
+    ModerationTransitions,
+    DeclineReasons,
+    ModerationFeedback,
 }
 
 impl fmt::Display for Resource {
@@ -43,7 +54,18 @@ impl fmt::Display for Resource {
             Resource::Coupons => write!(f, "coupons"),
             Resource::CouponScopeBaseProducts => write!(f, "coupon_scope_base_products"),
             Resource::CouponScopeCategories => write!(f, "coupon_scope_categories"),
+            Resource::CouponExclusionBaseProducts => write!(f, "coupon_exclusion_base_products"),
             Resource::UsedCoupons => write!(f, "used_coupons"),
+            Resource::ProductPriceHistory => write!(f, "product_price_history"),
+            Resource::ScheduledPriceChanges => write!(f, "scheduled_price_changes"),
+            Resource::OutboxEvents => write!(f, "outbox_events"),
+            Resource::SearchSynonyms => write!(f, "search_synonyms"),
+            Resource::StoreStaff => write!(f, "store_staff"),
+            Resource::AclDecisions => write!(f, "acl_decisions"),
+            Resource::ModerationQueue => write!(f, "moderation_queue"),
+            Resource::ModerationTransitions => write!(f, "moderation_transitions"),
+            Resource::DeclineReasons => write!(f, "decline_reasons"),
+            Resource::ModerationFeedback => write!(f, "moderation_feedback"),
         }
     }
 }
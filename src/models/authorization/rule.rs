@@ -4,7 +4,7 @@ use std::fmt;
 use stq_static_resources::ModerationStatus;
 
 // Any - gives all permissions.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Rule {
     Any,
     ModerationStatus(ModerationStatus),
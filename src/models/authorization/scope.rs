@@ -1,10 +1,16 @@
 //! Enum for scopes available in ACLs
 
-#[derive(PartialEq, Eq)]
+use models::StoreStaffRole;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Scope {
     /// Resource with any id
     All,
 
     /// Resource with id of the owner equal to the id of the current user.
     Owned,
+
+    /// Resource of the store owned by the current user or having the current user
+    /// as staff member with the role not lower than the given one.
+    Staff(StoreStaffRole),
 }
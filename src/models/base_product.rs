@@ -9,7 +9,10 @@ use stq_static_resources::{Currency, ModerationStatus};
 use stq_types::{AttributeId, BaseProductId, BaseProductSlug, CategoryId, ProductId, ProductPrice, StoreId};
 
 use models::validation_rules::*;
-use models::{NewProductWithAttributes, Product, ProductWithAttributes, Store};
+use models::{
+    AttributeFilter, Category, ModerationFeedbackPayload, NewProductWithAttributes, Product, ProductWithAttributes, RangeFilter,
+    StockStatus, Store,
+};
 
 use schema::base_products;
 
@@ -232,6 +235,7 @@ pub struct ElasticVariant {
     pub discount: Option<f64>,
     pub price: ProductPrice,
     pub attrs: Vec<ElasticAttrValue>,
+    pub stock_status: Option<StockStatus>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -279,10 +283,35 @@ pub struct ModeratorBaseProductSearchResults {
     pub total_count: u32,
 }
 
+/// Hits of products search together with facets aggregated in the same elastic request
+#[derive(Clone, Debug, Default)]
+pub struct ElasticProductsFacetedSearch {
+    pub products: Vec<ElasticProduct>,
+    pub total_count: u32,
+    pub price_filter: RangeFilter,
+    pub category_ids: Vec<CategoryId>,
+    pub attribute_filters: Vec<AttributeFilter>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BaseProductsFacetedSearchResults {
+    pub base_products: Vec<BaseProductWithVariants>,
+    pub total_count: u32,
+    pub price_filter: RangeFilter,
+    pub category: Category,
+    pub attribute_filters: Option<Vec<AttributeFilter>>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct BaseProductModerate {
     pub base_product_id: BaseProductId,
     pub status: ModerationStatus,
+    /// Moderator comment saved in moderation history
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Decline reasons pointing to fields seller has to fix
+    #[serde(default)]
+    pub feedback: Vec<ModerationFeedbackPayload>,
 }
 
 #[derive(Default, Serialize, Deserialize, Insertable, AsChangeset, Debug)]
@@ -0,0 +1,242 @@
+//! Models for bulk import of base products from CSV
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use serde_json;
+use uuid::Uuid;
+use validator::{ValidationError, ValidationErrors};
+
+use stq_static_resources::Currency;
+use stq_types::{AttributeId, AttributeValueCode, BaseProductId, CategoryId, ProductPrice, StoreId};
+
+use models::{
+    AttrValue, BaseProduct, NewBaseProduct, NewProductWithAttributes, NewProductWithoutCurrency, ProductWithAttributes, StockStatus,
+};
+
+/// Row of base products import CSV, each row describes one variant.
+/// Rows with the same `base_product_key` are variants of one base product,
+/// base product columns are taken from the first of them.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BaseProductImportRow {
+    pub base_product_key: String,
+    /// Translations in JSON, e.g. `[{"lang": "en", "text": "Shoes"}]`
+    pub name: String,
+    pub short_description: String,
+    pub long_description: Option<String>,
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
+    pub category_id: CategoryId,
+    pub currency: Currency,
+    pub slug: Option<String>,
+    pub length_cm: Option<i32>,
+    pub width_cm: Option<i32>,
+    pub height_cm: Option<i32>,
+    pub weight_g: Option<i32>,
+    pub vendor_code: String,
+    pub price: ProductPrice,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+    pub photo_main: Option<String>,
+    /// Urls in JSON array
+    pub additional_photos: Option<String>,
+    pub pre_order: Option<bool>,
+    pub pre_order_days: Option<i32>,
+    pub stock_status: Option<StockStatus>,
+    /// Attribute values as `attr_id:value_code` pairs separated by `;`
+    pub attributes: Option<String>,
+}
+
+impl BaseProductImportRow {
+    /// Row of catalog export that can be imported back, base products are keyed by slug
+    pub fn from_catalog(base_product: &BaseProduct, variant: &ProductWithAttributes) -> Self {
+        let to_json = |value: &serde_json::Value| value.to_string();
+        let product = &variant.product;
+        let attributes = variant
+            .attributes
+            .iter()
+            .map(|&(ref prod_attr, _)| format!("{}:{}", prod_attr.attr_id.0, prod_attr.value.0))
+            .collect::<Vec<_>>();
+
+        Self {
+            base_product_key: base_product.slug.0.clone(),
+            name: to_json(&base_product.name),
+            short_description: to_json(&base_product.short_description),
+            long_description: base_product.long_description.as_ref().map(to_json),
+            seo_title: base_product.seo_title.as_ref().map(to_json),
+            seo_description: base_product.seo_description.as_ref().map(to_json),
+            category_id: base_product.category_id,
+            currency: base_product.currency,
+            slug: Some(base_product.slug.0.clone()),
+            length_cm: base_product.length_cm,
+            width_cm: base_product.width_cm,
+            height_cm: base_product.height_cm,
+            weight_g: base_product.weight_g,
+            vendor_code: product.vendor_code.clone(),
+            price: product.price,
+            discount: product.discount,
+            cashback: product.cashback,
+            photo_main: product.photo_main.clone(),
+            additional_photos: product.additional_photos.as_ref().map(to_json),
+            pre_order: Some(product.pre_order),
+            pre_order_days: Some(product.pre_order_days),
+            stock_status: product.stock_status,
+            attributes: if attributes.is_empty() { None } else { Some(attributes.join(";")) },
+        }
+    }
+
+    pub fn new_base_product(&self, store_id: StoreId) -> Result<NewBaseProduct, ValidationErrors> {
+        Ok(NewBaseProduct {
+            name: parse_json_column("name", &self.name)?,
+            store_id,
+            short_description: parse_json_column("short_description", &self.short_description)?,
+            long_description: parse_optional_json_column("long_description", &self.long_description)?,
+            seo_title: parse_optional_json_column("seo_title", &self.seo_title)?,
+            seo_description: parse_optional_json_column("seo_description", &self.seo_description)?,
+            currency: self.currency,
+            category_id: self.category_id,
+            slug: self.slug.clone(),
+            length_cm: self.length_cm,
+            width_cm: self.width_cm,
+            height_cm: self.height_cm,
+            weight_g: self.weight_g,
+            uuid: Uuid::new_v4(),
+            store_status: None,
+        })
+    }
+
+    pub fn new_variant(&self) -> Result<NewProductWithAttributes, ValidationErrors> {
+        let product = NewProductWithoutCurrency {
+            base_product_id: None,
+            discount: self.discount,
+            photo_main: self.photo_main.clone(),
+            additional_photos: parse_optional_json_column("additional_photos", &self.additional_photos)?,
+            vendor_code: self.vendor_code.clone(),
+            cashback: self.cashback,
+            price: self.price,
+            pre_order: self.pre_order,
+            pre_order_days: self.pre_order_days,
+            uuid: Uuid::new_v4(),
+            stock_status: self.stock_status,
+        };
+
+        Ok(NewProductWithAttributes {
+            product,
+            attributes: self.attribute_values()?,
+        })
+    }
+
+    fn attribute_values(&self) -> Result<Vec<AttrValue>, ValidationErrors> {
+        let attributes = match self.attributes {
+            Some(ref attributes) => attributes,
+            None => return Ok(vec![]),
+        };
+
+        attributes
+            .split(';')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, ':').map(str::trim);
+                let attr_id = parts.next().and_then(|attr_id| attr_id.parse::<i32>().ok());
+                let value = parts.next().filter(|value| !value.is_empty());
+                match (attr_id, value) {
+                    (Some(attr_id), Some(value)) => Ok(AttrValue {
+                        attr_id: AttributeId(attr_id),
+                        attr_value_id: None,
+                        value: AttributeValueCode(value.to_string()),
+                        meta_field: None,
+                    }),
+                    _ => Err(column_error(
+                        "attributes",
+                        format!("Invalid attribute value '{}', must be in `attr_id:value_code` format.", pair),
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_json_column(column: &'static str, value: &str) -> Result<serde_json::Value, ValidationErrors> {
+    serde_json::from_str(value).map_err(|e| column_error(column, format!("Invalid JSON: {}", e)))
+}
+
+fn parse_optional_json_column(column: &'static str, value: &Option<String>) -> Result<Option<serde_json::Value>, ValidationErrors> {
+    match *value {
+        Some(ref value) => parse_json_column(column, value).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn column_error(column: &'static str, message: String) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        column,
+        ValidationError {
+            code: Cow::from(column),
+            message: Some(Cow::from(message)),
+            params: HashMap::new(),
+        },
+    );
+    errors
+}
+
+/// Variant parsed from import row
+#[derive(Clone, Debug)]
+pub struct BaseProductImportVariant {
+    pub line: u64,
+    pub variant: NewProductWithAttributes,
+}
+
+/// Base product with variants parsed from import rows
+#[derive(Clone, Debug)]
+pub struct BaseProductImportGroup {
+    pub line: u64,
+    pub new_base_product: NewBaseProduct,
+    pub variants: Vec<BaseProductImportVariant>,
+}
+
+/// Error of specific import row
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BaseProductsImportRowError {
+    /// Line of the row in CSV, header is line 1
+    pub line: u64,
+    pub vendor_code: Option<String>,
+    pub message: String,
+    /// Validation errors in the same format as in API error payload
+    pub validation_errors: Option<serde_json::Value>,
+}
+
+impl BaseProductsImportRowError {
+    pub fn new(line: u64, vendor_code: Option<String>, message: String) -> Self {
+        Self {
+            line,
+            vendor_code,
+            message,
+            validation_errors: None,
+        }
+    }
+
+    pub fn validation(line: u64, vendor_code: Option<String>, errors: ValidationErrors) -> Self {
+        Self {
+            line,
+            vendor_code,
+            message: "Validation error".to_string(),
+            validation_errors: serde_json::to_value(errors).ok(),
+        }
+    }
+}
+
+/// Result of base products import
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct BaseProductsImportReport {
+    pub dry_run: bool,
+    /// `true` if import was saved, imports with errors are always rolled back
+    pub committed: bool,
+    /// Number of base products imported without errors
+    pub base_products_count: usize,
+    /// Number of variants imported without errors
+    pub variants_count: usize,
+    /// Created base products, empty unless import was saved
+    pub base_product_ids: Vec<BaseProductId>,
+    pub errors: Vec<BaseProductsImportRowError>,
+}
@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use stq_types::BaseProductId;
+
+use models::CatalogWithAttributes;
+
+/// Format of store catalog export
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CatalogExportFormat {
+    /// One variant per row, the same format as base products import
+    Csv,
+    /// One base product with variants and attributes per line
+    JsonLines,
+}
+
+impl Default for CatalogExportFormat {
+    fn default() -> Self {
+        CatalogExportFormat::Csv
+    }
+}
+
+impl CatalogExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match *self {
+            CatalogExportFormat::Csv => "text/csv; charset=utf-8",
+            CatalogExportFormat::JsonLines => "application/x-ndjson; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for CatalogExportFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_ref() {
+            "csv" => Ok(CatalogExportFormat::Csv),
+            "jsonl" => Ok(CatalogExportFormat::JsonLines),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Page of store catalog with base products the user can read
+#[derive(Debug, Clone, Default)]
+pub struct StoreCatalogChunk {
+    pub catalog: Vec<CatalogWithAttributes>,
+    /// Id to load the next page from, `None` for the last page
+    pub next_from: Option<BaseProductId>,
+}
@@ -0,0 +1,129 @@
+//! Models for incremental catalog feed
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Position in feed of one kind of rows, the feed continues with rows updated after `updated_at`
+/// or updated at the same moment and having id greater than `last_id`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CatalogFeedPosition {
+    pub updated_at: SystemTime,
+    pub last_id: i32,
+}
+
+impl CatalogFeedPosition {
+    pub fn new(updated_at: SystemTime, last_id: i32) -> Self {
+        Self { updated_at, last_id }
+    }
+
+    /// Position before all rows updated at `updated_at` or later
+    pub fn since(updated_at: SystemTime) -> Self {
+        Self::new(updated_at, 0)
+    }
+}
+
+impl fmt::Display for CatalogFeedPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let since = self.updated_at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let micros = since.as_secs() * 1_000_000 + u64::from(since.subsec_micros());
+        write!(f, "{:x}.{:x}", micros, self.last_id)
+    }
+}
+
+impl FromStr for CatalogFeedPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '.');
+        let updated_at = parse_micros(parts.next().unwrap_or_default())?;
+        let last_id = match parts.next() {
+            Some(last_id) => i32::from_str_radix(last_id, 16).map_err(|_| ())?,
+            None => 0,
+        };
+        Ok(CatalogFeedPosition::new(updated_at, last_id))
+    }
+}
+
+fn parse_micros(s: &str) -> Result<SystemTime, ()> {
+    let micros = u64::from_str_radix(s, 16).map_err(|_| ())?;
+    let since = Duration::new(micros / 1_000_000, (micros % 1_000_000) as u32 * 1_000);
+    Ok(UNIX_EPOCH + since)
+}
+
+/// Opaque position in catalog feed, encodes position in feed of every kind of rows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CatalogFeedCursor {
+    pub categories: CatalogFeedPosition,
+    pub stores: CatalogFeedPosition,
+    pub base_products: CatalogFeedPosition,
+    pub products: CatalogFeedPosition,
+}
+
+impl CatalogFeedCursor {
+    /// Cursor before all rows updated at `updated_at` or later
+    pub fn since(updated_at: SystemTime) -> Self {
+        let position = CatalogFeedPosition::since(updated_at);
+        Self {
+            categories: position,
+            stores: position,
+            base_products: position,
+            products: position,
+        }
+    }
+}
+
+impl Default for CatalogFeedCursor {
+    fn default() -> Self {
+        CatalogFeedCursor::since(UNIX_EPOCH)
+    }
+}
+
+impl fmt::Display for CatalogFeedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}-{}-{}", self.categories, self.stores, self.base_products, self.products)
+    }
+}
+
+impl FromStr for CatalogFeedCursor {
+    type Err = ();
+
+    /// Cursors of a single `updated_at` issued before paging was introduced are accepted as well
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let positions = s.split('-').map(CatalogFeedPosition::from_str).collect::<Result<Vec<_>, _>>()?;
+        match positions.as_slice() {
+            [position] if position.last_id == 0 => Ok(CatalogFeedCursor::since(position.updated_at)),
+            [categories, stores, base_products, products] => Ok(CatalogFeedCursor {
+                categories: *categories,
+                stores: *stores,
+                base_products: *base_products,
+                products: *products,
+            }),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Page of rows changed since feed position, split into visible ones and ids of hidden or deactivated ones
+#[derive(Clone, Debug)]
+pub struct CatalogFeedChanges<T, Id> {
+    pub updated: Vec<T>,
+    pub deleted: Vec<Id>,
+    /// Position of the last row of the page
+    pub last_position: Option<CatalogFeedPosition>,
+}
+
+impl<T, Id> CatalogFeedChanges<T, Id> {
+    pub fn is_full_page(&self, limit: i64) -> bool {
+        (self.updated.len() + self.deleted.len()) as i64 >= limit
+    }
+}
+
+impl<T, Id> Default for CatalogFeedChanges<T, Id> {
+    fn default() -> Self {
+        Self {
+            updated: vec![],
+            deleted: vec![],
+            last_position: None,
+        }
+    }
+}
@@ -4,6 +4,7 @@
 pub mod category_attribute;
 
 use std::cmp::Ordering;
+use std::time::SystemTime;
 
 use serde_json;
 use uuid::Uuid;
@@ -28,6 +29,7 @@ pub struct RawCategory {
     pub is_active: bool,
     pub uuid: Uuid,
     pub slug: CategorySlug,
+    pub updated_at: SystemTime,
 }
 
 impl Eq for RawCategory {}
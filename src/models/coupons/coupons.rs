@@ -1,8 +1,11 @@
 //! Model coupons
 use std::time::SystemTime;
 
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 use validator::Validate;
 
+use stq_static_resources::Currency;
 use stq_types::{CouponCode, CouponId, StoreId};
 
 use models::validation_rules::*;
@@ -24,6 +27,13 @@ pub struct Coupon {
     pub is_active: bool,
     pub created_at: SystemTime,
     pub updated_at: SystemTime,
+    pub discount_type: CouponType,
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    pub per_user_limit: i32,
+    pub min_subtotal: Option<f64>,
+    pub active_from: Option<SystemTime>,
+    pub campaign_id: Option<Uuid>,
 }
 
 /// Payload for creating coupon
@@ -35,11 +45,23 @@ pub struct NewCoupon {
     pub title: String,
     pub store_id: StoreId,
     pub scope: CouponScope,
+    #[serde(default)]
     #[validate(range(min = "0", max = "100"))]
     pub percent: i32,
     #[validate(custom = "validate_non_negative_coupon_quantity")]
     pub quantity: i32,
     pub expired_at: Option<SystemTime>,
+    #[serde(default)]
+    pub discount_type: CouponType,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    #[validate(custom = "validate_non_negative_coupon_quantity")]
+    pub per_user_limit: Option<i32>,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub min_subtotal: Option<f64>,
+    pub active_from: Option<SystemTime>,
+    pub campaign_id: Option<Uuid>,
 }
 
 impl Coupon {
@@ -47,10 +69,112 @@ impl Coupon {
     pub const MAX_LENGTH_CODE: u64 = 12;
     pub const MIN_GENERATE_LENGTH_CODE: usize = 6;
     pub const INFINITE: i32 = 0;
+    pub const DEFAULT_PER_USER_LIMIT: i32 = 1;
+    pub const MAX_LENGTH_CODE_PREFIX: u64 = Coupon::MAX_LENGTH_CODE - Coupon::MIN_GENERATE_LENGTH_CODE as u64;
+    pub const MAX_BATCH_GENERATE_ATTEMPTS: usize = 10;
+    pub const BATCH_INSERT_CHUNK_SIZE: usize = 1000;
+}
+
+/// Payload for creating batch of coupons with generated codes
+#[derive(Serialize, Deserialize, Clone, Validate, Debug)]
+pub struct NewCouponsBatch {
+    #[validate(custom = "validate_coupon_code_prefix")]
+    pub prefix: String,
+    #[validate(range(min = "1", max = "10000"))]
+    pub count: i32,
+    pub title: String,
+    pub store_id: StoreId,
+    pub scope: CouponScope,
+    #[serde(default)]
+    #[validate(range(min = "0", max = "100"))]
+    pub percent: i32,
+    #[validate(custom = "validate_non_negative_coupon_quantity")]
+    pub quantity: i32,
+    pub expired_at: Option<SystemTime>,
+    #[serde(default)]
+    pub discount_type: CouponType,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    #[validate(custom = "validate_non_negative_coupon_quantity")]
+    pub per_user_limit: Option<i32>,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub min_subtotal: Option<f64>,
+    pub active_from: Option<SystemTime>,
+}
+
+impl NewCouponsBatch {
+    pub fn to_new_coupon(&self, code: CouponCode, campaign_id: Uuid) -> NewCoupon {
+        NewCoupon {
+            code,
+            title: self.title.clone(),
+            store_id: self.store_id,
+            scope: self.scope.clone(),
+            percent: self.percent,
+            quantity: self.quantity,
+            expired_at: self.expired_at,
+            discount_type: self.discount_type,
+            amount: self.amount,
+            currency: self.currency,
+            per_user_limit: self.per_user_limit,
+            min_subtotal: self.min_subtotal,
+            active_from: self.active_from,
+            campaign_id: Some(campaign_id),
+        }
+    }
+}
+
+/// Coupons created in one batch
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CouponsBatch {
+    pub campaign_id: Uuid,
+    pub coupons: Vec<Coupon>,
+}
+
+/// Row of coupons batch CSV export
+#[derive(Serialize, Debug)]
+pub struct CouponCsvRecord {
+    pub code: String,
+    pub campaign_id: Option<Uuid>,
+    pub store_id: StoreId,
+    pub title: String,
+    pub discount_type: CouponType,
+    pub percent: i32,
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    pub quantity: i32,
+    pub per_user_limit: i32,
+    pub min_subtotal: Option<f64>,
+    pub active_from: Option<String>,
+    pub expired_at: Option<String>,
+    pub is_active: bool,
+}
+
+impl From<Coupon> for CouponCsvRecord {
+    fn from(coupon: Coupon) -> Self {
+        let to_rfc3339 = |time: SystemTime| DateTime::<Utc>::from(time).to_rfc3339();
+
+        Self {
+            code: coupon.code.0,
+            campaign_id: coupon.campaign_id,
+            store_id: coupon.store_id,
+            title: coupon.title,
+            discount_type: coupon.discount_type,
+            percent: coupon.percent,
+            amount: coupon.amount,
+            currency: coupon.currency,
+            quantity: coupon.quantity,
+            per_user_limit: coupon.per_user_limit,
+            min_subtotal: coupon.min_subtotal,
+            active_from: coupon.active_from.map(to_rfc3339),
+            expired_at: coupon.expired_at.map(to_rfc3339),
+            is_active: coupon.is_active,
+        }
+    }
 }
 
 /// Payload for updating coupon
-#[derive(Serialize, Deserialize, Insertable, AsChangeset, Validate, Debug)]
+#[derive(Serialize, Deserialize, Insertable, AsChangeset, Validate, Debug, Default)]
 #[table_name = "coupons"]
 pub struct UpdateCoupon {
     #[validate(range(min = "0", max = "100"))]
@@ -59,6 +183,14 @@ pub struct UpdateCoupon {
     pub quantity: Option<i32>,
     pub expired_at: Option<SystemTime>,
     pub is_active: Option<bool>,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub amount: Option<f64>,
+    pub currency: Option<Currency>,
+    #[validate(custom = "validate_non_negative_coupon_quantity")]
+    pub per_user_limit: Option<i32>,
+    #[validate(custom = "validate_non_negative_coupon_amount")]
+    pub min_subtotal: Option<f64>,
+    pub active_from: Option<SystemTime>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, DieselTypes)]
@@ -68,8 +200,36 @@ pub enum CouponScope {
     BaseProducts,
 }
 
+/// Kind of discount given by coupon
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, DieselTypes)]
+pub enum CouponType {
+    /// Discount in percents of the price, stored in `percent`
+    Percent,
+    /// Fixed discount, stored in `amount` and denominated in `currency`
+    FixedAmount,
+    /// Shipping of the order is free of charge
+    FreeShipping,
+}
+
+impl Default for CouponType {
+    fn default() -> Self {
+        CouponType::Percent
+    }
+}
+
+/// Discount of coupon presented in customer's currency
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CouponDiscount {
+    pub discount_type: CouponType,
+    pub value: f64,
+    pub currency: Option<Currency>,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct CouponsSearchCodePayload {
     pub code: CouponCode,
     pub store_id: StoreId,
+    /// Cart subtotal in customer's currency, used to check coupon minimum subtotal
+    #[serde(default)]
+    pub subtotal: Option<f64>,
 }
@@ -0,0 +1,21 @@
+//! Model coupon_exclusion_base_products table
+
+use stq_types::{BaseProductId, CouponId};
+
+use schema::coupon_exclusion_base_products;
+
+#[derive(Debug, Serialize, Deserialize, Associations, Queryable, Clone, Identifiable)]
+#[table_name = "coupon_exclusion_base_products"]
+pub struct CouponExclusionBaseProducts {
+    pub id: i32,
+    pub coupon_id: CouponId,
+    pub base_product_id: BaseProductId,
+}
+
+/// Payload for creating coupon_exclusion_base_products
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "coupon_exclusion_base_products"]
+pub struct NewCouponExclusionBaseProducts {
+    pub coupon_id: CouponId,
+    pub base_product_id: BaseProductId,
+}
@@ -1,9 +1,11 @@
 pub mod coupons;
+pub mod exclusion_base_products;
 pub mod scope_base_products;
 pub mod scope_categories;
 pub mod used_coupons;
 
 pub use self::coupons::*;
+pub use self::exclusion_base_products::*;
 pub use self::scope_base_products::*;
 pub use self::scope_categories::*;
 pub use self::used_coupons::*;
@@ -11,6 +11,7 @@ use schema::used_coupons;
 pub struct UsedCoupon {
     pub coupon_id: CouponId,
     pub user_id: UserId,
+    pub times_used: i32,
 }
 
 /// Payload for creating coupon
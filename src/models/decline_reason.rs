@@ -0,0 +1,40 @@
+//! Models for catalog of reasons moderator declines stores and base products with
+use std::time::SystemTime;
+
+use serde_json;
+use validator::Validate;
+
+use models::validation_rules::*;
+use schema::decline_reasons;
+
+/// Reason of declining entity on moderation, `name` contains translations
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "decline_reasons"]
+pub struct DeclineReason {
+    pub id: i32,
+    pub code: String,
+    pub name: serde_json::Value,
+    /// Inactive reasons are kept for existing feedback but can not be used in new one
+    pub is_active: bool,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+/// Payload for creating decline reason
+#[derive(Serialize, Deserialize, Insertable, Validate, Clone, Debug)]
+#[table_name = "decline_reasons"]
+pub struct NewDeclineReason {
+    #[validate(custom = "validate_decline_reason_code")]
+    pub code: String,
+    #[validate(custom = "validate_translation")]
+    pub name: serde_json::Value,
+}
+
+/// Payload for updating decline reason
+#[derive(Default, Serialize, Deserialize, AsChangeset, Validate, Clone, Debug)]
+#[table_name = "decline_reasons"]
+pub struct UpdateDeclineReason {
+    #[validate(custom = "validate_translation")]
+    pub name: Option<serde_json::Value>,
+    pub is_active: Option<bool>,
+}
@@ -0,0 +1,7 @@
+/// Suggestion of search-as-you-type, `highlighted` is `text` with matched fragments wrapped in `<em>` tags
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AutoCompleteSuggestion {
+    pub text: String,
+    pub highlighted: String,
+    pub score: f32,
+}
@@ -0,0 +1,176 @@
+//! Documents of products and stores indices, built from db entities by reindexing
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use stq_static_resources::{AttributeType, Currency, ModerationStatus};
+use stq_types::{BaseProductId, CategoryId, ProductId, ProductPrice, StoreId, UserId};
+
+use models::{
+    CatalogWithAttributes, ElasticAttrValue, ElasticProduct, ElasticVariant, ProductCategories, ProductWithAttributes, StockStatus, Store,
+};
+
+/// Document of products index, one per active base product
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ProductDocument {
+    pub id: BaseProductId,
+    pub store_id: StoreId,
+    pub name: serde_json::Value,
+    pub short_description: serde_json::Value,
+    pub long_description: Option<serde_json::Value>,
+    pub category_id: CategoryId,
+    pub views: i32,
+    pub rating: f64,
+    pub status: ModerationStatus,
+    pub store_status: ModerationStatus,
+    /// Milliseconds since epoch
+    pub created_at: u64,
+    pub store_rating: f64,
+    pub variants: Vec<VariantDocument>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VariantDocument {
+    pub prod_id: ProductId,
+    pub discount: Option<f64>,
+    pub price: ProductPrice,
+    pub currency: Currency,
+    pub stock_status: Option<StockStatus>,
+    pub attrs: Vec<ElasticAttrValue>,
+}
+
+impl ProductDocument {
+    pub fn new(catalog: CatalogWithAttributes, store_rating: f64) -> Self {
+        let CatalogWithAttributes { base_product, variants } = catalog;
+
+        Self {
+            id: base_product.id,
+            store_id: base_product.store_id,
+            name: base_product.name,
+            short_description: base_product.short_description,
+            long_description: base_product.long_description,
+            category_id: base_product.category_id,
+            views: base_product.views,
+            rating: base_product.rating,
+            status: base_product.status,
+            store_status: base_product.store_status,
+            created_at: epoch_millis(base_product.created_at),
+            store_rating,
+            variants: variants.into_iter().map(VariantDocument::from).collect(),
+        }
+    }
+}
+
+impl From<ProductWithAttributes> for VariantDocument {
+    fn from(variant: ProductWithAttributes) -> Self {
+        let ProductWithAttributes { product, attributes } = variant;
+        let attrs = attributes
+            .into_iter()
+            .map(|(prod_attr, _)| {
+                let value = prod_attr.value.0;
+                let (str_val, float_val) = match prod_attr.value_type {
+                    AttributeType::Float => (None, value.parse::<f64>().ok()),
+                    AttributeType::Str => (Some(value), None),
+                };
+
+                ElasticAttrValue {
+                    attr_id: prod_attr.attr_id.0,
+                    str_val,
+                    float_val,
+                }
+            })
+            .collect();
+
+        Self {
+            prod_id: product.id,
+            discount: product.discount,
+            price: product.price,
+            currency: product.currency,
+            stock_status: product.stock_status,
+            attrs,
+        }
+    }
+}
+
+/// Product found in documents without going through Elastic, variants matched by search are set by caller
+impl From<ProductDocument> for ElasticProduct {
+    fn from(document: ProductDocument) -> Self {
+        Self {
+            id: document.id,
+            name: document.name,
+            short_description: document.short_description,
+            long_description: document.long_description,
+            views: document.views,
+            rating: Some(document.rating),
+            variants: document.variants.into_iter().map(ElasticVariant::from).collect(),
+            category_id: document.category_id.0,
+            matched_variants_ids: None,
+        }
+    }
+}
+
+impl From<VariantDocument> for ElasticVariant {
+    fn from(variant: VariantDocument) -> Self {
+        Self {
+            prod_id: variant.prod_id,
+            discount: variant.discount,
+            price: variant.price,
+            attrs: variant.attrs,
+            stock_status: variant.stock_status,
+        }
+    }
+}
+
+pub fn epoch_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() * 1000 + u64::from(duration.subsec_millis()))
+        .unwrap_or_default()
+}
+
+/// Document of stores index, one per active store
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoreDocument {
+    pub id: StoreId,
+    pub user_id: UserId,
+    pub name: serde_json::Value,
+    pub short_description: serde_json::Value,
+    pub rating: f64,
+    pub country: Option<String>,
+    pub locality: Option<String>,
+    pub location: Option<GeoPointDocument>,
+    pub status: ModerationStatus,
+    pub product_categories: Vec<ProductCategories>,
+}
+
+/// Elastic `geo_point` in object format
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct GeoPointDocument {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl From<Store> for StoreDocument {
+    fn from(store: Store) -> Self {
+        let location = store.location().map(|location| GeoPointDocument {
+            lat: location.latitude,
+            lon: location.longitude,
+        });
+        let product_categories = store
+            .product_categories
+            .and_then(|product_categories| serde_json::from_value::<Vec<ProductCategories>>(product_categories).ok())
+            .unwrap_or_default();
+
+        Self {
+            id: store.id,
+            user_id: store.user_id,
+            name: store.name,
+            short_description: store.short_description,
+            rating: store.rating,
+            country: store.country,
+            locality: store.locality,
+            location,
+            status: store.status,
+            product_categories,
+        }
+    }
+}
@@ -1,16 +1,21 @@
 //! Elastic search models
 use std::fmt;
 
+pub mod autocomplete_suggestion;
 pub mod count_response;
+pub mod index_document;
 pub mod index_response;
 pub mod search_response;
 pub mod shards;
 
+pub use self::autocomplete_suggestion::*;
 pub use self::count_response::*;
+pub use self::index_document::*;
 pub use self::index_response::*;
 pub use self::search_response::*;
 pub use self::shards::*;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ElasticIndex {
     Store,
     Product,
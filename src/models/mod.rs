@@ -4,16 +4,27 @@
 pub mod attributes;
 pub mod authorization;
 pub mod base_product;
+pub mod base_product_import;
+pub mod catalog_export;
+pub mod catalog_feed;
 pub mod category;
 pub mod coupons;
 pub mod currency_exchange;
 pub mod custom_attributes;
+pub mod decline_reason;
 pub mod elastic;
+pub mod moderation;
+pub mod moderation_state_machine;
 pub mod moderator_product_comment;
 pub mod moderator_store_comment;
+pub mod outbox_event;
 pub mod pagination;
+pub mod pre_moderation;
 pub mod product;
+pub mod product_price;
+pub mod search_synonym;
 pub mod store;
+pub mod store_staff;
 pub mod user_role;
 pub mod validation_rules;
 pub mod visibility;
@@ -22,16 +33,27 @@ pub mod wizard_store;
 pub use self::attributes::*;
 pub use self::authorization::*;
 pub use self::base_product::*;
+pub use self::base_product_import::*;
+pub use self::catalog_export::*;
+pub use self::catalog_feed::*;
 pub use self::category::*;
 pub use self::coupons::*;
 pub use self::currency_exchange::*;
 pub use self::custom_attributes::*;
+pub use self::decline_reason::*;
 pub use self::elastic::*;
+pub use self::moderation::*;
+pub use self::moderation_state_machine::*;
 pub use self::moderator_product_comment::*;
 pub use self::moderator_store_comment::*;
+pub use self::outbox_event::*;
 pub use self::pagination::*;
+pub use self::pre_moderation::*;
 pub use self::product::*;
+pub use self::product_price::*;
+pub use self::search_synonym::*;
 pub use self::store::*;
+pub use self::store_staff::*;
 pub use self::user_role::*;
 pub use self::validation_rules::*;
 pub use self::visibility::*;
@@ -0,0 +1,419 @@
+//! Models for moderation queue, history of moderation status changes and moderator feedback
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime};
+
+use serde_json;
+
+use stq_static_resources::ModerationStatus;
+use stq_types::UserId;
+
+use models::PreModerationFinding;
+use schema::{moderation_feedback, moderation_queue, moderation_transitions};
+
+/// Kind of entity passing moderation
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, DieselTypes)]
+pub enum ModerationEntityType {
+    Store,
+    BaseProduct,
+}
+
+impl ModerationEntityType {
+    /// Fields of entity editor moderator feedback can point to
+    pub fn feedback_fields(self) -> &'static [&'static str] {
+        match self {
+            ModerationEntityType::Store => &[
+                "name",
+                "short_description",
+                "long_description",
+                "slug",
+                "logo",
+                "cover",
+                "phone",
+                "email",
+                "address",
+                "facebook_url",
+                "twitter_url",
+                "instagram_url",
+            ],
+            ModerationEntityType::BaseProduct => &[
+                "name",
+                "short_description",
+                "long_description",
+                "seo_title",
+                "seo_description",
+                "category_id",
+                "slug",
+                "photo_main",
+                "additional_photos",
+                "vendor_code",
+                "price",
+                "attributes",
+            ],
+        }
+    }
+}
+
+/// Why moderation status of entity was changed
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, DieselTypes)]
+pub enum ModerationReason {
+    /// Seller sent entity to moderation
+    SentToModeration,
+    /// Moderator published, declined or blocked entity
+    ModeratorDecision,
+    /// Seller hid entity from search
+    HiddenBySeller,
+    /// Declined entity was edited by seller and returned to draft
+    EditedAfterDecline,
+    /// Entity sent to moderation failed automated pre-moderation checks
+    PreModerationCheck,
+}
+
+/// Requested change of moderation status of entity
+#[derive(Clone, Debug)]
+pub struct ModerationStatusChange {
+    pub status: ModerationStatus,
+    pub reason: ModerationReason,
+    pub comment: Option<String>,
+    /// Decline reasons given by moderator, allowed for `Decline` status only
+    pub feedback: Vec<ModerationFeedbackPayload>,
+    /// Findings of pre-moderation checks moderator has to look at, saved with the queue item
+    pub review_notes: Vec<PreModerationFinding>,
+}
+
+impl ModerationStatusChange {
+    pub fn new(status: ModerationStatus, reason: ModerationReason) -> Self {
+        Self {
+            status,
+            reason,
+            comment: None,
+            feedback: vec![],
+            review_notes: vec![],
+        }
+    }
+
+    pub fn with_comment(self, comment: Option<String>) -> Self {
+        Self { comment, ..self }
+    }
+
+    pub fn with_feedback(self, feedback: Vec<ModerationFeedbackPayload>) -> Self {
+        Self { feedback, ..self }
+    }
+
+    pub fn with_review_notes(self, review_notes: Vec<PreModerationFinding>) -> Self {
+        Self { review_notes, ..self }
+    }
+}
+
+/// Moderator decision applied to several stores or base products at once
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BulkModerationAction {
+    Approve,
+    Decline,
+    SendToDraft,
+}
+
+impl BulkModerationAction {
+    /// Moderation status items are moved to
+    pub fn status(self) -> ModerationStatus {
+        match self {
+            BulkModerationAction::Approve => ModerationStatus::Published,
+            BulkModerationAction::Decline => ModerationStatus::Decline,
+            BulkModerationAction::SendToDraft => ModerationStatus::Draft,
+        }
+    }
+}
+
+/// Payload of bulk moderation, comment and feedback are saved with change of status of every item
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BulkModeration<Id> {
+    pub ids: Vec<Id>,
+    pub action: BulkModerationAction,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Decline reasons, allowed for `Decline` action only
+    #[serde(default)]
+    pub feedback: Vec<ModerationFeedbackPayload>,
+}
+
+/// Result of bulk moderation of specific item
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BulkModerationItemResult<Id> {
+    pub id: Id,
+    /// Moderation status of the item after the change, `None` if the change failed
+    pub status: Option<ModerationStatus>,
+    pub error: Option<String>,
+    /// Validation errors in the same format as in API error payload
+    pub validation_errors: Option<serde_json::Value>,
+}
+
+impl<Id> BulkModerationItemResult<Id> {
+    pub fn succeeded(id: Id, status: ModerationStatus) -> Self {
+        Self {
+            id,
+            status: Some(status),
+            error: None,
+            validation_errors: None,
+        }
+    }
+
+    pub fn failed(id: Id, error: String, validation_errors: Option<serde_json::Value>) -> Self {
+        Self {
+            id,
+            status: None,
+            error: Some(error),
+            validation_errors,
+        }
+    }
+}
+
+/// Result of bulk moderation, every item is changed on its own so failed items do not affect the rest
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BulkModerationReport<Id> {
+    pub succeeded_count: usize,
+    pub failed_count: usize,
+    /// Results in the order of requested ids
+    pub items: Vec<BulkModerationItemResult<Id>>,
+}
+
+impl<Id> BulkModerationReport<Id> {
+    pub fn new(items: Vec<BulkModerationItemResult<Id>>) -> Self {
+        let succeeded_count = items.iter().filter(|item| item.error.is_none()).count();
+        Self {
+            succeeded_count,
+            failed_count: items.len() - succeeded_count,
+            items,
+        }
+    }
+}
+
+/// DB presenting by entity waiting for moderator decision
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "moderation_queue"]
+pub struct ModerationQueueItem {
+    pub id: i32,
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    /// Moderator who claimed the item
+    pub assignee_id: Option<UserId>,
+    pub enqueued_at: SystemTime,
+    pub assigned_at: Option<SystemTime>,
+    /// Pre-moderation checks found issues moderator has to look at
+    pub needs_review: bool,
+    /// Findings of pre-moderation checks
+    pub review_notes: serde_json::Value,
+}
+
+impl ModerationQueueItem {
+    pub fn time_in_queue(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.enqueued_at).unwrap_or_default()
+    }
+}
+
+/// Payload for putting entity into moderation queue
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "moderation_queue"]
+pub struct NewModerationQueueItem {
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub needs_review: bool,
+    pub review_notes: serde_json::Value,
+}
+
+/// Item of moderation queue with its waiting time against moderation SLA
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModerationQueueEntry {
+    pub id: i32,
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub assignee_id: Option<UserId>,
+    pub enqueued_at: SystemTime,
+    pub assigned_at: Option<SystemTime>,
+    pub needs_review: bool,
+    pub review_notes: serde_json::Value,
+    pub time_in_queue_sec: u64,
+    /// Time by which moderator decision is expected
+    pub sla_deadline: SystemTime,
+    pub sla_breached: bool,
+}
+
+impl ModerationQueueEntry {
+    pub fn new(item: ModerationQueueItem, sla: Duration, now: SystemTime) -> Self {
+        let sla_deadline = item.enqueued_at + sla;
+
+        Self {
+            time_in_queue_sec: item.time_in_queue(now).as_secs(),
+            sla_deadline,
+            sla_breached: now > sla_deadline,
+            id: item.id,
+            entity_type: item.entity_type,
+            entity_id: item.entity_id,
+            assignee_id: item.assignee_id,
+            enqueued_at: item.enqueued_at,
+            assigned_at: item.assigned_at,
+            needs_review: item.needs_review,
+            review_notes: item.review_notes,
+        }
+    }
+}
+
+/// Filters of moderation queue, items are returned from the longest waiting one
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ModerationQueueSearch {
+    pub entity_type: Option<ModerationEntityType>,
+    pub assignee_id: Option<UserId>,
+    /// Return only items not claimed by any moderator
+    #[serde(default)]
+    pub unassigned: bool,
+    /// Return only items pre-moderation checks found issues with, or only ones without them
+    pub needs_review: Option<bool>,
+}
+
+/// DB presenting by change of moderation status of entity
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "moderation_transitions"]
+pub struct ModerationTransition {
+    pub id: i32,
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    /// User who changed the status
+    pub user_id: Option<UserId>,
+    pub from_status: ModerationStatus,
+    pub to_status: ModerationStatus,
+    pub reason: ModerationReason,
+    pub comment: Option<String>,
+    /// Time entity was waiting in moderation queue, set for transitions from `Moderation` status
+    pub time_in_queue_sec: Option<i64>,
+    pub created_at: SystemTime,
+}
+
+/// Payload for recording change of moderation status
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "moderation_transitions"]
+pub struct NewModerationTransition {
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub user_id: Option<UserId>,
+    pub from_status: ModerationStatus,
+    pub to_status: ModerationStatus,
+    pub reason: ModerationReason,
+    pub comment: Option<String>,
+    pub time_in_queue_sec: Option<i64>,
+}
+
+/// Decline reason given by moderator, optionally pointing to the field of entity which has to be fixed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModerationFeedbackPayload {
+    pub reason_code: String,
+    pub field: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// DB presenting by decline reason given by moderator with change of moderation status
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "moderation_feedback"]
+pub struct ModerationFeedback {
+    pub id: i32,
+    /// Change of moderation status the feedback was given with
+    pub transition_id: i32,
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub reason_code: String,
+    pub field: Option<String>,
+    pub comment: Option<String>,
+    pub created_at: SystemTime,
+}
+
+/// Payload for recording moderator feedback
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "moderation_feedback"]
+pub struct NewModerationFeedback {
+    pub transition_id: i32,
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub reason_code: String,
+    pub field: Option<String>,
+    pub comment: Option<String>,
+}
+
+impl NewModerationFeedback {
+    pub fn new(transition: &ModerationTransition, payload: ModerationFeedbackPayload) -> Self {
+        Self {
+            transition_id: transition.id,
+            entity_type: transition.entity_type,
+            entity_id: transition.entity_id,
+            reason_code: payload.reason_code,
+            field: payload.field,
+            comment: payload.comment,
+        }
+    }
+}
+
+/// Filters of moderator feedback used in decline reasons report
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ModerationFeedbackSearch {
+    pub entity_type: Option<ModerationEntityType>,
+    pub created_from: Option<SystemTime>,
+    pub created_to: Option<SystemTime>,
+}
+
+/// How often decline reason was used
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DeclineReasonStat {
+    pub reason_code: String,
+    /// Count of declines the reason was given with
+    pub declines_count: u64,
+    /// Count of declines the reason was given with by field, feedback without field is not counted here
+    pub fields: BTreeMap<String, u64>,
+}
+
+/// Builds decline reasons report from counts of declines by reason and by reason and field,
+/// the most common reasons go first
+pub fn decline_reasons_report(reasons: Vec<(String, i64)>, reason_fields: Vec<(String, String, i64)>) -> Vec<DeclineReasonStat> {
+    let mut fields = HashMap::<String, BTreeMap<String, u64>>::new();
+    for (reason_code, field, declines_count) in reason_fields {
+        fields.entry(reason_code).or_default().insert(field, declines_count as u64);
+    }
+
+    let mut report = reasons
+        .into_iter()
+        .map(|(reason_code, declines_count)| DeclineReasonStat {
+            fields: fields.remove(&reason_code).unwrap_or_default(),
+            reason_code,
+            declines_count: declines_count as u64,
+        })
+        .collect::<Vec<_>>();
+    report.sort_by(|a, b| {
+        b.declines_count
+            .cmp(&a.declines_count)
+            .then_with(|| a.reason_code.cmp(&b.reason_code))
+    });
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use models::*;
+
+    #[test]
+    fn test_decline_reasons_report() {
+        let reasons = vec![
+            ("prohibited_item".to_string(), 1),
+            ("missing_translation".to_string(), 1),
+            ("bad_photo".to_string(), 2),
+        ];
+        let reason_fields = vec![
+            ("bad_photo".to_string(), "photo_main".to_string(), 2),
+            ("bad_photo".to_string(), "additional_photos".to_string(), 1),
+            ("missing_translation".to_string(), "long_description".to_string(), 1),
+        ];
+
+        let report = decline_reasons_report(reasons, reason_fields);
+
+        let codes = report.iter().map(|stat| stat.reason_code.as_str()).collect::<Vec<_>>();
+        assert_eq!(codes, vec!["bad_photo", "missing_translation", "prohibited_item"]);
+        assert_eq!(report[0].declines_count, 2);
+        assert_eq!(report[0].fields.get("photo_main"), Some(&2));
+        assert_eq!(report[0].fields.get("additional_photos"), Some(&1));
+        assert!(report[2].fields.is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+//! Moderation state machine shared by stores and base products. Declares allowed changes of moderation status,
+//! guards limiting who can make them and side effects applied together with them
+use stq_static_resources::ModerationStatus;
+
+use models::{ModerationEntityType, ModerationReason, ModerationStatusChange};
+
+/// All moderation statuses of stores and base products
+pub const MODERATION_STATUSES: [ModerationStatus; 5] = [
+    ModerationStatus::Draft,
+    ModerationStatus::Moderation,
+    ModerationStatus::Decline,
+    ModerationStatus::Blocked,
+    ModerationStatus::Published,
+];
+
+/// Statuses in which seller can edit entity
+pub const EDITABLE_STATUSES: [ModerationStatus; 3] = [ModerationStatus::Draft, ModerationStatus::Decline, ModerationStatus::Published];
+
+/// Who changes moderation status of entity
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationActor {
+    /// Store owner or store manager
+    Seller,
+    Moderator,
+    /// Automated checks run on behalf of the service
+    System,
+}
+
+impl ModerationReason {
+    pub fn actor(self) -> ModerationActor {
+        match self {
+            ModerationReason::ModeratorDecision => ModerationActor::Moderator,
+            ModerationReason::PreModerationCheck => ModerationActor::System,
+            ModerationReason::SentToModeration | ModerationReason::HiddenBySeller | ModerationReason::EditedAfterDecline => {
+                ModerationActor::Seller
+            }
+        }
+    }
+}
+
+/// Effect applied together with change of moderation status
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationSideEffect {
+    /// Entity is put into moderation queue
+    Enqueue,
+    /// Entity is removed from moderation queue
+    Dequeue,
+    /// Status of store is copied to `store_status` of its base products, which are reindexed too
+    CascadeToBaseProducts,
+    /// Entity is reindexed in search through outbox event
+    Reindex,
+}
+
+/// Allowed change of moderation status
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModerationTransitionRule {
+    pub from: ModerationStatus,
+    pub to: ModerationStatus,
+    /// Guard of the transition, status can be changed with one of these reasons only
+    pub reasons: &'static [ModerationReason],
+}
+
+impl ModerationTransitionRule {
+    pub fn allows(&self, reason: ModerationReason) -> bool {
+        self.reasons.contains(&reason)
+    }
+
+    pub fn allows_actor(&self, actor: ModerationActor) -> bool {
+        self.reasons.iter().any(|reason| reason.actor() == actor)
+    }
+}
+
+/// Transitions of moderation status of stores and base products. Moderator can make any of them,
+/// seller only ones starting from editable statuses, as ACL lets seller moderate entity in these statuses only.
+/// Pre-moderation checks can only decline entity which has just been sent to moderation
+pub static MODERATION_TRANSITIONS: [ModerationTransitionRule; 9] = [
+    ModerationTransitionRule {
+        from: ModerationStatus::Draft,
+        to: ModerationStatus::Moderation,
+        reasons: &[ModerationReason::SentToModeration, ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Decline,
+        to: ModerationStatus::Moderation,
+        reasons: &[ModerationReason::SentToModeration, ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Published,
+        to: ModerationStatus::Moderation,
+        reasons: &[ModerationReason::SentToModeration, ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Blocked,
+        to: ModerationStatus::Moderation,
+        reasons: &[ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Moderation,
+        to: ModerationStatus::Published,
+        reasons: &[ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Moderation,
+        to: ModerationStatus::Decline,
+        reasons: &[ModerationReason::ModeratorDecision, ModerationReason::PreModerationCheck],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Moderation,
+        to: ModerationStatus::Blocked,
+        reasons: &[ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Published,
+        to: ModerationStatus::Draft,
+        reasons: &[ModerationReason::HiddenBySeller, ModerationReason::ModeratorDecision],
+    },
+    ModerationTransitionRule {
+        from: ModerationStatus::Decline,
+        to: ModerationStatus::Draft,
+        reasons: &[
+            ModerationReason::HiddenBySeller,
+            ModerationReason::EditedAfterDecline,
+            ModerationReason::ModeratorDecision,
+        ],
+    },
+];
+
+/// Returns rule of transition between statuses if there is one
+pub fn find_moderation_transition(from: ModerationStatus, to: ModerationStatus) -> Option<&'static ModerationTransitionRule> {
+    MODERATION_TRANSITIONS.iter().find(|rule| rule.from == from && rule.to == to)
+}
+
+/// Checks that the change can be made to entity in status `from`
+pub fn is_moderation_transition_allowed(from: ModerationStatus, change: &ModerationStatusChange) -> bool {
+    let allowed = find_moderation_transition(from, change.status)
+        .map(|rule| rule.allows(change.reason))
+        .unwrap_or(false);
+    if !allowed {
+        debug!(
+            "change status from {} to {} with reason {:?} unreachable.",
+            from, change.status, change.reason
+        );
+    }
+
+    allowed
+}
+
+/// Returns statuses actor can move entity to from status `from`
+pub fn next_moderation_statuses(from: ModerationStatus, actor: ModerationActor) -> Vec<ModerationStatus> {
+    MODERATION_TRANSITIONS
+        .iter()
+        .filter(|rule| rule.from == from && rule.allows_actor(actor))
+        .map(|rule| rule.to)
+        .collect()
+}
+
+/// Returns effects of change of entity status from `from` to `to`, in the order they are applied
+pub fn moderation_side_effects(
+    entity_type: ModerationEntityType,
+    from: ModerationStatus,
+    to: ModerationStatus,
+) -> Vec<ModerationSideEffect> {
+    let mut side_effects = vec![];
+    if from == ModerationStatus::Moderation {
+        side_effects.push(ModerationSideEffect::Dequeue);
+    }
+    if to == ModerationStatus::Moderation {
+        side_effects.push(ModerationSideEffect::Enqueue);
+    }
+    if entity_type == ModerationEntityType::Store {
+        side_effects.push(ModerationSideEffect::CascadeToBaseProducts);
+    }
+    side_effects.push(ModerationSideEffect::Reindex);
+
+    side_effects
+}
+
+/// Checks that seller can edit entity in the status
+pub fn is_editable_status(status: ModerationStatus) -> bool {
+    let editable = EDITABLE_STATUSES.contains(&status);
+    if !editable {
+        debug!("update object in status {} unreachable.", status);
+    }
+
+    editable
+}
+
+/// Returns status entity is moved to after it was edited by seller, if edit changes the status
+pub fn moderation_status_after_edit(status: ModerationStatus) -> Option<ModerationStatus> {
+    MODERATION_TRANSITIONS
+        .iter()
+        .find(|rule| rule.from == status && rule.allows(ModerationReason::EditedAfterDecline))
+        .map(|rule| rule.to)
+}
+
+/// Moderation statuses user can move entity to from its current status
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AllowedModerationTransitions {
+    pub entity_type: ModerationEntityType,
+    pub entity_id: i32,
+    pub status: ModerationStatus,
+    /// Who user acts as, `None` if user can not change moderation status of the entity
+    pub actor: Option<ModerationActor>,
+    /// Whether seller can edit the entity in its current status
+    pub editable: bool,
+    pub next_statuses: Vec<ModerationStatus>,
+}
+
+impl AllowedModerationTransitions {
+    pub fn new(entity_type: ModerationEntityType, entity_id: i32, status: ModerationStatus, actor: Option<ModerationActor>) -> Self {
+        Self {
+            entity_type,
+            entity_id,
+            status,
+            actor,
+            editable: EDITABLE_STATUSES.contains(&status),
+            next_statuses: actor.map(|actor| next_moderation_statuses(status, actor)).unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::{thread_rng, Rng, SeedableRng, StdRng};
+
+    use stq_static_resources::ModerationStatus;
+
+    use models::*;
+
+    const MODERATION_REASONS: [ModerationReason; 5] = [
+        ModerationReason::SentToModeration,
+        ModerationReason::ModeratorDecision,
+        ModerationReason::HiddenBySeller,
+        ModerationReason::EditedAfterDecline,
+        ModerationReason::PreModerationCheck,
+    ];
+
+    const MODERATION_ACTORS: [ModerationActor; 3] = [ModerationActor::Seller, ModerationActor::Moderator, ModerationActor::System];
+
+    const ENTITY_TYPES: [ModerationEntityType; 2] = [ModerationEntityType::Store, ModerationEntityType::BaseProduct];
+
+    fn is_allowed(from: ModerationStatus, to: ModerationStatus, reason: ModerationReason) -> bool {
+        is_moderation_transition_allowed(from, &ModerationStatusChange::new(to, reason))
+    }
+
+    #[test]
+    fn test_transitions_are_unique_and_guarded() {
+        for (i, rule) in MODERATION_TRANSITIONS.iter().enumerate() {
+            assert!(rule.from != rule.to, "{:?} does not change status", rule);
+            assert!(!rule.reasons.is_empty(), "{:?} has no reasons", rule);
+            assert!(
+                MODERATION_TRANSITIONS[i + 1..]
+                    .iter()
+                    .all(|other| other.from != rule.from || other.to != rule.to),
+                "{:?} is declared twice",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn test_every_transition_is_allowed_by_its_reasons_only() {
+        for from in &MODERATION_STATUSES {
+            for to in &MODERATION_STATUSES {
+                for reason in &MODERATION_REASONS {
+                    let expected = find_moderation_transition(*from, *to)
+                        .map(|rule| rule.reasons.contains(reason))
+                        .unwrap_or(false);
+                    assert_eq!(is_allowed(*from, *to, *reason), expected, "{} -> {} by {:?}", from, to, reason);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_moderator_can_make_every_transition() {
+        for from in &MODERATION_STATUSES {
+            for to in &MODERATION_STATUSES {
+                assert_eq!(
+                    is_allowed(*from, *to, ModerationReason::ModeratorDecision),
+                    find_moderation_transition(*from, *to).is_some(),
+                    "{} -> {}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_seller_moves_editable_entities_and_never_decides() {
+        for from in &MODERATION_STATUSES {
+            for to in &MODERATION_STATUSES {
+                for reason in MODERATION_REASONS.iter().filter(|reason| reason.actor() == ModerationActor::Seller) {
+                    if is_allowed(*from, *to, *reason) {
+                        assert!(EDITABLE_STATUSES.contains(from), "{} -> {} by {:?}", from, to, reason);
+                        assert!(
+                            *to == ModerationStatus::Moderation || *to == ModerationStatus::Draft,
+                            "{} -> {} by {:?}",
+                            from,
+                            to,
+                            reason
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_system_only_declines_entity_in_moderation() {
+        for from in &MODERATION_STATUSES {
+            let next_statuses = next_moderation_statuses(*from, ModerationActor::System);
+            if *from == ModerationStatus::Moderation {
+                assert_eq!(next_statuses, vec![ModerationStatus::Decline]);
+            } else {
+                assert!(next_statuses.is_empty(), "{} -> {:?}", from, next_statuses);
+            }
+        }
+    }
+
+    #[test]
+    fn test_next_statuses_match_transitions() {
+        for from in &MODERATION_STATUSES {
+            for actor in &MODERATION_ACTORS {
+                let next_statuses = next_moderation_statuses(*from, *actor);
+                for to in &MODERATION_STATUSES {
+                    let expected = MODERATION_REASONS
+                        .iter()
+                        .any(|reason| reason.actor() == *actor && is_allowed(*from, *to, *reason));
+                    assert_eq!(next_statuses.contains(to), expected, "{} -> {} by {:?}", from, to, actor);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_side_effects_of_every_transition() {
+        for entity_type in &ENTITY_TYPES {
+            for rule in MODERATION_TRANSITIONS.iter() {
+                let side_effects = moderation_side_effects(*entity_type, rule.from, rule.to);
+                assert_eq!(
+                    side_effects.contains(&ModerationSideEffect::Enqueue),
+                    rule.to == ModerationStatus::Moderation
+                );
+                assert_eq!(
+                    side_effects.contains(&ModerationSideEffect::Dequeue),
+                    rule.from == ModerationStatus::Moderation
+                );
+                assert_eq!(
+                    side_effects.contains(&ModerationSideEffect::CascadeToBaseProducts),
+                    *entity_type == ModerationEntityType::Store
+                );
+                assert_eq!(side_effects.last(), Some(&ModerationSideEffect::Reindex));
+            }
+        }
+    }
+
+    #[test]
+    fn test_edit_moves_to_editable_status() {
+        for status in &MODERATION_STATUSES {
+            match moderation_status_after_edit(*status) {
+                Some(next_status) => {
+                    assert!(is_editable_status(*status), "{} is not editable", status);
+                    assert!(is_editable_status(next_status), "{} is not editable", next_status);
+                    assert!(is_allowed(*status, next_status, ModerationReason::EditedAfterDecline));
+                }
+                None => assert!(*status != ModerationStatus::Decline),
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_status_is_reachable_and_can_be_published() {
+        let reachable = |from: ModerationStatus| {
+            let mut visited = vec![from];
+            let mut i = 0;
+            while i < visited.len() {
+                let current = visited[i];
+                for rule in MODERATION_TRANSITIONS.iter().filter(|rule| rule.from == current) {
+                    if !visited.contains(&rule.to) {
+                        visited.push(rule.to);
+                    }
+                }
+                i += 1;
+            }
+            visited
+        };
+
+        let from_draft = reachable(ModerationStatus::Draft);
+        for status in &MODERATION_STATUSES {
+            assert!(from_draft.contains(status), "{} is not reachable", status);
+            assert!(
+                reachable(*status).contains(&ModerationStatus::Published),
+                "{} can not be published",
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn test_random_changes_keep_queue_in_sync() {
+        // Failed run is replayed by setting the seed from assertion message to `MODERATION_TEST_SEED`
+        let seed = env::var("MODERATION_TEST_SEED")
+            .ok()
+            .and_then(|seed| seed.parse::<usize>().ok())
+            .unwrap_or_else(|| thread_rng().gen::<usize>());
+        let mut rng = StdRng::from_seed(&[seed][..]);
+        for _ in 0..100 {
+            let entity_type = *rng.choose(&ENTITY_TYPES).unwrap();
+            let mut status = ModerationStatus::Draft;
+            let mut in_queue = false;
+            for _ in 0..50 {
+                let to = *rng.choose(&MODERATION_STATUSES).unwrap();
+                let reason = *rng.choose(&MODERATION_REASONS).unwrap();
+                if !is_allowed(status, to, reason) {
+                    continue;
+                }
+
+                for side_effect in moderation_side_effects(entity_type, status, to) {
+                    match side_effect {
+                        ModerationSideEffect::Enqueue => {
+                            assert!(!in_queue, "{:?} is enqueued twice, seed: {}", entity_type, seed);
+                            in_queue = true;
+                        }
+                        ModerationSideEffect::Dequeue => {
+                            assert!(in_queue, "{:?} is not in queue, seed: {}", entity_type, seed);
+                            in_queue = false;
+                        }
+                        ModerationSideEffect::CascadeToBaseProducts | ModerationSideEffect::Reindex => {}
+                    }
+                }
+                status = to;
+                assert_eq!(
+                    in_queue,
+                    status == ModerationStatus::Moderation,
+                    "{:?} in status {:?}, seed: {}",
+                    entity_type,
+                    status,
+                    seed
+                );
+            }
+        }
+    }
+}
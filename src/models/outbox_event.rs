@@ -0,0 +1,105 @@
+//! Models for transactional outbox of catalog change events
+use std::time::SystemTime;
+
+use serde::Serialize;
+use serde_json;
+
+use models::{BaseProduct, RawProduct, Store};
+use schema::outbox_events;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, DieselTypes)]
+pub enum OutboxAggregateType {
+    Store,
+    BaseProduct,
+    Product,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, DieselTypes)]
+pub enum OutboxEventType {
+    Created,
+    Updated,
+    /// Entity was deactivated, payload keeps its last state
+    Deactivated,
+    /// Entity was removed from db, payload keeps only its id
+    Deleted,
+}
+
+/// DB presenting by change event waiting to be relayed to consumers
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "outbox_events"]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub aggregate_type: OutboxAggregateType,
+    pub aggregate_id: i32,
+    pub event_type: OutboxEventType,
+    pub kafka_update_no: Option<i32>,
+    pub payload: serde_json::Value,
+    pub created_at: SystemTime,
+    pub published_at: Option<SystemTime>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+}
+
+/// Payload for writing change event to outbox
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "outbox_events"]
+pub struct NewOutboxEvent {
+    pub aggregate_type: OutboxAggregateType,
+    pub aggregate_id: i32,
+    pub event_type: OutboxEventType,
+    pub kafka_update_no: Option<i32>,
+    pub payload: serde_json::Value,
+}
+
+impl NewOutboxEvent {
+    pub fn new<T: Serialize>(
+        aggregate_type: OutboxAggregateType,
+        aggregate_id: i32,
+        event_type: OutboxEventType,
+        kafka_update_no: Option<i32>,
+        entity: &T,
+    ) -> Result<Self, serde_json::Error> {
+        let payload = match event_type {
+            OutboxEventType::Deleted => json!({ "id": aggregate_id }),
+            _ => serde_json::to_value(entity)?,
+        };
+
+        Ok(Self {
+            aggregate_type,
+            aggregate_id,
+            event_type,
+            kafka_update_no,
+            payload,
+        })
+    }
+
+    pub fn store(event_type: OutboxEventType, store: &Store) -> Result<Self, serde_json::Error> {
+        Self::new(
+            OutboxAggregateType::Store,
+            store.id.0,
+            event_type,
+            Some(store.kafka_update_no),
+            store,
+        )
+    }
+
+    pub fn base_product(event_type: OutboxEventType, base_product: &BaseProduct) -> Result<Self, serde_json::Error> {
+        Self::new(
+            OutboxAggregateType::BaseProduct,
+            base_product.id.0,
+            event_type,
+            Some(base_product.kafka_update_no),
+            base_product,
+        )
+    }
+
+    pub fn product(event_type: OutboxEventType, product: &RawProduct) -> Result<Self, serde_json::Error> {
+        Self::new(
+            OutboxAggregateType::Product,
+            product.id.0,
+            event_type,
+            Some(product.kafka_update_no),
+            product,
+        )
+    }
+}
@@ -0,0 +1,92 @@
+//! Models for automated checks of base products sent to moderation
+use models::{BaseProduct, ModerationFeedbackPayload, RawProduct, Store};
+
+/// What happens to base product failing pre-moderation check
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreModerationOutcome {
+    /// Base product is declined without moderator
+    Decline,
+    /// Base product goes to moderation marked as needing moderator review
+    Review,
+}
+
+/// Issue found by pre-moderation check
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PreModerationFinding {
+    /// Name of the check
+    pub rule: String,
+    pub outcome: PreModerationOutcome,
+    /// Decline reason code, required for declining findings
+    pub reason_code: Option<String>,
+    /// Field of base product the issue was found in
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl PreModerationFinding {
+    pub fn decline(rule: &str, reason_code: &str, field: Option<&str>, message: String) -> Self {
+        Self {
+            rule: rule.to_string(),
+            outcome: PreModerationOutcome::Decline,
+            reason_code: Some(reason_code.to_string()),
+            field: field.map(|field| field.to_string()),
+            message,
+        }
+    }
+
+    pub fn review(rule: &str, field: Option<&str>, message: String) -> Self {
+        Self {
+            rule: rule.to_string(),
+            outcome: PreModerationOutcome::Review,
+            reason_code: None,
+            field: field.map(|field| field.to_string()),
+            message,
+        }
+    }
+}
+
+/// Base product sent to moderation with everything pre-moderation checks look at
+#[derive(Debug, Clone)]
+pub struct BaseProductSubmission {
+    pub base_product: BaseProduct,
+    pub store: Store,
+    pub variants: Vec<RawProduct>,
+    /// Variants of other base products of the store sharing vendor codes with the submitted variants
+    pub store_variants: Vec<RawProduct>,
+}
+
+/// Result of pre-moderation checks of base product
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PreModerationReport {
+    pub findings: Vec<PreModerationFinding>,
+}
+
+impl PreModerationReport {
+    pub fn is_declined(&self) -> bool {
+        self.findings.iter().any(|finding| finding.outcome == PreModerationOutcome::Decline)
+    }
+
+    /// Declining findings as decline reasons seller has to fix
+    pub fn feedback(&self) -> Vec<ModerationFeedbackPayload> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.outcome == PreModerationOutcome::Decline)
+            .filter_map(|finding| {
+                finding.reason_code.clone().map(|reason_code| ModerationFeedbackPayload {
+                    reason_code,
+                    field: finding.field.clone(),
+                    comment: Some(finding.message.clone()),
+                })
+            })
+            .collect()
+    }
+
+    /// Findings moderator has to look at
+    pub fn review_notes(&self) -> Vec<PreModerationFinding> {
+        self.findings
+            .iter()
+            .filter(|finding| finding.outcome == PreModerationOutcome::Review)
+            .cloned()
+            .collect()
+    }
+}
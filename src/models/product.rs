@@ -36,6 +36,28 @@ pub struct RawProduct {
     pub pre_order: bool,
     pub pre_order_days: i32,
     pub uuid: Uuid,
+    /// Stock status set by seller, `None` when stock is not tracked
+    pub stock_status: Option<StockStatus>,
+}
+
+impl RawProduct {
+    /// Stock status of variant, untracked variants are in stock or available for pre-order
+    pub fn effective_stock_status(&self) -> StockStatus {
+        match self.stock_status {
+            Some(stock_status) => stock_status,
+            None if self.pre_order => StockStatus::PreOrder,
+            None => StockStatus::InStock,
+        }
+    }
+}
+
+/// Stock status of product variant
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, DieselTypes)]
+pub enum StockStatus {
+    InStock,
+    OutOfStock,
+    /// Variant is not in stock but can be ordered with `pre_order_days` delay
+    PreOrder,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -92,6 +114,7 @@ pub struct NewProduct {
     pub pre_order: Option<bool>,
     pub pre_order_days: Option<i32>,
     pub uuid: Uuid,
+    pub stock_status: Option<StockStatus>,
 }
 
 /// Payload for creating products
@@ -112,6 +135,7 @@ pub struct NewProductWithoutCurrency {
     pub pre_order: Option<bool>,
     pub pre_order_days: Option<i32>,
     pub uuid: Uuid,
+    pub stock_status: Option<StockStatus>,
 }
 
 impl From<(NewProductWithoutCurrency, Currency)> for NewProduct {
@@ -128,6 +152,7 @@ impl From<(NewProductWithoutCurrency, Currency)> for NewProduct {
             pre_order: other.0.pre_order,
             pre_order_days: other.0.pre_order_days,
             uuid: other.0.uuid,
+            stock_status: other.0.stock_status,
         }
     }
 }
@@ -158,6 +183,7 @@ pub struct UpdateProduct {
     pub currency: Option<Currency>,
     pub pre_order: Option<bool>,
     pub pre_order_days: Option<i32>,
+    pub stock_status: Option<StockStatus>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -176,12 +202,41 @@ pub struct ProductsSearchOptions {
     pub categories_ids: Option<Vec<CategoryId>>,
     pub sort_by: Option<ProductsSorting>,
     pub status: Option<ModerationStatus>,
+    /// Hide out of stock variants and base products without variants in stock
+    pub in_stock_only: Option<bool>,
+}
+
+impl ProductsSearchOptions {
+    /// Ids of attributes having filters
+    pub fn filtered_attributes_ids(&self) -> Vec<i32> {
+        let mut ids = self
+            .attr_filters
+            .as_ref()
+            .map(|attr_filters| attr_filters.iter().map(|attr_filter| attr_filter.id).collect::<Vec<i32>>())
+            .unwrap_or_default();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Options with all filters except filters of attribute `attr_id`,
+    /// values of the attribute are counted among products matching them
+    pub fn without_attribute_filter(&self, attr_id: i32) -> Self {
+        let mut options = self.clone();
+        options.attr_filters = options
+            .attr_filters
+            .map(|attr_filters| attr_filters.into_iter().filter(|attr_filter| attr_filter.id != attr_id).collect());
+        options
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct SearchProductsByName {
     pub name: String,
     pub options: Option<ProductsSearchOptions>,
+    /// Variants of `name` with synonyms applied, filled from synonyms dictionary
+    #[serde(default, skip_deserializing)]
+    pub name_synonyms: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -202,6 +257,10 @@ pub enum ProductsSorting {
     PriceAsc,
     PriceDesc,
     Discount,
+    Rating,
+    Newest,
+    /// Text score combined with relevance signals, the same as no sorting
+    Relevance,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
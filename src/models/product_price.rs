@@ -0,0 +1,177 @@
+//! Models for product price history and scheduled price changes
+use std::time::SystemTime;
+
+use validator::Validate;
+
+use stq_static_resources::Currency;
+use stq_types::{ProductId, ProductPrice, UserId};
+
+use models::validation_rules::*;
+use models::RawProduct;
+use schema::{product_price_history, products, scheduled_price_changes};
+
+/// Price, discount and cashback of product at the moment of change
+#[derive(Debug, Serialize, Deserialize, Associations, Queryable, Clone, Identifiable)]
+#[belongs_to(RawProduct, foreign_key = "product_id")]
+#[table_name = "product_price_history"]
+pub struct ProductPriceHistoryRecord {
+    pub id: i32,
+    pub product_id: ProductId,
+    pub price: ProductPrice,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+    pub currency: Currency,
+    /// User who changed the price, `None` for changes made by price scheduler
+    pub user_id: Option<UserId>,
+    pub created_at: SystemTime,
+}
+
+/// Payload for creating product price history record
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "product_price_history"]
+pub struct NewProductPriceHistoryRecord {
+    pub product_id: ProductId,
+    pub price: ProductPrice,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+    pub currency: Currency,
+    pub user_id: Option<UserId>,
+}
+
+impl NewProductPriceHistoryRecord {
+    pub fn new(product: &RawProduct, user_id: Option<UserId>) -> Self {
+        Self {
+            product_id: product.id,
+            price: product.price,
+            discount: product.discount,
+            cashback: product.cashback,
+            currency: product.currency,
+            user_id,
+        }
+    }
+}
+
+/// Payload for setting product price fields, `None` values are written as `NULL`
+#[derive(Serialize, Deserialize, AsChangeset, Clone, Debug, PartialEq)]
+#[table_name = "products"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct UpdateProductPrices {
+    pub price: ProductPrice,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+}
+
+impl<'a> From<&'a RawProduct> for UpdateProductPrices {
+    fn from(product: &'a RawProduct) -> Self {
+        Self {
+            price: product.price,
+            discount: product.discount,
+            cashback: product.cashback,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, DieselTypes)]
+pub enum ScheduledPriceChangeStatus {
+    /// Waiting for `starts_at`
+    Pending,
+    /// Applied to product, waiting for `ends_at`
+    Active,
+    /// Reverted or skipped
+    Finished,
+}
+
+/// DB presenting by scheduled price change
+#[derive(Debug, Serialize, Deserialize, Associations, Queryable, Clone, Identifiable)]
+#[belongs_to(RawProduct, foreign_key = "product_id")]
+#[table_name = "scheduled_price_changes"]
+pub struct ScheduledPriceChange {
+    pub id: i32,
+    pub product_id: ProductId,
+    pub price: Option<ProductPrice>,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+    pub starts_at: SystemTime,
+    pub ends_at: SystemTime,
+    pub status: ScheduledPriceChangeStatus,
+    /// Product price fields captured when the change was applied
+    pub previous_price: Option<ProductPrice>,
+    pub previous_discount: Option<f64>,
+    pub previous_cashback: Option<f64>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl ScheduledPriceChange {
+    /// Product price fields after applying this change to `prices`
+    pub fn apply_to(&self, prices: &UpdateProductPrices) -> UpdateProductPrices {
+        UpdateProductPrices {
+            price: self.price.unwrap_or(prices.price),
+            discount: self.discount.or(prices.discount),
+            cashback: self.cashback.or(prices.cashback),
+        }
+    }
+
+    /// Product price fields captured before this change was applied
+    pub fn previous_prices(&self) -> Option<UpdateProductPrices> {
+        self.previous_price.map(|price| UpdateProductPrices {
+            price,
+            discount: self.previous_discount,
+            cashback: self.previous_cashback,
+        })
+    }
+
+    pub fn overlaps(&self, starts_at: SystemTime, ends_at: SystemTime) -> bool {
+        self.starts_at < ends_at && starts_at < self.ends_at
+    }
+}
+
+/// Payload for scheduling price change of product
+#[derive(Serialize, Deserialize, Validate, Clone, Debug)]
+pub struct NewScheduledPriceChangePayload {
+    #[validate(custom = "validate_non_negative_price")]
+    pub price: Option<ProductPrice>,
+    #[validate(range(min = "0.0", max = "1.0"))]
+    pub discount: Option<f64>,
+    #[validate(range(min = "0.0", max = "1.0"))]
+    pub cashback: Option<f64>,
+    pub starts_at: SystemTime,
+    pub ends_at: SystemTime,
+}
+
+/// Payload for creating scheduled price change
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "scheduled_price_changes"]
+pub struct NewScheduledPriceChange {
+    pub product_id: ProductId,
+    pub price: Option<ProductPrice>,
+    pub discount: Option<f64>,
+    pub cashback: Option<f64>,
+    pub starts_at: SystemTime,
+    pub ends_at: SystemTime,
+}
+
+impl From<(ProductId, NewScheduledPriceChangePayload)> for NewScheduledPriceChange {
+    fn from(other: (ProductId, NewScheduledPriceChangePayload)) -> Self {
+        let (product_id, payload) = other;
+
+        Self {
+            product_id,
+            price: payload.price,
+            discount: payload.discount,
+            cashback: payload.cashback,
+            starts_at: payload.starts_at,
+            ends_at: payload.ends_at,
+        }
+    }
+}
+
+/// Payload for updating status of scheduled price change
+#[derive(Serialize, Deserialize, AsChangeset, Clone, Debug, Default)]
+#[table_name = "scheduled_price_changes"]
+pub struct UpdateScheduledPriceChange {
+    pub status: Option<ScheduledPriceChangeStatus>,
+    pub previous_price: Option<ProductPrice>,
+    pub previous_discount: Option<f64>,
+    pub previous_cashback: Option<f64>,
+}
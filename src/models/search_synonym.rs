@@ -0,0 +1,115 @@
+//! Models for managed dictionary of search synonyms
+use std::time::SystemTime;
+
+use validator::Validate;
+
+use models::validation_rules::*;
+use schema::search_synonyms;
+
+/// Max count of consecutive words in search text compared with synonym terms
+const MAX_SYNONYM_TERM_WORDS: usize = 3;
+
+/// Group of terms treated by search as equivalent
+#[derive(Debug, Serialize, Deserialize, Queryable, Clone, Identifiable)]
+#[table_name = "search_synonyms"]
+pub struct SearchSynonym {
+    pub id: i32,
+    pub terms: Vec<String>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+/// Payload for creating search synonym
+#[derive(Serialize, Deserialize, Insertable, Validate, Clone, Debug)]
+#[table_name = "search_synonyms"]
+pub struct NewSearchSynonym {
+    #[validate(custom = "validate_search_synonym_terms")]
+    pub terms: Vec<String>,
+}
+
+/// Payload for updating search synonym
+#[derive(Serialize, Deserialize, AsChangeset, Validate, Clone, Debug)]
+#[table_name = "search_synonyms"]
+pub struct UpdateSearchSynonym {
+    #[validate(custom = "validate_search_synonym_terms")]
+    pub terms: Vec<String>,
+}
+
+/// Terms are compared with lowercased search text, so they are stored lowercased and without duplicates
+pub fn normalize_synonym_terms(terms: Vec<String>) -> Vec<String> {
+    let mut normalized = Vec::<String>::with_capacity(terms.len());
+    for term in terms {
+        let term = term.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if !term.is_empty() && !normalized.contains(&term) {
+            normalized.push(term);
+        }
+    }
+    normalized
+}
+
+/// Phrases of search text which may be synonym terms
+pub fn search_text_phrases(text: &str) -> Vec<String> {
+    let words = text.to_lowercase().split_whitespace().map(|word| word.to_string()).collect::<Vec<String>>();
+    let mut phrases = vec![];
+    for len in 1..=MAX_SYNONYM_TERM_WORDS {
+        for window in words.windows(len) {
+            let phrase = window.join(" ");
+            if !phrases.contains(&phrase) {
+                phrases.push(phrase);
+            }
+        }
+    }
+    phrases
+}
+
+/// Variants of search text with phrases replaced by their synonyms, the text itself is not included
+pub fn expand_search_text(text: &str, synonyms: &[SearchSynonym]) -> Vec<String> {
+    let text = text.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ");
+    let padded_text = format!(" {} ", text);
+    let mut variants = vec![];
+    for synonym in synonyms {
+        for term in &synonym.terms {
+            let padded_term = format!(" {} ", term);
+            if !padded_text.contains(&padded_term) {
+                continue;
+            }
+            for other_term in synonym.terms.iter().filter(|other_term| *other_term != term) {
+                let variant = padded_text.replace(&padded_term, &format!(" {} ", other_term)).trim().to_string();
+                if variant != text && !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use models::*;
+
+    fn create_synonym(terms: &[&str]) -> SearchSynonym {
+        SearchSynonym {
+            id: 1,
+            terms: terms.iter().map(|term| term.to_string()).collect(),
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        }
+    }
+
+    #[test]
+    fn test_expand_search_text() {
+        let synonyms = vec![create_synonym(&["tv", "television"]), create_synonym(&["smart phone", "smartphone"])];
+        assert_eq!(expand_search_text("Black TV", &synonyms), vec!["black television".to_string()]);
+        assert_eq!(expand_search_text("new smart phone", &synonyms), vec!["new smartphone".to_string()]);
+        assert!(expand_search_text("tvs", &synonyms).is_empty());
+    }
+
+    #[test]
+    fn test_normalize_synonym_terms() {
+        let terms = vec!["TV".to_string(), " Smart   TV ".to_string(), "tv".to_string(), "".to_string()];
+        assert_eq!(normalize_synonym_terms(terms), vec!["tv".to_string(), "smart tv".to_string()]);
+    }
+}
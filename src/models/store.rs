@@ -1,6 +1,7 @@
 //! Module containg store model for query, insert, update
 use std::time::SystemTime;
 
+use serde::{Deserialize, Deserializer};
 use serde_json;
 use uuid::Uuid;
 use validator::Validate;
@@ -9,7 +10,7 @@ use stq_static_resources::ModerationStatus;
 use stq_types::{Alpha3, CategoryId, SagaId, StoreId, UserId};
 
 use models::validation_rules::*;
-use models::BaseProductWithVariants;
+use models::{BaseProductWithVariants, ModerationFeedbackPayload};
 use schema::stores;
 
 /// Payload for querying stores
@@ -50,11 +51,28 @@ pub struct Store {
     pub country_code: Option<Alpha3>,
     pub uuid: Uuid,
     pub saga_id: Option<SagaId>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
 }
 
 impl Store {
     pub const MAX_LENGTH_SHORT_DESCRIPTION: u64 = 170;
     pub const MAX_LENGTH_LONG_DESCRIPTION: u64 = 8000;
+
+    /// Location of the store, set only when both coordinates are known
+    pub fn location(&self) -> Option<GeoPoint> {
+        match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => Some(GeoPoint { latitude, longitude }),
+            _ => None,
+        }
+    }
+}
+
+/// Point on the map in degrees
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -77,6 +95,7 @@ impl From<Store> for ElasticStore {
 /// Payload for creating stores
 #[derive(Serialize, Deserialize, Insertable, Validate, Clone, Debug)]
 #[table_name = "stores"]
+#[validate(schema(function = "validate_new_store_location"))]
 pub struct NewStore {
     #[validate(custom = "validate_translation")]
     pub name: serde_json::Value,
@@ -112,11 +131,16 @@ pub struct NewStore {
     pub country_code: Option<Alpha3>,
     pub uuid: Uuid,
     pub saga_id: Option<SagaId>,
+    #[validate(range(min = "-90.0", max = "90.0"))]
+    pub latitude: Option<f64>,
+    #[validate(range(min = "-180.0", max = "180.0"))]
+    pub longitude: Option<f64>,
 }
 
 /// Payload for updating users
 #[derive(Default, Serialize, Deserialize, Insertable, Validate, AsChangeset, Debug)]
 #[table_name = "stores"]
+#[validate(schema(function = "validate_update_store_location"))]
 pub struct UpdateStore {
     #[validate(custom = "validate_translation")]
     pub name: Option<serde_json::Value>,
@@ -149,6 +173,22 @@ pub struct UpdateStore {
     pub street_number: Option<String>,
     pub place_id: Option<String>,
     pub country_code: Option<Alpha3>,
+    /// Absent coordinates are left as they are, `null` ones are removed
+    #[validate(range(min = "-90.0", max = "90.0"))]
+    #[serde(default, deserialize_with = "deserialize_nullable", skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<Option<f64>>,
+    #[validate(range(min = "-180.0", max = "180.0"))]
+    #[serde(default, deserialize_with = "deserialize_nullable", skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<Option<f64>>,
+}
+
+/// Deserializes present field as `Some` even if it's `null`, so that absent field stays `None` with `serde(default)`
+fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Default, Serialize, Deserialize, Insertable, AsChangeset, Debug)]
@@ -251,16 +291,41 @@ impl ServiceUpdateStore {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Validate, Clone, Debug)]
+#[validate(schema(function = "validate_search_store_location"))]
 pub struct SearchStore {
     pub name: String,
     pub options: Option<StoresSearchOptions>,
+    /// Variants of `name` with synonyms applied, filled from synonyms dictionary
+    #[serde(default, skip_deserializing)]
+    pub name_synonyms: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StoresSearchOptions {
     pub category_id: Option<CategoryId>,
     pub country: Option<String>,
+    pub locality: Option<String>,
+    /// Location of buyer, distance filter and sorting are measured from it
+    pub location: Option<GeoPoint>,
+    /// Only stores within the radius from `location` are found, must be positive and requires `location`
+    pub radius_km: Option<f64>,
+    /// Sorting by distance requires `location`
+    pub sort_by: Option<StoresSorting>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum StoresSorting {
+    Rating,
+    /// Nearest to `location` first, stores without location are the last
+    Distance,
+}
+
+/// Country of found stores with localities of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CountryLocalities {
+    pub country: String,
+    pub localities: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -305,4 +370,10 @@ pub struct ModeratorStoreSearchResults {
 pub struct StoreModerate {
     pub store_id: StoreId,
     pub status: ModerationStatus,
+    /// Moderator comment saved in moderation history
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Decline reasons pointing to fields seller has to fix
+    #[serde(default)]
+    pub feedback: Vec<ModerationFeedbackPayload>,
 }
@@ -0,0 +1,62 @@
+//! Models for staff members of stores
+use std::time::SystemTime;
+
+use stq_types::{StoreId, UserId};
+
+use models::Store;
+use schema::store_staff;
+
+/// Role of user in store, roles are ordered from the least to the most privileged,
+/// so that role grants everything granted by lower roles
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, DieselTypes)]
+pub enum StoreStaffRole {
+    /// Read-only analyst, can see store and its unpublished products
+    Viewer,
+    /// Content editor, can create and edit products and their prices
+    Editor,
+    /// Store manager, can edit store, send it and its products to moderation and manage coupons
+    Manager,
+    /// Has the same rights as the user who created the store, including managing staff
+    Owner,
+}
+
+/// DB presenting by store staff member
+#[derive(Debug, Serialize, Deserialize, Associations, Queryable, Clone, Identifiable)]
+#[belongs_to(Store)]
+#[table_name = "store_staff"]
+pub struct StoreStaffMember {
+    pub id: i32,
+    pub store_id: StoreId,
+    pub user_id: UserId,
+    pub role: StoreStaffRole,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+/// Payload for creating store staff member
+#[derive(Serialize, Deserialize, Insertable, Clone, Debug)]
+#[table_name = "store_staff"]
+pub struct NewStoreStaffMember {
+    pub store_id: StoreId,
+    pub user_id: UserId,
+    pub role: StoreStaffRole,
+}
+
+/// Payload for inviting user to store staff, store is taken from the route
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewStoreStaffMemberPayload {
+    pub user_id: UserId,
+    pub role: StoreStaffRole,
+}
+
+impl From<(StoreId, NewStoreStaffMemberPayload)> for NewStoreStaffMember {
+    fn from(other: (StoreId, NewStoreStaffMemberPayload)) -> Self {
+        let (store_id, payload) = other;
+
+        Self {
+            store_id,
+            user_id: payload.user_id,
+            role: payload.role,
+        }
+    }
+}
@@ -9,7 +9,7 @@ use validator::validate_length;
 use validator::ValidationError;
 use validator::Validator;
 
-use models::{BaseProduct, Coupon, Store};
+use models::{normalize_synonym_terms, BaseProduct, Coupon, NewStore, SearchStore, Store, StoresSorting, UpdateStore};
 use stq_static_resources::Translation;
 use stq_types::{CouponCode, ProductPrice};
 
@@ -89,6 +89,10 @@ pub fn validate_non_negative_coupon_quantity(value: i32) -> Result<(), Validatio
     validate_non_negative(value)
 }
 
+pub fn validate_non_negative_coupon_amount(value: f64) -> Result<(), ValidationError> {
+    validate_non_negative(value)
+}
+
 pub fn validate_coupon_code(val: &CouponCode) -> Result<(), ValidationError> {
     lazy_static! {
         static ref CODE_VALIDATION_RE: Regex = Regex::new(r"^[a-zA-Z0-9]*$").unwrap();
@@ -125,6 +129,33 @@ pub fn validate_coupon_code(val: &CouponCode) -> Result<(), ValidationError> {
     check_result
 }
 
+pub fn validate_coupon_code_prefix(prefix: &str) -> Result<(), ValidationError> {
+    lazy_static! {
+        static ref PREFIX_VALIDATION_RE: Regex = Regex::new(r"^[a-zA-Z0-9]+$").unwrap();
+    }
+
+    if prefix.len() as u64 > Coupon::MAX_LENGTH_CODE_PREFIX {
+        return Err(ValidationError {
+            code: Cow::from("prefix"),
+            message: Some(Cow::from(format!(
+                "Value must be <= {} characters.",
+                Coupon::MAX_LENGTH_CODE_PREFIX
+            ))),
+            params: HashMap::new(),
+        });
+    }
+
+    if PREFIX_VALIDATION_RE.is_match(prefix) {
+        Ok(())
+    } else {
+        Err(ValidationError {
+            code: Cow::from("prefix"),
+            message: Some(Cow::from("Incorrect prefix format. Must be only (a-z,A-Z,0-9)")),
+            params: HashMap::new(),
+        })
+    }
+}
+
 fn get_translations(text: &serde_json::Value) -> Result<Vec<Translation>, ValidationError> {
     serde_json::from_value::<Vec<Translation>>(text.clone()).map_err(|_| ValidationError {
         code: Cow::from("text"),
@@ -222,10 +253,101 @@ pub fn validate_urls(text: &serde_json::Value) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// Terms are checked as they are stored, that is after normalization
+pub fn validate_search_synonym_terms(terms: &[String]) -> Result<(), ValidationError> {
+    let has_empty_terms = terms.iter().any(|term| term.trim().is_empty());
+
+    if has_empty_terms || normalize_synonym_terms(terms.to_vec()).len() < 2 {
+        Err(ValidationError {
+            code: Cow::from("terms"),
+            message: Some(Cow::from("Synonym must contain at least two distinct non empty terms.")),
+            params: HashMap::new(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Store location is set by both coordinates or none of them
+fn validate_coordinates(latitude: Option<f64>, longitude: Option<f64>) -> Result<(), ValidationError> {
+    if latitude.is_some() == longitude.is_some() {
+        Ok(())
+    } else {
+        Err(ValidationError {
+            code: Cow::from("location"),
+            message: Some(Cow::from("Latitude and longitude must be set together.")),
+            params: HashMap::new(),
+        })
+    }
+}
+
+pub fn validate_new_store_location(store: &NewStore) -> Result<(), ValidationError> {
+    validate_coordinates(store.latitude, store.longitude)
+}
+
+/// Coordinates are updated or removed together
+pub fn validate_update_store_location(store: &UpdateStore) -> Result<(), ValidationError> {
+    if store.latitude.is_some() != store.longitude.is_some() {
+        return Err(ValidationError {
+            code: Cow::from("location"),
+            message: Some(Cow::from("Latitude and longitude must be updated together.")),
+            params: HashMap::new(),
+        });
+    }
+
+    validate_coordinates(store.latitude.unwrap_or_default(), store.longitude.unwrap_or_default())
+}
+
+pub fn validate_search_store_location(search_store: &SearchStore) -> Result<(), ValidationError> {
+    let options = match search_store.options {
+        Some(ref options) => options,
+        None => return Ok(()),
+    };
+
+    if options.radius_km.map_or(false, |radius_km| radius_km <= 0f64) {
+        return Err(ValidationError {
+            code: Cow::from("radius_km"),
+            message: Some(Cow::from("Radius must be positive.")),
+            params: HashMap::new(),
+        });
+    }
+
+    let needs_location = options.radius_km.is_some() || options.sort_by == Some(StoresSorting::Distance);
+    if needs_location && options.location.is_none() {
+        return Err(ValidationError {
+            code: Cow::from("location"),
+            message: Some(Cow::from("Location is required for distance filter and sorting.")),
+            params: HashMap::new(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn validate_decline_reason_code<T: AsRef<str>>(val: T) -> Result<(), ValidationError> {
+    let val = val.as_ref();
+    lazy_static! {
+        static ref DECLINE_REASON_CODE_VALIDATION_RE: Regex = Regex::new(r"^[a-z0-9]+(?:_[a-z0-9]+)*$").unwrap();
+    }
+
+    if DECLINE_REASON_CODE_VALIDATION_RE.is_match(val) {
+        Ok(())
+    } else {
+        Err(ValidationError {
+            code: Cow::from("code"),
+            message: Some(Cow::from("Code must consist of lowercase words separated by underscore.")),
+            params: HashMap::new(),
+        })
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
 
+    use validator::Validate;
+
     use models::*;
+    use services::stores::tests::create_new_store;
     use stq_static_resources::*;
 
     #[test]
@@ -301,4 +423,86 @@ pub mod tests {
             Err(_) => true,
         });
     }
+
+    #[test]
+    fn test_search_synonym_terms() {
+        let terms = |terms: &[&str]| terms.iter().map(|term| term.to_string()).collect::<Vec<String>>();
+
+        assert!(validate_search_synonym_terms(&terms(&["tv", "smart tv"])).is_ok());
+        assert!(validate_search_synonym_terms(&terms(&["tv", " "])).is_err());
+        assert!(validate_search_synonym_terms(&terms(&["TV", "tv "])).is_err());
+        assert!(validate_search_synonym_terms(&terms(&["smart tv", "smart  tv"])).is_err());
+    }
+
+    #[test]
+    fn test_store_location() {
+        let mut new_store = create_new_store(serde_json::from_str("[{\"lang\": \"en\", \"text\": \"Store\"}]").unwrap());
+        assert!(new_store.validate().is_ok());
+        new_store.latitude = Some(55.75);
+        assert!(new_store.validate().is_err());
+        new_store.longitude = Some(37.62);
+        assert!(new_store.validate().is_ok());
+
+        let mut update_store = UpdateStore {
+            longitude: Some(Some(37.62)),
+            ..Default::default()
+        };
+        assert!(update_store.validate().is_err());
+        update_store.latitude = Some(None);
+        assert!(update_store.validate().is_err());
+        update_store.latitude = Some(Some(55.75));
+        assert!(update_store.validate().is_ok());
+        update_store.latitude = Some(Some(95.0));
+        assert!(update_store.validate().is_err());
+
+        let update_store = UpdateStore {
+            latitude: Some(None),
+            longitude: Some(None),
+            ..Default::default()
+        };
+        assert!(update_store.validate().is_ok());
+    }
+
+    #[test]
+    fn test_update_store_location_deserialization() {
+        let update_store = serde_json::from_str::<UpdateStore>("{\"latitude\": null, \"longitude\": null}").unwrap();
+        assert_eq!((update_store.latitude, update_store.longitude), (Some(None), Some(None)));
+
+        let update_store = serde_json::from_str::<UpdateStore>("{\"latitude\": 55.75, \"longitude\": 37.62}").unwrap();
+        assert_eq!(
+            (update_store.latitude, update_store.longitude),
+            (Some(Some(55.75)), Some(Some(37.62)))
+        );
+
+        let update_store = serde_json::from_str::<UpdateStore>("{}").unwrap();
+        assert_eq!((update_store.latitude, update_store.longitude), (None, None));
+        assert_eq!(serde_json::to_string(&update_store).unwrap().contains("latitude"), false);
+    }
+
+    #[test]
+    fn test_search_store_location() {
+        let search_store = |location: Option<GeoPoint>, radius_km: Option<f64>, sort_by: Option<StoresSorting>| SearchStore {
+            name: "store".to_string(),
+            options: Some(StoresSearchOptions {
+                category_id: None,
+                country: None,
+                locality: None,
+                location,
+                radius_km,
+                sort_by,
+            }),
+            name_synonyms: vec![],
+        };
+        let location = Some(GeoPoint {
+            latitude: 55.75,
+            longitude: 37.62,
+        });
+
+        assert!(search_store(location, Some(10.0), Some(StoresSorting::Distance)).validate().is_ok());
+        assert!(search_store(location, Some(0.0), None).validate().is_err());
+        assert!(search_store(location, Some(-1.0), None).validate().is_err());
+        assert!(search_store(None, Some(10.0), None).validate().is_err());
+        assert!(search_store(None, None, Some(StoresSorting::Distance)).validate().is_err());
+        assert!(search_store(None, None, Some(StoresSorting::Rating)).validate().is_ok());
+    }
 }
@@ -2,6 +2,11 @@ use stq_types::UserId;
 /// Implement this trait on resource to signal if it's in the current scope
 pub trait CheckScope<Scope, T> {
     fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&T>) -> bool;
+
+    /// Id of the object written to ACL audit log, `None` for objects without id
+    fn object_id(&self, _obj: &T) -> Option<String> {
+        None
+    }
 }
 
 /// Access control layer for repos. It tells if a user can do a certain action with
@@ -4,15 +4,18 @@
 #[macro_use]
 pub mod macros;
 pub mod legacy_acl;
+pub mod policy;
 pub mod roles_cache;
 
+pub use self::policy::AclPolicy;
 pub use self::roles_cache::RolesCacheImpl;
 
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::cell::RefCell;
+use std::sync::Arc;
 
 use errors::Error;
 use failure::Error as FailureError;
+use log::Level;
 
 use stq_static_resources::ModerationStatus;
 use stq_types::{StoresRole, UserId};
@@ -21,6 +24,73 @@ use self::legacy_acl::{Acl, CheckScope};
 
 use models::authorization::*;
 
+/// Target of ACL audit log records, every ACL decision is logged with it
+pub const ACL_AUDIT_LOG_TARGET: &str = "acl_audit";
+
+/// Decisions of ACL with the same resource, action, rule and outcome
+struct AclAuditRecord {
+    decision: AclDecision,
+    scopes: Vec<Scope>,
+    object_ids: Vec<String>,
+    count: usize,
+}
+
+/// Audit log of ACL decisions. Every decision is logged at debug level, decisions with the same resource, action,
+/// rule and outcome are written to audit log in one record when ACL is dropped, so that checks of every row
+/// of a list make one record with ids of all the rows
+#[derive(Default)]
+pub struct AclAudit {
+    records: RefCell<Vec<AclAuditRecord>>,
+}
+
+impl AclAudit {
+    pub fn record(&self, decision: AclDecision, object_id: Option<String>) {
+        let scopes = decision.matched_scopes();
+        let object_ids = object_id.into_iter().collect::<Vec<_>>();
+        log_acl_decision(Level::Debug, &decision, &scopes, &object_ids, 1);
+
+        let mut records = self.records.borrow_mut();
+        let same_record = records.iter_mut().find(|record| {
+            record.decision.resource == decision.resource
+                && record.decision.action == decision.action
+                && record.decision.rule == decision.rule
+                && record.decision.allowed == decision.allowed
+        });
+        match same_record {
+            Some(record) => {
+                for scope in scopes {
+                    if !record.scopes.contains(&scope) {
+                        record.scopes.push(scope);
+                    }
+                }
+                record.object_ids.extend(object_ids);
+                record.count += 1;
+            }
+            None => records.push(AclAuditRecord {
+                decision,
+                scopes,
+                object_ids,
+                count: 1,
+            }),
+        }
+    }
+}
+
+/// Copy of ACL keeps its own audit records
+impl Clone for AclAudit {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Drop for AclAudit {
+    fn drop(&mut self) {
+        for record in self.records.borrow().iter() {
+            log_acl_decision(Level::Info, &record.decision, &record.scopes, &record.object_ids, record.count);
+        }
+    }
+}
+
 pub fn check<T>(
     acl: &Acl<Resource, Action, Scope, Rule, FailureError, T>,
     resource: Resource,
@@ -63,247 +133,138 @@ pub fn check_with_rule<T>(
 /// ApplicationAcl contains main logic for manipulation with resources
 #[derive(Clone)]
 pub struct ApplicationAcl {
-    acls: Rc<HashMap<StoresRole, Vec<Permission>>>,
+    policy: Arc<AclPolicy>,
     roles: Vec<StoresRole>,
     user_id: UserId,
+    audit: AclAudit,
 }
 
 impl ApplicationAcl {
+    /// Creates ACL with the built-in policy
     pub fn new(roles: Vec<StoresRole>, user_id: UserId) -> Self {
-        let mut hash = ::std::collections::HashMap::new();
-        hash.insert(
-            StoresRole::Superuser,
-            vec![
-                permission!(Resource::Attributes),
-                permission!(Resource::AttributeValues),
-                permission!(Resource::BaseProducts),
-                permission!(Resource::Categories),
-                permission!(Resource::CategoryAttrs),
-                permission!(Resource::CurrencyExchange),
-                permission!(Resource::CustomAttributes),
-                permission!(Resource::ModeratorProductComments),
-                permission!(Resource::ModeratorStoreComments),
-                permission!(Resource::ProductAttrs),
-                permission!(Resource::Products),
-                permission!(Resource::Stores),
-                permission!(Resource::UserRoles),
-                permission!(Resource::WizardStores),
-                permission!(Resource::Coupons),
-                permission!(Resource::CouponScopeBaseProducts),
-                permission!(Resource::CouponScopeCategories),
-                permission!(Resource::UsedCoupons),
-            ],
-        );
-        hash.insert(
-            StoresRole::User,
-            vec![
-                permission!(Resource::Attributes, Action::Read),
-                permission!(Resource::AttributeValues, Action::Read),
-                permission!(Resource::BaseProducts, Action::Create, Scope::Owned),
-                permission!(Resource::BaseProducts, Action::Delete, Scope::Owned),
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Read,
-                    Scope::All,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                permission!(Resource::BaseProducts, Action::Read, Scope::Owned, Rule::Any),
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Draft)
-                ),
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Decline)
-                ),
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                // Store manager can sent base product to moderation `Draft -> Moderation`
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Draft)
-                ),
-                // Store manager can modified moderation status base product from `Decline -> Draft`
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Decline)
-                ),
-                // Store manager can modified moderation status base product from `Published -> Draft`
-                permission!(
-                    Resource::BaseProducts,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                permission!(Resource::Categories, Action::Read),
-                permission!(Resource::CategoryAttrs, Action::Read),
-                permission!(Resource::CurrencyExchange, Action::Read),
-                permission!(Resource::CustomAttributes, Action::All, Scope::Owned),
-                permission!(Resource::CustomAttributes, Action::Read),
-                permission!(Resource::ModeratorProductComments, Action::All, Scope::Owned),
-                permission!(Resource::ModeratorProductComments, Action::Read),
-                permission!(Resource::ModeratorStoreComments, Action::All, Scope::Owned),
-                permission!(Resource::ModeratorStoreComments, Action::Read),
-                permission!(Resource::ProductAttrs, Action::All, Scope::Owned),
-                permission!(Resource::ProductAttrs, Action::Read),
-                permission!(Resource::Products, Action::All, Scope::Owned),
-                permission!(Resource::Products, Action::Read),
-                permission!(Resource::Stores, Action::Create, Scope::Owned),
-                permission!(Resource::Stores, Action::Delete, Scope::Owned),
-                permission!(
-                    Resource::Stores,
-                    Action::Read,
-                    Scope::All,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                permission!(Resource::Stores, Action::Read, Scope::Owned, Rule::Any),
-                permission!(
-                    Resource::Stores,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Draft)
-                ),
-                permission!(
-                    Resource::Stores,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Decline)
-                ),
-                permission!(
-                    Resource::Stores,
-                    Action::Update,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                // Store manager can sent store to moderation `Draft -> Moderation`
-                permission!(
-                    Resource::Stores,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Draft)
-                ),
-                // Store manager can modified moderation status store from `Decline -> Draft`
-                permission!(
-                    Resource::Stores,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Decline)
-                ),
-                // Store manager can modified moderation status store from `Published -> Draft`
-                permission!(
-                    Resource::Stores,
-                    Action::Moderate,
-                    Scope::Owned,
-                    Rule::ModerationStatus(ModerationStatus::Published)
-                ),
-                permission!(Resource::UserRoles, Action::Read, Scope::Owned),
-                permission!(Resource::WizardStores, Action::All, Scope::Owned),
-                permission!(Resource::WizardStores, Action::Read),
-                permission!(Resource::Coupons, Action::All, Scope::Owned),
-                permission!(Resource::Coupons, Action::Read),
-                permission!(Resource::CouponScopeBaseProducts, Action::All, Scope::Owned),
-                permission!(Resource::CouponScopeBaseProducts, Action::Read),
-                permission!(Resource::CouponScopeCategories, Action::All, Scope::Owned),
-                permission!(Resource::CouponScopeCategories, Action::Read),
-                permission!(Resource::UsedCoupons, Action::Read),
-            ],
-        );
-
-        hash.insert(
-            StoresRole::Moderator,
-            vec![
-                permission!(Resource::BaseProducts),
-                permission!(Resource::ModeratorProductComments),
-                permission!(Resource::ModeratorStoreComments),
-                permission!(Resource::Stores),
-            ],
-        );
-
-        hash.insert(
-            StoresRole::PlatformAdmin,
-            vec![
-                permission!(Resource::Attributes),
-                permission!(Resource::AttributeValues),
-                permission!(Resource::Categories),
-                permission!(Resource::CategoryAttrs),
-            ],
-        );
+        Self::with_policy(Arc::new(AclPolicy::default()), roles, user_id)
+    }
 
+    pub fn with_policy(policy: Arc<AclPolicy>, roles: Vec<StoresRole>, user_id: UserId) -> Self {
         ApplicationAcl {
-            acls: Rc::new(hash),
+            policy,
             roles,
             user_id,
+            audit: AclAudit::default(),
         }
     }
-}
 
-impl<T> Acl<Resource, Action, Scope, Rule, FailureError, T> for ApplicationAcl {
-    fn allows(
+    /// Checks all permissions of user's roles against the request. Scope is checked only for permissions
+    /// matching by resource, action and rule, request is allowed if any of them has the object in scope.
+    pub fn decide<T>(
         &self,
         resource: Resource,
         action: Action,
         scope_checker: &CheckScope<Scope, T>,
         rule: Option<Rule>,
         obj: Option<&T>,
-    ) -> Result<bool, FailureError> {
-        let empty: Vec<Permission> = Vec::new();
-        let user_id = &self.user_id;
-        let hashed_acls = self.acls.clone();
-        let acls = self
+    ) -> AclDecision {
+        let checks = self
             .roles
             .iter()
-            .flat_map(|role| hashed_acls.get(role).unwrap_or(&empty))
-            .filter(|permission| {
-                let check_result =
-                    (permission.resource == resource) && ((permission.action == action) || (permission.action == Action::All));
-
-                let check_rule = match (rule, permission.rule) {
+            .flat_map(|role| self.policy.permissions(role).iter().map(move |permission| (*role, permission)))
+            .map(|(role, permission)| {
+                let resource_matches = permission.resource == resource;
+                let action_matches = (permission.action == action) || (permission.action == Action::All);
+                let rule_matches = match (rule, permission.rule) {
                     (Some(rule), Some(permission_rule)) => ((permission_rule == rule) || (permission_rule == Rule::Any)),
                     _ => true,
                 };
 
-                check_result && check_rule
+                let in_scope = if resource_matches && action_matches && rule_matches {
+                    Some(scope_checker.is_in_scope(self.user_id, &permission.scope, obj))
+                } else {
+                    None
+                };
+
+                PermissionCheck {
+                    role,
+                    permission: permission.clone(),
+                    resource_matches,
+                    action_matches,
+                    rule_matches,
+                    in_scope,
+                }
             })
-            .filter(|permission| scope_checker.is_in_scope(*user_id, &permission.scope, obj));
+            .collect::<Vec<PermissionCheck>>();
+
+        AclDecision {
+            user_id: Some(self.user_id),
+            roles: self.roles.clone(),
+            resource,
+            action,
+            rule,
+            allowed: checks.iter().any(|check| check.is_match()),
+            checks,
+        }
+    }
+}
 
-        if acls.count() > 0 {
-            Ok(true)
-        } else {
+/// Writes ACL decisions on `count` objects to audit log as JSON, so that records can be searched by fields in log sink
+fn log_acl_decision(level: Level, decision: &AclDecision, scopes: &[Scope], object_ids: &[String], count: usize) {
+    log!(
+        target: ACL_AUDIT_LOG_TARGET,
+        level,
+        "{}",
+        json!({
+            "user_id": decision.user_id,
+            "roles": decision.roles,
+            "resource": decision.resource,
+            "action": decision.action,
+            "rule": decision.rule,
+            "scopes": scopes,
+            "allowed": decision.allowed,
+            "object_ids": object_ids,
+            "count": count,
+        })
+    );
+}
+
+impl<T> Acl<Resource, Action, Scope, Rule, FailureError, T> for ApplicationAcl {
+    fn allows(
+        &self,
+        resource: Resource,
+        action: Action,
+        scope_checker: &CheckScope<Scope, T>,
+        rule: Option<Rule>,
+        obj: Option<&T>,
+    ) -> Result<bool, FailureError> {
+        let decision = self.decide(resource, action, scope_checker, rule, obj);
+        let allowed = decision.allowed;
+        if !allowed {
             error!(
                 "Denied request from user {} to do {} on {} by rule: {:?}.",
-                user_id, action, resource, rule
+                self.user_id, action, resource, rule
             );
-            Ok(false)
         }
+
+        self.audit.record(decision, obj.and_then(|obj| scope_checker.object_id(obj)));
+
+        Ok(allowed)
     }
 }
 
 /// UnauthorizedAcl contains main logic for manipulation with resources
 #[derive(Clone, Default)]
-pub struct UnauthorizedAcl;
+pub struct UnauthorizedAcl {
+    audit: AclAudit,
+}
 
 impl<T> Acl<Resource, Action, Scope, Rule, FailureError, T> for UnauthorizedAcl {
     fn allows(
         &self,
         resource: Resource,
         action: Action,
-        _scope_checker: &CheckScope<Scope, T>,
+        scope_checker: &CheckScope<Scope, T>,
         rule: Option<Rule>,
-        _obj: Option<&T>,
+        obj: Option<&T>,
     ) -> Result<bool, FailureError> {
-        if action == Action::Read {
+        let allowed = if action == Action::Read {
             match resource {
                 Resource::Categories
                 | Resource::Products
@@ -314,21 +275,35 @@ impl<T> Acl<Resource, Action, Scope, Rule, FailureError, T> for UnauthorizedAcl
                 | Resource::WizardStores
                 | Resource::ModeratorProductComments
                 | Resource::ModeratorStoreComments
-                | Resource::CategoryAttrs => Ok(true),
+                | Resource::SearchSynonyms
+                | Resource::CategoryAttrs => true,
 
                 Resource::Stores | Resource::BaseProducts => match rule {
                     Some(value) => match value {
-                        Rule::Any => Ok(true),
-                        Rule::ModerationStatus(status) => Ok(status == ModerationStatus::Published),
+                        Rule::Any => true,
+                        Rule::ModerationStatus(status) => status == ModerationStatus::Published,
                     },
-                    _ => Ok(true),
+                    _ => true,
                 },
-                _ => Ok(false),
+                _ => false,
             }
         } else {
             error!("Denied unauthorized request to do {} on {} by rule: {:?}.", action, resource, rule);
-            Ok(false)
-        }
+            false
+        };
+
+        let decision = AclDecision {
+            user_id: None,
+            roles: vec![],
+            resource,
+            action,
+            rule,
+            allowed,
+            checks: vec![],
+        };
+        self.audit.record(decision, obj.and_then(|obj| scope_checker.object_id(obj)));
+
+        Ok(allowed)
     }
 }
 
@@ -380,6 +355,8 @@ mod tests {
             saga_id: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
             kafka_update_no: 0,
             uuid: uuid::Uuid::new_v4(),
         }
@@ -399,6 +376,22 @@ mod tests {
                         false
                     }
                 }
+                Scope::Staff(_) => false,
+            }
+        }
+    }
+
+    /// Scope checker for user being staff member of any store with `role`
+    struct StaffScopeChecker {
+        role: StoreStaffRole,
+    }
+
+    impl CheckScope<Scope, Store> for StaffScopeChecker {
+        fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&Store>) -> bool {
+            match *scope {
+                Scope::All => true,
+                Scope::Owned => false,
+                Scope::Staff(role) => self.role >= role,
             }
         }
     }
@@ -414,6 +407,7 @@ mod tests {
                         false
                     }
                 }
+                Scope::Staff(_) => false,
             }
         }
     }
@@ -563,6 +557,86 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_store_staff_for_stores() {
+        let user_id = UserId(2);
+        let acl = ApplicationAcl::new(vec![StoresRole::User], user_id);
+        let resource = create_store(UserId(1));
+        let draft = Some(Rule::ModerationStatus(ModerationStatus::Draft));
+
+        let viewer = StaffScopeChecker {
+            role: StoreStaffRole::Viewer,
+        };
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Read, &viewer, Some(Rule::Any), Some(&resource))
+                .unwrap(),
+            true,
+            "ACL does not allow read action on store for store viewer."
+        );
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Update, &viewer, draft, Some(&resource))
+                .unwrap(),
+            false,
+            "ACL allows update actions on store for store viewer."
+        );
+
+        let editor = StaffScopeChecker {
+            role: StoreStaffRole::Editor,
+        };
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Update, &editor, draft, Some(&resource))
+                .unwrap(),
+            false,
+            "ACL allows update actions on store for store editor."
+        );
+
+        let manager = StaffScopeChecker {
+            role: StoreStaffRole::Manager,
+        };
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Update, &manager, draft, Some(&resource))
+                .unwrap(),
+            true,
+            "ACL does not allow update actions on store for store manager."
+        );
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Moderate, &manager, draft, Some(&resource))
+                .unwrap(),
+            true,
+            "ACL does not allow moderate actions on store for store manager."
+        );
+        assert_eq!(
+            acl.allows(Resource::Stores, Action::Delete, &manager, Some(Rule::Any), Some(&resource))
+                .unwrap(),
+            false,
+            "ACL allows delete actions on store for store manager."
+        );
+    }
+
+    #[test]
+    fn test_decision_for_store_manager() {
+        let acl = ApplicationAcl::new(vec![StoresRole::User], UserId(2));
+        let resource = create_store(UserId(1));
+        let manager = StaffScopeChecker {
+            role: StoreStaffRole::Manager,
+        };
+        let draft = Some(Rule::ModerationStatus(ModerationStatus::Draft));
+
+        let decision = acl.decide(Resource::Stores, Action::Update, &manager, draft, Some(&resource));
+        assert_eq!(decision.allowed, true);
+        assert_eq!(decision.user_id, Some(UserId(2)));
+        assert_eq!(decision.matched_scopes(), vec![Scope::Staff(StoreStaffRole::Manager)]);
+        assert!(decision
+            .checks
+            .iter()
+            .filter(|check| check.permission.resource != Resource::Stores)
+            .all(|check| check.in_scope.is_none()));
+
+        let decision = acl.decide(Resource::Stores, Action::Delete, &manager, Some(Rule::Any), Some(&resource));
+        assert_eq!(decision.allowed, false);
+        assert!(decision.matched_scopes().is_empty());
+    }
+
     #[test]
     fn test_super_user_for_user_roles() {
         let acl = ApplicationAcl::new(vec![StoresRole::Superuser], UserId(1232));
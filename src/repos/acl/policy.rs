@@ -0,0 +1,474 @@
+//! ACL policy is a set of permissions granted to each of the roles. Built-in policy is used by default,
+//! it can be replaced with policy loaded from JSON file without rebuilding the app
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+
+use failure::Error as FailureError;
+use serde_json;
+
+use stq_static_resources::ModerationStatus;
+use stq_types::StoresRole;
+
+use models::authorization::*;
+use models::StoreStaffRole;
+
+/// Permissions granted to each of the roles
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct AclPolicy(HashMap<StoresRole, Vec<Permission>>);
+
+impl AclPolicy {
+    /// Loads policy from JSON file and validates it
+    pub fn from_file(path: &str) -> Result<Self, FailureError> {
+        let mut content = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .map_err(|e| format_err!("Reading ACL policy from {} failed: {}", path, e))?;
+
+        let policy =
+            serde_json::from_str::<AclPolicy>(&content).map_err(|e| format_err!("Parsing ACL policy from {} failed: {}", path, e))?;
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// Returns permissions granted to the role, roles missing in policy have no permissions
+    pub fn permissions(&self, role: &StoresRole) -> &[Permission] {
+        self.0.get(role).map(|permissions| permissions.as_slice()).unwrap_or(&[])
+    }
+
+    /// Checks that every permission of policy can take effect. Rules are checked only on stores and base products
+    /// and staff scope is resolved only for resources belonging to stores, such permissions on other resources
+    /// would either match any request or never match.
+    pub fn validate(&self) -> Result<(), FailureError> {
+        let mut errors = vec![];
+
+        for (role, permissions) in &self.0 {
+            for (i, permission) in permissions.iter().enumerate() {
+                if permission.rule.is_some() && !is_checked_with_rule(permission.resource) {
+                    errors.push(format!(
+                        "{:?}: rule of {:?} is not checked on {}",
+                        role, permission, permission.resource
+                    ));
+                }
+
+                if let Scope::Staff(_) = permission.scope {
+                    if !belongs_to_store(permission.resource) {
+                        errors.push(format!(
+                            "{:?}: staff scope of {:?} is not supported by {}",
+                            role, permission, permission.resource
+                        ));
+                    }
+                }
+
+                if permissions[..i].contains(permission) {
+                    errors.push(format!("{:?}: {:?} is duplicated", role, permission));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("ACL policy is invalid: {}", errors.join("; ")))
+        }
+    }
+}
+
+/// Resources whose repos pass moderation status of object as rule to ACL
+fn is_checked_with_rule(resource: Resource) -> bool {
+    match resource {
+        Resource::Stores | Resource::BaseProducts => true,
+        _ => false,
+    }
+}
+
+/// Resources whose repos check `Scope::Staff` against the store of object
+fn belongs_to_store(resource: Resource) -> bool {
+    match resource {
+        Resource::Stores
+        | Resource::BaseProducts
+        | Resource::Products
+        | Resource::ProductAttrs
+        | Resource::CustomAttributes
+        | Resource::Coupons
+        | Resource::CouponScopeBaseProducts
+        | Resource::CouponScopeCategories
+        | Resource::CouponExclusionBaseProducts
+        | Resource::ProductPriceHistory
+        | Resource::ScheduledPriceChanges
+        | Resource::StoreStaff
+        | Resource::ModerationTransitions
+        | Resource::ModerationFeedback => true,
+        _ => false,
+    }
+}
+
+impl Default for AclPolicy {
+    /// Built-in policy, `config/acl_policy.json` contains the same permissions
+    fn default() -> Self {
+        let mut hash = HashMap::new();
+        hash.insert(
+            StoresRole::Superuser,
+            vec![
+                permission!(Resource::Attributes),
+                permission!(Resource::AttributeValues),
+                permission!(Resource::BaseProducts),
+                permission!(Resource::Categories),
+                permission!(Resource::CategoryAttrs),
+                permission!(Resource::CurrencyExchange),
+                permission!(Resource::CustomAttributes),
+                permission!(Resource::ModeratorProductComments),
+                permission!(Resource::ModeratorStoreComments),
+                permission!(Resource::ProductAttrs),
+                permission!(Resource::Products),
+                permission!(Resource::Stores),
+                permission!(Resource::UserRoles),
+                permission!(Resource::WizardStores),
+                permission!(Resource::Coupons),
+                permission!(Resource::CouponScopeBaseProducts),
+                permission!(Resource::CouponScopeCategories),
+                permission!(Resource::CouponExclusionBaseProducts),
+                permission!(Resource::UsedCoupons),
+                permission!(Resource::ProductPriceHistory),
+                permission!(Resource::ScheduledPriceChanges),
+                permission!(Resource::OutboxEvents),
+                permission!(Resource::SearchSynonyms),
+                permission!(Resource::StoreStaff),
+                permission!(Resource::AclDecisions),
+                permission!(Resource::ModerationQueue),
+                permission!(Resource::ModerationTransitions),
+                permission!(Resource::DeclineReasons),
+                permission!(Resource::ModerationFeedback),
+            ],
+        );
+        hash.insert(
+            StoresRole::User,
+            vec![
+                permission!(Resource::Attributes, Action::Read),
+                permission!(Resource::AttributeValues, Action::Read),
+                permission!(Resource::BaseProducts, Action::Create, Scope::Owned),
+                permission!(Resource::BaseProducts, Action::Delete, Scope::Owned),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Read,
+                    Scope::All,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::BaseProducts, Action::Read, Scope::Owned, Rule::Any),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                // Store manager can sent base product to moderation `Draft -> Moderation`
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                // Store manager can modified moderation status base product from `Decline -> Draft`
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                // Store manager can modified moderation status base product from `Published -> Draft`
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::Categories, Action::Read),
+                permission!(Resource::CategoryAttrs, Action::Read),
+                permission!(Resource::CurrencyExchange, Action::Read),
+                permission!(Resource::CustomAttributes, Action::All, Scope::Owned),
+                permission!(Resource::CustomAttributes, Action::Read),
+                permission!(Resource::ModeratorProductComments, Action::All, Scope::Owned),
+                permission!(Resource::ModeratorProductComments, Action::Read),
+                permission!(Resource::ModeratorStoreComments, Action::All, Scope::Owned),
+                permission!(Resource::ModeratorStoreComments, Action::Read),
+                permission!(Resource::ProductAttrs, Action::All, Scope::Owned),
+                permission!(Resource::ProductAttrs, Action::Read),
+                permission!(Resource::Products, Action::All, Scope::Owned),
+                permission!(Resource::Products, Action::Read),
+                permission!(Resource::Stores, Action::Create, Scope::Owned),
+                permission!(Resource::Stores, Action::Delete, Scope::Owned),
+                permission!(
+                    Resource::Stores,
+                    Action::Read,
+                    Scope::All,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::Stores, Action::Read, Scope::Owned, Rule::Any),
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                // Store manager can sent store to moderation `Draft -> Moderation`
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                // Store manager can modified moderation status store from `Decline -> Draft`
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                // Store manager can modified moderation status store from `Published -> Draft`
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Owned,
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::UserRoles, Action::Read, Scope::Owned),
+                permission!(Resource::WizardStores, Action::All, Scope::Owned),
+                permission!(Resource::WizardStores, Action::Read),
+                permission!(Resource::Coupons, Action::All, Scope::Owned),
+                permission!(Resource::Coupons, Action::Read),
+                permission!(Resource::CouponScopeBaseProducts, Action::All, Scope::Owned),
+                permission!(Resource::CouponScopeBaseProducts, Action::Read),
+                permission!(Resource::CouponScopeCategories, Action::All, Scope::Owned),
+                permission!(Resource::CouponScopeCategories, Action::Read),
+                permission!(Resource::CouponExclusionBaseProducts, Action::All, Scope::Owned),
+                permission!(Resource::CouponExclusionBaseProducts, Action::Read),
+                permission!(Resource::UsedCoupons, Action::Read),
+                permission!(Resource::ProductPriceHistory, Action::All, Scope::Owned),
+                permission!(Resource::ProductPriceHistory, Action::Read),
+                permission!(Resource::ScheduledPriceChanges, Action::All, Scope::Owned),
+                permission!(Resource::SearchSynonyms, Action::Read),
+                permission!(Resource::StoreStaff, Action::Read, Scope::Owned),
+                permission!(Resource::ModerationTransitions, Action::Read, Scope::Owned),
+                permission!(Resource::DeclineReasons, Action::Read),
+                permission!(Resource::ModerationFeedback, Action::Read, Scope::Owned),
+                // Store staff members act on store resources according to their role in the store.
+                // Viewers can read unpublished store and products
+                permission!(Resource::Stores, Action::Read, Scope::Staff(StoreStaffRole::Viewer), Rule::Any),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Read,
+                    Scope::Staff(StoreStaffRole::Viewer),
+                    Rule::Any
+                ),
+                permission!(Resource::ProductPriceHistory, Action::Read, Scope::Staff(StoreStaffRole::Viewer)),
+                permission!(Resource::ScheduledPriceChanges, Action::Read, Scope::Staff(StoreStaffRole::Viewer)),
+                permission!(Resource::StoreStaff, Action::Read, Scope::Staff(StoreStaffRole::Viewer)),
+                permission!(Resource::ModerationTransitions, Action::Read, Scope::Staff(StoreStaffRole::Viewer)),
+                permission!(Resource::ModerationFeedback, Action::Read, Scope::Staff(StoreStaffRole::Viewer)),
+                // Editors can create and edit products, but can not send them to moderation
+                permission!(Resource::BaseProducts, Action::Create, Scope::Staff(StoreStaffRole::Editor)),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Editor),
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Editor),
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Editor),
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::Products, Action::All, Scope::Staff(StoreStaffRole::Editor)),
+                permission!(Resource::ProductAttrs, Action::All, Scope::Staff(StoreStaffRole::Editor)),
+                permission!(Resource::CustomAttributes, Action::All, Scope::Staff(StoreStaffRole::Editor)),
+                permission!(Resource::ProductPriceHistory, Action::All, Scope::Staff(StoreStaffRole::Editor)),
+                permission!(Resource::ScheduledPriceChanges, Action::All, Scope::Staff(StoreStaffRole::Editor)),
+                // Managers can edit store, delete products, change moderation status and manage coupons
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Update,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::Stores,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::BaseProducts, Action::Delete, Scope::Staff(StoreStaffRole::Manager)),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Draft)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Decline)
+                ),
+                permission!(
+                    Resource::BaseProducts,
+                    Action::Moderate,
+                    Scope::Staff(StoreStaffRole::Manager),
+                    Rule::ModerationStatus(ModerationStatus::Published)
+                ),
+                permission!(Resource::Coupons, Action::All, Scope::Staff(StoreStaffRole::Manager)),
+                permission!(
+                    Resource::CouponScopeBaseProducts,
+                    Action::All,
+                    Scope::Staff(StoreStaffRole::Manager)
+                ),
+                permission!(Resource::CouponScopeCategories, Action::All, Scope::Staff(StoreStaffRole::Manager)),
+                permission!(
+                    Resource::CouponExclusionBaseProducts,
+                    Action::All,
+                    Scope::Staff(StoreStaffRole::Manager)
+                ),
+                // Owners can also manage staff of the store
+                permission!(Resource::StoreStaff, Action::All, Scope::Staff(StoreStaffRole::Owner)),
+            ],
+        );
+
+        hash.insert(
+            StoresRole::Moderator,
+            vec![
+                permission!(Resource::BaseProducts),
+                permission!(Resource::ModeratorProductComments),
+                permission!(Resource::ModeratorStoreComments),
+                permission!(Resource::Stores),
+                permission!(Resource::ModerationQueue),
+                permission!(Resource::ModerationTransitions),
+                permission!(Resource::DeclineReasons),
+                permission!(Resource::ModerationFeedback),
+            ],
+        );
+
+        hash.insert(
+            StoresRole::PlatformAdmin,
+            vec![
+                permission!(Resource::Attributes),
+                permission!(Resource::AttributeValues),
+                permission!(Resource::Categories),
+                permission!(Resource::CategoryAttrs),
+                permission!(Resource::SearchSynonyms),
+            ],
+        );
+
+        AclPolicy(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use stq_types::StoresRole;
+
+    use models::authorization::*;
+    use models::StoreStaffRole;
+    use repos::acl::AclPolicy;
+
+    #[test]
+    fn test_default_policy_is_valid() {
+        assert!(AclPolicy::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_policy_file_equals_default_policy() {
+        let policy = AclPolicy::from_file("config/acl_policy.json").unwrap();
+        let default_policy = AclPolicy::default();
+        for role in &[
+            StoresRole::Superuser,
+            StoresRole::User,
+            StoresRole::Moderator,
+            StoresRole::PlatformAdmin,
+        ] {
+            assert_eq!(
+                policy.permissions(role),
+                default_policy.permissions(role),
+                "Permissions of {:?} in policy file differ from built-in policy.",
+                role
+            );
+        }
+        assert_eq!(policy, default_policy);
+    }
+
+    #[test]
+    fn test_policy_with_rule_on_resource_without_rules_is_invalid() {
+        let policy =
+            serde_json::from_str::<AclPolicy>(r#"{"user": [{"resource": "Attributes", "action": "Read", "scope": "All", "rule": "Any"}]}"#)
+                .unwrap();
+        assert!(policy.validate().is_err());
+    }
+
+    #[test]
+    fn test_policy_with_staff_scope_on_resource_without_stores_is_invalid() {
+        let policy = serde_json::from_str::<AclPolicy>(
+            r#"{"user": [{"resource": "Categories", "action": "Update", "scope": {"Staff": "Editor"}}]}"#,
+        )
+        .unwrap();
+        assert!(policy.validate().is_err());
+        assert_eq!(policy.permissions(&StoresRole::Moderator).len(), 0);
+        assert_eq!(policy.permissions(&StoresRole::User)[0].scope, Scope::Staff(StoreStaffRole::Editor));
+    }
+}
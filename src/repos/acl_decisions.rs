@@ -0,0 +1,142 @@
+//! Repo explaining ACL decisions, it evaluates ACL of any user against objects from db
+//! the same way as repos do it, without changing anything
+use std::sync::Arc;
+
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{BaseProductId, CouponId, ProductId, StoreId, StoresRole, UserId};
+
+use models::*;
+use repos::acl;
+use repos::acl::{AclPolicy, ApplicationAcl};
+use repos::legacy_acl::{CheckScope, SystemACL};
+use repos::types::{RepoAcl, RepoResult};
+use repos::{BaseProductsRepoImpl, CouponsRepoImpl, ProductsRepoImpl, StoreStaffRepoImpl, StoresRepoImpl};
+use schema::base_products::dsl as DslBaseProducts;
+use schema::coupons::dsl as DslCoupons;
+use schema::products::dsl as DslProducts;
+use schema::store_staff::dsl as DslStoreStaff;
+use schema::stores::dsl as DslStores;
+use schema::user_roles::dsl as DslUserRoles;
+
+/// AclDecisions repository, responsible for explaining ACL decisions
+pub struct AclDecisionsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<AclDecision>>,
+    pub acl_policy: Arc<AclPolicy>,
+}
+
+pub trait AclDecisionsRepo {
+    /// Explains which permissions of user allow or deny action on resource
+    fn explain(&self, request: AclExplainRequest) -> RepoResult<AclDecision>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> AclDecisionsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<AclDecision>>, acl_policy: Arc<AclPolicy>) -> Self {
+        Self { db_conn, acl, acl_policy }
+    }
+
+    fn not_found(request: &AclExplainRequest, id: i32) -> FailureError {
+        format_err!("Object {} of resource {} not found.", id, request.resource)
+            .context(Error::NotFound)
+            .into()
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> AclDecisionsRepo
+    for AclDecisionsRepoImpl<'a, T>
+{
+    /// Explains which permissions of user allow or deny action on resource
+    fn explain(&self, request: AclExplainRequest) -> RepoResult<AclDecision> {
+        debug!("Explain ACL decision {:?}.", request);
+        acl::check(&*self.acl, Resource::AclDecisions, Action::Read, self, None)
+            .and_then(|_| {
+                let roles = DslUserRoles::user_roles
+                    .filter(DslUserRoles::user_id.eq(request.user_id))
+                    .select(DslUserRoles::name)
+                    .get_results::<StoresRole>(self.db_conn)
+                    .map_err(Error::from)?;
+                let user_acl = ApplicationAcl::with_policy(self.acl_policy.clone(), roles, request.user_id);
+
+                match (request.resource, request.object_id) {
+                    (Resource::Stores, Some(id)) => {
+                        let store = DslStores::stores
+                            .filter(DslStores::id.eq(StoreId(id)))
+                            .get_result::<Store>(self.db_conn)
+                            .optional()
+                            .map_err(Error::from)?
+                            .ok_or_else(|| Self::not_found(&request, id))?;
+                        let rule = request.rule.or(Some(Rule::ModerationStatus(store.status)));
+                        let checker = StoresRepoImpl::new(self.db_conn, Box::new(SystemACL::default()));
+                        Ok(user_acl.decide(request.resource, request.action, &checker, rule, Some(&store)))
+                    }
+                    (Resource::BaseProducts, Some(id)) => {
+                        let base_product = DslBaseProducts::base_products
+                            .filter(DslBaseProducts::id.eq(BaseProductId(id)))
+                            .get_result::<BaseProductRaw>(self.db_conn)
+                            .optional()
+                            .map_err(Error::from)?
+                            .map(BaseProduct::from)
+                            .ok_or_else(|| Self::not_found(&request, id))?;
+                        let rule = request.rule.or(Some(Rule::ModerationStatus(base_product.status)));
+                        let checker = BaseProductsRepoImpl::new(self.db_conn, Box::new(SystemACL::default()));
+                        Ok(user_acl.decide(request.resource, request.action, &checker, rule, Some(&base_product)))
+                    }
+                    (Resource::Products, Some(id)) => {
+                        let product = DslProducts::products
+                            .filter(DslProducts::id.eq(ProductId(id)))
+                            .get_result::<RawProduct>(self.db_conn)
+                            .optional()
+                            .map_err(Error::from)?
+                            .ok_or_else(|| Self::not_found(&request, id))?;
+                        let checker = ProductsRepoImpl::new(self.db_conn, Box::new(SystemACL::default()));
+                        Ok(user_acl.decide(request.resource, request.action, &checker, request.rule, Some(&product)))
+                    }
+                    (Resource::Coupons, Some(id)) => {
+                        let coupon = DslCoupons::coupons
+                            .filter(DslCoupons::id.eq(CouponId(id)))
+                            .get_result::<Coupon>(self.db_conn)
+                            .optional()
+                            .map_err(Error::from)?
+                            .ok_or_else(|| Self::not_found(&request, id))?;
+                        let checker = CouponsRepoImpl::new(self.db_conn, Box::new(SystemACL::default()));
+                        Ok(user_acl.decide(request.resource, request.action, &checker, request.rule, Some(&coupon)))
+                    }
+                    (Resource::StoreStaff, Some(id)) => {
+                        let member = DslStoreStaff::store_staff
+                            .filter(DslStoreStaff::id.eq(id))
+                            .get_result::<StoreStaffMember>(self.db_conn)
+                            .optional()
+                            .map_err(Error::from)?
+                            .ok_or_else(|| Self::not_found(&request, id))?;
+                        let checker = StoreStaffRepoImpl::new(self.db_conn, Box::new(SystemACL::default()));
+                        Ok(user_acl.decide(request.resource, request.action, &checker, request.rule, Some(&member)))
+                    }
+                    (resource, Some(_)) => Err(format_err!("Explaining ACL decisions on objects of {} is not supported.", resource)
+                        .context(Error::Validate(
+                            validation_errors!({"object_id": ["object_id" => "Objects of the resource are not supported"]}),
+                        ))
+                        .into()),
+                    (resource, None) => Ok(user_acl.decide::<AclDecision>(resource, request.action, self, request.rule, None)),
+                }
+            })
+            .map_err(|e: FailureError| e.context(format!("Explain ACL decision {:?} error occurred", request)).into())
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, AclDecision>
+    for AclDecisionsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&AclDecision>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned | Scope::Staff(_) => false,
+        }
+    }
+}
@@ -144,7 +144,11 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
     fn is_in_scope(&self, _user_id_arg: UserId, scope: &Scope, _obj: Option<&AttributeValue>) -> bool {
         match *scope {
             Scope::All => true,
-            Scope::Owned => false,
+            Scope::Owned | Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &AttributeValue) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
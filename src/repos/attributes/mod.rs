@@ -166,7 +166,11 @@ where
     fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&Attribute>) -> bool {
         match *scope {
             Scope::All => true,
-            Scope::Owned => false,
+            Scope::Owned | Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &Attribute) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
 
 use diesel;
 use diesel::connection::AnsiTransactionManager;
@@ -23,6 +24,7 @@ use errors;
 use repos::{
     acl,
     legacy_acl::*,
+    store_staff::StoreRoles,
     types::{RepoAcl, RepoResult},
 };
 use schema::attributes::dsl as DslAttributes;
@@ -35,6 +37,7 @@ use schema::stores::dsl as Stores;
 pub struct BaseProductsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<BaseProduct>>,
+    pub store_roles: StoreRoles,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -128,8 +131,8 @@ pub trait BaseProductsRepo {
     /// Set moderation status for base_product_id
     fn set_moderation_status(&self, base_product_id: BaseProductId, status: ModerationStatus) -> RepoResult<BaseProduct>;
 
-    /// Set moderation status for base_products by store. For store manager
-    fn update_moderation_status_by_store(&self, store_id: StoreId, status: ModerationStatus) -> RepoResult<Vec<BaseProduct>>;
+    /// Checks that user can change moderation status of the base product in its current status
+    fn can_moderate(&self, base_product: &BaseProduct) -> RepoResult<bool>;
 
     /// Updates service base product fields as root
     fn update_service_fields(
@@ -143,16 +146,118 @@ pub trait BaseProductsRepo {
 
     /// Getting all base products with variants
     fn get_all_catalog(&self) -> RepoResult<Vec<CatalogWithAttributes>>;
+
+    /// Getting active base products of the store with variants and attributes, limited by `from` and `count` parameters.
+    /// Base products the user can not read are skipped.
+    fn get_catalog_of_the_store(&self, store_id: StoreId, from: BaseProductId, count: i64) -> RepoResult<StoreCatalogChunk>;
+
+    /// Getting active base products of all stores with variants and attributes, limited by `from` and `count` parameters
+    fn get_active_catalog(&self, from: BaseProductId, count: i64) -> RepoResult<Vec<CatalogWithAttributes>>;
+
+    /// Getting active base products with given ids with variants and attributes
+    fn get_active_catalog_by_ids(&self, ids: Vec<BaseProductId>) -> RepoResult<Vec<CatalogWithAttributes>>;
+
+    /// Returns ids of base products updated after `since` by themselves or through their variants or stores,
+    /// deactivated base products included
+    fn find_ids_updated_since(&self, since: SystemTime) -> RepoResult<Vec<BaseProductId>>;
+
+    /// Returns page of published base products updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<BaseProduct, BaseProductId>>;
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> BaseProductsRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<BaseProduct>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 
     fn execute_query<Ty: Send + 'static, U: LoadQuery<T, Ty> + Send + 'static>(&self, query: U) -> RepoResult<Ty> {
         query.get_result::<Ty>(self.db_conn).map_err(|e| Error::from(e).into())
     }
+
+    /// Checks access to base products and loads their active variants with attributes
+    fn load_catalog(&self, raw_base_products: Vec<BaseProductRaw>) -> RepoResult<Vec<CatalogWithAttributes>> {
+        for base_product in &raw_base_products {
+            let base_product = BaseProduct::from(base_product.clone());
+            acl::check_with_rule(
+                &*self.acl,
+                Resource::BaseProducts,
+                Action::Read,
+                self,
+                Rule::ModerationStatus(base_product.status),
+                Some(&base_product),
+            )?;
+        }
+
+        self.load_catalog_variants(raw_base_products)
+    }
+
+    /// Keeps base products the user can read
+    fn filter_readable(&self, raw_base_products: Vec<BaseProductRaw>) -> RepoResult<Vec<BaseProductRaw>> {
+        let mut readable = Vec::with_capacity(raw_base_products.len());
+        for raw_base_product in raw_base_products {
+            let base_product = BaseProduct::from(raw_base_product.clone());
+            let allowed = self.acl.allows(
+                Resource::BaseProducts,
+                Action::Read,
+                self,
+                Some(Rule::ModerationStatus(base_product.status)),
+                Some(&base_product),
+            )?;
+            if allowed {
+                readable.push(raw_base_product);
+            }
+        }
+
+        Ok(readable)
+    }
+
+    /// Loads active variants with attributes of base products
+    fn load_catalog_variants(&self, raw_base_products: Vec<BaseProductRaw>) -> RepoResult<Vec<CatalogWithAttributes>> {
+        let products = RawProduct::belonging_to(&raw_base_products)
+            .filter(Products::is_active.eq(true))
+            .order(Products::id)
+            .get_results::<RawProduct>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .map_err(|e: FailureError| e.context("Getting variants of base products."))?;
+
+        let prod_ids = products.iter().map(|p| p.id).collect::<Vec<ProductId>>();
+        let mut attributes_by_product = HashMap::<ProductId, Vec<(ProdAttr, Attribute)>>::new();
+        for (prod_attr, attribute) in DslProdAttr::prod_attr_values
+            .filter(DslProdAttr::prod_id.eq_any(prod_ids))
+            .inner_join(DslAttributes::attributes)
+            .order(DslProdAttr::id)
+            .get_results::<(ProdAttr, Attribute)>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .map_err(|e: FailureError| e.context("Getting attributes of variants."))?
+        {
+            attributes_by_product
+                .entry(prod_attr.prod_id)
+                .or_insert_with(Vec::new)
+                .push((prod_attr, attribute));
+        }
+
+        let products = products.grouped_by(&raw_base_products);
+
+        Ok(raw_base_products
+            .into_iter()
+            .zip(products)
+            .map(|(base_raw, variants)| {
+                let variants = variants
+                    .into_iter()
+                    .map(|variant| {
+                        let attributes = attributes_by_product.remove(&variant.id).unwrap_or_default();
+                        ProductWithAttributes::new(variant, attributes)
+                    })
+                    .collect();
+
+                CatalogWithAttributes::new(BaseProduct::from(base_raw), variants)
+            })
+            .collect())
+    }
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> BaseProductsRepo
@@ -937,30 +1042,19 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
         }
     }
 
-    /// Set moderation status for base_products by store. For store manager
-    fn update_moderation_status_by_store(&self, store_id_arg: StoreId, status_arg: ModerationStatus) -> RepoResult<Vec<BaseProduct>> {
-        debug!(
-            "Update moderation status base products by store_id {}. New status {:?}.",
-            store_id_arg, status_arg
-        );
-
-        let query = base_products.filter(store_id.eq(store_id_arg));
-
-        query
-            .get_results::<BaseProductRaw>(self.db_conn)
-            .map(|raw_base_products| raw_base_products.into_iter().map(BaseProduct::from).collect::<Vec<_>>())
-            .map_err(|e| Error::from(e).into())
-            .and_then(|results: Vec<BaseProduct>| {
-                let ids = results.into_iter().map(|p| p.id).collect();
-
-                self.set_moderation_statuses(ids, status_arg)
-            })
+    /// Checks that user can change moderation status of the base product in its current status
+    fn can_moderate(&self, base_product: &BaseProduct) -> RepoResult<bool> {
+        self.acl
+            .allows(
+                Resource::BaseProducts,
+                Action::Moderate,
+                self,
+                Some(Rule::ModerationStatus(base_product.status)),
+                Some(base_product),
+            )
             .map_err(|e: FailureError| {
-                e.context(format!(
-                    "Update moderation status for base_products by store_id {} error occurred",
-                    store_id_arg
-                ))
-                .into()
+                e.context(format!("Check moderation of base product {} error occurred", base_product.id))
+                    .into()
             })
     }
 
@@ -1077,6 +1171,145 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             })
             .collect::<RepoResult<Vec<_>>>()
     }
+
+    /// Getting active base products of the store with variants and attributes, limited by `from` and `count` parameters
+    fn get_catalog_of_the_store(&self, store_id_arg: StoreId, from: BaseProductId, count: i64) -> RepoResult<StoreCatalogChunk> {
+        debug!(
+            "Getting catalog of the store with id = {}, from id = {}, count = {}.",
+            store_id_arg, from, count
+        );
+
+        base_products
+            .filter(store_id.eq(store_id_arg))
+            .filter(is_active.eq(true))
+            .filter(id.ge(from))
+            .order(id)
+            .limit(count)
+            .get_results::<BaseProductRaw>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|store_base_products| {
+                let next_from = match store_base_products.last() {
+                    Some(last) if store_base_products.len() as i64 == count => Some(BaseProductId(last.id.0 + 1)),
+                    _ => None,
+                };
+                let readable = self.filter_readable(store_base_products)?;
+                let catalog = self.load_catalog_variants(readable)?;
+
+                Ok(StoreCatalogChunk { catalog, next_from })
+            })
+            .map_err(|e: FailureError| e.context("Getting catalog of the store.").into())
+    }
+
+    /// Getting active base products of all stores with variants and attributes, limited by `from` and `count` parameters
+    fn get_active_catalog(&self, from: BaseProductId, count: i64) -> RepoResult<Vec<CatalogWithAttributes>> {
+        debug!("Getting active catalog from id = {}, count = {}.", from, count);
+
+        base_products
+            .filter(is_active.eq(true))
+            .filter(id.ge(from))
+            .order(id)
+            .limit(count)
+            .get_results::<BaseProductRaw>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|active_base_products| self.load_catalog(active_base_products))
+            .map_err(|e: FailureError| e.context(format!("Getting active catalog from id = {} error occurred.", from)).into())
+    }
+
+    /// Getting active base products with given ids with variants and attributes
+    fn get_active_catalog_by_ids(&self, ids: Vec<BaseProductId>) -> RepoResult<Vec<CatalogWithAttributes>> {
+        debug!("Getting active catalog of base products with ids {:?}.", ids);
+
+        base_products
+            .filter(is_active.eq(true))
+            .filter(id.eq_any(ids.clone()))
+            .order(id)
+            .get_results::<BaseProductRaw>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|active_base_products| self.load_catalog(active_base_products))
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Getting active catalog of base products with ids {:?} error occurred.",
+                    ids
+                ))
+                .into()
+            })
+    }
+
+    /// Returns ids of base products updated after `since` by themselves or through their variants or stores,
+    /// deactivated base products included
+    fn find_ids_updated_since(&self, since: SystemTime) -> RepoResult<Vec<BaseProductId>> {
+        debug!("Find ids of base products updated after {:?}.", since);
+
+        let updated_variants = Products::products
+            .filter(Products::updated_at.ge(since))
+            .select(Products::base_product_id);
+        let updated_stores = Stores::stores.filter(Stores::updated_at.ge(since)).select(Stores::id);
+        let query = base_products
+            .filter(
+                updated_at
+                    .ge(since)
+                    .or(id.eq_any(updated_variants))
+                    .or(store_id.eq_any(updated_stores)),
+            )
+            .select(id)
+            .order(id);
+
+        acl::check(&*self.acl, Resource::BaseProducts, Action::Read, self, None)
+            .and_then(|_| query.get_results::<BaseProductId>(self.db_conn).map_err(|e| Error::from(e).into()))
+            .map_err(|e: FailureError| {
+                e.context(format!("Find ids of base products updated after {:?} error occurred", since))
+                    .into()
+            })
+    }
+
+    /// Returns page of published base products updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<BaseProduct, BaseProductId>> {
+        debug!("Find base products updated after {:?}, limit {}.", position, limit);
+
+        let query = base_products
+            .filter(
+                updated_at
+                    .gt(position.updated_at)
+                    .or(updated_at.eq(position.updated_at).and(id.gt(BaseProductId(position.last_id)))),
+            )
+            .order((updated_at, id))
+            .limit(limit);
+
+        acl::check(&*self.acl, Resource::BaseProducts, Action::Read, self, None)
+            .and_then(|_| query.get_results::<BaseProductRaw>(self.db_conn).map_err(|e| Error::from(e).into()))
+            .and_then(|raw_base_products| {
+                let last_position = raw_base_products
+                    .last()
+                    .map(|base_product| CatalogFeedPosition::new(base_product.updated_at, base_product.id.0));
+                let (updated, deleted): (Vec<BaseProduct>, Vec<BaseProduct>) =
+                    raw_base_products.into_iter().map(BaseProduct::from).partition(|base_product| {
+                        base_product.is_active
+                            && base_product.status == ModerationStatus::Published
+                            && base_product.store_status == ModerationStatus::Published
+                    });
+
+                for base_product in &updated {
+                    acl::check_with_rule(
+                        &*self.acl,
+                        Resource::BaseProducts,
+                        Action::Read,
+                        self,
+                        Rule::ModerationStatus(base_product.status),
+                        Some(base_product),
+                    )?;
+                }
+
+                Ok(CatalogFeedChanges {
+                    updated,
+                    deleted: deleted.into_iter().map(|base_product| base_product.id).collect(),
+                    last_position,
+                })
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find base products updated after {:?} error occurred", position))
+                    .into()
+            })
+    }
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, BaseProduct>
@@ -1097,8 +1330,19 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(base_prod) = obj {
+                    self.store_roles.is_store_staff(self.db_conn, user_id, base_prod.store_id, role)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &BaseProduct) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
 
 fn by_moderator_search_terms(term: &ModeratorBaseProductSearchTerms) -> Box<BoxableExpression<base_products, Pg, SqlType = Bool>> {
@@ -149,7 +149,11 @@ where
     fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&CatAttr>) -> bool {
         match *scope {
             Scope::All => true,
-            Scope::Owned => false,
+            Scope::Owned | Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &CatAttr) -> Option<String> {
+        Some(obj.id.to_string())
+    }
 }
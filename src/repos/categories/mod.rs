@@ -17,7 +17,10 @@ use stq_static_resources::ModerationStatus;
 use stq_types::{AttributeId, CategoryId, CategorySlug, UserId};
 
 use models::authorization::*;
-use models::{Attribute, BaseProductRaw, CatAttr, Category, InsertCategory, NewCategory, RawCategory, UpdateCategory};
+use models::{
+    Attribute, BaseProductRaw, CatAttr, CatalogFeedChanges, CatalogFeedPosition, Category, InsertCategory, NewCategory, RawCategory,
+    UpdateCategory,
+};
 use repos::acl;
 use repos::legacy_acl::CheckScope;
 use repos::types::{RepoAcl, RepoResult};
@@ -70,6 +73,9 @@ pub trait CategoriesRepo {
 
     /// Returns all raw categories
     fn get_raw_categories(&self) -> RepoResult<Vec<RawCategory>>;
+
+    /// Returns page of active categories updated after feed position and ids of deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<RawCategory, CategoryId>>;
 }
 
 impl<'a, C, T> CategoriesRepoImpl<'a, C, T>
@@ -274,6 +280,34 @@ where
             .map_err(|e: FailureError| e.context("Get raw categories error occurred").into())
     }
 
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<RawCategory, CategoryId>> {
+        debug!("Find categories updated after {:?}, limit {}.", position, limit);
+
+        acl::check(&*self.acl, Resource::Categories, Action::Read, self, None)
+            .and_then(|_| {
+                categories
+                    .filter(
+                        updated_at
+                            .gt(position.updated_at)
+                            .or(updated_at.eq(position.updated_at).and(id.gt(CategoryId(position.last_id)))),
+                    )
+                    .order((updated_at, id))
+                    .limit(limit)
+                    .load::<RawCategory>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map(|cats| {
+                let last_position = cats.last().map(|cat| CatalogFeedPosition::new(cat.updated_at, cat.id.0));
+                let (updated, deleted): (Vec<RawCategory>, Vec<RawCategory>) = cats.into_iter().partition(|cat| cat.is_active);
+                CatalogFeedChanges {
+                    updated,
+                    deleted: deleted.into_iter().map(|cat| cat.id).collect(),
+                    last_position,
+                }
+            })
+            .map_err(|e: FailureError| e.context(format!("Find categories updated after {:?} error occurred", position)).into())
+    }
+
     fn get_all_categories(&self) -> RepoResult<Category> {
         if let Some(cat) = self.cache.get() {
             debug!("Get all categories from cache request.");
@@ -488,9 +522,13 @@ where
     fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&Category>) -> bool {
         match *scope {
             Scope::All => true,
-            Scope::Owned => false,
+            Scope::Owned | Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &Category) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
 
 #[cfg(test)]
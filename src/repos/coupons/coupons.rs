@@ -9,10 +9,12 @@ use errors::Error;
 use failure::Error as FailureError;
 
 use stq_types::{CouponCode, CouponId, StoreId, UserId};
+use uuid::Uuid;
 
 use models::*;
 use repos::acl;
 use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
 use repos::types::{RepoAcl, RepoResult};
 use schema::coupons::dsl as Coupons;
 use schema::stores::dsl as Stores;
@@ -21,18 +23,24 @@ use schema::stores::dsl as Stores;
 #[derive(Clone, Debug)]
 pub enum CouponSearch {
     Store(StoreId),
+    Campaign(Uuid),
+    Codes(StoreId, Vec<CouponCode>),
 }
 
 /// Coupons repository, responsible for handling coupon
 pub struct CouponsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<Coupon>>,
+    pub store_roles: StoreRoles,
 }
 
 pub trait CouponsRepo {
     /// Creates new coupon
     fn create(&self, payload: NewCoupon) -> RepoResult<Coupon>;
 
+    /// Creates batch of coupons
+    fn create_batch(&self, payloads: Vec<NewCoupon>) -> RepoResult<Vec<Coupon>>;
+
     /// List all coupons
     fn list(&self) -> RepoResult<Vec<Coupon>>;
 
@@ -54,7 +62,11 @@ pub trait CouponsRepo {
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponsRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<Coupon>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 }
 
@@ -77,6 +89,38 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             .map_err(|e: FailureError| e.context(format!("Creates new coupon: {:?} error occurred", payload)).into())
     }
 
+    /// Creates batch of coupons
+    fn create_batch(&self, payloads: Vec<NewCoupon>) -> RepoResult<Vec<Coupon>> {
+        debug!("Create batch of {} coupons.", payloads.len());
+        let payloads = payloads
+            .into_iter()
+            .map(|mut payload| {
+                payload.code = payload.code.0.to_uppercase().into();
+                payload
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = Vec::with_capacity(payloads.len());
+        // Each chunk is inserted with one query, so it must fit into postgres bind parameters limit
+        for chunk in payloads.chunks(Coupon::BATCH_INSERT_CHUNK_SIZE) {
+            let query = diesel::insert_into(Coupons::coupons).values(chunk);
+            let values = query
+                .get_results::<Coupon>(self.db_conn)
+                .map_err(|e| Error::from(e).into())
+                .and_then(|values: Vec<Coupon>| {
+                    for value in &values {
+                        acl::check(&*self.acl, Resource::Coupons, Action::Create, self, Some(&value))?;
+                    }
+
+                    Ok(values)
+                })
+                .map_err(|e: FailureError| e.context("Creates batch of coupons error occurred"))?;
+            result.extend(values);
+        }
+
+        Ok(result)
+    }
+
     /// List all coupons
     fn list(&self) -> RepoResult<Vec<Coupon>> {
         debug!("Find all coupons.");
@@ -145,6 +189,8 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
 
         let search_exp: Box<BoxableExpression<Coupons::coupons, _, SqlType = Bool>> = match search {
             CouponSearch::Store(value) => Box::new(Coupons::store_id.eq(value)),
+            CouponSearch::Campaign(value) => Box::new(Coupons::campaign_id.eq(value)),
+            CouponSearch::Codes(store_id, codes) => Box::new(Coupons::store_id.eq(store_id).and(Coupons::code.eq_any(codes))),
         };
 
         let query = Coupons::coupons.filter(search_exp);
@@ -224,6 +270,17 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    self.store_roles.is_store_staff(self.db_conn, user_id, value.store_id, role)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &Coupon) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
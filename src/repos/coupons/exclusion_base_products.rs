@@ -0,0 +1,161 @@
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{BaseProductId, CouponId, StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::coupon_exclusion_base_products::dsl as DslCouponExclusion;
+use schema::coupons::dsl as DslCoupons;
+use schema::stores::dsl as DslStores;
+
+/// CouponExclusionBaseProducts repository, responsible for handling coupon_exclusion_base_products table
+pub struct CouponExclusionBaseProductsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<CouponExclusionBaseProducts>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait CouponExclusionBaseProductsRepo {
+    /// Exclude base product from coupon
+    fn create(&self, payload: NewCouponExclusionBaseProducts) -> RepoResult<CouponExclusionBaseProducts>;
+
+    /// Search excluded base_products by coupon id
+    fn find_base_products(&self, id_arg: CouponId) -> RepoResult<Vec<BaseProductId>>;
+
+    /// Delete base product from coupon exclusions
+    fn delete(&self, id_arg: CouponId, base_product_arg: BaseProductId) -> RepoResult<CouponExclusionBaseProducts>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponExclusionBaseProductsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<CouponExclusionBaseProducts>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponExclusionBaseProductsRepo
+    for CouponExclusionBaseProductsRepoImpl<'a, T>
+{
+    /// Exclude base product from coupon
+    fn create(&self, payload: NewCouponExclusionBaseProducts) -> RepoResult<CouponExclusionBaseProducts> {
+        debug!("Add coupon exclusion for base product {:?}.", payload);
+
+        let query = diesel::insert_into(DslCouponExclusion::coupon_exclusion_base_products).values(&payload);
+        query
+            .get_result::<CouponExclusionBaseProducts>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| {
+                acl::check(&*self.acl, Resource::CouponExclusionBaseProducts, Action::Create, self, Some(&value))?;
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Add coupon exclusion for base product: {:?} error occurred", payload))
+                    .into()
+            })
+    }
+
+    /// Search excluded base_products by coupon id
+    fn find_base_products(&self, id_arg: CouponId) -> RepoResult<Vec<BaseProductId>> {
+        debug!("Get excluded base product ids by coupon_id: {}.", id_arg);
+
+        let query = DslCouponExclusion::coupon_exclusion_base_products.filter(DslCouponExclusion::coupon_id.eq(&id_arg));
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values: Vec<CouponExclusionBaseProducts>| {
+                let mut results = vec![];
+
+                for value in &values {
+                    acl::check(&*self.acl, Resource::CouponExclusionBaseProducts, Action::Read, self, Some(&value))?;
+                    results.push(value.base_product_id);
+                }
+
+                Ok(results)
+            })
+            .map_err(|e: FailureError| e.context("Search records coupon exclusion for base products failed.").into())
+    }
+
+    /// Delete base product from coupon exclusions
+    fn delete(&self, id_arg: CouponId, base_product_arg: BaseProductId) -> RepoResult<CouponExclusionBaseProducts> {
+        debug!("Delete record for coupon_id: {} and base_product_id: {}.", id_arg, base_product_arg);
+        let query = DslCouponExclusion::coupon_exclusion_base_products
+            .filter(DslCouponExclusion::coupon_id.eq(&id_arg))
+            .filter(DslCouponExclusion::base_product_id.eq(&base_product_arg));
+
+        query
+            .get_result::<CouponExclusionBaseProducts>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| acl::check(&*self.acl, Resource::CouponExclusionBaseProducts, Action::Delete, self, Some(&value)))
+            .and_then(|_| {
+                let filtered = DslCouponExclusion::coupon_exclusion_base_products
+                    .filter(DslCouponExclusion::coupon_id.eq(&id_arg))
+                    .filter(DslCouponExclusion::base_product_id.eq(&base_product_arg));
+                let query = diesel::delete(filtered);
+
+                query
+                    .get_result::<CouponExclusionBaseProducts>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Delete record coupon exclusion for base product, coupon_id: {} and base_product_id: {} error occurred",
+                    id_arg, base_product_arg
+                ))
+                .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, CouponExclusionBaseProducts>
+    for CouponExclusionBaseProductsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&CouponExclusionBaseProducts>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(value) = obj {
+                    DslCoupons::coupons
+                        .filter(DslCoupons::id.eq(value.coupon_id))
+                        .inner_join(DslStores::stores)
+                        .get_result::<(Coupon, Store)>(self.db_conn)
+                        .map(|(_, s)| s.user_id == user_id)
+                        .ok()
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    DslCoupons::coupons
+                        .find(value.coupon_id)
+                        .select(DslCoupons::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn object_id(&self, obj: &CouponExclusionBaseProducts) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
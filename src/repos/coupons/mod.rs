@@ -1,18 +1,36 @@
 pub mod coupons;
+pub mod exclusion_base_products;
 pub mod scope_base_products;
 pub mod scope_categories;
 pub mod used_coupons;
 
 pub use self::coupons::*;
+pub use self::exclusion_base_products::*;
 pub use self::scope_base_products::*;
 pub use self::scope_categories::*;
 pub use self::used_coupons::*;
 
+use models::CouponDiscount;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum CouponValidate {
     NotActive,
+    NotStarted,
     HasExpired,
     NoActivationsAvailable,
+    /// Single use coupon has already been used by user
     AlreadyActivated,
+    /// User has used coupon `per_user_limit` times
+    PerUserLimitReached,
+    BelowMinimumSubtotal,
+    /// Coupon has minimum subtotal but no subtotal was given to check it against
+    SubtotalRequired,
     Valid,
 }
+
+/// Result of coupon validation along with the discount it gives
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CouponValidateResult {
+    pub status: CouponValidate,
+    pub discount: CouponDiscount,
+}
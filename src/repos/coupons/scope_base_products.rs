@@ -7,11 +7,12 @@ use diesel::Connection;
 use errors::Error;
 use failure::Error as FailureError;
 
-use stq_types::{BaseProductId, CouponId, UserId};
+use stq_types::{BaseProductId, CouponId, StoreId, UserId};
 
 use models::*;
 use repos::acl;
 use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
 use repos::types::{RepoAcl, RepoResult};
 use schema::base_products::dsl as DslBaseProducts;
 use schema::coupon_scope_base_products::dsl as DslCouponScope;
@@ -21,6 +22,7 @@ use schema::stores::dsl as DslStores;
 pub struct CouponScopeBaseProductsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<CouponScopeBaseProducts>>,
+    pub store_roles: StoreRoles,
 }
 
 pub trait CouponScopeBaseProductsRepo {
@@ -36,7 +38,11 @@ pub trait CouponScopeBaseProductsRepo {
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponScopeBaseProductsRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<CouponScopeBaseProducts>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 }
 
@@ -127,6 +133,22 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    DslBaseProducts::base_products
+                        .find(value.base_product_id)
+                        .select(DslBaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &CouponScopeBaseProducts) -> Option<String> {
+        Some(obj.id.to_string())
+    }
 }
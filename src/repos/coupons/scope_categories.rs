@@ -1 +1,161 @@
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
 
+use stq_types::{CategoryId, CouponId, StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::coupon_scope_categories::dsl as DslCouponScope;
+use schema::coupons::dsl as DslCoupons;
+use schema::stores::dsl as DslStores;
+
+/// CouponScopeCategories repository, responsible for handling coupon_scope_categories table
+pub struct CouponScopeCategoriesRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<CouponScopeCategories>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait CouponScopeCategoriesRepo {
+    /// Add category in to coupon
+    fn create(&self, payload: NewCouponScopeCategories) -> RepoResult<CouponScopeCategories>;
+
+    /// Search categories by coupon id
+    fn find_categories(&self, id_arg: CouponId) -> RepoResult<Vec<CategoryId>>;
+
+    /// Delete coupon for scope categories
+    fn delete(&self, id_arg: CouponId, category_arg: CategoryId) -> RepoResult<CouponScopeCategories>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponScopeCategoriesRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<CouponScopeCategories>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CouponScopeCategoriesRepo
+    for CouponScopeCategoriesRepoImpl<'a, T>
+{
+    /// Add category in to coupon
+    fn create(&self, payload: NewCouponScopeCategories) -> RepoResult<CouponScopeCategories> {
+        debug!("Add coupon scope for category {:?}.", payload);
+
+        let query = diesel::insert_into(DslCouponScope::coupon_scope_categories).values(&payload);
+        query
+            .get_result::<CouponScopeCategories>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| {
+                acl::check(&*self.acl, Resource::CouponScopeCategories, Action::Create, self, Some(&value))?;
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Add coupon scope for category: {:?} error occurred", payload))
+                    .into()
+            })
+    }
+
+    /// Search categories by coupon id
+    fn find_categories(&self, id_arg: CouponId) -> RepoResult<Vec<CategoryId>> {
+        debug!("Get category ids by coupon_id: {}.", id_arg);
+
+        let query = DslCouponScope::coupon_scope_categories.filter(DslCouponScope::coupon_id.eq(&id_arg));
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values: Vec<CouponScopeCategories>| {
+                let mut results = vec![];
+
+                for value in &values {
+                    acl::check(&*self.acl, Resource::CouponScopeCategories, Action::Read, self, Some(&value))?;
+                    results.push(value.category_id);
+                }
+
+                Ok(results)
+            })
+            .map_err(|e: FailureError| e.context("Search records coupon scope for categories failed.").into())
+    }
+
+    /// Delete coupon for scope categories
+    fn delete(&self, id_arg: CouponId, category_arg: CategoryId) -> RepoResult<CouponScopeCategories> {
+        debug!("Delete record for coupon_id: {} and category_id: {}.", id_arg, category_arg);
+        let query = DslCouponScope::coupon_scope_categories
+            .filter(DslCouponScope::coupon_id.eq(&id_arg))
+            .filter(DslCouponScope::category_id.eq(&category_arg));
+
+        query
+            .get_result::<CouponScopeCategories>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| acl::check(&*self.acl, Resource::CouponScopeCategories, Action::Delete, self, Some(&value)))
+            .and_then(|_| {
+                let filtered = DslCouponScope::coupon_scope_categories
+                    .filter(DslCouponScope::coupon_id.eq(&id_arg))
+                    .filter(DslCouponScope::category_id.eq(&category_arg));
+                let query = diesel::delete(filtered);
+
+                query
+                    .get_result::<CouponScopeCategories>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Delete record coupon scope for category, coupon_id: {} and category_id: {} error occurred",
+                    id_arg, category_arg
+                ))
+                .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, CouponScopeCategories>
+    for CouponScopeCategoriesRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&CouponScopeCategories>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(value) = obj {
+                    DslCoupons::coupons
+                        .filter(DslCoupons::id.eq(value.coupon_id))
+                        .inner_join(DslStores::stores)
+                        .get_result::<(Coupon, Store)>(self.db_conn)
+                        .map(|(_, s)| s.user_id == user_id)
+                        .ok()
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    DslCoupons::coupons
+                        .find(value.coupon_id)
+                        .select(DslCoupons::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn object_id(&self, obj: &CouponScopeCategories) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
@@ -30,7 +30,7 @@ pub struct UsedCouponsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManage
 }
 
 pub trait UsedCouponsRepo {
-    /// Creates new used coupon
+    /// Creates new used coupon or increments times coupon was used by user
     fn create(&self, payload: NewUsedCoupon) -> RepoResult<UsedCoupon>;
 
     /// List all used coupons
@@ -55,11 +55,15 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> UsedCouponsRepo
     for UsedCouponsRepoImpl<'a, T>
 {
-    /// Creates new used coupon
+    /// Creates new used coupon or increments times coupon was used by user
     fn create(&self, payload: NewUsedCoupon) -> RepoResult<UsedCoupon> {
         debug!("Create new used coupon record {:?}.", payload);
 
-        let query = diesel::insert_into(DslUsedCoupons::used_coupons).values(&payload);
+        let query = diesel::insert_into(DslUsedCoupons::used_coupons)
+            .values(&payload)
+            .on_conflict((DslUsedCoupons::coupon_id, DslUsedCoupons::user_id))
+            .do_update()
+            .set(DslUsedCoupons::times_used.eq(DslUsedCoupons::times_used + 1));
         query
             .get_result::<UsedCoupon>(self.db_conn)
             .map_err(|e| Error::from(e).into())
@@ -171,7 +175,11 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
     fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&UsedCoupon>) -> bool {
         match *scope {
             Scope::All => true,
-            Scope::Owned => false,
+            Scope::Owned | Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &UsedCoupon) -> Option<String> {
+        Some(format!("{}/{}", obj.coupon_id.0, obj.user_id.0))
+    }
 }
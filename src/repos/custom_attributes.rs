@@ -7,12 +7,13 @@ use diesel::Connection;
 use errors::Error;
 use failure::Error as FailureError;
 
-use stq_types::{BaseProductId, CustomAttributeId, UserId};
+use stq_types::{BaseProductId, CustomAttributeId, StoreId, UserId};
 
 use models::authorization::*;
 use models::{BaseProductRaw, CustomAttribute, NewCustomAttribute, Store};
 use repos::acl;
 use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
 use repos::types::{RepoAcl, RepoResult};
 use schema::base_products::dsl as BaseProducts;
 use schema::custom_attributes::dsl::*;
@@ -22,6 +23,7 @@ use schema::stores::dsl as Stores;
 pub struct CustomAttributesRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<CustomAttribute>>,
+    pub store_roles: StoreRoles,
 }
 
 pub trait CustomAttributesRepo {
@@ -46,7 +48,11 @@ pub trait CustomAttributesRepo {
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CustomAttributesRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<CustomAttribute>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 }
 
@@ -206,6 +212,22 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(custom_attribute) = obj {
+                    BaseProducts::base_products
+                        .find(custom_attribute.base_product_id)
+                        .select(BaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &CustomAttribute) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
@@ -0,0 +1,122 @@
+//! Decline reasons repo, presents CRUD operations with db for catalog of decline reasons
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::UserId;
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::types::{RepoAcl, RepoResult};
+use schema::decline_reasons::dsl as DslDeclineReasons;
+
+/// Decline reasons repository, responsible for handling decline_reasons table
+pub struct DeclineReasonsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<DeclineReason>>,
+}
+
+pub trait DeclineReasonsRepo {
+    /// Returns all decline reasons
+    fn list(&self) -> RepoResult<Vec<DeclineReason>>;
+
+    /// Returns decline reasons with `codes`
+    fn find_by_codes(&self, codes: Vec<String>) -> RepoResult<Vec<DeclineReason>>;
+
+    /// Creates new decline reason
+    fn create(&self, payload: NewDeclineReason) -> RepoResult<DeclineReason>;
+
+    /// Updates specific decline reason
+    fn update(&self, id_arg: i32, payload: UpdateDeclineReason) -> RepoResult<DeclineReason>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> DeclineReasonsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<DeclineReason>>) -> Self {
+        Self { db_conn, acl }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> DeclineReasonsRepo
+    for DeclineReasonsRepoImpl<'a, T>
+{
+    /// Returns all decline reasons
+    fn list(&self) -> RepoResult<Vec<DeclineReason>> {
+        debug!("Find all decline reasons.");
+
+        acl::check(&*self.acl, Resource::DeclineReasons, Action::Read, self, None)
+            .and_then(|_| {
+                let query = DslDeclineReasons::decline_reasons.order(DslDeclineReasons::id);
+                query.get_results(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context("Find all decline reasons error occurred").into())
+    }
+
+    /// Returns decline reasons with `codes`
+    fn find_by_codes(&self, codes: Vec<String>) -> RepoResult<Vec<DeclineReason>> {
+        debug!("Find decline reasons by codes {:?}.", codes);
+
+        acl::check(&*self.acl, Resource::DeclineReasons, Action::Read, self, None)
+            .and_then(|_| {
+                let query = DslDeclineReasons::decline_reasons
+                    .filter(DslDeclineReasons::code.eq_any(&codes))
+                    .order(DslDeclineReasons::id);
+                query.get_results(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find decline reasons by codes {:?} error occurred", codes))
+                    .into()
+            })
+    }
+
+    /// Creates new decline reason
+    fn create(&self, payload: NewDeclineReason) -> RepoResult<DeclineReason> {
+        debug!("Create decline reason {:?}.", payload);
+
+        acl::check(&*self.acl, Resource::DeclineReasons, Action::Create, self, None)
+            .and_then(|_| {
+                let query = diesel::insert_into(DslDeclineReasons::decline_reasons).values(&payload);
+                query.get_result::<DeclineReason>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Create decline reason {:?} error occurred", payload)).into())
+    }
+
+    /// Updates specific decline reason
+    fn update(&self, id_arg: i32, payload: UpdateDeclineReason) -> RepoResult<DeclineReason> {
+        debug!("Updating decline reason with id {} and payload {:?}.", id_arg, payload);
+
+        acl::check(&*self.acl, Resource::DeclineReasons, Action::Update, self, None)
+            .and_then(|_| {
+                let filtered = DslDeclineReasons::decline_reasons.filter(DslDeclineReasons::id.eq(id_arg));
+                let query = diesel::update(filtered).set(&payload);
+                query.get_result::<DeclineReason>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Updating decline reason with id {} and payload {:?} error occurred",
+                    id_arg, payload
+                ))
+                .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, DeclineReason>
+    for DeclineReasonsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&DeclineReason>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned | Scope::Staff(_) => false,
+        }
+    }
+
+    fn object_id(&self, obj: &DeclineReason) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
@@ -1,6 +1,7 @@
 //! Repos is a module responsible for interacting with postgres db
 #[macro_use]
 pub mod acl;
+pub mod acl_decisions;
 pub mod attribute_values;
 pub mod attributes;
 pub mod base_products;
@@ -8,17 +9,27 @@ pub mod categories;
 pub mod coupons;
 pub mod currency_exchange;
 pub mod custom_attributes;
+pub mod decline_reasons;
+pub mod moderation_feedback;
+pub mod moderation_queue;
+pub mod moderation_transitions;
 pub mod moderator_product;
 pub mod moderator_store;
+pub mod outbox_events;
 pub mod product_attrs;
+pub mod product_price_history;
 pub mod products;
 pub mod repo_factory;
+pub mod scheduled_price_changes;
+pub mod search_synonyms;
+pub mod store_staff;
 pub mod stores;
 pub mod types;
 pub mod user_roles;
 pub mod wizard_stores;
 
 pub use self::acl::*;
+pub use self::acl_decisions::*;
 pub use self::attribute_values::*;
 pub use self::attributes::*;
 pub use self::base_products::*;
@@ -26,11 +37,20 @@ pub use self::categories::*;
 pub use self::coupons::*;
 pub use self::currency_exchange::*;
 pub use self::custom_attributes::*;
+pub use self::decline_reasons::*;
+pub use self::moderation_feedback::*;
+pub use self::moderation_queue::*;
+pub use self::moderation_transitions::*;
 pub use self::moderator_product::*;
 pub use self::moderator_store::*;
+pub use self::outbox_events::*;
 pub use self::product_attrs::*;
+pub use self::product_price_history::*;
 pub use self::products::*;
 pub use self::repo_factory::*;
+pub use self::scheduled_price_changes::*;
+pub use self::search_synonyms::*;
+pub use self::store_staff::*;
 pub use self::stores::*;
 pub use self::types::*;
 pub use self::user_roles::*;
@@ -0,0 +1,188 @@
+//! Moderation feedback repo, presents operations with db for decline reasons given by moderators
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::dsl::sql;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::sql_types::{BigInt, Bool};
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::UserId;
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::moderation_transitions::is_moderation_entity_in_scope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::moderation_feedback;
+use schema::moderation_feedback::dsl as DslModerationFeedback;
+
+/// Count of declines, feedback items given with the same change of moderation status make up one decline
+const DECLINES_COUNT: &str = "COUNT(DISTINCT transition_id)";
+
+/// Moderation feedback repository, responsible for handling moderation_feedback table
+pub struct ModerationFeedbackRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<ModerationFeedback>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait ModerationFeedbackRepo {
+    /// Returns feedback given with change of moderation status
+    fn list_by_transition(&self, transition_id_arg: i32) -> RepoResult<Vec<ModerationFeedback>>;
+
+    /// Returns count of declines by decline reason for feedback matching filters
+    fn count_declines_by_reason(&self, search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, i64)>>;
+
+    /// Returns count of declines by decline reason and field for feedback matching filters, feedback without field is skipped
+    fn count_declines_by_reason_and_field(&self, search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, String, i64)>>;
+
+    /// Records feedback item
+    fn create(&self, payload: NewModerationFeedback) -> RepoResult<ModerationFeedback>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationFeedbackRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ModerationFeedback>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationFeedbackRepo
+    for ModerationFeedbackRepoImpl<'a, T>
+{
+    /// Returns feedback given with change of moderation status
+    fn list_by_transition(&self, transition_id_arg: i32) -> RepoResult<Vec<ModerationFeedback>> {
+        debug!("Find moderation feedback of transition {}.", transition_id_arg);
+        let query = DslModerationFeedback::moderation_feedback
+            .filter(DslModerationFeedback::transition_id.eq(transition_id_arg))
+            .order_by(DslModerationFeedback::id);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|feedback: Vec<ModerationFeedback>| {
+                for item in &feedback {
+                    acl::check(&*self.acl, Resource::ModerationFeedback, Action::Read, self, Some(item))?;
+                }
+
+                Ok(feedback)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Find moderation feedback of transition {} error occurred",
+                    transition_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Returns count of declines by decline reason for feedback matching filters
+    fn count_declines_by_reason(&self, search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, i64)>> {
+        debug!("Count declines by reason of moderation feedback {:?}.", search);
+        let query = DslModerationFeedback::moderation_feedback
+            .filter(by_search(&search))
+            .group_by(DslModerationFeedback::reason_code)
+            .select((DslModerationFeedback::reason_code, sql::<BigInt>(DECLINES_COUNT)));
+
+        acl::check(&*self.acl, Resource::ModerationFeedback, Action::Read, self, None)
+            .and_then(|_| query.get_results(self.db_conn).map_err(|e| Error::from(e).into()))
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Count declines by reason of moderation feedback {:?} error occurred",
+                    search
+                ))
+                .into()
+            })
+    }
+
+    /// Returns count of declines by decline reason and field for feedback matching filters, feedback without field is skipped
+    fn count_declines_by_reason_and_field(&self, search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, String, i64)>> {
+        debug!("Count declines by reason and field of moderation feedback {:?}.", search);
+        let query = DslModerationFeedback::moderation_feedback
+            .filter(by_search(&search))
+            .filter(DslModerationFeedback::field.is_not_null())
+            .group_by((DslModerationFeedback::reason_code, DslModerationFeedback::field))
+            .select((
+                DslModerationFeedback::reason_code,
+                DslModerationFeedback::field,
+                sql::<BigInt>(DECLINES_COUNT),
+            ));
+
+        acl::check(&*self.acl, Resource::ModerationFeedback, Action::Read, self, None)
+            .and_then(|_| {
+                query
+                    .get_results::<(String, Option<String>, i64)>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map(|counts| {
+                counts
+                    .into_iter()
+                    .filter_map(|(reason_code, field, count)| field.map(|field| (reason_code, field, count)))
+                    .collect()
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Count declines by reason and field of moderation feedback {:?} error occurred",
+                    search
+                ))
+                .into()
+            })
+    }
+
+    /// Records feedback item
+    fn create(&self, payload: NewModerationFeedback) -> RepoResult<ModerationFeedback> {
+        debug!("Create moderation feedback {:?}.", payload);
+        let query = diesel::insert_into(DslModerationFeedback::moderation_feedback).values(&payload);
+
+        query
+            .get_result::<ModerationFeedback>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item| {
+                acl::check(&*self.acl, Resource::ModerationFeedback, Action::Create, self, Some(&item))?;
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| e.context(format!("Create moderation feedback {:?} error occurred", payload)).into())
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ModerationFeedback>
+    for ModerationFeedbackRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&ModerationFeedback>) -> bool {
+        match obj {
+            Some(item) => is_moderation_entity_in_scope(self.db_conn, &self.store_roles, user_id, scope, item.entity_type, item.entity_id),
+            None => *scope == Scope::All,
+        }
+    }
+
+    fn object_id(&self, obj: &ModerationFeedback) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
+
+fn by_search(search: &ModerationFeedbackSearch) -> Box<BoxableExpression<moderation_feedback::table, Pg, SqlType = Bool>> {
+    let mut expr: Box<BoxableExpression<moderation_feedback::table, Pg, SqlType = Bool>> = Box::new(true.into_sql::<Bool>());
+
+    if let Some(entity_type_arg) = search.entity_type {
+        expr = Box::new(expr.and(DslModerationFeedback::entity_type.eq(entity_type_arg)));
+    }
+
+    if let Some(created_from) = search.created_from {
+        expr = Box::new(expr.and(DslModerationFeedback::created_at.ge(created_from)));
+    }
+
+    if let Some(created_to) = search.created_to {
+        expr = Box::new(expr.and(DslModerationFeedback::created_at.lt(created_to)));
+    }
+
+    expr
+}
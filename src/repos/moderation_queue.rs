@@ -0,0 +1,285 @@
+//! Moderation queue repo, presents operations with db for entities waiting for moderator decision
+use std::time::SystemTime;
+
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::UserId;
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::types::{RepoAcl, RepoResult};
+use schema::moderation_queue::dsl as DslModerationQueue;
+
+/// Moderation queue repository, responsible for handling moderation_queue table
+pub struct ModerationQueueRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<ModerationQueueItem>>,
+}
+
+pub trait ModerationQueueRepo {
+    /// Returns queue item by id
+    fn find(&self, id_arg: i32) -> RepoResult<Option<ModerationQueueItem>>;
+
+    /// Returns queue item of the entity
+    fn find_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>>;
+
+    /// Returns queue items matching filters, the longest waiting items go first
+    fn search(&self, search: ModerationQueueSearch) -> RepoResult<Vec<ModerationQueueItem>>;
+
+    /// Puts entity into the queue
+    fn enqueue(&self, payload: NewModerationQueueItem) -> RepoResult<ModerationQueueItem>;
+
+    /// Assigns unassigned queue item or queue item already claimed by the moderator to the moderator,
+    /// returns `None` if the item is claimed by another moderator or does not exist
+    fn claim(&self, id_arg: i32, assignee_id_arg: UserId) -> RepoResult<Option<ModerationQueueItem>>;
+
+    /// Returns queue item back to unassigned ones, `assignee_id_arg` restricts releasing to the moderator's items,
+    /// returns `None` if the item is claimed by another moderator or does not exist
+    fn release(&self, id_arg: i32, assignee_id_arg: Option<UserId>) -> RepoResult<Option<ModerationQueueItem>>;
+
+    /// Removes entity from the queue
+    fn delete_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationQueueRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ModerationQueueItem>>) -> Self {
+        Self { db_conn, acl }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationQueueRepo
+    for ModerationQueueRepoImpl<'a, T>
+{
+    /// Returns queue item by id
+    fn find(&self, id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+        debug!("Find moderation queue item {}.", id_arg);
+        let query = DslModerationQueue::moderation_queue.filter(DslModerationQueue::id.eq(id_arg));
+
+        query
+            .get_result(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item: Option<ModerationQueueItem>| {
+                if let Some(ref item) = item {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Read, self, Some(item))?;
+                }
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| e.context(format!("Find moderation queue item {} error occurred", id_arg)).into())
+    }
+
+    /// Returns queue item of the entity
+    fn find_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+        debug!("Find moderation queue item of {:?} {}.", entity_type_arg, entity_id_arg);
+        let query = DslModerationQueue::moderation_queue
+            .filter(DslModerationQueue::entity_type.eq(entity_type_arg))
+            .filter(DslModerationQueue::entity_id.eq(entity_id_arg));
+
+        query
+            .get_result(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item: Option<ModerationQueueItem>| {
+                if let Some(ref item) = item {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Read, self, Some(item))?;
+                }
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Find moderation queue item of {:?} {} error occurred",
+                    entity_type_arg, entity_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Returns queue items matching filters, the longest waiting items go first
+    fn search(&self, search: ModerationQueueSearch) -> RepoResult<Vec<ModerationQueueItem>> {
+        debug!("Search moderation queue {:?}.", search);
+        let mut query = DslModerationQueue::moderation_queue
+            .order_by((DslModerationQueue::enqueued_at, DslModerationQueue::id))
+            .into_boxed();
+
+        if let Some(entity_type_arg) = search.entity_type {
+            query = query.filter(DslModerationQueue::entity_type.eq(entity_type_arg));
+        }
+
+        if let Some(assignee_id_arg) = search.assignee_id {
+            query = query.filter(DslModerationQueue::assignee_id.eq(assignee_id_arg));
+        }
+
+        if search.unassigned {
+            query = query.filter(DslModerationQueue::assignee_id.is_null());
+        }
+
+        if let Some(needs_review_arg) = search.needs_review {
+            query = query.filter(DslModerationQueue::needs_review.eq(needs_review_arg));
+        }
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|items: Vec<ModerationQueueItem>| {
+                for item in &items {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Read, self, Some(item))?;
+                }
+
+                Ok(items)
+            })
+            .map_err(|e: FailureError| e.context(format!("Search moderation queue {:?} error occurred", search)).into())
+    }
+
+    /// Puts entity into the queue
+    fn enqueue(&self, payload: NewModerationQueueItem) -> RepoResult<ModerationQueueItem> {
+        debug!("Enqueue for moderation {:?}.", payload);
+        let query = diesel::insert_into(DslModerationQueue::moderation_queue).values(&payload);
+
+        query
+            .get_result::<ModerationQueueItem>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item| {
+                acl::check(&*self.acl, Resource::ModerationQueue, Action::Create, self, Some(&item))?;
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| e.context(format!("Enqueue for moderation {:?} error occurred", payload)).into())
+    }
+
+    /// Assigns unassigned queue item or queue item already claimed by the moderator to the moderator,
+    /// returns `None` if the item is claimed by another moderator or does not exist
+    fn claim(&self, id_arg: i32, assignee_id_arg: UserId) -> RepoResult<Option<ModerationQueueItem>> {
+        debug!("Claim moderation queue item {} by {}.", id_arg, assignee_id_arg);
+        let filtered = DslModerationQueue::moderation_queue
+            .filter(DslModerationQueue::id.eq(id_arg))
+            .filter(
+                DslModerationQueue::assignee_id
+                    .is_null()
+                    .or(DslModerationQueue::assignee_id.eq(assignee_id_arg)),
+            );
+        let query = diesel::update(filtered).set((
+            DslModerationQueue::assignee_id.eq(Some(assignee_id_arg)),
+            DslModerationQueue::assigned_at.eq(Some(SystemTime::now())),
+        ));
+
+        query
+            .get_result::<ModerationQueueItem>(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item| {
+                if let Some(ref item) = item {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Update, self, Some(item))?;
+                }
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Claim moderation queue item {} by {} error occurred",
+                    id_arg, assignee_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Returns queue item back to unassigned ones, `assignee_id_arg` restricts releasing to the moderator's items,
+    /// returns `None` if the item is claimed by another moderator or does not exist
+    fn release(&self, id_arg: i32, assignee_id_arg: Option<UserId>) -> RepoResult<Option<ModerationQueueItem>> {
+        debug!("Release moderation queue item {} by {:?}.", id_arg, assignee_id_arg);
+        let filtered = DslModerationQueue::moderation_queue.filter(DslModerationQueue::id.eq(id_arg));
+        let values = (
+            DslModerationQueue::assignee_id.eq(None::<UserId>),
+            DslModerationQueue::assigned_at.eq(None::<SystemTime>),
+        );
+
+        let result = match assignee_id_arg {
+            Some(assignee_id_arg) => {
+                let filtered = filtered.filter(
+                    DslModerationQueue::assignee_id
+                        .is_null()
+                        .or(DslModerationQueue::assignee_id.eq(assignee_id_arg)),
+                );
+                diesel::update(filtered).set(values).get_result::<ModerationQueueItem>(self.db_conn)
+            }
+            None => diesel::update(filtered).set(values).get_result::<ModerationQueueItem>(self.db_conn),
+        };
+
+        result
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item| {
+                if let Some(ref item) = item {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Update, self, Some(item))?;
+                }
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Release moderation queue item {} by {:?} error occurred",
+                    id_arg, assignee_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Removes entity from the queue
+    fn delete_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+        debug!("Remove {:?} {} from moderation queue.", entity_type_arg, entity_id_arg);
+        let filtered = DslModerationQueue::moderation_queue
+            .filter(DslModerationQueue::entity_type.eq(entity_type_arg))
+            .filter(DslModerationQueue::entity_id.eq(entity_id_arg));
+        let query = diesel::delete(filtered);
+
+        query
+            .get_result::<ModerationQueueItem>(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|item| {
+                if let Some(ref item) = item {
+                    acl::check(&*self.acl, Resource::ModerationQueue, Action::Delete, self, Some(item))?;
+                }
+
+                Ok(item)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Remove {:?} {} from moderation queue error occurred",
+                    entity_type_arg, entity_id_arg
+                ))
+                .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ModerationQueueItem>
+    for ModerationQueueRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&ModerationQueueItem>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(item) = obj {
+                    item.assignee_id == Some(user_id)
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(_) => false,
+        }
+    }
+
+    fn object_id(&self, obj: &ModerationQueueItem) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
@@ -0,0 +1,161 @@
+//! Moderation transitions repo, presents operations with db for history of moderation status changes
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{BaseProductId, StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::base_products::dsl as DslBaseProducts;
+use schema::moderation_transitions::dsl as DslModerationTransitions;
+use schema::stores::dsl as DslStores;
+
+/// Moderation transitions repository, responsible for handling moderation_transitions table
+pub struct ModerationTransitionsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<ModerationTransition>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait ModerationTransitionsRepo {
+    /// Returns all changes of moderation status of the entity from the oldest one
+    fn list_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Vec<ModerationTransition>>;
+
+    /// Records change of moderation status
+    fn create(&self, payload: NewModerationTransition) -> RepoResult<ModerationTransition>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationTransitionsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ModerationTransition>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+/// Checks scope of user against the store of entity passing moderation
+pub fn is_moderation_entity_in_scope<T>(
+    db_conn: &T,
+    store_roles: &StoreRoles,
+    user_id: UserId,
+    scope: &Scope,
+    entity_type: ModerationEntityType,
+    entity_id: i32,
+) -> bool
+where
+    T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    match *scope {
+        Scope::All => true,
+        Scope::Owned => moderation_entity_store_id(db_conn, entity_type, entity_id)
+            .and_then(|store_id| {
+                DslStores::stores
+                    .filter(DslStores::id.eq(store_id))
+                    .select(DslStores::user_id)
+                    .get_result::<UserId>(db_conn)
+                    .ok()
+            })
+            .map(|owner_id| owner_id == user_id)
+            .unwrap_or(false),
+        Scope::Staff(role) => moderation_entity_store_id(db_conn, entity_type, entity_id)
+            .map(|store_id| store_roles.is_store_staff(db_conn, user_id, store_id, role))
+            .unwrap_or(false),
+    }
+}
+
+fn moderation_entity_store_id<T>(db_conn: &T, entity_type: ModerationEntityType, entity_id: i32) -> Option<StoreId>
+where
+    T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    match entity_type {
+        ModerationEntityType::Store => Some(StoreId(entity_id)),
+        ModerationEntityType::BaseProduct => DslBaseProducts::base_products
+            .filter(DslBaseProducts::id.eq(BaseProductId(entity_id)))
+            .select(DslBaseProducts::store_id)
+            .get_result::<StoreId>(db_conn)
+            .ok(),
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ModerationTransitionsRepo
+    for ModerationTransitionsRepoImpl<'a, T>
+{
+    /// Returns all changes of moderation status of the entity from the oldest one
+    fn list_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Vec<ModerationTransition>> {
+        debug!("Find moderation transitions of {:?} {}.", entity_type_arg, entity_id_arg);
+        let query = DslModerationTransitions::moderation_transitions
+            .filter(DslModerationTransitions::entity_type.eq(entity_type_arg))
+            .filter(DslModerationTransitions::entity_id.eq(entity_id_arg))
+            .order_by(DslModerationTransitions::id);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|transitions: Vec<ModerationTransition>| {
+                for transition in &transitions {
+                    acl::check(&*self.acl, Resource::ModerationTransitions, Action::Read, self, Some(transition))?;
+                }
+
+                Ok(transitions)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Find moderation transitions of {:?} {} error occurred",
+                    entity_type_arg, entity_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Records change of moderation status
+    fn create(&self, payload: NewModerationTransition) -> RepoResult<ModerationTransition> {
+        debug!("Create moderation transition {:?}.", payload);
+        let query = diesel::insert_into(DslModerationTransitions::moderation_transitions).values(&payload);
+
+        query
+            .get_result::<ModerationTransition>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|transition| {
+                acl::check(&*self.acl, Resource::ModerationTransitions, Action::Create, self, Some(&transition))?;
+
+                Ok(transition)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Create moderation transition {:?} error occurred", payload))
+                    .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ModerationTransition>
+    for ModerationTransitionsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&ModerationTransition>) -> bool {
+        match obj {
+            Some(transition) => is_moderation_entity_in_scope(
+                self.db_conn,
+                &self.store_roles,
+                user_id,
+                scope,
+                transition.entity_type,
+                transition.entity_id,
+            ),
+            None => *scope == Scope::All,
+        }
+    }
+
+    fn object_id(&self, obj: &ModerationTransition) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::SystemTime;
+
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::UserId;
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::types::{RepoAcl, RepoResult};
+use schema::outbox_events::dsl as DslOutbox;
+
+/// OutboxEvents repository, responsible for handling outbox_events table
+pub struct OutboxEventsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<OutboxEvent>>,
+}
+
+pub trait OutboxEventsRepo {
+    /// Writes new change event to outbox
+    fn create(&self, payload: NewOutboxEvent) -> RepoResult<OutboxEvent>;
+
+    /// Returns oldest events not relayed yet, ordered by id
+    fn find_unpublished(&self, limit: i64) -> RepoResult<Vec<OutboxEvent>>;
+
+    /// Marks events as relayed to consumers
+    fn mark_published(&self, ids: Vec<i64>, published_at: SystemTime) -> RepoResult<()>;
+
+    /// Records failed relay attempt of events
+    fn mark_failed(&self, ids: Vec<i64>, error: String) -> RepoResult<()>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> OutboxEventsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<OutboxEvent>>) -> Self {
+        Self { db_conn, acl }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> OutboxEventsRepo
+    for OutboxEventsRepoImpl<'a, T>
+{
+    /// Writes new change event to outbox
+    fn create(&self, payload: NewOutboxEvent) -> RepoResult<OutboxEvent> {
+        debug!(
+            "Create outbox event {:?} of {:?} {}.",
+            payload.event_type, payload.aggregate_type, payload.aggregate_id
+        );
+
+        acl::check(&*self.acl, Resource::OutboxEvents, Action::Create, self, None)
+            .and_then(|_| {
+                let query = diesel::insert_into(DslOutbox::outbox_events).values(&payload);
+                query.get_result::<OutboxEvent>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Create outbox event {:?} of {:?} {} error occurred",
+                    payload.event_type, payload.aggregate_type, payload.aggregate_id
+                ))
+                .into()
+            })
+    }
+
+    /// Returns oldest events not relayed yet, ordered by id
+    fn find_unpublished(&self, limit: i64) -> RepoResult<Vec<OutboxEvent>> {
+        debug!("Find {} unpublished outbox events.", limit);
+
+        acl::check(&*self.acl, Resource::OutboxEvents, Action::Read, self, None)
+            .and_then(|_| {
+                let query = DslOutbox::outbox_events
+                    .filter(DslOutbox::published_at.is_null())
+                    .order(DslOutbox::id)
+                    .limit(limit);
+
+                query.get_results(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Find {} unpublished outbox events error occurred", limit)).into())
+    }
+
+    /// Marks events as relayed to consumers
+    fn mark_published(&self, ids: Vec<i64>, published_at: SystemTime) -> RepoResult<()> {
+        debug!("Mark outbox events {:?} as published.", ids);
+
+        acl::check(&*self.acl, Resource::OutboxEvents, Action::Update, self, None)
+            .and_then(|_| {
+                let filtered = DslOutbox::outbox_events.filter(DslOutbox::id.eq_any(&ids));
+                let query = diesel::update(filtered).set((
+                    DslOutbox::published_at.eq(Some(published_at)),
+                    DslOutbox::attempts.eq(DslOutbox::attempts + 1),
+                    DslOutbox::last_error.eq(None::<String>),
+                ));
+
+                query.execute(self.db_conn).map(|_| ()).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Mark outbox events {:?} as published error occurred", ids)).into())
+    }
+
+    /// Records failed relay attempt of events
+    fn mark_failed(&self, ids: Vec<i64>, error: String) -> RepoResult<()> {
+        debug!("Mark outbox events {:?} as failed with error {}.", ids, error);
+
+        acl::check(&*self.acl, Resource::OutboxEvents, Action::Update, self, None)
+            .and_then(|_| {
+                let filtered = DslOutbox::outbox_events.filter(DslOutbox::id.eq_any(&ids));
+                let query = diesel::update(filtered).set((
+                    DslOutbox::attempts.eq(DslOutbox::attempts + 1),
+                    DslOutbox::last_error.eq(Some(&error)),
+                ));
+
+                query.execute(self.db_conn).map(|_| ()).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Mark outbox events {:?} as failed error occurred", ids)).into())
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, OutboxEvent>
+    for OutboxEventsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&OutboxEvent>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned | Scope::Staff(_) => false,
+        }
+    }
+
+    fn object_id(&self, obj: &OutboxEvent) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
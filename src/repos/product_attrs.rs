@@ -8,13 +8,15 @@ use diesel::Connection;
 use errors::Error;
 use failure::Error as FailureError;
 
-use stq_types::{AttributeId, AttributeValueId, BaseProductId, ProductId, UserId};
+use stq_types::{AttributeId, AttributeValueId, BaseProductId, ProductId, StoreId, UserId};
 
 use super::acl;
 use models::authorization::*;
-use models::{BaseProductRaw, NewProdAttr, ProdAttr, Store, UpdateProdAttr};
+use models::{Attribute, BaseProductRaw, NewProdAttr, ProdAttr, Store, UpdateProdAttr};
 use repos::legacy_acl::*;
+use repos::store_staff::StoreRoles;
 use repos::types::{RepoAcl, RepoResult};
+use schema::attributes::dsl as Attributes;
 use schema::base_products::dsl as BaseProducts;
 use schema::prod_attr_values::dsl::*;
 use schema::stores::dsl as Stores;
@@ -23,6 +25,7 @@ use schema::stores::dsl as Stores;
 pub struct ProductAttrsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<ProdAttr>>,
+    pub store_roles: StoreRoles,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -61,11 +64,17 @@ pub trait ProductAttrsRepo {
 
     /// Delete attribute values by base_product ID
     fn delete_by_base_product_id(&self, base_product_id: BaseProductId) -> RepoResult<()>;
+    /// Find attribute values of products with their attributes
+    fn find_with_attributes_by_products(&self, product_ids: Vec<ProductId>) -> RepoResult<Vec<(ProdAttr, Attribute)>>;
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ProductAttrsRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ProdAttr>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 }
 
@@ -310,6 +319,30 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     .into()
             })
     }
+
+    /// Find attribute values of products with their attributes
+    fn find_with_attributes_by_products(&self, product_ids: Vec<ProductId>) -> RepoResult<Vec<(ProdAttr, Attribute)>> {
+        debug!("Find attribute values of products {:?}.", product_ids);
+
+        let query = prod_attr_values
+            .filter(prod_id.eq_any(product_ids.clone()))
+            .inner_join(Attributes::attributes)
+            .order(id);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|results: Vec<(ProdAttr, Attribute)>| {
+                for &(ref prod_attr, _) in &results {
+                    acl::check(&*self.acl, Resource::ProductAttrs, Action::Read, self, Some(prod_attr))?;
+                }
+                Ok(results)
+            })
+            .map_err(move |e: FailureError| {
+                e.context(format!("Find attribute values of products {:?} error occurred", product_ids))
+                    .into()
+            })
+    }
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ProdAttr>
@@ -331,6 +364,22 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(prod_attr) = obj {
+                    BaseProducts::base_products
+                        .find(prod_attr.base_prod_id)
+                        .select(BaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &ProdAttr) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
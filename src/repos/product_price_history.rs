@@ -0,0 +1,132 @@
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{ProductId, StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::base_products::dsl as DslBaseProducts;
+use schema::product_price_history::dsl as DslPriceHistory;
+use schema::products::dsl as DslProducts;
+use schema::stores::dsl as DslStores;
+
+/// ProductPriceHistory repository, responsible for handling product_price_history table
+pub struct ProductPriceHistoryRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<ProductPriceHistoryRecord>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait ProductPriceHistoryRepo {
+    /// Creates new price history record
+    fn create(&self, payload: NewProductPriceHistoryRecord) -> RepoResult<ProductPriceHistoryRecord>;
+
+    /// Returns price history of product, latest records first
+    fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ProductPriceHistoryRecord>>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ProductPriceHistoryRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ProductPriceHistoryRecord>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ProductPriceHistoryRepo
+    for ProductPriceHistoryRepoImpl<'a, T>
+{
+    /// Creates new price history record
+    fn create(&self, payload: NewProductPriceHistoryRecord) -> RepoResult<ProductPriceHistoryRecord> {
+        debug!("Create product price history record {:?}.", payload);
+
+        let query = diesel::insert_into(DslPriceHistory::product_price_history).values(&payload);
+        query
+            .get_result::<ProductPriceHistoryRecord>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| {
+                acl::check(&*self.acl, Resource::ProductPriceHistory, Action::Create, self, Some(&value))?;
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Create product price history record {:?} error occurred", payload))
+                    .into()
+            })
+    }
+
+    /// Returns price history of product, latest records first
+    fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ProductPriceHistoryRecord>> {
+        debug!("Find price history of product {}.", product_id_arg);
+
+        let query = DslPriceHistory::product_price_history
+            .filter(DslPriceHistory::product_id.eq(product_id_arg))
+            .order((DslPriceHistory::created_at.desc(), DslPriceHistory::id.desc()));
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values: Vec<ProductPriceHistoryRecord>| {
+                for value in &values {
+                    acl::check(&*self.acl, Resource::ProductPriceHistory, Action::Read, self, Some(value))?;
+                }
+
+                Ok(values)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find price history of product {} error occurred", product_id_arg))
+                    .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ProductPriceHistoryRecord>
+    for ProductPriceHistoryRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&ProductPriceHistoryRecord>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(value) = obj {
+                    DslProducts::products
+                        .filter(DslProducts::id.eq(value.product_id))
+                        .inner_join(DslBaseProducts::base_products.inner_join(DslStores::stores))
+                        .get_result::<(RawProduct, (BaseProductRaw, Store))>(self.db_conn)
+                        .map(|(_, (_, s))| s.user_id == user_id)
+                        .ok()
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    DslProducts::products
+                        .find(value.product_id)
+                        .inner_join(DslBaseProducts::base_products)
+                        .select(DslBaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn object_id(&self, obj: &ProductPriceHistoryRecord) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
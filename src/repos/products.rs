@@ -8,11 +8,12 @@ use diesel::Connection;
 use errors::Error;
 use failure::Error as FailureError;
 
-use stq_static_resources::Currency;
-use stq_types::{BaseProductId, ProductId, UserId};
+use stq_static_resources::{Currency, ModerationStatus};
+use stq_types::{BaseProductId, ProductId, StoreId, UserId};
 
-use models::{BaseProductRaw, NewProduct, RawProduct, Store, UpdateProduct};
+use models::{BaseProductRaw, CatalogFeedChanges, CatalogFeedPosition, NewProduct, RawProduct, Store, UpdateProduct, UpdateProductPrices};
 use repos::legacy_acl::*;
+use repos::store_staff::StoreRoles;
 use schema::base_products::dsl as BaseProducts;
 use schema::products::dsl::*;
 use schema::stores::dsl as Stores;
@@ -25,6 +26,7 @@ use repos::types::{RepoAcl, RepoResult};
 pub struct ProductsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<RawProduct>>,
+    pub store_roles: StoreRoles,
 }
 
 #[derive(Debug, Default)]
@@ -51,12 +53,18 @@ pub trait ProductsRepo {
     /// Returns list of products with base ids
     fn find_with_base_ids(&self, base_ids: Vec<BaseProductId>) -> RepoResult<Vec<RawProduct>>;
 
+    /// Returns active products of active base products of the store with any of vendor codes
+    fn find_by_vendor_codes(&self, store_id: StoreId, vendor_codes: Vec<String>) -> RepoResult<Vec<RawProduct>>;
+
     /// Creates new product
     fn create(&self, payload: NewProduct) -> RepoResult<RawProduct>;
 
     /// Updates specific product
     fn update(&self, product_id: ProductId, payload: UpdateProduct) -> RepoResult<RawProduct>;
 
+    /// Sets price, discount and cashback of specific product
+    fn update_prices(&self, product_id: ProductId, payload: UpdateProductPrices) -> RepoResult<RawProduct>;
+
     /// Deactivates specific product
     fn deactivate(&self, product_id: ProductId) -> RepoResult<RawProduct>;
 
@@ -65,11 +73,17 @@ pub trait ProductsRepo {
 
     /// Update currency on all products with base_product_id
     fn update_currency(&self, currency: Currency, base_product_id: BaseProductId) -> RepoResult<usize>;
+    /// Returns page of active products of published base products updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<RawProduct, ProductId>>;
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ProductsRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<RawProduct>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 
     fn execute_query<Ty: Send + 'static, U: LoadQuery<T, Ty> + Send + 'static>(&self, query: U) -> RepoResult<Ty> {
@@ -217,6 +231,37 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             .map_err(|e: FailureError| e.context(format!("Find in products with ids error occurred.")).into())
     }
 
+    /// Returns active products of active base products of the store with any of vendor codes
+    fn find_by_vendor_codes(&self, store_id_arg: StoreId, vendor_codes: Vec<String>) -> RepoResult<Vec<RawProduct>> {
+        debug!("Find in products of store {} with vendor codes {:?}.", store_id_arg, vendor_codes);
+        let store_base_products = BaseProducts::base_products
+            .filter(BaseProducts::store_id.eq(store_id_arg))
+            .filter(BaseProducts::is_active.eq(true))
+            .select(BaseProducts::id);
+        let query = products
+            .filter(base_product_id.eq_any(store_base_products))
+            .filter(vendor_code.eq_any(vendor_codes.clone()))
+            .filter(is_active.eq(true))
+            .order_by(id);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|products_res: Vec<RawProduct>| {
+                for product in &products_res {
+                    acl::check(&*self.acl, Resource::Products, Action::Read, self, Some(&product))?;
+                }
+                Ok(products_res)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Find in products of store {} with vendor codes {:?} error occurred.",
+                    store_id_arg, vendor_codes
+                ))
+                .into()
+            })
+    }
+
     /// Updates specific product
     fn update(&self, product_id_arg: ProductId, payload: UpdateProduct) -> RepoResult<RawProduct> {
         debug!("Updating product with id {} and payload {:?}.", product_id_arg, payload);
@@ -237,6 +282,26 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             })
     }
 
+    /// Sets price, discount and cashback of specific product
+    fn update_prices(&self, product_id_arg: ProductId, payload: UpdateProductPrices) -> RepoResult<RawProduct> {
+        debug!("Setting prices of product with id {} to {:?}.", product_id_arg, payload);
+        self.execute_query(products.find(product_id_arg))
+            .and_then(|product: RawProduct| acl::check(&*self.acl, Resource::Products, Action::Update, self, Some(&product)))
+            .and_then(|_| {
+                let filter = products.filter(id.eq(product_id_arg)).filter(is_active.eq(true));
+
+                let query = diesel::update(filter).set(&payload);
+                query.get_result::<RawProduct>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Setting prices of product with id {} to {:?} error occurred.",
+                    product_id_arg, payload
+                ))
+                .into()
+            })
+    }
+
     /// Deactivates specific product
     fn deactivate(&self, product_id_arg: ProductId) -> RepoResult<RawProduct> {
         debug!("Deactivate product with id {}.", product_id_arg);
@@ -314,6 +379,51 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                 .into()
             })
     }
+
+    /// Returns page of active products of published base products updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<RawProduct, ProductId>> {
+        debug!("Find products updated after {:?}, limit {}.", position, limit);
+
+        let query = products
+            .filter(
+                updated_at
+                    .gt(position.updated_at)
+                    .or(updated_at.eq(position.updated_at).and(id.gt(ProductId(position.last_id)))),
+            )
+            .inner_join(BaseProducts::base_products)
+            .order((updated_at, id))
+            .limit(limit);
+
+        query
+            .get_results::<(RawProduct, BaseProductRaw)>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|products_res| {
+                let last_position = products_res
+                    .last()
+                    .map(|&(ref product, _)| CatalogFeedPosition::new(product.updated_at, product.id.0));
+                let (updated, deleted): (Vec<_>, Vec<_>) = products_res.into_iter().partition(|&(ref product, ref base_product)| {
+                    product.is_active
+                        && base_product.is_active
+                        && base_product.status == ModerationStatus::Published
+                        && base_product.store_status == ModerationStatus::Published
+                });
+                let updated = updated.into_iter().map(|(product, _)| product).collect::<Vec<RawProduct>>();
+
+                for product in &updated {
+                    acl::check(&*self.acl, Resource::Products, Action::Read, self, Some(product))?;
+                }
+
+                Ok(CatalogFeedChanges {
+                    updated,
+                    deleted: deleted.into_iter().map(|(product, _)| product.id).collect(),
+                    last_position,
+                })
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find products updated after {:?} error occurred", position))
+                    .into()
+            })
+    }
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, RawProduct>
@@ -335,6 +445,22 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(product) = obj {
+                    BaseProducts::base_products
+                        .find(product.base_product_id)
+                        .select(BaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &RawProduct) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
@@ -27,7 +27,27 @@ pub trait ReposFactory<C: Connection<Backend = Pg, TransactionManager = AnsiTran
     fn create_user_roles_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<UserRolesRepo + 'a>;
     fn create_coupon_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<CouponsRepo + 'a>;
     fn create_coupon_scope_base_products_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<CouponScopeBaseProductsRepo + 'a>;
+    fn create_coupon_scope_categories_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<CouponScopeCategoriesRepo + 'a>;
+    fn create_coupon_exclusion_base_products_repo<'a>(
+        &self,
+        db_conn: &'a C,
+        user_id: Option<UserId>,
+    ) -> Box<CouponExclusionBaseProductsRepo + 'a>;
     fn create_used_coupons_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<UsedCouponsRepo + 'a>;
+    fn create_product_price_history_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ProductPriceHistoryRepo + 'a>;
+    fn create_scheduled_price_changes_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ScheduledPriceChangesRepo + 'a>;
+    fn create_outbox_events_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<OutboxEventsRepo + 'a>;
+    fn create_search_synonyms_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<SearchSynonymsRepo + 'a>;
+    fn create_store_staff_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<StoreStaffRepo + 'a>;
+    fn create_acl_decisions_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<AclDecisionsRepo + 'a>;
+    fn create_moderation_queue_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationQueueRepo + 'a>;
+    fn create_moderation_queue_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationQueueRepo + 'a>;
+    fn create_moderation_transitions_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationTransitionsRepo + 'a>;
+    fn create_moderation_transitions_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationTransitionsRepo + 'a>;
+    fn create_decline_reasons_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<DeclineReasonsRepo + 'a>;
+    fn create_decline_reasons_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<DeclineReasonsRepo + 'a>;
+    fn create_moderation_feedback_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationFeedbackRepo + 'a>;
+    fn create_moderation_feedback_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationFeedbackRepo + 'a>;
 }
 
 pub struct ReposFactoryImpl<C1, C2, C3>
@@ -39,6 +59,7 @@ where
     roles_cache: Arc<RolesCacheImpl<C1>>,
     category_cache: Arc<CategoryCacheImpl<C2>>,
     attribute_cache: Arc<AttributeCacheImpl<C3>>,
+    acl_policy: Arc<AclPolicy>,
 }
 
 impl<C1, C2, C3> Clone for ReposFactoryImpl<C1, C2, C3>
@@ -52,6 +73,7 @@ where
             roles_cache: self.roles_cache.clone(),
             category_cache: self.category_cache.clone(),
             attribute_cache: self.attribute_cache.clone(),
+            acl_policy: self.acl_policy.clone(),
         }
     }
 }
@@ -62,11 +84,17 @@ where
     C2: CacheSingle<Category> + Send + Sync + 'static,
     C3: Cache<Attribute> + Send + Sync + 'static,
 {
-    pub fn new(roles_cache: RolesCacheImpl<C1>, category_cache: CategoryCacheImpl<C2>, attribute_cache: AttributeCacheImpl<C3>) -> Self {
+    pub fn new(
+        roles_cache: RolesCacheImpl<C1>,
+        category_cache: CategoryCacheImpl<C2>,
+        attribute_cache: AttributeCacheImpl<C3>,
+        acl_policy: AclPolicy,
+    ) -> Self {
         Self {
             roles_cache: Arc::new(roles_cache),
             category_cache: Arc::new(category_cache),
             attribute_cache: Arc::new(attribute_cache),
+            acl_policy: Arc::new(acl_policy),
         }
     }
 
@@ -88,7 +116,7 @@ where
     ) -> Box<RepoAcl<T>> {
         user_id.map_or(Box::new(UnauthorizedAcl::default()) as Box<RepoAcl<T>>, |id| {
             let roles = self.get_roles(id, db_conn);
-            (Box::new(ApplicationAcl::new(roles, id)) as Box<RepoAcl<T>>)
+            (Box::new(ApplicationAcl::with_policy(self.acl_policy.clone(), roles, id)) as Box<RepoAcl<T>>)
         })
     }
 }
@@ -174,22 +202,117 @@ where
         Box::new(CouponScopeBaseProductsRepoImpl::new(db_conn, acl)) as Box<CouponScopeBaseProductsRepo>
     }
 
+    fn create_coupon_scope_categories_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<CouponScopeCategoriesRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(CouponScopeCategoriesRepoImpl::new(db_conn, acl)) as Box<CouponScopeCategoriesRepo>
+    }
+
+    fn create_coupon_exclusion_base_products_repo<'a>(
+        &self,
+        db_conn: &'a C,
+        user_id: Option<UserId>,
+    ) -> Box<CouponExclusionBaseProductsRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(CouponExclusionBaseProductsRepoImpl::new(db_conn, acl)) as Box<CouponExclusionBaseProductsRepo>
+    }
+
     fn create_used_coupons_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<UsedCouponsRepo + 'a> {
         let acl = self.get_acl(db_conn, user_id);
         Box::new(UsedCouponsRepoImpl::new(db_conn, acl)) as Box<UsedCouponsRepo>
     }
+
+    fn create_product_price_history_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ProductPriceHistoryRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(ProductPriceHistoryRepoImpl::new(db_conn, acl)) as Box<ProductPriceHistoryRepo>
+    }
+
+    fn create_scheduled_price_changes_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ScheduledPriceChangesRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(ScheduledPriceChangesRepoImpl::new(db_conn, acl)) as Box<ScheduledPriceChangesRepo>
+    }
+
+    fn create_outbox_events_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<OutboxEventsRepo + 'a> {
+        Box::new(OutboxEventsRepoImpl::new(
+            db_conn,
+            Box::new(SystemACL::default()) as Box<RepoAcl<OutboxEvent>>,
+        )) as Box<OutboxEventsRepo>
+    }
+
+    fn create_search_synonyms_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<SearchSynonymsRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(SearchSynonymsRepoImpl::new(db_conn, acl)) as Box<SearchSynonymsRepo>
+    }
+
+    fn create_store_staff_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<StoreStaffRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(StoreStaffRepoImpl::new(db_conn, acl)) as Box<StoreStaffRepo>
+    }
+
+    fn create_acl_decisions_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<AclDecisionsRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(AclDecisionsRepoImpl::new(db_conn, acl, self.acl_policy.clone())) as Box<AclDecisionsRepo>
+    }
+
+    fn create_moderation_queue_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationQueueRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(ModerationQueueRepoImpl::new(db_conn, acl)) as Box<ModerationQueueRepo>
+    }
+
+    fn create_moderation_queue_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationQueueRepo + 'a> {
+        Box::new(ModerationQueueRepoImpl::new(
+            db_conn,
+            Box::new(SystemACL::default()) as Box<RepoAcl<ModerationQueueItem>>,
+        )) as Box<ModerationQueueRepo>
+    }
+
+    fn create_moderation_transitions_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationTransitionsRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(ModerationTransitionsRepoImpl::new(db_conn, acl)) as Box<ModerationTransitionsRepo>
+    }
+
+    fn create_moderation_transitions_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationTransitionsRepo + 'a> {
+        Box::new(ModerationTransitionsRepoImpl::new(
+            db_conn,
+            Box::new(SystemACL::default()) as Box<RepoAcl<ModerationTransition>>,
+        )) as Box<ModerationTransitionsRepo>
+    }
+
+    fn create_decline_reasons_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<DeclineReasonsRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(DeclineReasonsRepoImpl::new(db_conn, acl)) as Box<DeclineReasonsRepo>
+    }
+
+    fn create_decline_reasons_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<DeclineReasonsRepo + 'a> {
+        Box::new(DeclineReasonsRepoImpl::new(
+            db_conn,
+            Box::new(SystemACL::default()) as Box<RepoAcl<DeclineReason>>,
+        )) as Box<DeclineReasonsRepo>
+    }
+
+    fn create_moderation_feedback_repo<'a>(&self, db_conn: &'a C, user_id: Option<UserId>) -> Box<ModerationFeedbackRepo + 'a> {
+        let acl = self.get_acl(db_conn, user_id);
+        Box::new(ModerationFeedbackRepoImpl::new(db_conn, acl)) as Box<ModerationFeedbackRepo>
+    }
+
+    fn create_moderation_feedback_repo_with_sys_acl<'a>(&self, db_conn: &'a C) -> Box<ModerationFeedbackRepo + 'a> {
+        Box::new(ModerationFeedbackRepoImpl::new(
+            db_conn,
+            Box::new(SystemACL::default()) as Box<RepoAcl<ModerationFeedback>>,
+        )) as Box<ModerationFeedbackRepo>
+    }
 }
 
 #[cfg(test)]
 pub mod tests {
 
     use errors::Error as MyError;
+    use std::cell::RefCell;
     use std::collections::HashMap;
     use std::collections::HashSet;
     use std::error::Error;
     use std::fmt;
     use std::sync::Arc;
-    use std::time::SystemTime;
+    use std::time::{Duration, SystemTime};
 
     use diesel::connection::AnsiTransactionManager;
     use diesel::connection::SimpleConnection;
@@ -217,6 +340,7 @@ pub mod tests {
     use config::Config;
     use controller::context::*;
     use models::*;
+    use repos::legacy_acl::CheckScope;
     use repos::*;
     use services::*;
 
@@ -231,8 +355,21 @@ pub mod tests {
     pub static MOCK_BASE_PRODUCT_NAME_JSON: &'static str = r##"[{"lang": "en","text": "base product"}]"##;
 
     pub static MOCK_COUPON_ID: CouponId = CouponId(1);
+    pub static MOCK_CATEGORIES_COUPON_ID: CouponId = CouponId(2);
+    pub static MOCK_STORE_COUPON_ID: CouponId = CouponId(3);
     pub static MOCK_STORE_ID: StoreId = StoreId(1);
     pub static MOCK_COUPON_CODE: &'static str = "ASD";
+    pub static MOCK_SCHEDULED_PRICE_CHANGE_ID: i32 = 1;
+    /// Moderation queue item claimed by `MOCK_MODERATOR_ID`
+    pub static MOCK_CLAIMED_QUEUE_ITEM_ID: i32 = 2;
+    pub static MOCK_MODERATOR_ID: UserId = UserId(2);
+    /// Base product in `Draft` status, others are published
+    pub static MOCK_DRAFT_BASE_PRODUCT_ID: BaseProductId = BaseProductId(3);
+    /// Base product excluded from all coupons
+    pub static MOCK_EXCLUDED_BASE_PRODUCT_ID: BaseProductId = BaseProductId(4);
+    /// Base product of `MOCK_STORE_ID` in other category than the rest of its base products
+    pub static MOCK_OTHER_CATEGORY_BASE_PRODUCT_ID: BaseProductId = BaseProductId(5);
+    pub static MOCK_STAFF_USER_ID: UserId = UserId(2);
 
     pub fn create_service(
         user_id: Option<UserId>,
@@ -313,9 +450,85 @@ pub mod tests {
             Box::new(CouponScopeBaseProductsRepoMock::default()) as Box<CouponScopeBaseProductsRepo>
         }
 
+        fn create_coupon_scope_categories_repo<'a>(
+            &self,
+            _db_conn: &'a C,
+            _user_id: Option<UserId>,
+        ) -> Box<CouponScopeCategoriesRepo + 'a> {
+            Box::new(CouponScopeCategoriesRepoMock::default()) as Box<CouponScopeCategoriesRepo>
+        }
+
+        fn create_coupon_exclusion_base_products_repo<'a>(
+            &self,
+            _db_conn: &'a C,
+            _user_id: Option<UserId>,
+        ) -> Box<CouponExclusionBaseProductsRepo + 'a> {
+            Box::new(CouponExclusionBaseProductsRepoMock::default()) as Box<CouponExclusionBaseProductsRepo>
+        }
+
         fn create_used_coupons_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<UsedCouponsRepo + 'a> {
             Box::new(UsedCouponsRepoMock::default()) as Box<UsedCouponsRepo>
         }
+
+        fn create_product_price_history_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<ProductPriceHistoryRepo + 'a> {
+            Box::new(ProductPriceHistoryRepoMock::default()) as Box<ProductPriceHistoryRepo>
+        }
+
+        fn create_scheduled_price_changes_repo<'a>(
+            &self,
+            _db_conn: &'a C,
+            _user_id: Option<UserId>,
+        ) -> Box<ScheduledPriceChangesRepo + 'a> {
+            Box::new(ScheduledPriceChangesRepoMock::default()) as Box<ScheduledPriceChangesRepo>
+        }
+
+        fn create_outbox_events_repo_with_sys_acl<'a>(&self, _db_conn: &'a C) -> Box<OutboxEventsRepo + 'a> {
+            Box::new(OutboxEventsRepoMock::default()) as Box<OutboxEventsRepo>
+        }
+
+        fn create_search_synonyms_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<SearchSynonymsRepo + 'a> {
+            Box::new(SearchSynonymsRepoMock::default()) as Box<SearchSynonymsRepo>
+        }
+
+        fn create_store_staff_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<StoreStaffRepo + 'a> {
+            Box::new(StoreStaffRepoMock::default()) as Box<StoreStaffRepo>
+        }
+
+        fn create_acl_decisions_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<AclDecisionsRepo + 'a> {
+            Box::new(AclDecisionsRepoMock::default()) as Box<AclDecisionsRepo>
+        }
+
+        fn create_moderation_queue_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<ModerationQueueRepo + 'a> {
+            Box::new(ModerationQueueRepoMock::default()) as Box<ModerationQueueRepo>
+        }
+
+        fn create_moderation_queue_repo_with_sys_acl<'a>(&self, _db_conn: &'a C) -> Box<ModerationQueueRepo + 'a> {
+            Box::new(ModerationQueueRepoMock::default()) as Box<ModerationQueueRepo>
+        }
+
+        fn create_moderation_transitions_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<ModerationTransitionsRepo + 'a> {
+            Box::new(ModerationTransitionsRepoMock::default()) as Box<ModerationTransitionsRepo>
+        }
+
+        fn create_moderation_transitions_repo_with_sys_acl<'a>(&self, _db_conn: &'a C) -> Box<ModerationTransitionsRepo + 'a> {
+            Box::new(ModerationTransitionsRepoMock::default()) as Box<ModerationTransitionsRepo>
+        }
+
+        fn create_decline_reasons_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<DeclineReasonsRepo + 'a> {
+            Box::new(DeclineReasonsRepoMock::default()) as Box<DeclineReasonsRepo>
+        }
+
+        fn create_decline_reasons_repo_with_sys_acl<'a>(&self, _db_conn: &'a C) -> Box<DeclineReasonsRepo + 'a> {
+            Box::new(DeclineReasonsRepoMock::default()) as Box<DeclineReasonsRepo>
+        }
+
+        fn create_moderation_feedback_repo<'a>(&self, _db_conn: &'a C, _user_id: Option<UserId>) -> Box<ModerationFeedbackRepo + 'a> {
+            Box::new(ModerationFeedbackRepoMock::default()) as Box<ModerationFeedbackRepo>
+        }
+
+        fn create_moderation_feedback_repo_with_sys_acl<'a>(&self, _db_conn: &'a C) -> Box<ModerationFeedbackRepo + 'a> {
+            Box::new(ModerationFeedbackRepoMock::default()) as Box<ModerationFeedbackRepo>
+        }
     }
 
     #[derive(Clone, Default)]
@@ -488,9 +701,21 @@ pub mod tests {
                 is_active: true,
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: payload.discount_type,
+                amount: payload.amount,
+                currency: payload.currency,
+                per_user_limit: payload.per_user_limit.unwrap_or(Coupon::DEFAULT_PER_USER_LIMIT),
+                min_subtotal: payload.min_subtotal,
+                active_from: payload.active_from,
+                campaign_id: payload.campaign_id,
             })
         }
 
+        /// Creates batch of coupons
+        fn create_batch(&self, payloads: Vec<NewCoupon>) -> RepoResult<Vec<Coupon>> {
+            payloads.into_iter().map(|payload| self.create(payload)).collect()
+        }
+
         /// List all coupons
         fn list(&self) -> RepoResult<Vec<Coupon>> {
             Ok(vec![Coupon {
@@ -505,23 +730,45 @@ pub mod tests {
                 is_active: true,
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: CouponType::Percent,
+                amount: None,
+                currency: None,
+                per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                min_subtotal: None,
+                active_from: None,
+                campaign_id: None,
             }])
         }
 
         /// Get coupon
         fn get(&self, id_arg: CouponId) -> RepoResult<Option<Coupon>> {
+            let scope = if id_arg == MOCK_CATEGORIES_COUPON_ID {
+                CouponScope::Categories
+            } else if id_arg == MOCK_STORE_COUPON_ID {
+                CouponScope::Store
+            } else {
+                CouponScope::BaseProducts
+            };
+
             Ok(Some(Coupon {
                 id: id_arg,
                 code: CouponCode("COUPONCODE".to_string()),
                 title: "Coupon title".to_string(),
                 store_id: MOCK_STORE_ID,
-                scope: CouponScope::BaseProducts,
+                scope,
                 percent: 30,
                 quantity: 0,
                 expired_at: None,
                 is_active: true,
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: CouponType::Percent,
+                amount: None,
+                currency: None,
+                per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                min_subtotal: None,
+                active_from: None,
+                campaign_id: None,
             }))
         }
 
@@ -539,6 +786,13 @@ pub mod tests {
                 is_active: true,
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: CouponType::Percent,
+                amount: None,
+                currency: None,
+                per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                min_subtotal: None,
+                active_from: None,
+                campaign_id: None,
             }))
         }
 
@@ -557,7 +811,35 @@ pub mod tests {
                     is_active: true,
                     created_at: SystemTime::now(),
                     updated_at: SystemTime::now(),
+                    discount_type: CouponType::Percent,
+                    amount: None,
+                    currency: None,
+                    per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                    min_subtotal: None,
+                    active_from: None,
+                    campaign_id: None,
+                }]),
+                CouponSearch::Campaign(campaign_id) => Ok(vec![Coupon {
+                    id: MOCK_COUPON_ID,
+                    code: CouponCode(MOCK_COUPON_CODE.to_string()),
+                    title: "title".to_string(),
+                    store_id: MOCK_STORE_ID,
+                    scope: CouponScope::BaseProducts,
+                    percent: 0,
+                    quantity: 1,
+                    expired_at: None,
+                    is_active: true,
+                    created_at: SystemTime::now(),
+                    updated_at: SystemTime::now(),
+                    discount_type: CouponType::Percent,
+                    amount: None,
+                    currency: None,
+                    per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                    min_subtotal: None,
+                    active_from: None,
+                    campaign_id: Some(campaign_id),
                 }]),
+                CouponSearch::Codes(_, _) => Ok(vec![]),
             }
         }
 
@@ -575,6 +857,13 @@ pub mod tests {
                 is_active: payload.is_active.unwrap_or_default(),
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: CouponType::Percent,
+                amount: payload.amount,
+                currency: payload.currency,
+                per_user_limit: payload.per_user_limit.unwrap_or(Coupon::DEFAULT_PER_USER_LIMIT),
+                min_subtotal: payload.min_subtotal,
+                active_from: payload.active_from,
+                campaign_id: None,
             })
         }
 
@@ -592,6 +881,13 @@ pub mod tests {
                 is_active: true,
                 created_at: SystemTime::now(),
                 updated_at: SystemTime::now(),
+                discount_type: CouponType::Percent,
+                amount: None,
+                currency: None,
+                per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+                min_subtotal: None,
+                active_from: None,
+                campaign_id: None,
             })
         }
     }
@@ -611,7 +907,7 @@ pub mod tests {
 
         /// Search base_products by coupon id
         fn find_base_products(&self, _id_arg: CouponId) -> RepoResult<Vec<BaseProductId>> {
-            Ok(vec![MOCK_BASE_PRODUCT_ID])
+            Ok(vec![MOCK_BASE_PRODUCT_ID, MOCK_EXCLUDED_BASE_PRODUCT_ID])
         }
 
         /// Delete coupon for scope base products
@@ -624,6 +920,62 @@ pub mod tests {
         }
     }
 
+    #[derive(Clone, Default)]
+    pub struct CouponScopeCategoriesRepoMock;
+
+    impl CouponScopeCategoriesRepo for CouponScopeCategoriesRepoMock {
+        /// Add category in to coupon
+        fn create(&self, payload: NewCouponScopeCategories) -> RepoResult<CouponScopeCategories> {
+            Ok(CouponScopeCategories {
+                id: 0,
+                coupon_id: payload.coupon_id,
+                category_id: payload.category_id,
+            })
+        }
+
+        /// Search categories by coupon id
+        fn find_categories(&self, _id_arg: CouponId) -> RepoResult<Vec<CategoryId>> {
+            Ok(vec![CategoryId(1)])
+        }
+
+        /// Delete coupon for scope categories
+        fn delete(&self, id_arg: CouponId, category_arg: CategoryId) -> RepoResult<CouponScopeCategories> {
+            Ok(CouponScopeCategories {
+                id: 0,
+                coupon_id: id_arg,
+                category_id: category_arg,
+            })
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct CouponExclusionBaseProductsRepoMock;
+
+    impl CouponExclusionBaseProductsRepo for CouponExclusionBaseProductsRepoMock {
+        /// Exclude base product from coupon
+        fn create(&self, payload: NewCouponExclusionBaseProducts) -> RepoResult<CouponExclusionBaseProducts> {
+            Ok(CouponExclusionBaseProducts {
+                id: 0,
+                coupon_id: payload.coupon_id,
+                base_product_id: payload.base_product_id,
+            })
+        }
+
+        /// Search excluded base_products by coupon id
+        fn find_base_products(&self, _id_arg: CouponId) -> RepoResult<Vec<BaseProductId>> {
+            Ok(vec![MOCK_EXCLUDED_BASE_PRODUCT_ID])
+        }
+
+        /// Delete base product from coupon exclusions
+        fn delete(&self, id_arg: CouponId, base_product_arg: BaseProductId) -> RepoResult<CouponExclusionBaseProducts> {
+            Ok(CouponExclusionBaseProducts {
+                id: 0,
+                coupon_id: id_arg,
+                base_product_id: base_product_arg,
+            })
+        }
+    }
+
     #[derive(Clone, Default)]
     pub struct UsedCouponsRepoMock;
 
@@ -632,6 +984,7 @@ pub mod tests {
             Ok(UsedCoupon {
                 coupon_id: payload.coupon_id,
                 user_id: payload.user_id,
+                times_used: 1,
             })
         }
 
@@ -639,6 +992,7 @@ pub mod tests {
             Ok(vec![UsedCoupon {
                 coupon_id: MOCK_COUPON_ID,
                 user_id: MOCK_USER_ID,
+                times_used: 1,
             }])
         }
 
@@ -647,10 +1001,12 @@ pub mod tests {
                 UsedCouponSearch::Coupon(coupon_id) => UsedCoupon {
                     coupon_id,
                     user_id: MOCK_USER_ID,
+                    times_used: 1,
                 },
                 UsedCouponSearch::User(user_id) => UsedCoupon {
                     coupon_id: MOCK_COUPON_ID,
                     user_id,
+                    times_used: 1,
                 },
             };
 
@@ -665,6 +1021,7 @@ pub mod tests {
             Ok(UsedCoupon {
                 coupon_id: id_arg,
                 user_id: user_id_arg,
+                times_used: 1,
             })
         }
     }
@@ -750,6 +1107,17 @@ pub mod tests {
         fn get_raw_categories(&self) -> RepoResult<Vec<RawCategory>> {
             Ok(create_raw_mock_categories())
         }
+
+        fn find_updated_since(
+            &self,
+            _position: CatalogFeedPosition,
+            _limit: i64,
+        ) -> RepoResult<CatalogFeedChanges<RawCategory, CategoryId>> {
+            Ok(CatalogFeedChanges {
+                updated: create_raw_mock_categories(),
+                ..Default::default()
+            })
+        }
     }
 
     fn create_mock_categories() -> Category {
@@ -810,6 +1178,7 @@ pub mod tests {
                 meta_field: None,
                 uuid: uuid::Uuid::new_v4(),
                 slug: CategorySlug("1".to_string()),
+                updated_at: SystemTime::now(),
             },
             RawCategory {
                 id: CategoryId(2),
@@ -820,6 +1189,7 @@ pub mod tests {
                 meta_field: None,
                 uuid: uuid::Uuid::new_v4(),
                 slug: CategorySlug("2".to_string()),
+                updated_at: SystemTime::now(),
             },
             RawCategory {
                 id: CategoryId(3),
@@ -830,6 +1200,7 @@ pub mod tests {
                 meta_field: None,
                 uuid: uuid::Uuid::new_v4(),
                 slug: CategorySlug("3".to_string()),
+                updated_at: SystemTime::now(),
             },
         ]
     }
@@ -1072,32 +1443,11 @@ pub mod tests {
 
         /// Find specific base_product by ID
         fn find(&self, base_product_id: BaseProductId, _visibility: Visibility) -> RepoResult<Option<BaseProduct>> {
-            Ok(Some(BaseProduct {
-                id: base_product_id,
-                is_active: true,
-                store_id: MOCK_STORE_ID,
-                name: serde_json::from_str("{}").unwrap(),
-                short_description: serde_json::from_str("{}").unwrap(),
-                long_description: None,
-                seo_title: None,
-                seo_description: None,
-                currency: Currency::STQ,
-                category_id: CategoryId(1),
-                views: 1,
-                created_at: SystemTime::now(),
-                updated_at: SystemTime::now(),
-                rating: 0f64,
-                slug: BaseProductSlug("slug".to_string()),
-                status: ModerationStatus::Published,
-                kafka_update_no: 0,
-                uuid: uuid::Uuid::new_v4(),
-                length_cm: Some(60),
-                width_cm: Some(40),
-                height_cm: Some(20),
-                volume_cubic_cm: Some(48000),
-                weight_g: Some(100),
-                store_status: ModerationStatus::Published,
-            }))
+            let mut base_product = create_base_product(base_product_id);
+            if base_product_id == MOCK_DRAFT_BASE_PRODUCT_ID {
+                base_product.status = ModerationStatus::Draft;
+            }
+            Ok(Some(base_product))
         }
 
         fn find_many(&self, base_product_ids: Vec<BaseProductId>) -> RepoResult<Vec<BaseProduct>> {
@@ -1137,9 +1487,34 @@ pub mod tests {
             Ok(result)
         }
 
-        /// Search many products by search terms
-        fn search(&self, _search_terms: BaseProductsSearchTerms) -> RepoResult<Vec<BaseProduct>> {
-            Ok(vec![])
+        /// Search many products by search terms, only base products of `MOCK_STORE_ID` are found
+        fn search(&self, search_terms: BaseProductsSearchTerms) -> RepoResult<Vec<BaseProduct>> {
+            if search_terms.store_id != Some(MOCK_STORE_ID) {
+                return Ok(vec![]);
+            }
+
+            let base_product_ids = vec![
+                MOCK_BASE_PRODUCT_ID,
+                MOCK_EXCLUDED_BASE_PRODUCT_ID,
+                MOCK_OTHER_CATEGORY_BASE_PRODUCT_ID,
+            ];
+            let base_products = base_product_ids
+                .into_iter()
+                .map(|id| {
+                    let mut base_product = create_base_product(id);
+                    if id == MOCK_OTHER_CATEGORY_BASE_PRODUCT_ID {
+                        base_product.category_id = CategoryId(2);
+                    }
+                    base_product
+                })
+                .filter(|base_product| {
+                    search_terms
+                        .category_ids
+                        .as_ref()
+                        .map_or(true, |category_ids| category_ids.contains(&base_product.category_id))
+                })
+                .collect();
+            Ok(base_products)
         }
 
         fn find_by_filters(
@@ -1451,8 +1826,8 @@ pub mod tests {
             })
         }
 
-        fn update_moderation_status_by_store(&self, _store_id_arg: StoreId, _status_arg: ModerationStatus) -> RepoResult<Vec<BaseProduct>> {
-            Ok(vec![])
+        fn can_moderate(&self, _base_product: &BaseProduct) -> RepoResult<bool> {
+            Ok(true)
         }
 
         fn update_service_fields(
@@ -1470,6 +1845,74 @@ pub mod tests {
         fn get_all_catalog(&self) -> RepoResult<Vec<CatalogWithAttributes>> {
             Ok(vec![])
         }
+
+        /// Store catalog consists of readable `MOCK_BASE_PRODUCT_ID` on the first page
+        /// and a base product the user can not read on the second one
+        fn get_catalog_of_the_store(&self, store_id: StoreId, from: BaseProductId, _count: i64) -> RepoResult<StoreCatalogChunk> {
+            let unreadable_base_product_id = BaseProductId(MOCK_BASE_PRODUCT_ID.0 + 1);
+            if from.0 > unreadable_base_product_id.0 {
+                return Ok(StoreCatalogChunk::default());
+            }
+
+            if from.0 == unreadable_base_product_id.0 {
+                return Ok(StoreCatalogChunk {
+                    catalog: vec![],
+                    next_from: Some(BaseProductId(unreadable_base_product_id.0 + 1)),
+                });
+            }
+
+            let mut catalog = self.get_active_catalog(from, 1)?;
+            for item in &mut catalog {
+                item.base_product.store_id = store_id;
+            }
+
+            Ok(StoreCatalogChunk {
+                catalog,
+                next_from: Some(unreadable_base_product_id),
+            })
+        }
+
+        fn get_active_catalog(&self, from: BaseProductId, _count: i64) -> RepoResult<Vec<CatalogWithAttributes>> {
+            if from.0 > MOCK_BASE_PRODUCT_ID.0 {
+                return Ok(vec![]);
+            }
+
+            let mut base_product = self.find(MOCK_BASE_PRODUCT_ID, Visibility::Active)?.unwrap();
+            base_product.store_id = MOCK_STORE_ID;
+            base_product.name = serde_json::from_str(MOCK_BASE_PRODUCT_NAME_JSON).unwrap();
+            base_product.short_description = serde_json::from_str(MOCK_BASE_PRODUCT_NAME_JSON).unwrap();
+            let mut product = create_product(MOCK_PRODUCT_ID, MOCK_BASE_PRODUCT_ID);
+            product.price = ProductPrice(10f64);
+            let variant = ProductWithAttributes::new(product, vec![]);
+
+            Ok(vec![CatalogWithAttributes::new(base_product, vec![variant])])
+        }
+
+        fn get_active_catalog_by_ids(&self, ids: Vec<BaseProductId>) -> RepoResult<Vec<CatalogWithAttributes>> {
+            if ids.contains(&MOCK_BASE_PRODUCT_ID) {
+                self.get_active_catalog(MOCK_BASE_PRODUCT_ID, 1)
+            } else {
+                Ok(vec![])
+            }
+        }
+
+        fn find_ids_updated_since(&self, _since: SystemTime) -> RepoResult<Vec<BaseProductId>> {
+            Ok(vec![MOCK_BASE_PRODUCT_ID])
+        }
+
+        fn find_updated_since(
+            &self,
+            _position: CatalogFeedPosition,
+            _limit: i64,
+        ) -> RepoResult<CatalogFeedChanges<BaseProduct, BaseProductId>> {
+            let base_product = self.find(MOCK_BASE_PRODUCT_ID, Visibility::Published)?.unwrap();
+
+            Ok(CatalogFeedChanges {
+                last_position: Some(CatalogFeedPosition::new(base_product.updated_at, MOCK_BASE_PRODUCT_ID.0 + 1)),
+                updated: vec![base_product],
+                deleted: vec![BaseProductId(MOCK_BASE_PRODUCT_ID.0 + 1)],
+            })
+        }
     }
 
     #[derive(Clone, Default)]
@@ -1573,6 +2016,22 @@ pub mod tests {
         fn delete_by_base_product_id(&self, _base_product_id: BaseProductId) -> RepoResult<()> {
             Ok(())
         }
+
+        fn find_with_attributes_by_products(&self, product_ids: Vec<ProductId>) -> RepoResult<Vec<(ProdAttr, Attribute)>> {
+            let mut results = vec![];
+            for product_id in product_ids {
+                let prod_attr = self.find_all_attributes(product_id)?.remove(0);
+                let attribute = Attribute {
+                    id: prod_attr.attr_id,
+                    name: serde_json::from_str("{}").unwrap(),
+                    value_type: AttributeType::Str,
+                    meta_field: None,
+                    uuid: uuid::Uuid::new_v4(),
+                };
+                results.push((prod_attr, attribute));
+            }
+            Ok(results)
+        }
     }
 
     #[derive(Clone, Default)]
@@ -1723,6 +2182,24 @@ pub mod tests {
             Ok(())
         }
 
+        fn find_updated_since(&self, _position: CatalogFeedPosition, _limit: i64) -> RepoResult<CatalogFeedChanges<Store, StoreId>> {
+            Ok(CatalogFeedChanges {
+                updated: vec![create_store(MOCK_STORE_ID, serde_json::from_str(MOCK_STORE_NAME_JSON).unwrap())],
+                ..Default::default()
+            })
+        }
+
+        fn list_updated_since(&self, _since: SystemTime, from: StoreId, _count: i64) -> RepoResult<Vec<Store>> {
+            if from.0 > MOCK_STORE_ID.0 {
+                return Ok(vec![]);
+            }
+
+            Ok(vec![create_store(
+                MOCK_STORE_ID,
+                serde_json::from_str(MOCK_STORE_NAME_JSON).unwrap(),
+            )])
+        }
+
         fn moderator_search(
             &self,
             pagination_params: PaginationParams<StoreId>,
@@ -1744,9 +2221,13 @@ pub mod tests {
             let store = create_store(store_id_arg, serde_json::from_str(MOCK_STORE_NAME_JSON).unwrap());
             Ok(store)
         }
+
+        fn can_moderate(&self, _store: &Store) -> RepoResult<bool> {
+            Ok(true)
+        }
     }
 
-    fn create_store(id: StoreId, name: serde_json::Value) -> Store {
+    pub fn create_store(id: StoreId, name: serde_json::Value) -> Store {
         Store {
             id,
             user_id: UserId(1),
@@ -1781,6 +2262,8 @@ pub mod tests {
             saga_id: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
             kafka_update_no: 0,
             uuid: uuid::Uuid::new_v4(),
         }
@@ -1814,6 +2297,8 @@ pub mod tests {
             saga_id: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
             uuid: uuid::Uuid::new_v4(),
         }
     }
@@ -1844,6 +2329,8 @@ pub mod tests {
             route: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
         }
     }
 
@@ -1877,6 +2364,10 @@ pub mod tests {
             Ok(products)
         }
 
+        fn find_by_vendor_codes(&self, _store_id: StoreId, _vendor_codes: Vec<String>) -> RepoResult<Vec<RawProduct>> {
+            Ok(vec![])
+        }
+
         fn list(&self, from: i32, count: i32) -> RepoResult<Vec<RawProduct>> {
             let mut products = vec![];
             for i in from..(from + count) {
@@ -1900,6 +2391,15 @@ pub mod tests {
             Ok(product)
         }
 
+        fn update_prices(&self, product_id: ProductId, payload: UpdateProductPrices) -> RepoResult<RawProduct> {
+            let mut product = create_product(product_id, MOCK_BASE_PRODUCT_ID);
+            product.price = payload.price;
+            product.discount = payload.discount;
+            product.cashback = payload.cashback;
+
+            Ok(product)
+        }
+
         fn deactivate(&self, product_id: ProductId) -> RepoResult<RawProduct> {
             let mut product = create_product(product_id, MOCK_BASE_PRODUCT_ID);
             product.is_active = false;
@@ -1924,6 +2424,472 @@ pub mod tests {
             }
             Ok(products)
         }
+
+        fn find_updated_since(&self, _position: CatalogFeedPosition, _limit: i64) -> RepoResult<CatalogFeedChanges<RawProduct, ProductId>> {
+            let product = create_product(MOCK_PRODUCT_ID, MOCK_BASE_PRODUCT_ID);
+
+            Ok(CatalogFeedChanges {
+                last_position: Some(CatalogFeedPosition::new(product.updated_at, MOCK_PRODUCT_ID.0 + 1)),
+                updated: vec![product],
+                deleted: vec![ProductId(MOCK_PRODUCT_ID.0 + 1)],
+            })
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ProductPriceHistoryRepoMock;
+
+    impl ProductPriceHistoryRepo for ProductPriceHistoryRepoMock {
+        fn create(&self, payload: NewProductPriceHistoryRecord) -> RepoResult<ProductPriceHistoryRecord> {
+            Ok(ProductPriceHistoryRecord {
+                id: 1,
+                product_id: payload.product_id,
+                price: payload.price,
+                discount: payload.discount,
+                cashback: payload.cashback,
+                currency: payload.currency,
+                user_id: payload.user_id,
+                created_at: SystemTime::now(),
+            })
+        }
+
+        fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ProductPriceHistoryRecord>> {
+            let product = create_product(product_id_arg, MOCK_BASE_PRODUCT_ID);
+            self.create(NewProductPriceHistoryRecord::new(&product, None)).map(|record| vec![record])
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ScheduledPriceChangesRepoMock;
+
+    impl ScheduledPriceChangesRepo for ScheduledPriceChangesRepoMock {
+        fn create(&self, payload: NewScheduledPriceChange) -> RepoResult<ScheduledPriceChange> {
+            let mut value = create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, payload.product_id);
+            value.price = payload.price;
+            value.discount = payload.discount;
+            value.cashback = payload.cashback;
+            value.starts_at = payload.starts_at;
+            value.ends_at = payload.ends_at;
+
+            Ok(value)
+        }
+
+        fn find(&self, id_arg: i32) -> RepoResult<Option<ScheduledPriceChange>> {
+            Ok(Some(create_scheduled_price_change(id_arg, MOCK_PRODUCT_ID)))
+        }
+
+        fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ScheduledPriceChange>> {
+            Ok(vec![create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, product_id_arg)])
+        }
+
+        fn find_due_to_apply(&self, _now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>> {
+            Ok(vec![])
+        }
+
+        fn find_due_to_revert(&self, _now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>> {
+            Ok(vec![])
+        }
+
+        fn update(&self, id_arg: i32, payload: UpdateScheduledPriceChange) -> RepoResult<ScheduledPriceChange> {
+            let mut value = create_scheduled_price_change(id_arg, MOCK_PRODUCT_ID);
+            if let Some(status) = payload.status {
+                value.status = status;
+            }
+            value.previous_price = payload.previous_price.or(value.previous_price);
+            value.previous_discount = payload.previous_discount.or(value.previous_discount);
+            value.previous_cashback = payload.previous_cashback.or(value.previous_cashback);
+
+            Ok(value)
+        }
+
+        fn delete(&self, id_arg: i32) -> RepoResult<ScheduledPriceChange> {
+            Ok(create_scheduled_price_change(id_arg, MOCK_PRODUCT_ID))
+        }
+    }
+
+    pub fn create_scheduled_price_change(id: i32, product_id: ProductId) -> ScheduledPriceChange {
+        let now = SystemTime::now();
+
+        ScheduledPriceChange {
+            id,
+            product_id,
+            price: Some(ProductPrice(10f64)),
+            discount: None,
+            cashback: None,
+            starts_at: now + Duration::from_secs(3600),
+            ends_at: now + Duration::from_secs(7200),
+            status: ScheduledPriceChangeStatus::Pending,
+            previous_price: None,
+            previous_discount: None,
+            previous_cashback: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Outbox keeping written events, `unpublished` events are relayed in order of ids
+    #[derive(Clone, Default)]
+    pub struct OutboxEventsRepoMock {
+        pub created: RefCell<Vec<NewOutboxEvent>>,
+        pub unpublished: RefCell<Vec<OutboxEvent>>,
+        pub published: RefCell<Vec<i64>>,
+        pub failed: RefCell<Vec<(Vec<i64>, String)>>,
+    }
+
+    impl OutboxEventsRepoMock {
+        pub fn with_unpublished(count: i64) -> Self {
+            let outbox_repo = Self::default();
+            *outbox_repo.unpublished.borrow_mut() = (1..=count)
+                .map(|id| {
+                    let payload = NewOutboxEvent::new(OutboxAggregateType::Store, id as i32, OutboxEventType::Deleted, None, &()).unwrap();
+                    create_outbox_event(id, payload)
+                })
+                .collect();
+            outbox_repo
+        }
+    }
+
+    impl OutboxEventsRepo for OutboxEventsRepoMock {
+        fn create(&self, payload: NewOutboxEvent) -> RepoResult<OutboxEvent> {
+            self.created.borrow_mut().push(payload.clone());
+            Ok(create_outbox_event(1, payload))
+        }
+
+        fn find_unpublished(&self, limit: i64) -> RepoResult<Vec<OutboxEvent>> {
+            let published = self.published.borrow();
+            Ok(self
+                .unpublished
+                .borrow()
+                .iter()
+                .filter(|event| !published.contains(&event.id))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        fn mark_published(&self, ids: Vec<i64>, _published_at: SystemTime) -> RepoResult<()> {
+            self.published.borrow_mut().extend(ids);
+            Ok(())
+        }
+
+        fn mark_failed(&self, ids: Vec<i64>, error: String) -> RepoResult<()> {
+            self.failed.borrow_mut().push((ids, error));
+            Ok(())
+        }
+    }
+
+    fn create_outbox_event(id: i64, payload: NewOutboxEvent) -> OutboxEvent {
+        OutboxEvent {
+            id,
+            aggregate_type: payload.aggregate_type,
+            aggregate_id: payload.aggregate_id,
+            event_type: payload.event_type,
+            kafka_update_no: payload.kafka_update_no,
+            payload: payload.payload,
+            created_at: SystemTime::now(),
+            published_at: None,
+            attempts: 0,
+            last_error: None,
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct SearchSynonymsRepoMock;
+
+    impl SearchSynonymsRepo for SearchSynonymsRepoMock {
+        fn list(&self) -> RepoResult<Vec<SearchSynonym>> {
+            Ok(vec![create_search_synonym(1, vec!["tv".to_string(), "television".to_string()])])
+        }
+
+        fn find_by_terms(&self, terms: Vec<String>) -> RepoResult<Vec<SearchSynonym>> {
+            Ok(self
+                .list()?
+                .into_iter()
+                .filter(|synonym| synonym.terms.iter().any(|term| terms.contains(term)))
+                .collect())
+        }
+
+        fn create(&self, payload: NewSearchSynonym) -> RepoResult<SearchSynonym> {
+            Ok(create_search_synonym(1, payload.terms))
+        }
+
+        fn update(&self, id_arg: i32, payload: UpdateSearchSynonym) -> RepoResult<SearchSynonym> {
+            Ok(create_search_synonym(id_arg, payload.terms))
+        }
+
+        fn delete(&self, id_arg: i32) -> RepoResult<SearchSynonym> {
+            Ok(create_search_synonym(id_arg, vec!["tv".to_string(), "television".to_string()]))
+        }
+    }
+
+    pub fn create_search_synonym(id: i32, terms: Vec<String>) -> SearchSynonym {
+        SearchSynonym {
+            id,
+            terms,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct StoreStaffRepoMock;
+
+    impl StoreStaffRepo for StoreStaffRepoMock {
+        fn list_by_store(&self, store_id_arg: StoreId) -> RepoResult<Vec<StoreStaffMember>> {
+            Ok(vec![create_store_staff_member(store_id_arg, MOCK_STAFF_USER_ID, StoreStaffRole::Editor)])
+        }
+
+        fn get(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<Option<StoreStaffMember>> {
+            Ok(self.list_by_store(store_id_arg)?.into_iter().find(|member| member.user_id == user_id_arg))
+        }
+
+        fn create(&self, payload: NewStoreStaffMember) -> RepoResult<StoreStaffMember> {
+            Ok(create_store_staff_member(payload.store_id, payload.user_id, payload.role))
+        }
+
+        fn update_role(&self, store_id_arg: StoreId, user_id_arg: UserId, role_arg: StoreStaffRole) -> RepoResult<StoreStaffMember> {
+            Ok(create_store_staff_member(store_id_arg, user_id_arg, role_arg))
+        }
+
+        fn delete(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<StoreStaffMember> {
+            Ok(create_store_staff_member(store_id_arg, user_id_arg, StoreStaffRole::Editor))
+        }
+    }
+
+    pub fn create_store_staff_member(store_id: StoreId, user_id: UserId, role: StoreStaffRole) -> StoreStaffMember {
+        StoreStaffMember {
+            id: 1,
+            store_id,
+            user_id,
+            role,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct AclDecisionsRepoMock;
+
+    impl AclDecisionsRepo for AclDecisionsRepoMock {
+        fn explain(&self, request: AclExplainRequest) -> RepoResult<AclDecision> {
+            let user_acl = ApplicationAcl::new(vec![StoresRole::User], request.user_id);
+            Ok(user_acl.decide(
+                request.resource,
+                request.action,
+                &StoreScopeCheckerMock,
+                request.rule,
+                request.object_id.as_ref(),
+            ))
+        }
+    }
+
+    /// Checks scopes of store ids, `MOCK_USER_ID` owns `MOCK_STORE_ID` and `MOCK_STAFF_USER_ID` is its editor
+    pub struct StoreScopeCheckerMock;
+
+    impl CheckScope<Scope, i32> for StoreScopeCheckerMock {
+        fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&i32>) -> bool {
+            let is_mock_store = obj == Some(&MOCK_STORE_ID.0);
+            match *scope {
+                Scope::All => true,
+                Scope::Owned => is_mock_store && user_id == MOCK_USER_ID,
+                Scope::Staff(role) => is_mock_store && user_id == MOCK_STAFF_USER_ID && role <= StoreStaffRole::Editor,
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ModerationQueueRepoMock;
+
+    impl ModerationQueueRepo for ModerationQueueRepoMock {
+        fn find(&self, id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+            let assignee_id = if id_arg == MOCK_CLAIMED_QUEUE_ITEM_ID {
+                Some(MOCK_MODERATOR_ID)
+            } else {
+                None
+            };
+            Ok(Some(create_moderation_queue_item(id_arg, assignee_id)))
+        }
+
+        fn find_by_entity(&self, _entity_type_arg: ModerationEntityType, _entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+            Ok(None)
+        }
+
+        fn search(&self, _search: ModerationQueueSearch) -> RepoResult<Vec<ModerationQueueItem>> {
+            Ok(vec![create_moderation_queue_item(1, None)])
+        }
+
+        fn enqueue(&self, payload: NewModerationQueueItem) -> RepoResult<ModerationQueueItem> {
+            Ok(ModerationQueueItem {
+                entity_type: payload.entity_type,
+                entity_id: payload.entity_id,
+                needs_review: payload.needs_review,
+                review_notes: payload.review_notes,
+                ..create_moderation_queue_item(1, None)
+            })
+        }
+
+        fn claim(&self, id_arg: i32, assignee_id_arg: UserId) -> RepoResult<Option<ModerationQueueItem>> {
+            if id_arg == MOCK_CLAIMED_QUEUE_ITEM_ID && assignee_id_arg != MOCK_MODERATOR_ID {
+                return Ok(None);
+            }
+            Ok(Some(create_moderation_queue_item(id_arg, Some(assignee_id_arg))))
+        }
+
+        fn release(&self, id_arg: i32, assignee_id_arg: Option<UserId>) -> RepoResult<Option<ModerationQueueItem>> {
+            if id_arg == MOCK_CLAIMED_QUEUE_ITEM_ID && assignee_id_arg.map_or(false, |assignee_id| assignee_id != MOCK_MODERATOR_ID) {
+                return Ok(None);
+            }
+            Ok(Some(create_moderation_queue_item(id_arg, None)))
+        }
+
+        fn delete_by_entity(&self, _entity_type_arg: ModerationEntityType, _entity_id_arg: i32) -> RepoResult<Option<ModerationQueueItem>> {
+            Ok(None)
+        }
+    }
+
+    pub fn create_moderation_queue_item(id: i32, assignee_id: Option<UserId>) -> ModerationQueueItem {
+        ModerationQueueItem {
+            id,
+            entity_type: ModerationEntityType::BaseProduct,
+            entity_id: 1,
+            assignee_id,
+            enqueued_at: SystemTime::now(),
+            assigned_at: assignee_id.map(|_| SystemTime::now()),
+            needs_review: false,
+            review_notes: serde_json::Value::Array(vec![]),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ModerationTransitionsRepoMock;
+
+    impl ModerationTransitionsRepo for ModerationTransitionsRepoMock {
+        fn list_by_entity(&self, entity_type_arg: ModerationEntityType, entity_id_arg: i32) -> RepoResult<Vec<ModerationTransition>> {
+            let sent_to_moderation = ModerationTransition {
+                id: 1,
+                entity_type: entity_type_arg,
+                entity_id: entity_id_arg,
+                user_id: Some(MOCK_USER_ID),
+                from_status: ModerationStatus::Draft,
+                to_status: ModerationStatus::Moderation,
+                reason: ModerationReason::SentToModeration,
+                comment: None,
+                time_in_queue_sec: None,
+                created_at: SystemTime::now(),
+            };
+            let declined = ModerationTransition {
+                id: 2,
+                from_status: ModerationStatus::Moderation,
+                to_status: ModerationStatus::Decline,
+                reason: ModerationReason::ModeratorDecision,
+                time_in_queue_sec: Some(60),
+                ..sent_to_moderation.clone()
+            };
+            Ok(vec![sent_to_moderation, declined])
+        }
+
+        fn create(&self, payload: NewModerationTransition) -> RepoResult<ModerationTransition> {
+            Ok(ModerationTransition {
+                id: 1,
+                entity_type: payload.entity_type,
+                entity_id: payload.entity_id,
+                user_id: payload.user_id,
+                from_status: payload.from_status,
+                to_status: payload.to_status,
+                reason: payload.reason,
+                comment: payload.comment,
+                time_in_queue_sec: payload.time_in_queue_sec,
+                created_at: SystemTime::now(),
+            })
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct DeclineReasonsRepoMock;
+
+    impl DeclineReasonsRepo for DeclineReasonsRepoMock {
+        fn list(&self) -> RepoResult<Vec<DeclineReason>> {
+            Ok(vec![
+                create_decline_reason(1, "bad_photo", true),
+                create_decline_reason(2, "missing_translation", true),
+                create_decline_reason(3, "outdated_reason", false),
+            ])
+        }
+
+        fn find_by_codes(&self, codes: Vec<String>) -> RepoResult<Vec<DeclineReason>> {
+            Ok(self.list()?.into_iter().filter(|reason| codes.contains(&reason.code)).collect())
+        }
+
+        fn create(&self, payload: NewDeclineReason) -> RepoResult<DeclineReason> {
+            Ok(DeclineReason {
+                name: payload.name,
+                ..create_decline_reason(1, &payload.code, true)
+            })
+        }
+
+        fn update(&self, id_arg: i32, payload: UpdateDeclineReason) -> RepoResult<DeclineReason> {
+            Ok(create_decline_reason(id_arg, "bad_photo", payload.is_active.unwrap_or(true)))
+        }
+    }
+
+    pub fn create_decline_reason(id: i32, code: &str, is_active: bool) -> DeclineReason {
+        DeclineReason {
+            id,
+            code: code.to_string(),
+            name: serde_json::from_str(r#"[{"lang": "en","text": "Decline reason"}]"#).unwrap(),
+            is_active,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct ModerationFeedbackRepoMock;
+
+    impl ModerationFeedbackRepo for ModerationFeedbackRepoMock {
+        fn list_by_transition(&self, transition_id_arg: i32) -> RepoResult<Vec<ModerationFeedback>> {
+            Ok(vec![ModerationFeedback {
+                transition_id: transition_id_arg,
+                ..create_moderation_feedback("bad_photo", Some("photo_main"))
+            }])
+        }
+
+        fn count_declines_by_reason(&self, _search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, i64)>> {
+            Ok(vec![("missing_translation".to_string(), 1), ("bad_photo".to_string(), 2)])
+        }
+
+        fn count_declines_by_reason_and_field(&self, _search: ModerationFeedbackSearch) -> RepoResult<Vec<(String, String, i64)>> {
+            Ok(vec![
+                ("bad_photo".to_string(), "photo_main".to_string(), 1),
+                ("missing_translation".to_string(), "long_description".to_string(), 1),
+            ])
+        }
+
+        fn create(&self, payload: NewModerationFeedback) -> RepoResult<ModerationFeedback> {
+            Ok(ModerationFeedback {
+                id: 1,
+                transition_id: payload.transition_id,
+                entity_type: payload.entity_type,
+                entity_id: payload.entity_id,
+                reason_code: payload.reason_code,
+                field: payload.field,
+                comment: payload.comment,
+                created_at: SystemTime::now(),
+            })
+        }
+    }
+
+    pub fn create_moderation_feedback(reason_code: &str, field: Option<&str>) -> ModerationFeedback {
+        ModerationFeedback {
+            id: 1,
+            transition_id: 1,
+            entity_type: ModerationEntityType::BaseProduct,
+            entity_id: 1,
+            reason_code: reason_code.to_string(),
+            field: field.map(|field| field.to_string()),
+            comment: None,
+            created_at: SystemTime::now(),
+        }
     }
 
     #[derive(Default)]
@@ -2018,6 +2984,35 @@ pub mod tests {
         }
     }
 
+    pub fn create_base_product(id: BaseProductId) -> BaseProduct {
+        BaseProduct {
+            id,
+            is_active: true,
+            store_id: MOCK_STORE_ID,
+            name: serde_json::from_str("{}").unwrap(),
+            short_description: serde_json::from_str("{}").unwrap(),
+            long_description: None,
+            seo_title: None,
+            seo_description: None,
+            currency: Currency::STQ,
+            category_id: CategoryId(1),
+            views: 1,
+            created_at: SystemTime::now(),
+            updated_at: SystemTime::now(),
+            rating: 0f64,
+            slug: BaseProductSlug("slug".to_string()),
+            status: ModerationStatus::Published,
+            kafka_update_no: 0,
+            uuid: uuid::Uuid::new_v4(),
+            length_cm: Some(60),
+            width_cm: Some(40),
+            height_cm: Some(20),
+            volume_cubic_cm: Some(48000),
+            weight_g: Some(100),
+            store_status: ModerationStatus::Published,
+        }
+    }
+
     pub fn create_product(id: ProductId, base_product_id: BaseProductId) -> RawProduct {
         RawProduct {
             id,
@@ -2036,6 +3031,7 @@ pub mod tests {
             pre_order_days: 0,
             kafka_update_no: 0,
             uuid: uuid::Uuid::new_v4(),
+            stock_status: None,
         }
     }
 }
@@ -0,0 +1,251 @@
+use std::time::SystemTime;
+
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{ProductId, StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::store_staff::StoreRoles;
+use repos::types::{RepoAcl, RepoResult};
+use schema::base_products::dsl as DslBaseProducts;
+use schema::products::dsl as DslProducts;
+use schema::scheduled_price_changes::dsl as DslScheduled;
+use schema::stores::dsl as DslStores;
+
+/// ScheduledPriceChanges repository, responsible for handling scheduled_price_changes table
+pub struct ScheduledPriceChangesRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<ScheduledPriceChange>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait ScheduledPriceChangesRepo {
+    /// Creates new scheduled price change
+    fn create(&self, payload: NewScheduledPriceChange) -> RepoResult<ScheduledPriceChange>;
+
+    /// Find specific scheduled price change by ID
+    fn find(&self, id_arg: i32) -> RepoResult<Option<ScheduledPriceChange>>;
+
+    /// Returns scheduled price changes of product ordered by start time
+    fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ScheduledPriceChange>>;
+
+    /// Returns pending changes which start time has come
+    fn find_due_to_apply(&self, now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>>;
+
+    /// Returns active changes which end time has come
+    fn find_due_to_revert(&self, now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>>;
+
+    /// Updates specific scheduled price change
+    fn update(&self, id_arg: i32, payload: UpdateScheduledPriceChange) -> RepoResult<ScheduledPriceChange>;
+
+    /// Deletes specific scheduled price change
+    fn delete(&self, id_arg: i32) -> RepoResult<ScheduledPriceChange>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ScheduledPriceChangesRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<ScheduledPriceChange>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+
+    fn check_all(&self, values: Vec<ScheduledPriceChange>, action: Action) -> RepoResult<Vec<ScheduledPriceChange>> {
+        for value in &values {
+            acl::check(&*self.acl, Resource::ScheduledPriceChanges, action, self, Some(value))?;
+        }
+
+        Ok(values)
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> ScheduledPriceChangesRepo
+    for ScheduledPriceChangesRepoImpl<'a, T>
+{
+    /// Creates new scheduled price change
+    fn create(&self, payload: NewScheduledPriceChange) -> RepoResult<ScheduledPriceChange> {
+        debug!("Create scheduled price change {:?}.", payload);
+
+        let query = diesel::insert_into(DslScheduled::scheduled_price_changes).values(&payload);
+        query
+            .get_result::<ScheduledPriceChange>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| {
+                acl::check(&*self.acl, Resource::ScheduledPriceChanges, Action::Create, self, Some(&value))?;
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| e.context(format!("Create scheduled price change {:?} error occurred", payload)).into())
+    }
+
+    /// Find specific scheduled price change by ID
+    fn find(&self, id_arg: i32) -> RepoResult<Option<ScheduledPriceChange>> {
+        debug!("Find scheduled price change with id {}.", id_arg);
+
+        let query = DslScheduled::scheduled_price_changes.find(id_arg);
+        query
+            .get_result(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value: Option<ScheduledPriceChange>| {
+                if let Some(ref value) = value {
+                    acl::check(&*self.acl, Resource::ScheduledPriceChanges, Action::Read, self, Some(value))?;
+                };
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find scheduled price change with id {} error occurred", id_arg))
+                    .into()
+            })
+    }
+
+    /// Returns scheduled price changes of product ordered by start time
+    fn find_by_product(&self, product_id_arg: ProductId) -> RepoResult<Vec<ScheduledPriceChange>> {
+        debug!("Find scheduled price changes of product {}.", product_id_arg);
+
+        let query = DslScheduled::scheduled_price_changes
+            .filter(DslScheduled::product_id.eq(product_id_arg))
+            .order(DslScheduled::starts_at);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values| self.check_all(values, Action::Read))
+            .map_err(|e: FailureError| {
+                e.context(format!("Find scheduled price changes of product {} error occurred", product_id_arg))
+                    .into()
+            })
+    }
+
+    /// Returns pending changes which start time has come
+    fn find_due_to_apply(&self, now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>> {
+        debug!("Find scheduled price changes to apply at {:?}.", now);
+
+        let query = DslScheduled::scheduled_price_changes
+            .filter(DslScheduled::status.eq(ScheduledPriceChangeStatus::Pending))
+            .filter(DslScheduled::starts_at.le(now))
+            .order(DslScheduled::starts_at);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values| self.check_all(values, Action::Read))
+            .map_err(|e: FailureError| e.context("Find scheduled price changes to apply error occurred").into())
+    }
+
+    /// Returns active changes which end time has come
+    fn find_due_to_revert(&self, now: SystemTime) -> RepoResult<Vec<ScheduledPriceChange>> {
+        debug!("Find scheduled price changes to revert at {:?}.", now);
+
+        let query = DslScheduled::scheduled_price_changes
+            .filter(DslScheduled::status.eq(ScheduledPriceChangeStatus::Active))
+            .filter(DslScheduled::ends_at.le(now))
+            .order(DslScheduled::ends_at);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values| self.check_all(values, Action::Read))
+            .map_err(|e: FailureError| e.context("Find scheduled price changes to revert error occurred").into())
+    }
+
+    /// Updates specific scheduled price change
+    fn update(&self, id_arg: i32, payload: UpdateScheduledPriceChange) -> RepoResult<ScheduledPriceChange> {
+        debug!("Updating scheduled price change with id {} and payload {:?}.", id_arg, payload);
+
+        let query = DslScheduled::scheduled_price_changes.find(id_arg);
+        query
+            .get_result::<ScheduledPriceChange>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| acl::check(&*self.acl, Resource::ScheduledPriceChanges, Action::Update, self, Some(&value)))
+            .and_then(|_| {
+                let filtered = DslScheduled::scheduled_price_changes.filter(DslScheduled::id.eq(id_arg));
+                let query = diesel::update(filtered).set(&payload);
+
+                query
+                    .get_result::<ScheduledPriceChange>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Updating scheduled price change with id {} and payload {:?} error occurred",
+                    id_arg, payload
+                ))
+                .into()
+            })
+    }
+
+    /// Deletes specific scheduled price change
+    fn delete(&self, id_arg: i32) -> RepoResult<ScheduledPriceChange> {
+        debug!("Delete scheduled price change with id {}.", id_arg);
+
+        let query = DslScheduled::scheduled_price_changes.find(id_arg);
+        query
+            .get_result::<ScheduledPriceChange>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| acl::check(&*self.acl, Resource::ScheduledPriceChanges, Action::Delete, self, Some(&value)))
+            .and_then(|_| {
+                let filtered = DslScheduled::scheduled_price_changes.filter(DslScheduled::id.eq(id_arg));
+                let query = diesel::delete(filtered);
+
+                query
+                    .get_result::<ScheduledPriceChange>(self.db_conn)
+                    .map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Delete scheduled price change with id {} error occurred", id_arg))
+                    .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, ScheduledPriceChange>
+    for ScheduledPriceChangesRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&ScheduledPriceChange>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(value) = obj {
+                    DslProducts::products
+                        .filter(DslProducts::id.eq(value.product_id))
+                        .inner_join(DslBaseProducts::base_products.inner_join(DslStores::stores))
+                        .get_result::<(RawProduct, (BaseProductRaw, Store))>(self.db_conn)
+                        .map(|(_, (_, s))| s.user_id == user_id)
+                        .ok()
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    DslProducts::products
+                        .find(value.product_id)
+                        .inner_join(DslBaseProducts::base_products)
+                        .select(DslBaseProducts::store_id)
+                        .get_result::<StoreId>(self.db_conn)
+                        .map(|store_id| self.store_roles.is_store_staff(self.db_conn, user_id, store_id, role))
+                        .unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn object_id(&self, obj: &ScheduledPriceChange) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
@@ -0,0 +1,134 @@
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::UserId;
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::types::{RepoAcl, RepoResult};
+use schema::search_synonyms::dsl as DslSynonyms;
+
+/// SearchSynonyms repository, responsible for handling search_synonyms table
+pub struct SearchSynonymsRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<SearchSynonym>>,
+}
+
+pub trait SearchSynonymsRepo {
+    /// Returns all search synonyms
+    fn list(&self) -> RepoResult<Vec<SearchSynonym>>;
+
+    /// Returns search synonyms containing any of `terms`
+    fn find_by_terms(&self, terms: Vec<String>) -> RepoResult<Vec<SearchSynonym>>;
+
+    /// Creates new search synonym
+    fn create(&self, payload: NewSearchSynonym) -> RepoResult<SearchSynonym>;
+
+    /// Updates specific search synonym
+    fn update(&self, id_arg: i32, payload: UpdateSearchSynonym) -> RepoResult<SearchSynonym>;
+
+    /// Deletes specific search synonym
+    fn delete(&self, id_arg: i32) -> RepoResult<SearchSynonym>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> SearchSynonymsRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<SearchSynonym>>) -> Self {
+        Self { db_conn, acl }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> SearchSynonymsRepo
+    for SearchSynonymsRepoImpl<'a, T>
+{
+    /// Returns all search synonyms
+    fn list(&self) -> RepoResult<Vec<SearchSynonym>> {
+        debug!("Find all search synonyms.");
+
+        acl::check(&*self.acl, Resource::SearchSynonyms, Action::Read, self, None)
+            .and_then(|_| {
+                let query = DslSynonyms::search_synonyms.order(DslSynonyms::id);
+                query.get_results(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context("Find all search synonyms error occurred").into())
+    }
+
+    /// Returns search synonyms containing any of `terms`
+    fn find_by_terms(&self, terms: Vec<String>) -> RepoResult<Vec<SearchSynonym>> {
+        debug!("Find search synonyms by terms {:?}.", terms);
+
+        acl::check(&*self.acl, Resource::SearchSynonyms, Action::Read, self, None)
+            .and_then(|_| {
+                let query = DslSynonyms::search_synonyms
+                    .filter(DslSynonyms::terms.overlaps_with(&terms))
+                    .order(DslSynonyms::id);
+                query.get_results(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Find search synonyms by terms {:?} error occurred", terms)).into())
+    }
+
+    /// Creates new search synonym
+    fn create(&self, payload: NewSearchSynonym) -> RepoResult<SearchSynonym> {
+        debug!("Create search synonym {:?}.", payload);
+
+        acl::check(&*self.acl, Resource::SearchSynonyms, Action::Create, self, None)
+            .and_then(|_| {
+                let query = diesel::insert_into(DslSynonyms::search_synonyms).values(&payload);
+                query.get_result::<SearchSynonym>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Create search synonym {:?} error occurred", payload)).into())
+    }
+
+    /// Updates specific search synonym
+    fn update(&self, id_arg: i32, payload: UpdateSearchSynonym) -> RepoResult<SearchSynonym> {
+        debug!("Updating search synonym with id {} and payload {:?}.", id_arg, payload);
+
+        acl::check(&*self.acl, Resource::SearchSynonyms, Action::Update, self, None)
+            .and_then(|_| {
+                let filtered = DslSynonyms::search_synonyms.filter(DslSynonyms::id.eq(id_arg));
+                let query = diesel::update(filtered).set(&payload);
+                query.get_result::<SearchSynonym>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Updating search synonym with id {} and payload {:?} error occurred",
+                    id_arg, payload
+                ))
+                .into()
+            })
+    }
+
+    /// Deletes specific search synonym
+    fn delete(&self, id_arg: i32) -> RepoResult<SearchSynonym> {
+        debug!("Delete search synonym with id {}.", id_arg);
+
+        acl::check(&*self.acl, Resource::SearchSynonyms, Action::Delete, self, None)
+            .and_then(|_| {
+                let filtered = DslSynonyms::search_synonyms.filter(DslSynonyms::id.eq(id_arg));
+                let query = diesel::delete(filtered);
+                query.get_result::<SearchSynonym>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| e.context(format!("Delete search synonym with id {} error occurred", id_arg)).into())
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, SearchSynonym>
+    for SearchSynonymsRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, _user_id: UserId, scope: &Scope, _obj: Option<&SearchSynonym>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned | Scope::Staff(_) => false,
+        }
+    }
+
+    fn object_id(&self, obj: &SearchSynonym) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
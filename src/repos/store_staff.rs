@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use diesel;
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_dsl::RunQueryDsl;
+use diesel::Connection;
+use errors::Error;
+use failure::Error as FailureError;
+
+use stq_types::{StoreId, UserId};
+
+use models::*;
+use repos::acl;
+use repos::legacy_acl::CheckScope;
+use repos::types::{RepoAcl, RepoResult};
+use schema::store_staff::dsl as DslStoreStaff;
+use schema::stores::dsl as DslStores;
+
+/// StoreStaff repository, responsible for handling store_staff table
+pub struct StoreStaffRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
+    pub db_conn: &'a T,
+    pub acl: Box<RepoAcl<StoreStaffMember>>,
+    pub store_roles: StoreRoles,
+}
+
+pub trait StoreStaffRepo {
+    /// Returns staff members of the store
+    fn list_by_store(&self, store_id_arg: StoreId) -> RepoResult<Vec<StoreStaffMember>>;
+
+    /// Returns staff member of the store
+    fn get(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<Option<StoreStaffMember>>;
+
+    /// Creates new store staff member
+    fn create(&self, payload: NewStoreStaffMember) -> RepoResult<StoreStaffMember>;
+
+    /// Changes role of store staff member
+    fn update_role(&self, store_id_arg: StoreId, user_id_arg: UserId, role_arg: StoreStaffRole) -> RepoResult<StoreStaffMember>;
+
+    /// Removes user from store staff
+    fn delete(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<StoreStaffMember>;
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> StoreStaffRepoImpl<'a, T> {
+    pub fn new(db_conn: &'a T, acl: Box<RepoAcl<StoreStaffMember>>) -> Self {
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> StoreStaffRepo for StoreStaffRepoImpl<'a, T> {
+    /// Returns staff members of the store
+    fn list_by_store(&self, store_id_arg: StoreId) -> RepoResult<Vec<StoreStaffMember>> {
+        debug!("Find staff of store {}.", store_id_arg);
+        let query = DslStoreStaff::store_staff
+            .filter(DslStoreStaff::store_id.eq(store_id_arg))
+            .order(DslStoreStaff::id);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|values: Vec<StoreStaffMember>| {
+                for value in &values {
+                    acl::check(&*self.acl, Resource::StoreStaff, Action::Read, self, Some(value))?;
+                }
+
+                Ok(values)
+            })
+            .map_err(|e: FailureError| e.context(format!("Find staff of store {} error occurred", store_id_arg)).into())
+    }
+
+    /// Returns staff member of the store
+    fn get(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<Option<StoreStaffMember>> {
+        debug!("Find staff member {} of store {}.", user_id_arg, store_id_arg);
+        let query = DslStoreStaff::store_staff
+            .filter(DslStoreStaff::store_id.eq(store_id_arg))
+            .filter(DslStoreStaff::user_id.eq(user_id_arg));
+
+        query
+            .get_result(self.db_conn)
+            .optional()
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value: Option<StoreStaffMember>| {
+                if let Some(ref value) = value {
+                    acl::check(&*self.acl, Resource::StoreStaff, Action::Read, self, Some(value))?;
+                }
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Find staff member {} of store {} error occurred", user_id_arg, store_id_arg))
+                    .into()
+            })
+    }
+
+    /// Creates new store staff member
+    fn create(&self, payload: NewStoreStaffMember) -> RepoResult<StoreStaffMember> {
+        debug!("Create store staff member {:?}.", payload);
+        let query = diesel::insert_into(DslStoreStaff::store_staff).values(&payload);
+
+        query
+            .get_result::<StoreStaffMember>(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value| {
+                acl::check(&*self.acl, Resource::StoreStaff, Action::Create, self, Some(&value))?;
+
+                Ok(value)
+            })
+            .map_err(|e: FailureError| e.context(format!("Create store staff member {:?} error occurred", payload)).into())
+    }
+
+    /// Changes role of store staff member
+    fn update_role(&self, store_id_arg: StoreId, user_id_arg: UserId, role_arg: StoreStaffRole) -> RepoResult<StoreStaffMember> {
+        debug!("Set role {:?} to staff member {} of store {}.", role_arg, user_id_arg, store_id_arg);
+        let query = DslStoreStaff::store_staff
+            .filter(DslStoreStaff::store_id.eq(store_id_arg))
+            .filter(DslStoreStaff::user_id.eq(user_id_arg));
+
+        query
+            .get_result(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value: StoreStaffMember| {
+                acl::check(&*self.acl, Resource::StoreStaff, Action::Update, self, Some(&value))?;
+
+                let filtered = DslStoreStaff::store_staff.filter(DslStoreStaff::id.eq(value.id));
+                let query = diesel::update(filtered).set(DslStoreStaff::role.eq(role_arg));
+                query.get_result::<StoreStaffMember>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Set role {:?} to staff member {} of store {} error occurred",
+                    role_arg, user_id_arg, store_id_arg
+                ))
+                .into()
+            })
+    }
+
+    /// Removes user from store staff
+    fn delete(&self, store_id_arg: StoreId, user_id_arg: UserId) -> RepoResult<StoreStaffMember> {
+        debug!("Delete staff member {} of store {}.", user_id_arg, store_id_arg);
+        let query = DslStoreStaff::store_staff
+            .filter(DslStoreStaff::store_id.eq(store_id_arg))
+            .filter(DslStoreStaff::user_id.eq(user_id_arg));
+
+        query
+            .get_result(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|value: StoreStaffMember| {
+                acl::check(&*self.acl, Resource::StoreStaff, Action::Delete, self, Some(&value))?;
+
+                let filtered = DslStoreStaff::store_staff.filter(DslStoreStaff::id.eq(value.id));
+                let query = diesel::delete(filtered);
+                query.get_result::<StoreStaffMember>(self.db_conn).map_err(|e| Error::from(e).into())
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!("Delete staff member {} of store {} error occurred", user_id_arg, store_id_arg))
+                    .into()
+            })
+    }
+}
+
+impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, StoreStaffMember>
+    for StoreStaffRepoImpl<'a, T>
+{
+    fn is_in_scope(&self, user_id: UserId, scope: &Scope, obj: Option<&StoreStaffMember>) -> bool {
+        match *scope {
+            Scope::All => true,
+            Scope::Owned => {
+                if let Some(value) = obj {
+                    value.user_id == user_id
+                } else {
+                    false
+                }
+            }
+            Scope::Staff(role) => {
+                if let Some(value) = obj {
+                    self.store_roles.is_store_staff(self.db_conn, user_id, value.store_id, role)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    fn object_id(&self, obj: &StoreStaffMember) -> Option<String> {
+        Some(obj.id.to_string())
+    }
+}
+
+/// Roles of users in stores, each of them is loaded from db once and reused by scope checks
+/// of all objects of the store for the lifetime of the repo
+#[derive(Default)]
+pub struct StoreRoles {
+    roles: RefCell<HashMap<(UserId, StoreId), Option<StoreStaffRole>>>,
+}
+
+impl StoreRoles {
+    /// Tells if user is the owner of the store or its staff member with the role not lower than `role`.
+    /// Used by `is_in_scope` of repos of resources belonging to stores, db errors deny access
+    pub fn is_store_staff<T>(&self, db_conn: &T, user_id: UserId, store_id: StoreId, role: StoreStaffRole) -> bool
+    where
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+    {
+        let cached_role = self.roles.borrow().get(&(user_id, store_id)).cloned();
+        let store_role = match cached_role {
+            Some(store_role) => store_role,
+            None => match load_store_role(db_conn, user_id, store_id) {
+                Ok(store_role) => {
+                    self.roles.borrow_mut().insert((user_id, store_id), store_role);
+                    store_role
+                }
+                Err(e) => {
+                    error!("{}", e);
+                    None
+                }
+            },
+        };
+
+        store_role.map(|store_role| store_role >= role).unwrap_or(false)
+    }
+}
+
+/// Role of user in the store, the owner of the store has `Owner` role without staff membership
+fn load_store_role<T>(db_conn: &T, user_id: UserId, store_id: StoreId) -> RepoResult<Option<StoreStaffRole>>
+where
+    T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+{
+    let owner_id = DslStores::stores
+        .filter(DslStores::id.eq(store_id))
+        .select(DslStores::user_id)
+        .get_result::<UserId>(db_conn)
+        .optional()
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| e.context(format!("Getting owner of store {} error occurred", store_id)))?;
+
+    if owner_id == Some(user_id) {
+        return Ok(Some(StoreStaffRole::Owner));
+    }
+
+    DslStoreStaff::store_staff
+        .filter(DslStoreStaff::store_id.eq(store_id))
+        .filter(DslStoreStaff::user_id.eq(user_id))
+        .select(DslStoreStaff::role)
+        .get_result::<StoreStaffRole>(db_conn)
+        .optional()
+        .map_err(|e| Error::from(e).into())
+        .map_err(|e: FailureError| {
+            e.context(format!("Getting role of user {} in store {} error occurred", user_id, store_id))
+                .into()
+        })
+}
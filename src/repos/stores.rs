@@ -1,4 +1,7 @@
 //! Stores repo, presents CRUD operations with db for users
+
+use std::time::SystemTime;
+
 use diesel;
 use diesel::connection::AnsiTransactionManager;
 use diesel::dsl::exists;
@@ -19,6 +22,7 @@ use stq_types::{SagaId, StoreId, StoreSlug, UserId};
 use models::*;
 use repos::acl;
 use repos::legacy_acl::*;
+use repos::store_staff::StoreRoles;
 use repos::types::{RepoAcl, RepoResult};
 use schema::base_products::dsl as BaseProducts;
 use schema::products::dsl as Products;
@@ -28,6 +32,7 @@ use schema::stores::dsl::*;
 pub struct StoresRepoImpl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> {
     pub db_conn: &'a T,
     pub acl: Box<RepoAcl<Store>>,
+    pub store_roles: StoreRoles,
 }
 
 pub trait StoresRepo {
@@ -83,16 +88,29 @@ pub trait StoresRepo {
     /// Set moderation status for specific store
     fn set_moderation_status(&self, store_id: StoreId, status: ModerationStatus) -> RepoResult<Store>;
 
+    /// Checks that user can change moderation status of the store in its current status
+    fn can_moderate(&self, store: &Store) -> RepoResult<bool>;
+
     /// Updates service store fields as root
     fn update_service_fields(&self, store_id: StoreId, payload: ServiceUpdateStore) -> RepoResult<Store>;
 
     /// Delete store by id
     fn delete(&self, store_id: StoreId) -> RepoResult<()>;
+
+    /// Returns page of published stores updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<Store, StoreId>>;
+
+    /// Returns list of stores updated after `since` including deactivated ones, limited by `from` and `count` parameters
+    fn list_updated_since(&self, since: SystemTime, from: StoreId, count: i64) -> RepoResult<Vec<Store>>;
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> StoresRepoImpl<'a, T> {
     pub fn new(db_conn: &'a T, acl: Box<RepoAcl<Store>>) -> Self {
-        Self { db_conn, acl }
+        Self {
+            db_conn,
+            acl,
+            store_roles: StoreRoles::default(),
+        }
     }
 
     fn execute_query<Ty: Send + 'static, U: LoadQuery<T, Ty> + Send + 'static>(&self, query: U) -> RepoResult<Ty> {
@@ -529,6 +547,19 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             })
     }
 
+    /// Checks that user can change moderation status of the store in its current status
+    fn can_moderate(&self, store: &Store) -> RepoResult<bool> {
+        self.acl
+            .allows(
+                Resource::Stores,
+                Action::Moderate,
+                self,
+                Some(Rule::ModerationStatus(store.status)),
+                Some(store),
+            )
+            .map_err(|e: FailureError| e.context(format!("Check moderation of store {} error occurred", store.id)).into())
+    }
+
     /// Updates service store fields as root
     fn update_service_fields(&self, store_id_arg: StoreId, payload: ServiceUpdateStore) -> RepoResult<Store> {
         debug!("Updating service store fields with id {} and payload {:?}.", store_id_arg, payload);
@@ -555,6 +586,80 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
             .map_err(|e| e.context(format!("Delete store with id {} error occurred.", store_id_arg)).into())
             .map(|_| ())
     }
+
+    /// Returns page of published stores updated after feed position and ids of hidden or deactivated ones
+    fn find_updated_since(&self, position: CatalogFeedPosition, limit: i64) -> RepoResult<CatalogFeedChanges<Store, StoreId>> {
+        debug!("Find stores updated after {:?}, limit {}.", position, limit);
+
+        let query = stores
+            .filter(
+                updated_at
+                    .gt(position.updated_at)
+                    .or(updated_at.eq(position.updated_at).and(id.gt(StoreId(position.last_id)))),
+            )
+            .order((updated_at, id))
+            .limit(limit);
+
+        acl::check(&*self.acl, Resource::Stores, Action::Read, self, None)
+            .and_then(|_| query.get_results::<Store>(self.db_conn).map_err(|e| Error::from(e).into()))
+            .and_then(|stores_res| {
+                let last_position = stores_res
+                    .last()
+                    .map(|store| CatalogFeedPosition::new(store.updated_at, store.id.0));
+                let (updated, deleted): (Vec<Store>, Vec<Store>) = stores_res
+                    .into_iter()
+                    .partition(|store| store.is_active && store.status == ModerationStatus::Published);
+
+                for store in &updated {
+                    acl::check_with_rule(
+                        &*self.acl,
+                        Resource::Stores,
+                        Action::Read,
+                        self,
+                        Rule::ModerationStatus(store.status),
+                        Some(store),
+                    )?;
+                }
+
+                Ok(CatalogFeedChanges {
+                    updated,
+                    deleted: deleted.into_iter().map(|store| store.id).collect(),
+                    last_position,
+                })
+            })
+            .map_err(|e: FailureError| e.context(format!("Find stores updated after {:?} error occurred", position)).into())
+    }
+
+    /// Returns list of stores updated after `since` including deactivated ones, limited by `from` and `count` parameters
+    fn list_updated_since(&self, since: SystemTime, from: StoreId, count: i64) -> RepoResult<Vec<Store>> {
+        debug!("Find in stores updated after {:?} from {} count {}.", since, from, count);
+
+        let query = stores.filter(updated_at.ge(since)).filter(id.ge(from)).order(id).limit(count);
+
+        query
+            .get_results(self.db_conn)
+            .map_err(|e| Error::from(e).into())
+            .and_then(|stores_res: Vec<Store>| {
+                for store in &stores_res {
+                    acl::check_with_rule(
+                        &*self.acl,
+                        Resource::Stores,
+                        Action::Read,
+                        self,
+                        Rule::ModerationStatus(store.status),
+                        Some(store),
+                    )?;
+                }
+                Ok(stores_res)
+            })
+            .map_err(|e: FailureError| {
+                e.context(format!(
+                    "Find in stores updated after {:?} from {} count {} error occurred.",
+                    since, from, count
+                ))
+                .into()
+            })
+    }
 }
 
 impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static> CheckScope<Scope, Store>
@@ -570,8 +675,19 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(role) => {
+                if let Some(store) = obj {
+                    self.store_roles.is_store_staff(self.db_conn, user_id_arg, store.id, role)
+                } else {
+                    false
+                }
+            }
         }
     }
+
+    fn object_id(&self, obj: &Store) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
 
 fn by_moderator_search_terms(term: &ModeratorStoreSearchTerms) -> Box<BoxableExpression<stores, Pg, SqlType = Bool>> {
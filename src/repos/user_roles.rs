@@ -208,6 +208,11 @@ where
                     false
                 }
             }
+            Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &UserRole) -> Option<String> {
+        Some(obj.id.0.to_string())
+    }
 }
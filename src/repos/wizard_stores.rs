@@ -181,6 +181,11 @@ impl<'a, T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager
                     false
                 }
             }
+            Scope::Staff(_) => false,
         }
     }
+
+    fn object_id(&self, obj: &WizardStore) -> Option<String> {
+        Some(obj.id.to_string())
+    }
 }
@@ -63,6 +63,7 @@ table! {
         is_active -> Bool,
         uuid -> Uuid,
         slug -> Varchar,
+        updated_at -> Timestamp,
     }
 }
 
@@ -79,6 +80,21 @@ table! {
         is_active -> Bool,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        discount_type -> Varchar,
+        amount -> Nullable<Float8>,
+        currency -> Nullable<Varchar>,
+        per_user_limit -> Int4,
+        min_subtotal -> Nullable<Float8>,
+        active_from -> Nullable<Timestamp>,
+        campaign_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    coupon_exclusion_base_products (id) {
+        id -> Int4,
+        coupon_id -> Int4,
+        base_product_id -> Int4,
     }
 }
 
@@ -115,6 +131,58 @@ table! {
     }
 }
 
+table! {
+    decline_reasons (id) {
+        id -> Int4,
+        code -> Varchar,
+        name -> Jsonb,
+        is_active -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    moderation_feedback (id) {
+        id -> Int4,
+        transition_id -> Int4,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        reason_code -> Varchar,
+        field -> Nullable<Varchar>,
+        comment -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    moderation_queue (id) {
+        id -> Int4,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        assignee_id -> Nullable<Int4>,
+        enqueued_at -> Timestamp,
+        assigned_at -> Nullable<Timestamp>,
+        needs_review -> Bool,
+        review_notes -> Jsonb,
+    }
+}
+
+table! {
+    moderation_transitions (id) {
+        id -> Int4,
+        entity_type -> Varchar,
+        entity_id -> Int4,
+        user_id -> Nullable<Int4>,
+        from_status -> Varchar,
+        to_status -> Varchar,
+        reason -> Varchar,
+        comment -> Nullable<Varchar>,
+        time_in_queue_sec -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     moderator_product_comments (id) {
         id -> Int4,
@@ -135,6 +203,21 @@ table! {
     }
 }
 
+table! {
+    outbox_events (id) {
+        id -> Int8,
+        aggregate_type -> Varchar,
+        aggregate_id -> Int4,
+        event_type -> Varchar,
+        kafka_update_no -> Nullable<Int4>,
+        payload -> Jsonb,
+        created_at -> Timestamp,
+        published_at -> Nullable<Timestamp>,
+        attempts -> Int4,
+        last_error -> Nullable<Varchar>,
+    }
+}
+
 table! {
     prod_attr_values (id) {
         id -> Int4,
@@ -148,6 +231,19 @@ table! {
     }
 }
 
+table! {
+    product_price_history (id) {
+        id -> Int4,
+        product_id -> Int4,
+        price -> Float8,
+        discount -> Nullable<Float8>,
+        cashback -> Nullable<Float8>,
+        currency -> Varchar,
+        user_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     products (id) {
         id -> Int4,
@@ -166,6 +262,45 @@ table! {
         pre_order -> Bool,
         pre_order_days -> Int4,
         uuid -> Uuid,
+        stock_status -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    scheduled_price_changes (id) {
+        id -> Int4,
+        product_id -> Int4,
+        price -> Nullable<Float8>,
+        discount -> Nullable<Float8>,
+        cashback -> Nullable<Float8>,
+        starts_at -> Timestamp,
+        ends_at -> Timestamp,
+        status -> Varchar,
+        previous_price -> Nullable<Float8>,
+        previous_discount -> Nullable<Float8>,
+        previous_cashback -> Nullable<Float8>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    search_synonyms (id) {
+        id -> Int4,
+        terms -> Array<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    store_staff (id) {
+        id -> Int4,
+        store_id -> Int4,
+        user_id -> Int4,
+        role -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -206,6 +341,8 @@ table! {
         country_code -> Nullable<Varchar>,
         uuid -> Uuid,
         saga_id -> Nullable<Uuid>,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
     }
 }
 
@@ -213,6 +350,7 @@ table! {
     used_coupons (coupon_id, user_id) {
         coupon_id -> Int4,
         user_id -> Int4,
+        times_used -> Int4,
     }
 }
 
@@ -256,6 +394,8 @@ joinable!(base_products -> categories (category_id));
 joinable!(base_products -> stores (store_id));
 joinable!(cat_attr_values -> attributes (attr_id));
 joinable!(cat_attr_values -> categories (cat_id));
+joinable!(coupon_exclusion_base_products -> base_products (base_product_id));
+joinable!(coupon_exclusion_base_products -> coupons (coupon_id));
 joinable!(coupon_scope_base_products -> base_products (base_product_id));
 joinable!(coupon_scope_base_products -> coupons (coupon_id));
 joinable!(coupon_scope_categories -> categories (category_id));
@@ -263,13 +403,17 @@ joinable!(coupon_scope_categories -> coupons (coupon_id));
 joinable!(coupons -> stores (store_id));
 joinable!(custom_attributes -> attributes (attribute_id));
 joinable!(custom_attributes -> base_products (base_product_id));
+joinable!(moderation_feedback -> moderation_transitions (transition_id));
 joinable!(moderator_product_comments -> base_products (base_product_id));
 joinable!(moderator_store_comments -> stores (store_id));
 joinable!(prod_attr_values -> attribute_values (attr_value_id));
 joinable!(prod_attr_values -> attributes (attr_id));
 joinable!(prod_attr_values -> base_products (base_prod_id));
 joinable!(prod_attr_values -> products (prod_id));
+joinable!(product_price_history -> products (product_id));
 joinable!(products -> base_products (base_product_id));
+joinable!(scheduled_price_changes -> products (product_id));
+joinable!(store_staff -> stores (store_id));
 joinable!(used_coupons -> coupons (coupon_id));
 
 allow_tables_to_appear_in_same_query!(
@@ -279,14 +423,24 @@ allow_tables_to_appear_in_same_query!(
     cat_attr_values,
     categories,
     coupons,
+    coupon_exclusion_base_products,
     coupon_scope_base_products,
     coupon_scope_categories,
     currency_exchange,
     custom_attributes,
+    decline_reasons,
+    moderation_feedback,
+    moderation_queue,
+    moderation_transitions,
     moderator_product_comments,
     moderator_store_comments,
+    outbox_events,
     prod_attr_values,
+    product_price_history,
     products,
+    scheduled_price_changes,
+    search_synonyms,
+    store_staff,
     stores,
     used_coupons,
     user_roles,
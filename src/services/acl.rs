@@ -0,0 +1,96 @@
+//! Acl Services, presents explaining of ACL decisions
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use r2d2::ManageConnection;
+
+use super::types::ServiceFuture;
+use models::{AclDecision, AclExplainRequest};
+use repos::ReposFactory;
+use services::Service;
+
+pub trait AclService {
+    /// Explains which permissions of user allow or deny action on resource
+    fn explain_acl_decision(&self, payload: AclExplainRequest) -> ServiceFuture<AclDecision>;
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > AclService for Service<T, M, F>
+{
+    /// Explains which permissions of user allow or deny action on resource
+    fn explain_acl_decision(&self, payload: AclExplainRequest) -> ServiceFuture<AclDecision> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let acl_decisions_repo = repo_factory.create_acl_decisions_repo(&*conn, user_id);
+            acl_decisions_repo
+                .explain(payload)
+                .map_err(|e| e.context("Service Acl, explain_acl_decision endpoint error occurred.").into())
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use tokio_core::reactor::Core;
+
+    use stq_static_resources::ModerationStatus;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    #[test]
+    fn test_explain_allowed_acl_decision() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = AclExplainRequest {
+            user_id: MOCK_STAFF_USER_ID,
+            resource: Resource::Stores,
+            action: Action::Read,
+            object_id: Some(MOCK_STORE_ID.0),
+            rule: Some(Rule::ModerationStatus(ModerationStatus::Draft)),
+        };
+        let work = service.explain_acl_decision(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.user_id, Some(MOCK_STAFF_USER_ID));
+        assert!(result.allowed);
+        let matched = result.checks.iter().filter(|check| check.is_match()).collect::<Vec<_>>();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].permission.resource, Resource::Stores);
+        assert_eq!(matched[0].permission.action, Action::Read);
+        assert_eq!(matched[0].permission.rule, Some(Rule::Any));
+        assert_eq!(result.matched_scopes(), vec![Scope::Staff(StoreStaffRole::Viewer)]);
+    }
+
+    #[test]
+    fn test_explain_denied_acl_decision() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = AclExplainRequest {
+            user_id: MOCK_STAFF_USER_ID,
+            resource: Resource::Stores,
+            action: Action::Update,
+            object_id: Some(MOCK_STORE_ID.0),
+            rule: Some(Rule::ModerationStatus(ModerationStatus::Draft)),
+        };
+        let work = service.explain_acl_decision(payload);
+        let result = core.run(work).unwrap();
+        assert!(!result.allowed);
+        assert!(result.matched_scopes().is_empty());
+        let checked_scopes = result
+            .checks
+            .iter()
+            .filter(|check| check.in_scope == Some(false))
+            .map(|check| check.permission.scope)
+            .collect::<Vec<_>>();
+        assert_eq!(checked_scopes, vec![Scope::Owned, Scope::Staff(StoreStaffRole::Manager)]);
+    }
+}
@@ -1,6 +1,7 @@
 //! Base product service
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap};
 
+use csv;
 use diesel::connection::AnsiTransactionManager;
 use diesel::pg::Pg;
 use diesel::Connection;
@@ -8,12 +9,14 @@ use failure::Error as FailureError;
 use futures::future;
 use futures::future::*;
 use r2d2::ManageConnection;
+use serde_json;
+use validator::Validate;
 
 use stq_static_resources::{Currency, ModerationStatus};
 use stq_types::{BaseProductId, BaseProductSlug, CategoryId, ExchangeRate, ProductId, StoreId, StoreIdentifier};
 
 use super::types::ServiceFuture;
-use elastic::{ProductsElastic, ProductsElasticImpl};
+use elastic::ProductsElastic;
 use errors::Error;
 use models::*;
 use repos::clear_child_categories;
@@ -21,14 +24,16 @@ use repos::get_all_children_till_the_end;
 use repos::get_parent_category;
 use repos::remove_unused_categories;
 use repos::{
-    BaseProductsRepo, BaseProductsSearchTerms, CategoriesRepo, ProductAttrsRepo, ProductsRepo, RepoResult, ReposFactory, StoresRepo,
+    BaseProductsRepo, BaseProductsSearchTerms, CategoriesRepo, OutboxEventsRepo, ProductAttrsRepo, ProductsRepo, RepoResult, ReposFactory,
+    StoresRepo,
 };
 use services::create_product_attributes_values;
+use services::moderation::{check_moderation_transition, ModerationRecorder};
+use services::outbox::{write_base_product_event, write_base_product_events, write_product_event, write_product_events};
 use services::products::calculate_customer_price;
+use services::search_synonyms::SearchSynonymsService;
 use services::Service;
-use services::{check_can_update_by_status, check_change_status, check_vendor_code};
-
-const MAX_PRODUCTS_SEARCH_COUNT: i32 = 1000;
+use services::{check_stock_status, check_vendor_code, record_price_history};
 
 pub trait BaseProductsService {
     /// Returns base product count
@@ -59,7 +64,12 @@ pub trait BaseProductsService {
     ) -> ServiceFuture<Vec<BaseProductWithVariants>>;
 
     /// auto complete limited by `count` and `offset` parameters
-    fn base_products_auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> ServiceFuture<Vec<String>>;
+    fn base_products_auto_complete(
+        &self,
+        name: AutoCompleteProductName,
+        count: i32,
+        offset: i32,
+    ) -> ServiceFuture<Vec<AutoCompleteSuggestion>>;
 
     /// search filters
     fn search_base_products_filters_price(self, search_prod: SearchProductsByName) -> ServiceFuture<RangeFilter>;
@@ -73,6 +83,14 @@ pub trait BaseProductsService {
     /// search filters
     fn search_base_products_filters_count(&self, search_prod: SearchProductsByName) -> ServiceFuture<i32>;
 
+    /// Find product by name limited by `count` and `offset` parameters together with all search filters
+    fn search_base_products_faceted(
+        self,
+        prod: SearchProductsByName,
+        count: i32,
+        offset: i32,
+    ) -> ServiceFuture<BaseProductsFacetedSearchResults>;
+
     /// Returns product by ID
     fn get_base_product(&self, base_product_id: BaseProductId, visibility: Option<Visibility>) -> ServiceFuture<Option<BaseProduct>>;
 
@@ -116,6 +134,9 @@ pub trait BaseProductsService {
     /// Creates base product with variants
     fn create_base_product_with_variants(&self, payload: NewBaseProductWithVariants) -> ServiceFuture<BaseProduct>;
 
+    /// Imports base products with variants from CSV, changes are rolled back on dry run or any row error
+    fn import_base_products(&self, store_id: StoreId, payload: String, dry_run: bool) -> ServiceFuture<BaseProductsImportReport>;
+
     /// Lists base products limited by `from` and `count` parameters
     fn list_base_products(&self, from: BaseProductId, count: i32, visibility: Option<Visibility>) -> ServiceFuture<Vec<BaseProduct>>;
 
@@ -152,7 +173,13 @@ pub trait BaseProductsService {
     ) -> ServiceFuture<Vec<BaseProduct>>;
 
     /// Set moderation status for base_product_id
-    fn set_moderation_status_base_product(&self, base_product_id: BaseProductId, status: ModerationStatus) -> ServiceFuture<BaseProduct>;
+    fn set_moderation_status_base_product(
+        &self,
+        base_product_id: BaseProductId,
+        status: ModerationStatus,
+        comment: Option<String>,
+        feedback: Vec<ModerationFeedbackPayload>,
+    ) -> ServiceFuture<BaseProduct>;
 
     /// send base product to moderation from store manager
     fn send_base_product_to_moderation(&self, base_product_id: BaseProductId) -> ServiceFuture<BaseProduct>;
@@ -212,17 +239,18 @@ impl<
     ) -> ServiceFuture<Vec<BaseProductWithVariants>> {
         let repo_factory = self.static_context.repo_factory.clone();
         let user_id = self.dynamic_context.user_id;
-        let client_handle = self.static_context.client_handle.clone();
         let currency = self.dynamic_context.currency;
         let fiat_currency = self.dynamic_context.fiat_currency;
-        let address = self.static_context.config.server.elastic.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
         let service = self.clone();
+        let name_synonyms = self.find_search_text_synonyms(search_product.name.clone());
         Box::new(
             self.flatten_categories(search_product.options.clone())
                 .and_then(move |options| self.create_currency_map(options))
-                .and_then(move |options| {
+                .join(name_synonyms)
+                .and_then(move |(options, name_synonyms)| {
                     search_product.options = options;
+                    search_product.name_synonyms = name_synonyms;
                     products_el.search_by_name(search_product, count, offset)
                 })
                 .and_then({
@@ -279,9 +307,7 @@ impl<
         count: i32,
         offset: i32,
     ) -> ServiceFuture<Vec<BaseProductWithVariants>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
 
         let user_id = self.dynamic_context.user_id;
         let currency = self.dynamic_context.currency;
@@ -312,11 +338,14 @@ impl<
         )
     }
 
-    fn base_products_auto_complete(&self, name: AutoCompleteProductName, count: i32, offset: i32) -> ServiceFuture<Vec<String>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
+    fn base_products_auto_complete(
+        &self,
+        name: AutoCompleteProductName,
+        count: i32,
+        offset: i32,
+    ) -> ServiceFuture<Vec<AutoCompleteSuggestion>> {
         let products_names = {
-            let products_el = ProductsElasticImpl::new(client_handle, address);
+            let products_el = self.products_search();
             products_el.auto_complete(name, count, offset)
         };
 
@@ -327,14 +356,15 @@ impl<
     }
 
     fn search_base_products_filters_price(self, mut search_product: SearchProductsByName) -> ServiceFuture<RangeFilter> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
+        let name_synonyms = self.find_search_text_synonyms(search_product.name.clone());
         Box::new(
             self.flatten_categories(search_product.options.clone())
                 .and_then(move |options| self.create_currency_map(options))
-                .and_then(move |options| {
+                .join(name_synonyms)
+                .and_then(move |(options, name_synonyms)| {
                     search_product.options = options;
+                    search_product.name_synonyms = name_synonyms;
                     products_el.aggregate_price(search_product)
                 })
                 .map_err(|e| {
@@ -346,13 +376,13 @@ impl<
 
     /// search filters
     fn search_base_products_filters_count(&self, mut search_prod: SearchProductsByName) -> ServiceFuture<i32> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
         Box::new(
             self.flatten_categories(search_prod.options.clone())
-                .and_then(move |options| {
+                .join(self.find_search_text_synonyms(search_prod.name.clone()))
+                .and_then(move |(options, name_synonyms)| {
                     search_prod.options = options;
+                    search_prod.name_synonyms = name_synonyms;
                     products_el.count(search_prod)
                 })
                 .map_err(|e| {
@@ -364,12 +394,9 @@ impl<
 
     /// search filters
     fn search_base_products_filters_category(self, search_prod: SearchProductsByName) -> ServiceFuture<Category> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
 
         if search_prod.name.is_empty() {
             let category_id = search_prod.options.map(|options| options.category_id).and_then(|c| c);
@@ -408,20 +435,16 @@ impl<
 
     /// search filters
     fn search_base_products_attributes(&self, mut search_product: SearchProductsByName) -> ServiceFuture<Option<Vec<AttributeFilter>>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-        let products_el = ProductsElasticImpl::new(client_handle, address);
+        let products_el = self.products_search();
         Box::new(
             self.remove_non_third_level_categories(search_product.options.clone())
-                .and_then(move |options| -> ServiceFuture<Option<Vec<AttributeFilter>>> {
+                .join(self.find_search_text_synonyms(search_product.name.clone()))
+                .and_then(move |(options, name_synonyms)| -> ServiceFuture<Option<Vec<AttributeFilter>>> {
                     search_product.options = options;
+                    search_product.name_synonyms = name_synonyms;
                     if let Some(options) = search_product.options.clone() {
                         if options.categories_ids.is_some() {
-                            return Box::new(
-                                products_el
-                                    .search_by_name(search_product, MAX_PRODUCTS_SEARCH_COUNT, 0)
-                                    .map(get_attribute_filters),
-                            );
+                            return Box::new(products_el.aggregate_attributes(search_product).map(Some));
                         }
                     }
                     Box::new(future::ok(None))
@@ -433,6 +456,81 @@ impl<
         )
     }
 
+    /// Find product by name limited by `count` and `offset` parameters together with all search filters
+    fn search_base_products_faceted(
+        self,
+        mut search_product: SearchProductsByName,
+        count: i32,
+        offset: i32,
+    ) -> ServiceFuture<BaseProductsFacetedSearchResults> {
+        let repo_factory = self.static_context.repo_factory.clone();
+        let user_id = self.dynamic_context.user_id;
+        let currency = self.dynamic_context.currency;
+        let fiat_currency = self.dynamic_context.fiat_currency;
+        let products_el = self.products_search();
+        let service = self.clone();
+        let name = search_product.name.clone();
+        let category_id = search_product.options.as_ref().and_then(|options| options.category_id);
+        let name_synonyms = self.find_search_text_synonyms(search_product.name.clone());
+        let attributes_options = self.remove_non_third_level_categories(search_product.options.clone());
+        Box::new(
+            self.flatten_categories(search_product.options.clone())
+                .and_then(move |options| self.create_currency_map(options))
+                .join3(name_synonyms, attributes_options)
+                .and_then(move |(options, name_synonyms, attributes_options)| {
+                    // Attributes are only meaningful for the last level category, same as in `search_base_products_attributes`
+                    let with_attributes = attributes_options.map_or(false, |options| options.categories_ids.is_some());
+                    search_product.options = options;
+                    search_product.name_synonyms = name_synonyms;
+                    products_el
+                        .search_faceted(search_product, count, offset)
+                        .map(move |el_search| (el_search, with_attributes))
+                })
+                .and_then(move |(el_search, with_attributes)| {
+                    service.spawn_on_pool(move |conn| {
+                        let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+                        let currency_exchange = repo_factory.create_currency_exchange_repo(&*conn, user_id);
+                        let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
+
+                        let mut base_products = base_products_repo.convert_from_elastic(el_search.products)?;
+                        let latest_currencies = currency_exchange.get_latest()?;
+                        calculate_base_products_customer_price(&mut base_products, latest_currencies, currency, fiat_currency);
+
+                        let category = if name.is_empty() {
+                            let root = categories_repo.get_all_categories_with_products()?;
+                            if let Some(category_id) = category_id {
+                                let cat = categories_repo.find(category_id)?;
+                                get_path_to_searched_category(cat, root)
+                            } else {
+                                root
+                            }
+                        } else {
+                            let category = categories_repo.get_all_categories()?;
+                            remove_unused_categories(category, &el_search.category_ids)
+                        };
+
+                        let attribute_filters = if with_attributes {
+                            Some(el_search.attribute_filters)
+                        } else {
+                            None
+                        };
+
+                        Ok(BaseProductsFacetedSearchResults {
+                            base_products,
+                            total_count: el_search.total_count,
+                            price_filter: el_search.price_filter,
+                            category,
+                            attribute_filters,
+                        })
+                    })
+                })
+                .map_err(|e| {
+                    e.context("Service BaseProduct, search_base_products_faceted endpoint error occurred.")
+                        .into()
+                }),
+        )
+    }
+
     /// Returns product by ID
     fn get_base_product(&self, base_product_id: BaseProductId, visibility: Option<Visibility>) -> ServiceFuture<Option<BaseProduct>> {
         let user_id = self.dynamic_context.user_id;
@@ -550,9 +648,14 @@ impl<
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
             let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
             let products_repo = repo_factory.create_product_repo(&*conn, user_id);
+            let moderation_queue_repo = repo_factory.create_moderation_queue_repo_with_sys_acl(&*conn);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
             conn.transaction::<BaseProduct, FailureError, _>(move || {
                 let prod = base_products_repo.deactivate(base_product_id)?;
-                let _ = products_repo.deactivate_by_base_product(base_product_id)?;
+                write_base_product_event(&*outbox_repo, OutboxEventType::Deactivated, &prod)?;
+                moderation_queue_repo.delete_by_entity(ModerationEntityType::BaseProduct, base_product_id.0)?;
+                let products = products_repo.deactivate_by_base_product(base_product_id)?;
+                write_product_events(&*outbox_repo, OutboxEventType::Deactivated, &products)?;
                 // update product categories of the store
                 let store = stores_repo.find(prod.store_id, Visibility::Active)?;
                 if let Some(store) = store {
@@ -626,6 +729,7 @@ impl<
             let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
             let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
             conn.transaction::<(BaseProduct), FailureError, _>(move || {
                 //validate
                 validate_base_product(&*base_products_repo, &payload)?;
@@ -633,6 +737,7 @@ impl<
                 enrich_new_base_product(&*stores_repo, &mut payload)?;
                 // create base_product
                 let base_prod = base_products_repo.create(payload)?;
+                write_base_product_event(&*outbox_repo, OutboxEventType::Created, &base_prod)?;
 
                 // update product categories of the store
                 add_product_categories(&*stores_repo, &*categories_repo, base_prod.store_id, base_prod.category_id)?;
@@ -663,6 +768,8 @@ impl<
             let attr_repo = repo_factory.create_attributes_repo(&*conn, user_id);
             let attribute_values_repo = repo_factory.create_attribute_values_repo(&*conn, user_id);
             let custom_attributes_repo = repo_factory.create_custom_attributes_repo(&*conn, user_id);
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
 
             conn.transaction::<BaseProduct, FailureError, _>(move || {
                 //validate base_product
@@ -671,6 +778,7 @@ impl<
                 enrich_new_base_product(&*stores_repo, &mut new_base_product)?;
                 // create base_product
                 let base_prod = base_products_repo.create(new_base_product)?;
+                write_base_product_event(&*outbox_repo, OutboxEventType::Created, &base_prod)?;
                 let base_prod_id = base_prod.id;
                 let store_id = base_prod.store_id;
 
@@ -685,8 +793,11 @@ impl<
 
                 for variant in variants {
                     check_vendor_code(&*stores_repo, store_id, &variant.product.vendor_code)?;
+                    check_stock_status(variant.product.stock_status, variant.product.pre_order.unwrap_or(false))?;
                     // create variant
                     let product = products_repo.create((variant.product, base_prod.currency).into())?;
+                    record_price_history(&*price_history_repo, None, &product, user_id)?;
+                    write_product_event(&*outbox_repo, OutboxEventType::Created, &product)?;
                     // create attributes values for variant
                     create_product_attributes_values(
                         &*products_repo,
@@ -709,6 +820,109 @@ impl<
         })
     }
 
+    /// Imports base products with variants from CSV, changes are rolled back on dry run or any row error
+    fn import_base_products(&self, store_id: StoreId, payload: String, dry_run: bool) -> ServiceFuture<BaseProductsImportReport> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+            let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
+            let products_repo = repo_factory.create_product_repo(&*conn, user_id);
+            let prod_attr_repo = repo_factory.create_product_attrs_repo(&*conn, user_id);
+            let attr_repo = repo_factory.create_attributes_repo(&*conn, user_id);
+            let attribute_values_repo = repo_factory.create_attribute_values_repo(&*conn, user_id);
+            let custom_attributes_repo = repo_factory.create_custom_attributes_repo(&*conn, user_id);
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+
+            let (groups, mut errors) = parse_base_products_import(store_id, &payload);
+            let mut base_product_ids = vec![];
+            let mut variants_count = 0;
+
+            let import_result = conn.transaction::<(), FailureError, _>(|| {
+                for group in groups {
+                    let BaseProductImportGroup {
+                        line,
+                        mut new_base_product,
+                        variants,
+                    } = group;
+                    let variants_len = variants.len();
+                    // line and vendor code of the row being imported, used to report errors
+                    let mut current_row = (line, None);
+
+                    // every base product is imported in its own savepoint so that the rest of rows can be checked
+                    let result = conn.transaction::<BaseProduct, FailureError, _>(|| {
+                        validate_base_product(&*base_products_repo, &new_base_product)?;
+                        enrich_new_base_product(&*stores_repo, &mut new_base_product)?;
+                        let base_prod = base_products_repo.create(new_base_product)?;
+                        write_base_product_event(&*outbox_repo, OutboxEventType::Created, &base_prod)?;
+
+                        add_product_categories(&*stores_repo, &*categories_repo, base_prod.store_id, base_prod.category_id)?;
+
+                        for BaseProductImportVariant { line, mut variant } in variants {
+                            current_row = (line, Some(variant.product.vendor_code.clone()));
+                            variant.product.base_product_id = Some(base_prod.id);
+
+                            check_vendor_code(&*stores_repo, base_prod.store_id, &variant.product.vendor_code)?;
+                            check_stock_status(variant.product.stock_status, variant.product.pre_order.unwrap_or(false))?;
+                            let product = products_repo.create((variant.product, base_prod.currency).into())?;
+                            record_price_history(&*price_history_repo, None, &product, user_id)?;
+                            write_product_event(&*outbox_repo, OutboxEventType::Created, &product)?;
+                            create_product_attributes_values(
+                                &*products_repo,
+                                &*prod_attr_repo,
+                                &*attr_repo,
+                                &*custom_attributes_repo,
+                                &*attribute_values_repo,
+                                &product,
+                                base_prod.id,
+                                variant.attributes,
+                            )?;
+                        }
+
+                        Ok(base_prod)
+                    });
+
+                    match result {
+                        Ok(base_prod) => {
+                            base_product_ids.push(base_prod.id);
+                            variants_count += variants_len;
+                        }
+                        Err(e) => {
+                            let (line, vendor_code) = current_row;
+                            errors.push(import_row_error(line, vendor_code, &e));
+                        }
+                    }
+                }
+
+                if dry_run || !errors.is_empty() {
+                    Err(format_err!("Base products import is rolled back"))
+                } else {
+                    Ok(())
+                }
+            });
+
+            let committed = match import_result {
+                Ok(()) => true,
+                Err(_) if dry_run || !errors.is_empty() => false,
+                Err(e) => return Err(e.context("Service BaseProduct, import endpoint error occurred.").into()),
+            };
+
+            errors.sort_by_key(|error| error.line);
+
+            Ok(BaseProductsImportReport {
+                dry_run,
+                committed,
+                base_products_count: base_product_ids.len(),
+                variants_count,
+                base_product_ids: if committed { base_product_ids } else { vec![] },
+                errors,
+            })
+        })
+    }
+
     /// Updates specific product
     fn update_base_product(&self, base_product_id: BaseProductId, payload: UpdateBaseProduct) -> ServiceFuture<BaseProduct> {
         let user_id = self.dynamic_context.user_id;
@@ -719,6 +933,8 @@ impl<
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
             let products_repo = repo_factory.create_product_repo(&*conn, user_id);
             let product_attrs_repo = repo_factory.create_product_attrs_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+            let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
             conn.transaction::<BaseProduct, FailureError, _>(move || {
                 let old_prod = base_products_repo.find(base_product_id, Visibility::Active)?;
                 if let Some(old_prod) = old_prod {
@@ -728,7 +944,12 @@ impl<
                     if let Some(new_cat_id) = payload.category_id {
                         // updating product categories of the store
                         if old_prod.category_id != new_cat_id {
-                            let _ = after_base_product_category_update(&*products_repo, &*product_attrs_repo, base_product_id);
+                            let _ = after_base_product_category_update(
+                                &*products_repo,
+                                &*product_attrs_repo,
+                                &*outbox_repo,
+                                base_product_id,
+                            );
                         }
                         let _ = update_product_categories(&*stores_repo, old_prod.store_id, old_prod.category_id, new_cat_id)?;
                     }
@@ -736,11 +957,20 @@ impl<
                     if let Some(currency) = payload.currency {
                         // updating currency of base_products variants
                         products_repo.update_currency(currency, updated_prod.id)?;
+                        let products = products_repo.find_with_base_id(updated_prod.id)?;
+                        write_product_events(&*outbox_repo, OutboxEventType::Updated, &products)?;
                     }
 
-                    match updated_prod.status {
-                        ModerationStatus::Decline => base_products_repo.set_moderation_status(updated_prod.id, ModerationStatus::Draft),
-                        _ => Ok(updated_prod),
+                    match moderation_status_after_edit(updated_prod.status) {
+                        Some(status) => {
+                            let change = ModerationStatusChange::new(status, ModerationReason::EditedAfterDecline);
+                            change_base_product_status(&*base_products_repo, &*outbox_repo, &recorder, updated_prod.id, change)
+                        }
+                        None => {
+                            write_base_product_event(&*outbox_repo, OutboxEventType::Updated, &updated_prod)?;
+
+                            Ok(updated_prod)
+                        }
                     }
                 } else {
                     Err(Error::NotFound.into())
@@ -767,7 +997,9 @@ impl<
                 //find products
                 let products = products_repo.find_many(products_ids)?;
                 let mut group_by_base_product_id = BTreeMap::<BaseProductId, Vec<RawProduct>>::default();
-                for product in products {
+                for mut product in products {
+                    // cart needs explicit stock status to flag unavailable variants
+                    product.stock_status = Some(product.effective_stock_status());
                     let p = group_by_base_product_id.entry(product.base_product_id).or_insert_with(Vec::new);
                     p.push(product);
                 }
@@ -864,9 +1096,27 @@ impl<
 
         self.spawn_on_pool(move |conn| {
             let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
-            base_products_repo
-                .set_moderation_statuses(base_product_ids, status)
-                .map_err(|e: FailureError| {
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+            let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
+            conn.transaction::<Vec<BaseProduct>, FailureError, _>(move || {
+                let old_statuses = base_products_repo
+                    .find_many(base_product_ids.clone())?
+                    .into_iter()
+                    .map(|base_product| (base_product.id, base_product.status))
+                    .collect::<HashMap<_, _>>();
+                let base_products = base_products_repo.set_moderation_statuses(base_product_ids, status)?;
+                write_base_product_events(&*outbox_repo, OutboxEventType::Updated, &base_products)?;
+
+                let change = ModerationStatusChange::new(status, ModerationReason::ModeratorDecision);
+                for base_product in &base_products {
+                    if let Some(old_status) = old_statuses.get(&base_product.id) {
+                        recorder.record(ModerationEntityType::BaseProduct, base_product.id.0, *old_status, &change)?;
+                    }
+                }
+
+                Ok(base_products)
+            })
+            .map_err(|e: FailureError| {
                     e.context("Service base_products, set_moderation_status_base_products endpoint error occurred.")
                         .into()
                 })
@@ -874,7 +1124,13 @@ impl<
     }
 
     /// Set moderation status for base_product_id
-    fn set_moderation_status_base_product(&self, base_product_id: BaseProductId, status: ModerationStatus) -> ServiceFuture<BaseProduct> {
+    fn set_moderation_status_base_product(
+        &self,
+        base_product_id: BaseProductId,
+        status: ModerationStatus,
+        comment: Option<String>,
+        feedback: Vec<ModerationFeedbackPayload>,
+    ) -> ServiceFuture<BaseProduct> {
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
         info!("Set moderation status {} for base_product {}", status, base_product_id);
@@ -882,22 +1138,15 @@ impl<
         self.spawn_on_pool(move |conn| {
             {
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
-                let base_product = base_products_repo.find(base_product_id, Visibility::Active)?;
-
-                let current_status = match base_product {
-                    Some(value) => value.status,
-                    None => return Err(Error::NotFound.into()),
-                };
-
-                if check_change_status(current_status, status) {
-                    base_products_repo.set_moderation_status(base_product_id, status)
-                } else {
-                    Err(format_err!("Base product status: {} not valid for set", status)
-                        .context(Error::Validate(
-                            validation_errors!({"base_products": ["base_products" => "Base product new status is not valid"]}),
-                        ))
-                        .into())
-                }
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
+
+                conn.transaction::<BaseProduct, FailureError, _>(move || {
+                    let change = ModerationStatusChange::new(status, ModerationReason::ModeratorDecision)
+                        .with_comment(comment)
+                        .with_feedback(feedback);
+                    change_base_product_status(&*base_products_repo, &*outbox_repo, &recorder, base_product_id, change)
+                })
             }
             .map_err(|e: FailureError| {
                 e.context("Service base_products, set_moderation_status_base_product endpoint error occurred.")
@@ -910,29 +1159,36 @@ impl<
     fn send_base_product_to_moderation(&self, base_product_id: BaseProductId) -> ServiceFuture<BaseProduct> {
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
+        let pre_moderation = self.pre_moderation_engine();
         info!("Send base product: {} to moderation", base_product_id);
 
         self.spawn_on_pool(move |conn| {
             {
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
-                let base_product = base_products_repo.find(base_product_id, Visibility::Active)?;
-
-                let status = match base_product {
-                    Some(value) => value.status,
-                    None => return Err(Error::NotFound.into()),
-                };
+                let products_repo = repo_factory.create_product_repo(&conn, user_id);
+                let stores_repo = repo_factory.create_stores_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
+
+                conn.transaction::<BaseProduct, FailureError, _>(move || {
+                    let submission = find_base_product_submission(&*base_products_repo, &*products_repo, &*stores_repo, base_product_id)?;
+                    let report = pre_moderation.check(&submission);
+                    let change = ModerationStatusChange::new(ModerationStatus::Moderation, ModerationReason::SentToModeration)
+                        .with_review_notes(report.review_notes());
+                    let base_product =
+                        apply_base_product_status_change(&*base_products_repo, &*outbox_repo, &recorder, &submission.base_product, change)?;
+                    if !report.is_declined() {
+                        return Ok(base_product);
+                    }
 
-                if check_change_status(status, ModerationStatus::Moderation) {
-                    base_products_repo.set_moderation_status(base_product_id, ModerationStatus::Moderation)
-                } else {
-                    Err(
-                        format_err!("Base product with id: {}, cannot be sent to moderation", base_product_id)
-                            .context(Error::Validate(
-                                validation_errors!({"base_products": ["base_products" => "Base product can not be sent to moderation"]}),
-                            ))
-                            .into(),
-                    )
-                }
+                    info!(
+                        "Base product: {} failed pre-moderation checks: {:?}",
+                        base_product_id, report.findings
+                    );
+                    let change = ModerationStatusChange::new(ModerationStatus::Decline, ModerationReason::PreModerationCheck)
+                        .with_feedback(report.feedback());
+                    apply_base_product_status_change(&*base_products_repo, &*outbox_repo, &recorder, &base_product, change)
+                })
             }
             .map_err(|e: FailureError| {
                 e.context("Service base_products, send_base_product_to_moderation endpoint error occurred.")
@@ -950,8 +1206,13 @@ impl<
         self.spawn_on_pool(move |conn| {
             {
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
 
-                set_base_product_moderation_status_draft(&*base_products_repo, base_product_id)
+                conn.transaction::<BaseProduct, FailureError, _>(move || {
+                    let change = ModerationStatusChange::new(ModerationStatus::Draft, ModerationReason::HiddenBySeller);
+                    change_base_product_status(&*base_products_repo, &*outbox_repo, &recorder, base_product_id, change)
+                })
             }
             .map_err(|e: FailureError| {
                 e.context("Service base_products, set_base_product_moderation_status_draft endpoint error occurred.")
@@ -979,7 +1240,7 @@ impl<
                 None => return Err(Error::NotFound.into()),
             };
 
-            Ok(check_change_status(current_status, status))
+            Ok(find_moderation_transition(current_status, status).is_some())
         })
     }
 
@@ -1135,9 +1396,11 @@ impl<
             {
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
                 let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
 
                 conn.transaction::<Vec<BaseProduct>, FailureError, _>(move || {
                     let update_products = base_products_repo.replace_category(payload.clone())?;
+                    write_base_product_events(&*outbox_repo, OutboxEventType::Updated, &update_products)?;
 
                     for base_product in update_products.iter() {
                         let _ = update_product_categories(
@@ -1170,7 +1433,7 @@ impl<
                 None => return Err(Error::NotFound.into()),
             };
 
-            Ok(check_can_update_by_status(current_status))
+            Ok(is_editable_status(current_status))
         })
     }
 }
@@ -1178,6 +1441,7 @@ impl<
 fn after_base_product_category_update(
     products_repo: &ProductsRepo,
     product_attrs_repo: &ProductAttrsRepo,
+    outbox_repo: &OutboxEventsRepo,
     base_prod_id: BaseProductId,
 ) -> Result<(), FailureError> {
     product_attrs_repo.delete_by_base_product_id(base_prod_id)?;
@@ -1185,11 +1449,105 @@ fn after_base_product_category_update(
     all_products.sort_by_key(|p| p.created_at);
     //delete all except the first one
     for product in all_products.iter().skip(1) {
-        products_repo.deactivate(product.id)?;
+        let product = products_repo.deactivate(product.id)?;
+        write_product_event(outbox_repo, OutboxEventType::Deactivated, &product)?;
     }
     Ok(())
 }
 
+/// Parses base products import CSV, rows with the same `base_product_key` are grouped into one base product
+pub fn parse_base_products_import(store_id: StoreId, payload: &str) -> (Vec<BaseProductImportGroup>, Vec<BaseProductsImportRowError>) {
+    let mut reader = csv::Reader::from_reader(payload.as_bytes());
+    let mut groups: Vec<BaseProductImportGroup> = vec![];
+    // `None` marks base products which first row is invalid
+    let mut group_indices = HashMap::<String, Option<usize>>::new();
+    let mut errors = vec![];
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            errors.push(BaseProductsImportRowError::new(1, None, format!("Invalid CSV header: {}", e)));
+            return (groups, errors);
+        }
+    };
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                errors.push(BaseProductsImportRowError::new(line, None, format!("Invalid CSV row: {}", e)));
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+
+        let row = match record.deserialize::<BaseProductImportRow>(Some(&headers)) {
+            Ok(row) => row,
+            Err(e) => {
+                errors.push(BaseProductsImportRowError::new(line, None, format!("Invalid CSV row: {}", e)));
+                continue;
+            }
+        };
+        let vendor_code = Some(row.vendor_code.clone());
+
+        let variant = match row.new_variant().and_then(|variant| variant.validate().map(|_| variant)) {
+            Ok(variant) => BaseProductImportVariant { line, variant },
+            Err(e) => {
+                errors.push(BaseProductsImportRowError::validation(line, vendor_code, e));
+                continue;
+            }
+        };
+
+        match group_indices.get(&row.base_product_key).cloned() {
+            Some(Some(index)) => groups[index].variants.push(variant),
+            Some(None) => errors.push(BaseProductsImportRowError::new(
+                line,
+                vendor_code,
+                format!("Base product {} is invalid.", row.base_product_key),
+            )),
+            None => {
+                let new_base_product = row
+                    .new_base_product(store_id)
+                    .and_then(|new_base_product| new_base_product.validate().map(|_| new_base_product));
+                match new_base_product {
+                    Ok(new_base_product) => {
+                        group_indices.insert(row.base_product_key, Some(groups.len()));
+                        groups.push(BaseProductImportGroup {
+                            line,
+                            new_base_product,
+                            variants: vec![variant],
+                        });
+                    }
+                    Err(e) => {
+                        group_indices.insert(row.base_product_key, None);
+                        errors.push(BaseProductsImportRowError::validation(line, vendor_code, e));
+                    }
+                }
+            }
+        }
+    }
+
+    (groups, errors)
+}
+
+fn import_row_error(line: u64, vendor_code: Option<String>, error: &FailureError) -> BaseProductsImportRowError {
+    let validation_errors = error
+        .iter_chain()
+        .filter_map(|cause| match cause.downcast_ref::<Error>() {
+            Some(&Error::Validate(ref e)) => serde_json::to_value(e).ok(),
+            _ => None,
+        })
+        .next();
+
+    BaseProductsImportRowError {
+        line,
+        vendor_code,
+        message: error.find_root_cause().to_string(),
+        validation_errors,
+    }
+}
+
 fn validate_base_product(base_products_repo: &BaseProductsRepo, payload: &NewBaseProduct) -> Result<(), FailureError> {
     if let Some(base_product_slug) = payload.slug.clone() {
         let base_product_with_same_slug =
@@ -1273,42 +1631,6 @@ fn get_path_to_searched_category(searched_category: Option<Category>, root: Cate
     }
 }
 
-fn get_attribute_filters(el_products: Vec<ElasticProduct>) -> Option<Vec<AttributeFilter>> {
-    let mut equal_attrs = HashMap::<i32, HashSet<String>>::default();
-    let mut range_attrs = HashMap::<i32, RangeFilter>::default();
-
-    for product in el_products {
-        for variant in product.variants {
-            for attr_value in variant.attrs {
-                if let Some(value) = attr_value.str_val {
-                    let equal = equal_attrs.entry(attr_value.attr_id).or_insert_with(HashSet::<String>::default);
-                    equal.insert(value);
-                }
-                if let Some(value) = attr_value.float_val {
-                    let range = range_attrs.entry(attr_value.attr_id).or_insert_with(RangeFilter::default);
-                    range.add_value(value);
-                }
-            }
-        }
-    }
-
-    let eq_filters = equal_attrs.into_iter().map(|(k, v)| AttributeFilter {
-        id: k,
-        equal: Some(EqualFilter {
-            values: v.iter().cloned().collect(),
-        }),
-        range: None,
-    });
-
-    let range_filters = range_attrs.into_iter().map(|(k, v)| AttributeFilter {
-        id: k,
-        equal: None,
-        range: Some(v),
-    });
-
-    Some(eq_filters.chain(range_filters).collect())
-}
-
 fn get_first_level_category(third_level_category_id: CategoryId, root: Category) -> RepoResult<Category> {
     root.children
         .into_iter()
@@ -1355,30 +1677,76 @@ fn update_product_categories(
     Ok(())
 }
 
-pub fn set_base_product_moderation_status_draft(
+/// Changes moderation status of the base product according to moderation state machine
+pub fn change_base_product_status(
     base_products_repo: &BaseProductsRepo,
+    outbox_repo: &OutboxEventsRepo,
+    recorder: &ModerationRecorder,
     base_product_id: BaseProductId,
+    change: ModerationStatusChange,
 ) -> RepoResult<BaseProduct> {
-    let base_product = base_products_repo.find(base_product_id, Visibility::Active)?;
+    let base_product = match base_products_repo.find(base_product_id, Visibility::Active)? {
+        Some(value) => value,
+        None => return Err(Error::NotFound.into()),
+    };
 
-    let status = match base_product {
-        Some(value) => value.status,
+    apply_base_product_status_change(base_products_repo, outbox_repo, recorder, &base_product, change)
+}
+
+/// Changes moderation status of already loaded base product, returns the updated one
+fn apply_base_product_status_change(
+    base_products_repo: &BaseProductsRepo,
+    outbox_repo: &OutboxEventsRepo,
+    recorder: &ModerationRecorder,
+    base_product: &BaseProduct,
+    change: ModerationStatusChange,
+) -> RepoResult<BaseProduct> {
+    let status = base_product.status;
+    let side_effects = check_moderation_transition(ModerationEntityType::BaseProduct, base_product.id.0, status, &change)?;
+
+    let updated_base_product = base_products_repo.set_moderation_status(base_product.id, change.status)?;
+    for side_effect in side_effects {
+        match side_effect {
+            ModerationSideEffect::Reindex => write_base_product_event(outbox_repo, OutboxEventType::Updated, &updated_base_product)?,
+            // Moderation queue is kept in sync by recorder, base products have nothing to cascade to
+            ModerationSideEffect::Enqueue | ModerationSideEffect::Dequeue | ModerationSideEffect::CascadeToBaseProducts => {}
+        }
+    }
+    recorder.record(ModerationEntityType::BaseProduct, base_product.id.0, status, &change)?;
+
+    Ok(updated_base_product)
+}
+
+/// Loads base product sent to moderation with its store and variants for pre-moderation checks
+fn find_base_product_submission(
+    base_products_repo: &BaseProductsRepo,
+    products_repo: &ProductsRepo,
+    stores_repo: &StoresRepo,
+    base_product_id: BaseProductId,
+) -> RepoResult<BaseProductSubmission> {
+    let base_product = match base_products_repo.find(base_product_id, Visibility::Active)? {
+        Some(value) => value,
         None => return Err(Error::NotFound.into()),
     };
+    let store = match stores_repo.find(base_product.store_id, Visibility::Active)? {
+        Some(value) => value,
+        None => return Err(Error::NotFound.into()),
+    };
+    let variants = products_repo.find_with_base_id(base_product_id)?;
 
-    if check_change_status(status, ModerationStatus::Draft) {
-        base_products_repo.set_moderation_status(base_product_id, ModerationStatus::Draft)
+    let vendor_codes = variants.iter().map(|variant| variant.vendor_code.clone()).collect::<Vec<_>>();
+    let store_variants = if vendor_codes.is_empty() {
+        vec![]
     } else {
-        Err(format_err!(
-            "Base product with id: {}, cannot be hided when the store in status: {}",
-            base_product_id,
-            status
-        )
-        .context(Error::Validate(
-            validation_errors!({"base_products": ["base_products" => "Base product cannot be hided"]}),
-        ))
-        .into())
-    }
+        products_repo.find_by_vendor_codes(base_product.store_id, vendor_codes)?
+    };
+
+    Ok(BaseProductSubmission {
+        base_product,
+        store,
+        variants,
+        store_variants,
+    })
 }
 
 #[cfg(test)]
@@ -1389,7 +1757,7 @@ pub mod tests {
     use tokio_core::reactor::Core;
     use uuid::Uuid;
 
-    use stq_static_resources::Currency;
+    use stq_static_resources::{Currency, ModerationStatus};
     use stq_types::*;
 
     use models::*;
@@ -1476,6 +1844,74 @@ pub mod tests {
         assert_eq!(result.id, MOCK_BASE_PRODUCT_ID);
     }
 
+    const IMPORT_CSV_HEADER: &'static str = "base_product_key,name,short_description,long_description,seo_title,seo_description,\
+                                             category_id,currency,slug,length_cm,width_cm,height_cm,weight_g,vendor_code,price,\
+                                             discount,cashback,photo_main,additional_photos,pre_order,pre_order_days,stock_status,\
+                                             attributes";
+    const IMPORT_CSV_NAME: &'static str = r#""[{""lang"": ""en"", ""text"": ""Shoes""}]""#;
+
+    fn create_import_row(key: &str, name: &str, slug: &str, vendor_code: &str, stock_status: &str, attributes: &str) -> String {
+        format!(
+            "{},{},{},,,,3,STQ,{},,,,,{},10,,,,,,,{},{}",
+            key, name, IMPORT_CSV_NAME, slug, vendor_code, stock_status, attributes
+        )
+    }
+
+    fn create_import_csv(rows: Vec<String>) -> String {
+        let mut csv = IMPORT_CSV_HEADER.to_string();
+        for row in rows {
+            csv.push('\n');
+            csv.push_str(&row);
+        }
+        csv
+    }
+
+    #[test]
+    fn test_parse_base_products_import() {
+        let csv = create_import_csv(vec![
+            create_import_row("shoes", IMPORT_CSV_NAME, "shoes", "SH-1", "", "1:red;2:xl"),
+            create_import_row("shoes", IMPORT_CSV_NAME, "shoes", "SH-2", "InStock", ""),
+        ]);
+        let (groups, errors) = parse_base_products_import(MOCK_STORE_ID, &csv);
+        assert!(errors.is_empty());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].line, 2);
+        assert_eq!(groups[0].variants.len(), 2);
+        assert_eq!(groups[0].variants[0].variant.attributes.len(), 2);
+        assert_eq!(groups[0].variants[1].line, 3);
+        assert_eq!(groups[0].variants[1].variant.product.stock_status, Some(StockStatus::InStock));
+    }
+
+    #[test]
+    fn test_parse_base_products_import_reports_invalid_rows() {
+        let csv = create_import_csv(vec![
+            create_import_row("shoes", IMPORT_CSV_NAME, "Bad Slug", "SH-1", "", ""),
+            create_import_row("shoes", IMPORT_CSV_NAME, "shoes", "SH-2", "", ""),
+            create_import_row("hats", "not json", "hats", "HT-1", "", ""),
+            create_import_row("caps", IMPORT_CSV_NAME, "caps", "CP-1", "", "red"),
+        ]);
+        let (groups, errors) = parse_base_products_import(MOCK_STORE_ID, &csv);
+        assert!(groups.is_empty());
+        assert_eq!(errors.iter().map(|e| e.line).collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        assert_eq!(errors[0].vendor_code, Some("SH-1".to_string()));
+        assert!(errors[0].validation_errors.is_some());
+    }
+
+    #[test]
+    fn test_import_base_products_dry_run() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let csv = create_import_csv(vec![create_import_row("shoes", IMPORT_CSV_NAME, "shoes", "SH-1", "", "")]);
+        let work = service.import_base_products(MOCK_STORE_ID, csv, true);
+        let result = core.run(work).unwrap();
+        assert!(result.errors.is_empty());
+        assert_eq!(result.committed, false);
+        assert_eq!(result.base_products_count, 1);
+        assert_eq!(result.variants_count, 1);
+        assert!(result.base_product_ids.is_empty());
+    }
+
     #[test]
     fn test_deactivate() {
         let mut core = Core::new().unwrap();
@@ -1486,4 +1922,14 @@ pub mod tests {
         assert_eq!(result.id, BaseProductId(1));
         assert_eq!(result.is_active, false);
     }
+
+    #[test]
+    fn test_send_base_product_failing_pre_moderation_checks_is_declined() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.send_base_product_to_moderation(BaseProductId(1));
+        let result = core.run(work).unwrap();
+        assert_eq!(result.status, ModerationStatus::Decline);
+    }
 }
@@ -1,19 +1,54 @@
 //! Rocket Retail Services, provides data from rocket-retail service
 
+use std::time::{Duration, SystemTime};
+
+use csv;
 use diesel::connection::{AnsiTransactionManager, Connection};
 use diesel::pg::Pg;
+use failure::Error as FailureError;
+use failure::Fail;
+use futures::{stream, Stream};
 use r2d2::ManageConnection;
+use serde_json;
 
 use stq_types::newtypes::UserId;
+use stq_types::{BaseProductId, ProductId, StoreId};
 
 use super::types::ServiceFuture;
 use controller::responses::catalogs::*;
+use errors::Error;
 use models::visibility::Visibility;
+use models::{
+    BaseProductImportRow, CatalogExportFormat, CatalogFeedChanges, CatalogFeedCursor, CatalogFeedPosition, CatalogWithAttributes,
+};
 use repos::repo_factory::ReposFactory;
+use repos::BaseProductsRepo;
 use services::Service;
 
+/// Number of base products loaded from db at once during catalog export
+const CATALOG_EXPORT_CHUNK_SIZE: i64 = 100;
+
+/// Next feed cursor is moved back by this number of seconds,
+/// so rows updated in transactions committed during the request are not missed
+const CATALOG_FEED_CURSOR_OVERLAP_SECS: u64 = 60;
+
+/// Number of rows of every kind returned in catalog feed page if limit is not given
+const CATALOG_FEED_DEFAULT_LIMIT: i64 = 1000;
+
+const CATALOG_FEED_MAX_LIMIT: i64 = 10_000;
+
+/// Serialized store catalog sent to the client chunk by chunk
+pub type CatalogExportStream = Box<Stream<Item = Vec<u8>, Error = FailureError> + Send>;
+
 pub trait CatalogService {
     fn get_catalog(&self) -> ServiceFuture<CatalogResponse>;
+
+    /// Returns active base products of the store with variants and attributes
+    fn export_store_catalog(&self, store_id: StoreId, format: CatalogExportFormat) -> ServiceFuture<CatalogExportStream>;
+
+    /// Returns page of catalog rows updated since cursor and ids of rows removed from catalog,
+    /// at most `limit` rows of every kind
+    fn get_catalog_feed(&self, cursor: CatalogFeedCursor, limit: Option<i64>) -> ServiceFuture<CatalogFeedResponse>;
 }
 
 impl<
@@ -68,4 +103,308 @@ impl<
             })
         })
     }
+
+    /// Returns active base products of the store with variants and attributes
+    fn export_store_catalog(&self, store_id: StoreId, format: CatalogExportFormat) -> ServiceFuture<CatalogExportStream> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let db_pool = self.static_context.db_pool.clone();
+        let cpu_pool = self.static_context.cpu_pool.clone();
+
+        self.spawn_on_pool(move |conn| {
+            {
+                {
+                    let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+                    stores_repo
+                        .find(store_id, Visibility::Active)?
+                        .ok_or_else(|| format_err!("Store with id {} not found", store_id).context(Error::NotFound))?;
+                }
+
+                // Next chunk is loaded only when the client has taken the previous one,
+                // db connection is taken from the pool for every chunk, so slow clients do not hold it
+                let start = CatalogExportPosition {
+                    from: BaseProductId(0),
+                    with_header: true,
+                };
+                let chunks = stream::unfold(Some(start), move |position| {
+                    position.map(|position| {
+                        let repo_factory = repo_factory.clone();
+                        let db_pool = db_pool.clone();
+                        cpu_pool.spawn_fn(move || {
+                            let conn = db_pool.get().map_err(|e| e.context(Error::Connection))?;
+                            let base_product_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+                            load_store_catalog_chunk(&*base_product_repo, store_id, format, position)
+                        })
+                    })
+                });
+
+                Ok(Box::new(chunks.filter(|bytes| !bytes.is_empty())) as CatalogExportStream)
+            }
+            .map_err(|e: FailureError| e.context("Service Catalog, export_store_catalog endpoint error occurred.").into())
+        })
+    }
+
+    /// Returns page of catalog rows updated since cursor and ids of rows removed from catalog,
+    /// at most `limit` rows of every kind
+    fn get_catalog_feed(&self, cursor: CatalogFeedCursor, limit: Option<i64>) -> ServiceFuture<CatalogFeedResponse> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
+            let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let base_product_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+            let product_repo = repo_factory.create_product_repo(&*conn, user_id);
+            let product_attrs_repo = repo_factory.create_product_attrs_repo(&*conn, user_id);
+
+            {
+                let limit = catalog_feed_limit(limit)?;
+                let overlapped_now = SystemTime::now() - Duration::from_secs(CATALOG_FEED_CURSOR_OVERLAP_SECS);
+
+                let categories = categories_repo.find_updated_since(cursor.categories, limit)?;
+                let stores = stores_repo.find_updated_since(cursor.stores, limit)?;
+                let base_products = base_product_repo.find_updated_since(cursor.base_products, limit)?;
+                let products = product_repo.find_updated_since(cursor.products, limit)?;
+
+                let has_more = categories.is_full_page(limit)
+                    || stores.is_full_page(limit)
+                    || base_products.is_full_page(limit)
+                    || products.is_full_page(limit);
+                let next_cursor = CatalogFeedCursor {
+                    categories: next_feed_position(cursor.categories, &categories, limit, overlapped_now),
+                    stores: next_feed_position(cursor.stores, &stores, limit, overlapped_now),
+                    base_products: next_feed_position(cursor.base_products, &base_products, limit, overlapped_now),
+                    products: next_feed_position(cursor.products, &products, limit, overlapped_now),
+                };
+
+                // Base product could become visible again without changes in its variants
+                let base_product_ids = base_products.updated.iter().map(|base_product| base_product.id).collect();
+                let mut updated_products = products.updated;
+                updated_products.extend(product_repo.find_with_base_ids(base_product_ids)?);
+                updated_products.sort_by_key(|product| product.id.0);
+                updated_products.dedup_by_key(|product| product.id);
+
+                let product_ids = updated_products.iter().map(|product| product.id).collect::<Vec<ProductId>>();
+                let prod_attrs = product_attrs_repo.find_with_attributes_by_products(product_ids)?;
+
+                Ok(CatalogFeedResponse {
+                    categories: categories.updated.into_iter().map(From::from).collect(),
+                    stores: stores.updated.into_iter().map(From::from).collect(),
+                    base_products: base_products.updated.into_iter().map(From::from).collect(),
+                    products: updated_products.into_iter().map(From::from).collect(),
+                    prod_attrs: prod_attrs.into_iter().map(From::from).collect(),
+                    deleted: CatalogFeedDeleted {
+                        category_ids: categories.deleted,
+                        store_ids: stores.deleted,
+                        base_product_ids: base_products.deleted,
+                        product_ids: products.deleted,
+                    },
+                    next_cursor: next_cursor.to_string(),
+                    has_more,
+                })
+            }
+            .map_err(|e: FailureError| e.context("Service Catalog, get_catalog_feed endpoint error occurred.").into())
+        })
+    }
+}
+
+fn catalog_feed_limit(limit: Option<i64>) -> Result<i64, FailureError> {
+    match limit {
+        None => Ok(CATALOG_FEED_DEFAULT_LIMIT),
+        Some(limit) if limit > 0 && limit <= CATALOG_FEED_MAX_LIMIT => Ok(limit),
+        Some(limit) => Err(format_err!("Catalog feed limit {} is out of range", limit)
+            .context(Error::Validate(
+                validation_errors!({"limit": ["limit" => "Limit must be positive and not greater than 10000"]}),
+            ))
+            .into()),
+    }
+}
+
+/// Position to continue feed of one kind of rows from. Feed goes on after the last row of a full page,
+/// otherwise all rows are sent and the feed continues from a moment slightly in the past.
+fn next_feed_position<T, Id>(
+    position: CatalogFeedPosition,
+    changes: &CatalogFeedChanges<T, Id>,
+    limit: i64,
+    overlapped_now: SystemTime,
+) -> CatalogFeedPosition {
+    match changes.last_position {
+        Some(last_position) if changes.is_full_page(limit) => last_position,
+        _ if overlapped_now > position.updated_at => CatalogFeedPosition::since(overlapped_now),
+        _ => position,
+    }
+}
+
+/// Position of store catalog export
+#[derive(Clone, Copy, Debug)]
+struct CatalogExportPosition {
+    from: BaseProductId,
+    /// CSV header is not written yet, it goes before the first exported row
+    with_header: bool,
+}
+
+/// Loads chunk of store catalog and serializes it, returns position of the next chunk unless the whole catalog is loaded
+fn load_store_catalog_chunk(
+    base_product_repo: &BaseProductsRepo,
+    store_id: StoreId,
+    format: CatalogExportFormat,
+    position: CatalogExportPosition,
+) -> Result<(Vec<u8>, Option<CatalogExportPosition>), FailureError> {
+    let chunk = base_product_repo.get_catalog_of_the_store(store_id, position.from, CATALOG_EXPORT_CHUNK_SIZE)?;
+    let bytes = write_catalog_export_chunk(format, chunk.catalog, position.with_header)?;
+    let next_position = chunk.next_from.map(|from| CatalogExportPosition {
+        from,
+        with_header: position.with_header && bytes.is_empty(),
+    });
+
+    Ok((bytes, next_position))
+}
+
+/// Serializes base products of catalog export chunk, CSV header is written before the first row if `with_header` is set
+fn write_catalog_export_chunk(
+    format: CatalogExportFormat,
+    catalog: Vec<CatalogWithAttributes>,
+    with_header: bool,
+) -> Result<Vec<u8>, FailureError> {
+    match format {
+        CatalogExportFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new().has_headers(with_header).from_writer(vec![]);
+            for base_product in &catalog {
+                for variant in &base_product.variants {
+                    writer.serialize(BaseProductImportRow::from_catalog(&base_product.base_product, variant))?;
+                }
+            }
+
+            writer
+                .into_inner()
+                .map_err(|e| format_err!("Writing catalog CSV failed: {}", e).context(Error::Internal).into())
+        }
+        CatalogExportFormat::JsonLines => {
+            let mut buffer = vec![];
+            for base_product in catalog {
+                serde_json::to_writer(&mut buffer, &CatalogExportBaseProduct::from(base_product))?;
+                buffer.push(b'\n');
+            }
+
+            Ok(buffer)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use futures::{Future, Stream};
+    use tokio_core::reactor::Core;
+
+    use stq_types::{BaseProductId, ProductId};
+
+    use serde_json;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::types::ServiceFuture;
+    use services::*;
+
+    use super::CatalogExportStream;
+
+    #[test]
+    fn test_export_store_catalog_csv() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.export_store_catalog(MOCK_STORE_ID, CatalogExportFormat::Csv);
+        let result = run_export(&mut core, work);
+        let lines = result.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("base_product_key,name,"));
+
+        let (groups, errors) = parse_base_products_import(MOCK_STORE_ID, &result);
+        assert!(errors.is_empty());
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn test_get_catalog_feed() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_catalog_feed(CatalogFeedCursor::default(), None);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.products.len(), 1);
+        assert_eq!(result.prod_attrs.len(), 1);
+        assert_eq!(result.deleted.base_product_ids, vec![BaseProductId(MOCK_BASE_PRODUCT_ID.0 + 1)]);
+        assert_eq!(result.deleted.product_ids, vec![ProductId(MOCK_PRODUCT_ID.0 + 1)]);
+
+        assert!(!result.has_more);
+
+        let next_cursor = result.next_cursor.parse::<CatalogFeedCursor>().unwrap();
+        assert!(next_cursor.products.updated_at > UNIX_EPOCH);
+        assert_eq!(next_cursor.products.last_id, 0);
+    }
+
+    #[test]
+    fn test_get_catalog_feed_page() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_catalog_feed(CatalogFeedCursor::default(), Some(2));
+        let result = core.run(work).unwrap();
+        assert!(result.has_more);
+
+        let next_cursor = result.next_cursor.parse::<CatalogFeedCursor>().unwrap();
+        assert_eq!(next_cursor.base_products.last_id, MOCK_BASE_PRODUCT_ID.0 + 1);
+        assert_eq!(next_cursor.products.last_id, MOCK_PRODUCT_ID.0 + 1);
+        assert_eq!(next_cursor.stores.last_id, 0);
+
+        let work = service.get_catalog_feed(CatalogFeedCursor::default(), Some(0));
+        assert!(core.run(work).is_err());
+    }
+
+    #[test]
+    fn test_catalog_feed_cursor() {
+        let updated_at = UNIX_EPOCH + Duration::new(1_583_020_800, 123_456_000);
+        let mut cursor = CatalogFeedCursor::since(updated_at);
+        cursor.products = CatalogFeedPosition::new(updated_at + Duration::from_secs(1), 42);
+        assert_eq!(cursor.to_string().parse::<CatalogFeedCursor>(), Ok(cursor));
+        assert!("not a cursor".parse::<CatalogFeedCursor>().is_err());
+
+        let legacy_cursor = "59fbfc076a240";
+        assert_eq!(
+            legacy_cursor.parse::<CatalogFeedCursor>(),
+            Ok(CatalogFeedCursor::since(UNIX_EPOCH + Duration::new(1_583_020_800, 123_456_000)))
+        );
+    }
+
+    #[test]
+    fn test_export_store_catalog_json_lines() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.export_store_catalog(MOCK_STORE_ID, CatalogExportFormat::JsonLines);
+        let result = run_export(&mut core, work);
+        assert_eq!(result.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_export_store_catalog_skips_unreadable_base_products() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.export_store_catalog(MOCK_STORE_ID, CatalogExportFormat::JsonLines);
+        let result = run_export(&mut core, work);
+        let base_products = result
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(base_products.len(), 1);
+        assert_eq!(base_products[0]["id"], MOCK_BASE_PRODUCT_ID.0);
+    }
+
+    fn run_export(core: &mut Core, work: ServiceFuture<CatalogExportStream>) -> String {
+        let bytes = core.run(work.and_then(|stream| stream.concat2())).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
 }
@@ -1,5 +1,6 @@
 //! Coupons Services, presents CRUD operations with coupons
 
+use std::collections::{HashMap, HashSet};
 use std::time::SystemTime;
 
 use diesel::connection::AnsiTransactionManager;
@@ -7,20 +8,26 @@ use diesel::pg::Pg;
 use diesel::Connection;
 use r2d2::ManageConnection;
 
+use csv;
 use failure::Error as FailureError;
 use future::IntoFuture;
 use futures::future;
 
 use uuid::prelude::*;
 
-use stq_types::{BaseProductId, CouponId, UserId};
+use stq_static_resources::currency_type::CurrencyType;
+use stq_static_resources::Currency;
+use stq_types::{BaseProductId, CategoryId, CouponCode, CouponId, ExchangeRate, UserId};
 
 use super::types::ServiceFuture;
 use errors::Error;
 use models::*;
 use repos::CouponSearch;
 
-use repos::{CouponValidate, RepoResult, ReposFactory, UsedCouponSearch};
+use repos::{
+    get_all_children_till_the_end, BaseProductsSearchTerms, CouponValidate, CouponValidateResult, CurrencyExchangeRepo, RepoResult,
+    ReposFactory, UsedCouponSearch,
+};
 use services::products::calculate_product_customer_price;
 use services::Service;
 
@@ -43,18 +50,42 @@ pub trait CouponsService {
     fn add_base_product_coupon(&self, id_arg: CouponId, base_product_arg: BaseProductId) -> ServiceFuture<CouponScopeBaseProducts>;
     /// Delete base_product from coupon
     fn delete_base_product_from_coupon(&self, id_arg: CouponId, base_product_arg: BaseProductId) -> ServiceFuture<CouponScopeBaseProducts>;
-    /// Find base products for coupon
+    /// Add category to coupon
+    fn add_category_coupon(&self, id_arg: CouponId, category_arg: CategoryId) -> ServiceFuture<CouponScopeCategories>;
+    /// Delete category from coupon
+    fn delete_category_from_coupon(&self, id_arg: CouponId, category_arg: CategoryId) -> ServiceFuture<CouponScopeCategories>;
+    /// Find categories for coupon
+    fn find_categories_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<CategoryId>>;
+    /// Exclude base_product from coupon
+    fn add_excluded_base_product_coupon(
+        &self,
+        id_arg: CouponId,
+        base_product_arg: BaseProductId,
+    ) -> ServiceFuture<CouponExclusionBaseProducts>;
+    /// Delete base_product from coupon exclusions
+    fn delete_excluded_base_product_from_coupon(
+        &self,
+        id_arg: CouponId,
+        base_product_arg: BaseProductId,
+    ) -> ServiceFuture<CouponExclusionBaseProducts>;
+    /// Find base products excluded from coupon
+    fn find_excluded_base_products_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<BaseProductId>>;
+    /// Find base products for coupon, resolved by coupon scope without excluded base products
     fn find_base_products_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<BaseProductWithVariants>>;
     /// Generate coupon code
     fn generate_coupon_code(&self) -> ServiceFuture<String>;
+    /// Creates batch of coupons with unique generated codes
+    fn create_coupons_batch(&self, payload: NewCouponsBatch) -> ServiceFuture<CouponsBatch>;
+    /// Returns coupons of batch in CSV
+    fn export_coupons_batch(&self, campaign_id: Uuid) -> ServiceFuture<String>;
     /// Add used coupon for user
     fn add_used_coupon(&self, coupon_id: CouponId, user_id: UserId) -> ServiceFuture<UsedCoupon>;
     /// Delete coupon for user
     fn delete_used_coupon(&self, coupon_id: CouponId, user_id: UserId) -> ServiceFuture<UsedCoupon>;
     /// Validate coupon by coupon code
-    fn validate_coupon_by_code(&self, payload: CouponsSearchCodePayload) -> ServiceFuture<Option<CouponValidate>>;
-    /// Validate coupon by coupon id
-    fn validate_coupon(&self, id_arg: CouponId) -> ServiceFuture<Option<CouponValidate>>;
+    fn validate_coupon_by_code(&self, payload: CouponsSearchCodePayload) -> ServiceFuture<Option<CouponValidateResult>>;
+    /// Validate coupon by coupon id, `subtotal` is cart subtotal in customer's currency
+    fn validate_coupon(&self, id_arg: CouponId, subtotal: Option<f64>) -> ServiceFuture<Option<CouponValidateResult>>;
 }
 
 impl<
@@ -71,10 +102,12 @@ impl<
         self.spawn_on_pool(move |conn| {
             let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
             conn.transaction::<Coupon, FailureError, _>(move || {
-                coupon_repo
-                    .create(payload)
-                    .map_err(|e| e.context("Service Coupons, create endpoint error occurred.").into())
+                check_coupon_discount(&payload)?;
+                check_coupon_limits(&payload)?;
+
+                coupon_repo.create(payload)
             })
+            .map_err(|e| e.context("Service Coupons, create endpoint error occurred.").into())
         })
     }
 
@@ -141,10 +174,15 @@ impl<
 
         self.spawn_on_pool(move |conn| {
             let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
+            conn.transaction::<Coupon, FailureError, _>(move || {
+                let coupon = coupon_repo
+                    .get(id_arg)?
+                    .ok_or(format_err!("Not found such coupon id : {}", id_arg).context(Error::NotFound))?;
+                check_updated_coupon_limits(&coupon, &payload)?;
 
-            coupon_repo
-                .update(id_arg, payload)
-                .map_err(|e| e.context("Service Coupons, update_coupon endpoint error occurred.").into())
+                coupon_repo.update(id_arg, payload)
+            })
+            .map_err(|e| e.context("Service Coupons, update_coupon endpoint error occurred.").into())
         })
     }
 
@@ -219,7 +257,151 @@ impl<
         })
     }
 
-    /// Find base products for coupon
+    /// Add category to coupon
+    fn add_category_coupon(&self, coupon_id: CouponId, category_id: CategoryId) -> ServiceFuture<CouponScopeCategories> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let payload = NewCouponScopeCategories { coupon_id, category_id };
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_scope_categories_repo = repo_factory.create_coupon_scope_categories_repo(&*conn, user_id);
+            let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
+            let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
+
+            conn.transaction::<CouponScopeCategories, FailureError, _>(move || {
+                let coupon = coupon_repo
+                    .get(coupon_id)?
+                    .ok_or(format_err!("Not found such coupon id : {}", coupon_id).context(Error::NotFound))?;
+
+                if coupon.scope != CouponScope::Categories {
+                    return Err(format_err!("Coupon {} is not scoped to categories.", coupon_id)
+                        .context(Error::Validate(
+                            validation_errors!({"scope": ["scope" => "Categories can only be added to coupon with categories scope"]}),
+                        ))
+                        .into());
+                }
+
+                if categories_repo.find(category_id)?.is_none() {
+                    return Err(format_err!("Not found such category id : {}", category_id)
+                        .context(Error::NotFound)
+                        .into());
+                }
+
+                coupon_scope_categories_repo.create(payload)
+            })
+            .map_err(|e| e.context("Service Coupons, add_category_coupon endpoint error occurred.").into())
+        })
+    }
+
+    /// Delete category from coupon
+    fn delete_category_from_coupon(&self, id_arg: CouponId, category_arg: CategoryId) -> ServiceFuture<CouponScopeCategories> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_scope_categories_repo = repo_factory.create_coupon_scope_categories_repo(&*conn, user_id);
+
+            coupon_scope_categories_repo.delete(id_arg, category_arg).map_err(|e| {
+                e.context("Service Coupons, delete_category_from_coupon endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Find categories for coupon
+    fn find_categories_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<CategoryId>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_scope_categories_repo = repo_factory.create_coupon_scope_categories_repo(&*conn, user_id);
+
+            coupon_scope_categories_repo.find_categories(id_arg).map_err(|e| {
+                e.context("Service Coupons, find_categories_by_coupon endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Exclude base_product from coupon
+    fn add_excluded_base_product_coupon(
+        &self,
+        coupon_id: CouponId,
+        base_product_id: BaseProductId,
+    ) -> ServiceFuture<CouponExclusionBaseProducts> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let payload = NewCouponExclusionBaseProducts {
+            coupon_id,
+            base_product_id,
+        };
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_exclusion_base_products_repo = repo_factory.create_coupon_exclusion_base_products_repo(&*conn, user_id);
+            let base_product_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+            let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
+
+            conn.transaction::<CouponExclusionBaseProducts, FailureError, _>(move || {
+                let base_product = base_product_repo.find(base_product_id, Visibility::Active)?;
+                let coupon = coupon_repo.get(coupon_id)?;
+
+                match (base_product, coupon) {
+                    (Some(ref base_product), Some(ref coupon)) if base_product.store_id == coupon.store_id => {}
+                    _ => {
+                        return Err(format_err!(
+                            "Coupon {} and base product {} do not belong to same store.",
+                            coupon_id,
+                            base_product_id
+                        )
+                        .context(Error::Forbidden)
+                        .into());
+                    }
+                }
+
+                coupon_exclusion_base_products_repo.create(payload)
+            })
+            .map_err(|e| {
+                e.context("Service Coupons, add_excluded_base_product_coupon endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Delete base_product from coupon exclusions
+    fn delete_excluded_base_product_from_coupon(
+        &self,
+        id_arg: CouponId,
+        base_product_arg: BaseProductId,
+    ) -> ServiceFuture<CouponExclusionBaseProducts> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_exclusion_base_products_repo = repo_factory.create_coupon_exclusion_base_products_repo(&*conn, user_id);
+
+            coupon_exclusion_base_products_repo.delete(id_arg, base_product_arg).map_err(|e| {
+                e.context("Service Coupons, delete_excluded_base_product_from_coupon endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Find base products excluded from coupon
+    fn find_excluded_base_products_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<BaseProductId>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_exclusion_base_products_repo = repo_factory.create_coupon_exclusion_base_products_repo(&*conn, user_id);
+
+            coupon_exclusion_base_products_repo.find_base_products(id_arg).map_err(|e| {
+                e.context("Service Coupons, find_excluded_base_products_by_coupon endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Find base products for coupon, resolved by coupon scope without excluded base products
     fn find_base_products_by_coupon(&self, id_arg: CouponId) -> ServiceFuture<Vec<BaseProductWithVariants>> {
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
@@ -228,16 +410,59 @@ impl<
 
         self.spawn_on_pool(move |conn| {
             {
+                let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
                 let coupon_scope_base_products_repo = repo_factory.create_coupon_scope_base_products_repo(&*conn, user_id);
+                let coupon_scope_categories_repo = repo_factory.create_coupon_scope_categories_repo(&*conn, user_id);
+                let coupon_exclusion_base_products_repo = repo_factory.create_coupon_exclusion_base_products_repo(&*conn, user_id);
+                let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
                 let products_repo = repo_factory.create_product_repo(&*conn, user_id);
                 let currency_exchange = repo_factory.create_currency_exchange_repo(&*conn, user_id);
 
-                let base_product_ids = coupon_scope_base_products_repo.find_base_products(id_arg)?;
-                let base_products = base_products_repo.find_many(base_product_ids)?;
+                let coupon = match coupon_repo.get(id_arg)? {
+                    Some(coupon) => coupon,
+                    None => {
+                        return Err(format_err!("Not found such coupon id : {}", id_arg)
+                            .context(Error::NotFound)
+                            .into());
+                    }
+                };
+
+                let base_products = match coupon.scope {
+                    CouponScope::Store => base_products_repo.search(BaseProductsSearchTerms {
+                        is_active: Some(true),
+                        store_id: Some(coupon.store_id),
+                        ..Default::default()
+                    })?,
+                    CouponScope::Categories => {
+                        let mut category_ids = vec![];
+                        for category_id in coupon_scope_categories_repo.find_categories(id_arg)? {
+                            if let Some(category) = categories_repo.find(category_id)? {
+                                category_ids.extend(get_all_children_till_the_end(category).into_iter().map(|c| c.id));
+                            }
+                        }
+
+                        base_products_repo.search(BaseProductsSearchTerms {
+                            is_active: Some(true),
+                            store_id: Some(coupon.store_id),
+                            category_ids: Some(category_ids),
+                            ..Default::default()
+                        })?
+                    }
+                    CouponScope::BaseProducts => {
+                        let base_product_ids = coupon_scope_base_products_repo.find_base_products(id_arg)?;
+                        base_products_repo.find_many(base_product_ids)?
+                    }
+                };
+
+                let excluded_base_product_ids = coupon_exclusion_base_products_repo.find_base_products(id_arg)?;
 
                 let mut results = vec![];
                 for base_product in base_products {
+                    if excluded_base_product_ids.contains(&base_product.id) {
+                        continue;
+                    }
+
                     let raw_products = products_repo.find_with_base_id(base_product.id)?;
 
                     let result_products = raw_products
@@ -264,12 +489,86 @@ impl<
 
     /// Generate coupon code
     fn generate_coupon_code(&self) -> ServiceFuture<String> {
-        let new_uuid = Uuid::new_v4().simple().to_string().to_uppercase();
-        let result = Ok(new_uuid.chars().take(Coupon::MIN_GENERATE_LENGTH_CODE).collect::<String>());
+        let result = Ok(generate_random_code());
 
         Box::new(result.into_future())
     }
 
+    /// Creates batch of coupons with unique generated codes
+    fn create_coupons_batch(&self, payload: NewCouponsBatch) -> ServiceFuture<CouponsBatch> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
+            conn.transaction::<CouponsBatch, FailureError, _>(move || {
+                let campaign_id = Uuid::new_v4();
+                let prefix = payload.prefix.to_uppercase();
+                let count = payload.count as usize;
+
+                let mut codes = HashSet::with_capacity(count);
+                let mut attempts = 0;
+                while codes.len() < count {
+                    if attempts >= Coupon::MAX_BATCH_GENERATE_ATTEMPTS {
+                        return Err(format_err!("Could not generate {} unique codes with prefix {}.", count, prefix)
+                            .context(Error::Internal)
+                            .into());
+                    }
+                    attempts += 1;
+
+                    let mut candidates = HashSet::new();
+                    while codes.len() + candidates.len() < count {
+                        let code = format!("{}{}", prefix, generate_random_code());
+                        if !codes.contains(&code) {
+                            candidates.insert(code);
+                        }
+                    }
+
+                    let search = CouponSearch::Codes(payload.store_id, candidates.iter().cloned().map(CouponCode).collect());
+                    let existing_codes = coupon_repo
+                        .find_by(search)?
+                        .into_iter()
+                        .map(|coupon| coupon.code.0)
+                        .collect::<HashSet<_>>();
+
+                    codes.extend(candidates.into_iter().filter(|code| !existing_codes.contains(code)));
+                }
+
+                let new_coupons = codes
+                    .into_iter()
+                    .map(|code| payload.to_new_coupon(CouponCode(code), campaign_id))
+                    .collect::<Vec<_>>();
+                if let Some(new_coupon) = new_coupons.first() {
+                    check_coupon_discount(new_coupon)?;
+                    check_coupon_limits(new_coupon)?;
+                }
+
+                let coupons = coupon_repo.create_batch(new_coupons)?;
+
+                Ok(CouponsBatch { campaign_id, coupons })
+            })
+            .map_err(|e| e.context("Service Coupons, create_coupons_batch endpoint error occurred.").into())
+        })
+    }
+
+    /// Returns coupons of batch in CSV
+    fn export_coupons_batch(&self, campaign_id: Uuid) -> ServiceFuture<String> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let coupon_repo = repo_factory.create_coupon_repo(&*conn, user_id);
+
+            coupon_repo
+                .find_by(CouponSearch::Campaign(campaign_id))
+                .and_then(|mut coupons| {
+                    coupons.sort_by_key(|coupon| coupon.id.0);
+                    coupons_to_csv(coupons)
+                })
+                .map_err(|e| e.context("Service Coupons, export_coupons_batch endpoint error occurred.").into())
+        })
+    }
+
     /// Add used coupon for user
     fn add_used_coupon(&self, coupon_id_arg: CouponId, user_id_arg: UserId) -> ServiceFuture<UsedCoupon> {
         let user_id = self.dynamic_context.user_id;
@@ -304,8 +603,10 @@ impl<
     }
 
     /// Validate coupon by coupon code
-    fn validate_coupon_by_code(&self, payload: CouponsSearchCodePayload) -> ServiceFuture<Option<CouponValidate>> {
+    fn validate_coupon_by_code(&self, payload: CouponsSearchCodePayload) -> ServiceFuture<Option<CouponValidateResult>> {
         let repo_factory = self.static_context.repo_factory.clone();
+        let currency = self.dynamic_context.currency;
+        let fiat_currency = self.dynamic_context.fiat_currency;
 
         let user_id = match self.dynamic_context.user_id {
             Some(user_id) => user_id,
@@ -322,14 +623,23 @@ impl<
             {
                 let used_coupons_repo = repo_factory.create_used_coupons_repo(&*conn, Some(user_id));
                 let coupon_repo = repo_factory.create_coupon_repo(&*conn, Some(user_id));
+                let currency_exchange = repo_factory.create_currency_exchange_repo(&*conn, Some(user_id));
 
+                let subtotal = payload.subtotal;
                 let coupon = coupon_repo.get_by_code(payload.code, payload.store_id)?;
 
                 if let Some(coupon) = coupon {
                     let search_used_coupon = UsedCouponSearch::Coupon(coupon.id);
                     let used_coupons = used_coupons_repo.find_by(search_used_coupon)?;
 
-                    Ok(Some(validate_coupon(coupon, user_id, used_coupons)))
+                    let coupon_currency_map = get_coupon_currency_map(&*currency_exchange, &coupon)?;
+                    let discount = calculate_coupon_discount(&coupon, &coupon_currency_map, currency, fiat_currency);
+                    let subtotal = subtotal.map(|subtotal| {
+                        calculate_coupon_subtotal(&coupon, subtotal, &coupon_currency_map, currency, fiat_currency)
+                    });
+                    let status = validate_coupon(coupon, user_id, used_coupons, subtotal);
+
+                    Ok(Some(CouponValidateResult { status, discount }))
                 } else {
                     Ok(None)
                 }
@@ -341,9 +651,11 @@ impl<
         })
     }
 
-    /// Validate coupon by coupon id
-    fn validate_coupon(&self, id_arg: CouponId) -> ServiceFuture<Option<CouponValidate>> {
+    /// Validate coupon by coupon id, `subtotal` is cart subtotal in customer's currency
+    fn validate_coupon(&self, id_arg: CouponId, subtotal: Option<f64>) -> ServiceFuture<Option<CouponValidateResult>> {
         let repo_factory = self.static_context.repo_factory.clone();
+        let currency = self.dynamic_context.currency;
+        let fiat_currency = self.dynamic_context.fiat_currency;
 
         let user_id = match self.dynamic_context.user_id {
             Some(user_id) => user_id,
@@ -360,6 +672,7 @@ impl<
             {
                 let used_coupons_repo = repo_factory.create_used_coupons_repo(&*conn, Some(user_id));
                 let coupon_repo = repo_factory.create_coupon_repo(&*conn, Some(user_id));
+                let currency_exchange = repo_factory.create_currency_exchange_repo(&*conn, Some(user_id));
 
                 let coupon = coupon_repo.get(id_arg)?;
 
@@ -367,7 +680,14 @@ impl<
                     let search_used_coupon = UsedCouponSearch::Coupon(coupon.id);
                     let used_coupons = used_coupons_repo.find_by(search_used_coupon)?;
 
-                    Ok(Some(validate_coupon(coupon, user_id, used_coupons)))
+                    let coupon_currency_map = get_coupon_currency_map(&*currency_exchange, &coupon)?;
+                    let discount = calculate_coupon_discount(&coupon, &coupon_currency_map, currency, fiat_currency);
+                    let subtotal = subtotal.map(|subtotal| {
+                        calculate_coupon_subtotal(&coupon, subtotal, &coupon_currency_map, currency, fiat_currency)
+                    });
+                    let status = validate_coupon(coupon, user_id, used_coupons, subtotal);
+
+                    Ok(Some(CouponValidateResult { status, discount }))
                 } else {
                     Ok(None)
                 }
@@ -377,51 +697,235 @@ impl<
     }
 }
 
-pub fn validate_coupon(coupon: Coupon, user_id: UserId, used_coupons: Vec<UsedCoupon>) -> CouponValidate {
+/// Generates random code of `Coupon::MIN_GENERATE_LENGTH_CODE` characters
+pub fn generate_random_code() -> String {
+    let new_uuid = Uuid::new_v4().simple().to_string().to_uppercase();
+    new_uuid.chars().take(Coupon::MIN_GENERATE_LENGTH_CODE).collect::<String>()
+}
+
+pub fn coupons_to_csv(coupons: Vec<Coupon>) -> RepoResult<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for coupon in coupons {
+        writer.serialize(CouponCsvRecord::from(coupon))?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| format_err!("Writing coupons CSV failed: {}", e).context(Error::Internal))?;
+
+    String::from_utf8(bytes).map_err(|e| format_err!("Coupons CSV is not valid UTF-8: {}", e).context(Error::Internal).into())
+}
+
+/// Checks that coupon has all fields required by its discount type
+pub fn check_coupon_discount(payload: &NewCoupon) -> RepoResult<()> {
+    match payload.discount_type {
+        CouponType::FixedAmount if payload.amount.is_none() || payload.currency.is_none() => Err(format_err!(
+            "Fixed amount coupon {} must have amount and currency.",
+            payload.code
+        )
+        .context(Error::Validate(
+            validation_errors!({"amount": ["amount" => "Fixed amount coupon must have amount and currency"]}),
+        ))
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks coupon minimum subtotal and activity period
+pub fn check_coupon_limits(payload: &NewCoupon) -> RepoResult<()> {
+    check_limits(
+        &payload.code,
+        payload.min_subtotal,
+        payload.currency,
+        payload.active_from,
+        payload.expired_at,
+    )
+}
+
+/// Checks minimum subtotal and activity period of coupon with update applied
+pub fn check_updated_coupon_limits(coupon: &Coupon, payload: &UpdateCoupon) -> RepoResult<()> {
+    check_limits(
+        &coupon.code,
+        payload.min_subtotal.or(coupon.min_subtotal),
+        payload.currency.or(coupon.currency),
+        payload.active_from.or(coupon.active_from),
+        payload.expired_at.or(coupon.expired_at),
+    )
+}
+
+fn check_limits(
+    code: &CouponCode,
+    min_subtotal: Option<f64>,
+    currency: Option<Currency>,
+    active_from: Option<SystemTime>,
+    expired_at: Option<SystemTime>,
+) -> RepoResult<()> {
+    if min_subtotal.is_some() && currency.is_none() {
+        return Err(format_err!("Coupon {} with minimum subtotal must have currency.", code)
+            .context(Error::Validate(
+                validation_errors!({"min_subtotal": ["min_subtotal" => "Coupon with minimum subtotal must have currency"]}),
+            ))
+            .into());
+    }
+
+    if let (Some(active_from), Some(expired_at)) = (active_from, expired_at) {
+        if active_from >= expired_at {
+            return Err(format_err!("Coupon {} expires before it becomes active.", code)
+                .context(Error::Validate(
+                    validation_errors!({"active_from": ["active_from" => "Coupon must become active before expiration"]}),
+                ))
+                .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns exchange rates for coupon currency if coupon has one
+pub fn get_coupon_currency_map(
+    currency_exchange: &CurrencyExchangeRepo,
+    coupon: &Coupon,
+) -> RepoResult<Option<HashMap<Currency, ExchangeRate>>> {
+    match coupon.currency {
+        Some(coupon_currency) => currency_exchange.get_exchange_for_currency(coupon_currency),
+        None => Ok(None),
+    }
+}
+
+fn coupon_customer_currency(coupon_currency: Currency, crypto_currency: Currency, fiat_currency: Currency) -> Currency {
+    match coupon_currency.currency_type() {
+        CurrencyType::Crypto => crypto_currency,
+        CurrencyType::Fiat => fiat_currency,
+    }
+}
+
+pub fn calculate_coupon_discount(
+    coupon: &Coupon,
+    coupon_currency_map: &Option<HashMap<Currency, ExchangeRate>>,
+    crypto_currency: Currency,
+    fiat_currency: Currency,
+) -> CouponDiscount {
+    match coupon.discount_type {
+        CouponType::Percent => CouponDiscount {
+            discount_type: CouponType::Percent,
+            value: coupon.percent as f64,
+            currency: None,
+        },
+        CouponType::FreeShipping => CouponDiscount {
+            discount_type: CouponType::FreeShipping,
+            value: 0f64,
+            currency: None,
+        },
+        CouponType::FixedAmount => {
+            let amount = coupon.amount.unwrap_or_default();
+            let coupon_currency = match coupon.currency {
+                Some(coupon_currency) => coupon_currency,
+                None => {
+                    return CouponDiscount {
+                        discount_type: CouponType::FixedAmount,
+                        value: amount,
+                        currency: None,
+                    };
+                }
+            };
+
+            let header_currency = coupon_customer_currency(coupon_currency, crypto_currency, fiat_currency);
+
+            if let Some(currency_map) = coupon_currency_map {
+                CouponDiscount {
+                    discount_type: CouponType::FixedAmount,
+                    value: amount / currency_map.get(&header_currency).map(|c| c.0).unwrap_or(1.0),
+                    currency: Some(header_currency),
+                }
+            } else {
+                // Without exchange rates discount stays in coupon currency
+                CouponDiscount {
+                    discount_type: CouponType::FixedAmount,
+                    value: amount,
+                    currency: Some(coupon_currency),
+                }
+            }
+        }
+    }
+}
+
+/// Converts cart subtotal from customer's currency to coupon currency
+pub fn calculate_coupon_subtotal(
+    coupon: &Coupon,
+    subtotal: f64,
+    coupon_currency_map: &Option<HashMap<Currency, ExchangeRate>>,
+    crypto_currency: Currency,
+    fiat_currency: Currency,
+) -> f64 {
+    match (coupon.currency, coupon_currency_map) {
+        (Some(coupon_currency), Some(currency_map)) => {
+            let header_currency = coupon_customer_currency(coupon_currency, crypto_currency, fiat_currency);
+            subtotal * currency_map.get(&header_currency).map(|c| c.0).unwrap_or(1.0)
+        }
+        _ => subtotal,
+    }
+}
+
+/// Validates coupon for user, `subtotal` must be presented in coupon currency
+pub fn validate_coupon(coupon: Coupon, user_id: UserId, used_coupons: Vec<UsedCoupon>, subtotal: Option<f64>) -> CouponValidate {
     if !coupon.is_active {
         return CouponValidate::NotActive;
     }
 
+    let now = SystemTime::now();
+
+    if let Some(active_from) = coupon.active_from {
+        if active_from > now {
+            return CouponValidate::NotStarted;
+        }
+    }
+
     if let Some(expired_at) = coupon.expired_at {
-        let now = SystemTime::now();
         if expired_at < now {
             return CouponValidate::HasExpired;
         }
     }
 
-    let user_used_coupon = used_coupons.iter().find(|c| c.user_id == user_id);
-    if user_used_coupon.is_some() {
-        return CouponValidate::AlreadyActivated;
-    }
-
-    if coupon.quantity == Coupon::INFINITE {
-        return CouponValidate::Valid;
+    let user_times_used: i32 = used_coupons.iter().filter(|c| c.user_id == user_id).map(|c| c.times_used).sum();
+    if coupon.per_user_limit != Coupon::INFINITE && user_times_used >= coupon.per_user_limit {
+        return if coupon.per_user_limit == Coupon::DEFAULT_PER_USER_LIMIT {
+            CouponValidate::AlreadyActivated
+        } else {
+            CouponValidate::PerUserLimitReached
+        };
     }
 
     if coupon.quantity < 0 {
         return CouponValidate::NoActivationsAvailable;
     }
 
-    let check_result = match (used_coupons.len(), coupon.quantity as usize) {
-        (used_coupons_count, quantity) if used_coupons_count >= quantity => Some(CouponValidate::NoActivationsAvailable),
-        (used_coupons_count, quantity) if used_coupons_count < quantity => Some(CouponValidate::Valid),
-        (_, _) => unreachable!(),
-    };
+    let times_used: i32 = used_coupons.iter().map(|c| c.times_used).sum();
+    if coupon.quantity != Coupon::INFINITE && times_used >= coupon.quantity {
+        return CouponValidate::NoActivationsAvailable;
+    }
 
-    match check_result {
-        Some(check_result) => check_result,
-        None => CouponValidate::Valid,
+    if let Some(min_subtotal) = coupon.min_subtotal {
+        match subtotal {
+            None => return CouponValidate::SubtotalRequired,
+            Some(subtotal) if subtotal < min_subtotal => return CouponValidate::BelowMinimumSubtotal,
+            Some(_) => {}
+        }
     }
+
+    CouponValidate::Valid
 }
 
 #[cfg(test)]
 pub mod tests {
+    use std::collections::HashMap;
     use std::sync::Arc;
 
     use std::time::{self, Duration, SystemTime};
     use tokio_core::reactor::Core;
 
+    use stq_static_resources::Currency;
     use stq_types::*;
+    use uuid::Uuid;
 
     use models::*;
     use repos::repo_factory::tests::*;
@@ -438,6 +942,13 @@ pub mod tests {
             percent: 0,
             quantity: 1,
             expired_at: Some(SystemTime::now() + time::Duration::from_secs(3600)),
+            discount_type: CouponType::Percent,
+            amount: None,
+            currency: None,
+            per_user_limit: None,
+            min_subtotal: None,
+            active_from: None,
+            campaign_id: None,
         }
     }
 
@@ -470,6 +981,7 @@ pub mod tests {
         let payload = CouponsSearchCodePayload {
             code: CouponCode(MOCK_COUPON_CODE.to_string()),
             store_id: StoreId(1),
+            subtotal: None,
         };
         let work = service.get_coupon_by_code(payload);
         let result = core.run(work);
@@ -527,8 +1039,88 @@ pub mod tests {
     }
 
     #[test]
-    #[ignore]
-    fn test_find_base_products_by_coupon() {}
+    fn test_add_category_to_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.add_category_coupon(MOCK_CATEGORIES_COUPON_ID, CategoryId(1));
+        let result = core.run(work).unwrap();
+        assert_eq!(result.coupon_id, MOCK_CATEGORIES_COUPON_ID);
+    }
+
+    #[test]
+    fn test_add_category_to_coupon_with_other_scope() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.add_category_coupon(MOCK_COUPON_ID, CategoryId(1));
+        let result = core.run(work);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_delete_category_from_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.delete_category_from_coupon(MOCK_COUPON_ID, CategoryId(1));
+        let result = core.run(work);
+        assert_eq!(result.unwrap().category_id, CategoryId(1));
+    }
+
+    #[test]
+    fn test_add_excluded_base_product_to_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.add_excluded_base_product_coupon(MOCK_COUPON_ID, MOCK_BASE_PRODUCT_ID);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.base_product_id, MOCK_BASE_PRODUCT_ID);
+    }
+
+    #[test]
+    fn test_delete_excluded_base_product_from_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.delete_excluded_base_product_from_coupon(MOCK_COUPON_ID, MOCK_BASE_PRODUCT_ID);
+        let result = core.run(work);
+        assert_eq!(result.unwrap().coupon_id, MOCK_COUPON_ID);
+    }
+
+    #[test]
+    fn test_find_base_products_by_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.find_base_products_by_coupon(MOCK_COUPON_ID);
+        let result = core.run(work).unwrap();
+        let base_product_ids = result.iter().map(|base_product| base_product.base_product.id).collect::<Vec<_>>();
+        assert_eq!(base_product_ids, vec![MOCK_BASE_PRODUCT_ID]);
+        assert_eq!(result[0].variants.len(), 1);
+    }
+
+    #[test]
+    fn test_find_base_products_by_coupon_with_store_scope() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.find_base_products_by_coupon(MOCK_STORE_COUPON_ID);
+        let result = core.run(work).unwrap();
+        let base_product_ids = result.iter().map(|base_product| base_product.base_product.id).collect::<Vec<_>>();
+        assert_eq!(base_product_ids, vec![MOCK_BASE_PRODUCT_ID, MOCK_OTHER_CATEGORY_BASE_PRODUCT_ID]);
+    }
+
+    #[test]
+    fn test_find_base_products_by_coupon_with_categories_scope() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.find_base_products_by_coupon(MOCK_CATEGORIES_COUPON_ID);
+        let result = core.run(work).unwrap();
+        let base_product_ids = result.iter().map(|base_product| base_product.base_product.id).collect::<Vec<_>>();
+        assert_eq!(base_product_ids, vec![MOCK_BASE_PRODUCT_ID]);
+    }
 
     #[test]
     fn test_generate_coupon_code() {
@@ -540,6 +1132,46 @@ pub mod tests {
         assert_eq!(result.unwrap().len(), Coupon::MIN_GENERATE_LENGTH_CODE);
     }
 
+    #[test]
+    fn test_create_coupons_batch() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = NewCouponsBatch {
+            prefix: "spring".to_string(),
+            count: 3,
+            title: "title".to_string(),
+            store_id: MOCK_STORE_ID,
+            scope: CouponScope::Store,
+            percent: 10,
+            quantity: 1,
+            expired_at: None,
+            discount_type: CouponType::Percent,
+            amount: None,
+            currency: None,
+            per_user_limit: None,
+            min_subtotal: None,
+            active_from: None,
+        };
+        let work = service.create_coupons_batch(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.coupons.len(), 3);
+        assert!(result.coupons.iter().all(|coupon| coupon.code.0.starts_with("SPRING")));
+        assert!(result.coupons.iter().all(|coupon| coupon.campaign_id == Some(result.campaign_id)));
+    }
+
+    #[test]
+    fn test_export_coupons_batch() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.export_coupons_batch(Uuid::new_v4());
+        let result = core.run(work).unwrap();
+        let mut lines = result.lines();
+        assert!(lines.next().unwrap().starts_with("code,campaign_id,store_id"));
+        assert!(lines.next().unwrap().starts_with(MOCK_COUPON_CODE));
+    }
+
     #[test]
     fn test_validate_coupon_code() {
         // only success run function
@@ -549,6 +1181,7 @@ pub mod tests {
         let payload = CouponsSearchCodePayload {
             code: CouponCode(MOCK_COUPON_CODE.to_string()),
             store_id: StoreId(1),
+            subtotal: None,
         };
         let work = service.validate_coupon_by_code(payload);
         let result = core.run(work);
@@ -568,6 +1201,13 @@ pub mod tests {
             is_active: true,
             created_at: SystemTime::now(),
             updated_at: SystemTime::now(),
+            discount_type: CouponType::Percent,
+            amount: None,
+            currency: None,
+            per_user_limit: Coupon::DEFAULT_PER_USER_LIMIT,
+            min_subtotal: None,
+            active_from: None,
+            campaign_id: None,
         }
     }
 
@@ -575,6 +1215,7 @@ pub mod tests {
         vec![UsedCoupon {
             coupon_id: MOCK_COUPON_ID,
             user_id: MOCK_USER_ID,
+            times_used: 1,
         }]
     }
 
@@ -590,7 +1231,7 @@ pub mod tests {
         infinity_coupon.quantity = Coupon::INFINITE;
         assert_eq!(
             CouponValidate::Valid,
-            validate_coupon(infinity_coupon, MOCK_USER_ID_PLUS1, used_coupons)
+            validate_coupon(infinity_coupon, MOCK_USER_ID_PLUS1, used_coupons, None)
         );
     }
 
@@ -603,7 +1244,7 @@ pub mod tests {
         not_active_coupon.is_active = false;
         assert_eq!(
             CouponValidate::NotActive,
-            validate_coupon(not_active_coupon, MOCK_USER_ID, used_coupons)
+            validate_coupon(not_active_coupon, MOCK_USER_ID, used_coupons, None)
         );
     }
 
@@ -615,7 +1256,7 @@ pub mod tests {
         let already_activated_coupon = test_coupon;
         assert_eq!(
             CouponValidate::AlreadyActivated,
-            validate_coupon(already_activated_coupon, MOCK_USER_ID, used_coupons)
+            validate_coupon(already_activated_coupon, MOCK_USER_ID, used_coupons, None)
         );
     }
 
@@ -628,7 +1269,7 @@ pub mod tests {
         has_expired_coupon.expired_at = Some(SystemTime::now() - Duration::from_secs(86400));
         assert_eq!(
             CouponValidate::HasExpired,
-            validate_coupon(has_expired_coupon, MOCK_USER_ID_PLUS1, used_coupons)
+            validate_coupon(has_expired_coupon, MOCK_USER_ID_PLUS1, used_coupons, None)
         );
     }
 
@@ -642,7 +1283,7 @@ pub mod tests {
         activations_available_coupon.quantity = 1;
         assert_eq!(
             CouponValidate::Valid,
-            validate_coupon(activations_available_coupon, MOCK_USER_ID_PLUS1, used_coupons)
+            validate_coupon(activations_available_coupon, MOCK_USER_ID_PLUS1, used_coupons, None)
         );
     }
 
@@ -654,6 +1295,7 @@ pub mod tests {
         used_coupons.push(UsedCoupon {
             coupon_id: MOCK_COUPON_ID,
             user_id: MOCK_USER_ID_PLUS2,
+            times_used: 1,
         });
 
         assert!(used_coupons.len() == 2);
@@ -662,7 +1304,115 @@ pub mod tests {
         no_activations_available_coupon.quantity = 1;
         assert_eq!(
             CouponValidate::NoActivationsAvailable,
-            validate_coupon(no_activations_available_coupon, MOCK_USER_ID_PLUS1, used_coupons)
+            validate_coupon(no_activations_available_coupon, MOCK_USER_ID_PLUS1, used_coupons, None)
+        );
+    }
+
+    #[test]
+    fn test_calculate_fixed_amount_coupon_discount() {
+        let mut coupon = create_test_coupon();
+        coupon.discount_type = CouponType::FixedAmount;
+        coupon.amount = Some(100.0);
+        coupon.currency = Some(Currency::STQ);
+
+        let mut currency_map = HashMap::new();
+        currency_map.insert(Currency::ETH, ExchangeRate(10.0));
+
+        let discount = calculate_coupon_discount(&coupon, &Some(currency_map), Currency::ETH, Currency::USD);
+        assert_eq!(
+            CouponDiscount {
+                discount_type: CouponType::FixedAmount,
+                value: 10.0,
+                currency: Some(Currency::ETH),
+            },
+            discount
         );
     }
+
+    #[test]
+    fn test_check_fixed_amount_coupon_without_currency() {
+        let mut new_coupon = create_new_coupon(CouponCode(MOCK_COUPON_CODE.to_string()));
+        new_coupon.discount_type = CouponType::FixedAmount;
+        new_coupon.amount = Some(100.0);
+        assert!(check_coupon_discount(&new_coupon).is_err());
+
+        new_coupon.currency = Some(Currency::STQ);
+        assert!(check_coupon_discount(&new_coupon).is_ok());
+    }
+
+    #[test]
+    fn test_validate_not_started_coupon() {
+        let test_coupon = create_test_coupon();
+        let used_coupons = create_used_coupons();
+
+        let mut not_started_coupon = test_coupon;
+        not_started_coupon.active_from = Some(SystemTime::now() + Duration::from_secs(3600));
+        assert_eq!(
+            CouponValidate::NotStarted,
+            validate_coupon(not_started_coupon, MOCK_USER_ID_PLUS1, used_coupons, None)
+        );
+    }
+
+    #[test]
+    fn test_validate_per_user_limit_reached_coupon() {
+        let test_coupon = create_test_coupon();
+        let mut used_coupons = create_used_coupons();
+
+        let mut multi_use_coupon = test_coupon;
+        multi_use_coupon.per_user_limit = 2;
+        assert_eq!(
+            CouponValidate::Valid,
+            validate_coupon(multi_use_coupon.clone(), MOCK_USER_ID, used_coupons.clone(), None)
+        );
+
+        used_coupons[0].times_used = 2;
+        assert_eq!(
+            CouponValidate::PerUserLimitReached,
+            validate_coupon(multi_use_coupon, MOCK_USER_ID, used_coupons, None)
+        );
+    }
+
+    #[test]
+    fn test_validate_below_minimum_subtotal_coupon() {
+        let test_coupon = create_test_coupon();
+        let used_coupons = create_used_coupons();
+
+        let mut min_subtotal_coupon = test_coupon;
+        min_subtotal_coupon.min_subtotal = Some(100.0);
+        assert_eq!(
+            CouponValidate::BelowMinimumSubtotal,
+            validate_coupon(min_subtotal_coupon.clone(), MOCK_USER_ID_PLUS1, used_coupons.clone(), Some(99.0))
+        );
+        assert_eq!(
+            CouponValidate::SubtotalRequired,
+            validate_coupon(min_subtotal_coupon.clone(), MOCK_USER_ID_PLUS1, used_coupons.clone(), None)
+        );
+        assert_eq!(
+            CouponValidate::Valid,
+            validate_coupon(min_subtotal_coupon, MOCK_USER_ID_PLUS1, used_coupons, Some(100.0))
+        );
+    }
+
+    #[test]
+    fn test_update_coupon_checks_limits_of_updated_coupon() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = UpdateCoupon {
+            min_subtotal: Some(100.0),
+            ..Default::default()
+        };
+        let work = service.update_coupon(MOCK_COUPON_ID, payload);
+        let result = core.run(work);
+        assert!(result.is_err());
+
+        let payload = UpdateCoupon {
+            min_subtotal: Some(100.0),
+            currency: Some(Currency::STQ),
+            ..Default::default()
+        };
+        let work = service.update_coupon(MOCK_COUPON_ID, payload);
+        let result = core.run(work);
+        assert!(result.is_ok());
+    }
 }
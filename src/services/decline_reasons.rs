@@ -0,0 +1,91 @@
+//! DeclineReasons Services, presents CRUD operations with catalog of decline reasons
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use r2d2::ManageConnection;
+
+use super::types::ServiceFuture;
+use models::{DeclineReason, NewDeclineReason, UpdateDeclineReason};
+use repos::ReposFactory;
+use services::Service;
+
+pub trait DeclineReasonsService {
+    /// Returns all decline reasons
+    fn list_decline_reasons(&self) -> ServiceFuture<Vec<DeclineReason>>;
+    /// Creates new decline reason
+    fn create_decline_reason(&self, payload: NewDeclineReason) -> ServiceFuture<DeclineReason>;
+    /// Updates specific decline reason
+    fn update_decline_reason(&self, id: i32, payload: UpdateDeclineReason) -> ServiceFuture<DeclineReason>;
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > DeclineReasonsService for Service<T, M, F>
+{
+    /// Returns all decline reasons
+    fn list_decline_reasons(&self) -> ServiceFuture<Vec<DeclineReason>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let decline_reasons_repo = repo_factory.create_decline_reasons_repo(&*conn, user_id);
+            decline_reasons_repo
+                .list()
+                .map_err(|e| e.context("Service DeclineReasons, list endpoint error occurred.").into())
+        })
+    }
+
+    /// Creates new decline reason
+    fn create_decline_reason(&self, payload: NewDeclineReason) -> ServiceFuture<DeclineReason> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let decline_reasons_repo = repo_factory.create_decline_reasons_repo(&*conn, user_id);
+            decline_reasons_repo
+                .create(payload)
+                .map_err(|e| e.context("Service DeclineReasons, create endpoint error occurred.").into())
+        })
+    }
+
+    /// Updates specific decline reason
+    fn update_decline_reason(&self, id: i32, payload: UpdateDeclineReason) -> ServiceFuture<DeclineReason> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let decline_reasons_repo = repo_factory.create_decline_reasons_repo(&*conn, user_id);
+            decline_reasons_repo
+                .update(id, payload)
+                .map_err(|e| e.context("Service DeclineReasons, update endpoint error occurred.").into())
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use serde_json;
+    use tokio_core::reactor::Core;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    #[test]
+    fn test_create_decline_reason() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = NewDeclineReason {
+            code: "counterfeit".to_string(),
+            name: serde_json::from_str(r#"[{"lang": "en","text": "Counterfeit"}]"#).unwrap(),
+        };
+        let work = service.create_decline_reason(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.code, "counterfeit");
+    }
+}
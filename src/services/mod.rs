@@ -1,6 +1,7 @@
 //! Services is a core layer for the app business logic like
 //! validation, authorization, etc.
 
+pub mod acl;
 pub mod attribute_values;
 pub mod attributes;
 pub mod base_products;
@@ -9,13 +10,20 @@ pub mod categories;
 pub mod coupons;
 pub mod currency_exchange;
 pub mod custom_attributes;
+pub mod decline_reasons;
+pub mod moderation;
 pub mod moderator_comments;
+pub mod outbox;
+pub mod pre_moderation;
 pub mod products;
+pub mod search_synonyms;
+pub mod store_staff;
 pub mod stores;
 pub mod types;
 pub mod user_roles;
 pub mod wizard_stores;
 
+pub use self::acl::*;
 pub use self::attribute_values::*;
 pub use self::attributes::*;
 pub use self::base_products::*;
@@ -24,8 +32,14 @@ pub use self::categories::*;
 pub use self::coupons::*;
 pub use self::currency_exchange::*;
 pub use self::custom_attributes::*;
+pub use self::decline_reasons::*;
+pub use self::moderation::*;
 pub use self::moderator_comments::*;
+pub use self::outbox::*;
+pub use self::pre_moderation::*;
 pub use self::products::*;
+pub use self::search_synonyms::*;
+pub use self::store_staff::*;
 pub use self::stores::*;
 pub use self::types::*;
 pub use self::user_roles::*;
@@ -0,0 +1,860 @@
+//! Moderation Services, presents moderation queue, history of moderation status changes, moderator feedback
+//! and moderation statuses allowed for user
+use std::time::SystemTime;
+
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use failure::Error as FailureError;
+use r2d2::ManageConnection;
+use serde_json;
+
+use stq_static_resources::ModerationStatus;
+use stq_types::{BaseProductId, StoreId, StoresRole, UserId};
+
+use super::types::ServiceFuture;
+use errors::Error;
+use models::*;
+use repos::{
+    BaseProductsRepo, DeclineReasonsRepo, ModerationFeedbackRepo, ModerationQueueRepo, ModerationTransitionsRepo, ReposFactory, StoresRepo,
+    UserRolesRepo,
+};
+use services::base_products::change_base_product_status;
+use services::stores::change_store_status;
+use services::Service;
+
+pub trait ModerationService {
+    /// Returns items of moderation queue matching filters with their waiting time
+    fn search_moderation_queue(&self, search: ModerationQueueSearch) -> ServiceFuture<Vec<ModerationQueueEntry>>;
+    /// Assigns queue item to the current moderator
+    fn claim_moderation_queue_item(&self, item_id: i32) -> ServiceFuture<ModerationQueueEntry>;
+    /// Returns queue item back to unassigned ones, moderators can release only items claimed by them
+    fn release_moderation_queue_item(&self, item_id: i32) -> ServiceFuture<ModerationQueueEntry>;
+    /// Returns all changes of moderation status of the store
+    fn get_store_moderation_history(&self, store_id: StoreId) -> ServiceFuture<Vec<ModerationTransition>>;
+    /// Returns all changes of moderation status of the base product
+    fn get_base_product_moderation_history(&self, base_product_id: BaseProductId) -> ServiceFuture<Vec<ModerationTransition>>;
+    /// Returns feedback of the last moderator decision if the store was declined with it
+    fn get_store_moderation_feedback(&self, store_id: StoreId) -> ServiceFuture<Vec<ModerationFeedback>>;
+    /// Returns feedback of the last moderator decision if the base product was declined with it
+    fn get_base_product_moderation_feedback(&self, base_product_id: BaseProductId) -> ServiceFuture<Vec<ModerationFeedback>>;
+    /// Returns usage of decline reasons in feedback matching filters, the most common reasons go first
+    fn get_decline_reasons_report(&self, search: ModerationFeedbackSearch) -> ServiceFuture<Vec<DeclineReasonStat>>;
+    /// Returns moderation statuses the current user can move the store to
+    fn get_store_moderation_transitions(&self, store_id: StoreId) -> ServiceFuture<AllowedModerationTransitions>;
+    /// Returns moderation statuses the current user can move the base product to
+    fn get_base_product_moderation_transitions(&self, base_product_id: BaseProductId) -> ServiceFuture<AllowedModerationTransitions>;
+    /// Applies moderator decision to every store, returns result of each of them
+    fn bulk_moderate_stores(&self, payload: BulkModeration<StoreId>) -> ServiceFuture<BulkModerationReport<StoreId>>;
+    /// Applies moderator decision to every base product, returns result of each of them
+    fn bulk_moderate_base_products(&self, payload: BulkModeration<BaseProductId>) -> ServiceFuture<BulkModerationReport<BaseProductId>>;
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > ModerationService for Service<T, M, F>
+{
+    /// Returns items of moderation queue matching filters with their waiting time
+    fn search_moderation_queue(&self, search: ModerationQueueSearch) -> ServiceFuture<Vec<ModerationQueueEntry>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let sla = self.moderation_sla();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_queue_repo = repo_factory.create_moderation_queue_repo(&*conn, user_id);
+            let now = SystemTime::now();
+            moderation_queue_repo
+                .search(search)
+                .map(|items| items.into_iter().map(|item| ModerationQueueEntry::new(item, sla, now)).collect())
+                .map_err(|e| {
+                    e.context("Service Moderation, search_moderation_queue endpoint error occurred.")
+                        .into()
+                })
+        })
+    }
+
+    /// Assigns queue item to the current moderator
+    fn claim_moderation_queue_item(&self, item_id: i32) -> ServiceFuture<ModerationQueueEntry> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let sla = self.moderation_sla();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_queue_repo = repo_factory.create_moderation_queue_repo(&*conn, user_id);
+
+            conn.transaction::<ModerationQueueItem, FailureError, _>(move || {
+                let moderator_id = user_id
+                    .ok_or_else(|| format_err!("Moderation queue item can be claimed by moderator only").context(Error::Forbidden))?;
+
+                // Item is claimed in a single update, so that two moderators can not claim it at the same time
+                match moderation_queue_repo.claim(item_id, moderator_id)? {
+                    Some(item) => Ok(item),
+                    None => Err(not_claimable_queue_item_error(&*moderation_queue_repo, item_id)?),
+                }
+            })
+            .map(|item| ModerationQueueEntry::new(item, sla, SystemTime::now()))
+            .map_err(|e| {
+                e.context("Service Moderation, claim_moderation_queue_item endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns queue item back to unassigned ones, moderators can release only items claimed by them
+    fn release_moderation_queue_item(&self, item_id: i32) -> ServiceFuture<ModerationQueueEntry> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let sla = self.moderation_sla();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_queue_repo = repo_factory.create_moderation_queue_repo(&*conn, user_id);
+            let user_roles_repo = repo_factory.create_user_roles_repo_with_sys_acl(&*conn);
+
+            conn.transaction::<ModerationQueueItem, FailureError, _>(move || {
+                let user_id = user_id
+                    .ok_or_else(|| format_err!("Moderation queue item can be released by moderator only").context(Error::Forbidden))?;
+                let is_superuser = user_roles_repo.list_for_user(user_id)?.contains(&StoresRole::Superuser);
+                let assignee_id = if is_superuser { None } else { Some(user_id) };
+
+                match moderation_queue_repo.release(item_id, assignee_id)? {
+                    Some(item) => Ok(item),
+                    None => Err(not_claimable_queue_item_error(&*moderation_queue_repo, item_id)?),
+                }
+            })
+            .map(|item| ModerationQueueEntry::new(item, sla, SystemTime::now()))
+            .map_err(|e| {
+                e.context("Service Moderation, release_moderation_queue_item endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns all changes of moderation status of the store
+    fn get_store_moderation_history(&self, store_id: StoreId) -> ServiceFuture<Vec<ModerationTransition>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_transitions_repo = repo_factory.create_moderation_transitions_repo(&*conn, user_id);
+            moderation_transitions_repo
+                .list_by_entity(ModerationEntityType::Store, store_id.0)
+                .map_err(|e| {
+                    e.context("Service Moderation, get_store_moderation_history endpoint error occurred.")
+                        .into()
+                })
+        })
+    }
+
+    /// Returns all changes of moderation status of the base product
+    fn get_base_product_moderation_history(&self, base_product_id: BaseProductId) -> ServiceFuture<Vec<ModerationTransition>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_transitions_repo = repo_factory.create_moderation_transitions_repo(&*conn, user_id);
+            moderation_transitions_repo
+                .list_by_entity(ModerationEntityType::BaseProduct, base_product_id.0)
+                .map_err(|e| {
+                    e.context("Service Moderation, get_base_product_moderation_history endpoint error occurred.")
+                        .into()
+                })
+        })
+    }
+
+    /// Returns feedback of the last moderator decision if the store was declined with it
+    fn get_store_moderation_feedback(&self, store_id: StoreId) -> ServiceFuture<Vec<ModerationFeedback>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_transitions_repo = repo_factory.create_moderation_transitions_repo(&*conn, user_id);
+            let moderation_feedback_repo = repo_factory.create_moderation_feedback_repo(&*conn, user_id);
+            find_last_decline_feedback(
+                &*moderation_transitions_repo,
+                &*moderation_feedback_repo,
+                ModerationEntityType::Store,
+                store_id.0,
+            )
+            .map_err(|e| {
+                e.context("Service Moderation, get_store_moderation_feedback endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns feedback of the last moderator decision if the base product was declined with it
+    fn get_base_product_moderation_feedback(&self, base_product_id: BaseProductId) -> ServiceFuture<Vec<ModerationFeedback>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_transitions_repo = repo_factory.create_moderation_transitions_repo(&*conn, user_id);
+            let moderation_feedback_repo = repo_factory.create_moderation_feedback_repo(&*conn, user_id);
+            find_last_decline_feedback(
+                &*moderation_transitions_repo,
+                &*moderation_feedback_repo,
+                ModerationEntityType::BaseProduct,
+                base_product_id.0,
+            )
+            .map_err(|e| {
+                e.context("Service Moderation, get_base_product_moderation_feedback endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns usage of decline reasons in feedback matching filters, the most common reasons go first
+    fn get_decline_reasons_report(&self, search: ModerationFeedbackSearch) -> ServiceFuture<Vec<DeclineReasonStat>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let moderation_feedback_repo = repo_factory.create_moderation_feedback_repo(&*conn, user_id);
+
+            conn.transaction::<Vec<DeclineReasonStat>, FailureError, _>(move || {
+                let reasons = moderation_feedback_repo.count_declines_by_reason(search.clone())?;
+                let reason_fields = moderation_feedback_repo.count_declines_by_reason_and_field(search)?;
+
+                Ok(decline_reasons_report(reasons, reason_fields))
+            })
+            .map_err(|e| {
+                e.context("Service Moderation, get_decline_reasons_report endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns moderation statuses the current user can move the store to
+    fn get_store_moderation_transitions(&self, store_id: StoreId) -> ServiceFuture<AllowedModerationTransitions> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            {
+                let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+                let user_roles_repo = repo_factory.create_user_roles_repo_with_sys_acl(&*conn);
+                let store = stores_repo
+                    .find(store_id, Visibility::Active)?
+                    .ok_or(format_err!("Not found such store id : {}", store_id).context(Error::NotFound))?;
+
+                let actor = find_moderation_actor(&*user_roles_repo, user_id, stores_repo.can_moderate(&store)?)?;
+                Ok(AllowedModerationTransitions::new(
+                    ModerationEntityType::Store,
+                    store_id.0,
+                    store.status,
+                    actor,
+                ))
+            }
+            .map_err(|e: FailureError| {
+                e.context("Service Moderation, get_store_moderation_transitions endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Returns moderation statuses the current user can move the base product to
+    fn get_base_product_moderation_transitions(&self, base_product_id: BaseProductId) -> ServiceFuture<AllowedModerationTransitions> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            {
+                let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+                let user_roles_repo = repo_factory.create_user_roles_repo_with_sys_acl(&*conn);
+                let base_product = base_products_repo
+                    .find(base_product_id, Visibility::Active)?
+                    .ok_or(format_err!("Not found such base product id : {}", base_product_id).context(Error::NotFound))?;
+
+                let actor = find_moderation_actor(&*user_roles_repo, user_id, base_products_repo.can_moderate(&base_product)?)?;
+                Ok(AllowedModerationTransitions::new(
+                    ModerationEntityType::BaseProduct,
+                    base_product_id.0,
+                    base_product.status,
+                    actor,
+                ))
+            }
+            .map_err(|e: FailureError| {
+                e.context("Service Moderation, get_base_product_moderation_transitions endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Applies moderator decision to every store, returns result of each of them
+    fn bulk_moderate_stores(&self, payload: BulkModeration<StoreId>) -> ServiceFuture<BulkModerationReport<StoreId>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        info!("Bulk moderation {:?} of stores {:?}", payload.action, payload.ids);
+
+        self.spawn_on_pool(move |conn| {
+            let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+            let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
+
+            let change = bulk_moderation_change(&payload)
+                .map_err(|e| e.context("Service Moderation, bulk_moderate_stores endpoint error occurred."))?;
+            let items = unique_ids(payload.ids)
+                .into_iter()
+                .map(|store_id| {
+                    // every store is changed in its own transaction so that failed ones do not roll back the rest
+                    conn.transaction::<Store, FailureError, _>(|| {
+                        change_store_status(
+                            &*stores_repo,
+                            &*base_products_repo,
+                            &*outbox_repo,
+                            &recorder,
+                            store_id,
+                            change.clone(),
+                        )
+                    })
+                    .map(|_| BulkModerationItemResult::succeeded(store_id, change.status))
+                    .unwrap_or_else(|e| bulk_moderation_item_error(store_id, &e))
+                })
+                .collect();
+
+            Ok(BulkModerationReport::new(items))
+        })
+    }
+
+    /// Applies moderator decision to every base product, returns result of each of them
+    fn bulk_moderate_base_products(&self, payload: BulkModeration<BaseProductId>) -> ServiceFuture<BulkModerationReport<BaseProductId>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        info!("Bulk moderation {:?} of base products {:?}", payload.action, payload.ids);
+
+        self.spawn_on_pool(move |conn| {
+            let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+            let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
+
+            let change = bulk_moderation_change(&payload)
+                .map_err(|e| e.context("Service Moderation, bulk_moderate_base_products endpoint error occurred."))?;
+            let items = unique_ids(payload.ids)
+                .into_iter()
+                .map(|base_product_id| {
+                    // every base product is changed in its own transaction so that failed ones do not roll back the rest
+                    conn.transaction::<BaseProduct, FailureError, _>(|| {
+                        change_base_product_status(&*base_products_repo, &*outbox_repo, &recorder, base_product_id, change.clone())
+                    })
+                    .map(|_| BulkModerationItemResult::succeeded(base_product_id, change.status))
+                    .unwrap_or_else(|e| bulk_moderation_item_error(base_product_id, &e))
+                })
+                .collect();
+
+            Ok(BulkModerationReport::new(items))
+        })
+    }
+}
+
+/// Change of status made to every item of bulk moderation
+fn bulk_moderation_change<Id>(payload: &BulkModeration<Id>) -> Result<ModerationStatusChange, FailureError> {
+    if !payload.feedback.is_empty() && payload.action != BulkModerationAction::Decline {
+        return Err(format_err!("Feedback can not be given with action {:?}", payload.action)
+            .context(Error::Validate(
+                validation_errors!({"feedback": ["feedback" => "Feedback can be given on decline only"]}),
+            ))
+            .into());
+    }
+
+    Ok(
+        ModerationStatusChange::new(payload.action.status(), ModerationReason::ModeratorDecision)
+            .with_comment(payload.comment.clone())
+            .with_feedback(payload.feedback.clone()),
+    )
+}
+
+/// Requested ids without repeats in the order they were given
+fn unique_ids<Id: PartialEq>(ids: Vec<Id>) -> Vec<Id> {
+    let mut unique = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    unique
+}
+
+fn bulk_moderation_item_error<Id>(id: Id, error: &FailureError) -> BulkModerationItemResult<Id> {
+    let validation_errors = error
+        .iter_chain()
+        .filter_map(|cause| match cause.downcast_ref::<Error>() {
+            Some(&Error::Validate(ref e)) => serde_json::to_value(e).ok(),
+            _ => None,
+        })
+        .next();
+
+    BulkModerationItemResult::failed(id, error.find_root_cause().to_string(), validation_errors)
+}
+
+/// Error of claiming or releasing queue item which is absent or claimed by another moderator
+fn not_claimable_queue_item_error(moderation_queue_repo: &ModerationQueueRepo, item_id: i32) -> Result<FailureError, FailureError> {
+    let item = moderation_queue_repo.find(item_id)?;
+
+    Ok(match item {
+        Some(item) => format_err!("Moderation queue item {} is claimed by moderator {:?}.", item_id, item.assignee_id)
+            .context(Error::Validate(
+                validation_errors!({"assignee_id": ["assignee_id" => "Item is already claimed by another moderator"]}),
+            ))
+            .into(),
+        None => format_err!("Moderation queue item {} not found.", item_id)
+            .context(Error::NotFound)
+            .into(),
+    })
+}
+
+/// Moderators act on any entity, other users allowed by ACL to change moderation status act as sellers
+fn find_moderation_actor(
+    user_roles_repo: &UserRolesRepo,
+    user_id: Option<UserId>,
+    can_moderate: bool,
+) -> Result<Option<ModerationActor>, FailureError> {
+    if !can_moderate {
+        return Ok(None);
+    }
+
+    let roles = match user_id {
+        Some(user_id) => user_roles_repo.list_for_user(user_id)?,
+        None => vec![],
+    };
+    let is_moderator = roles
+        .iter()
+        .any(|role| *role == StoresRole::Superuser || *role == StoresRole::Moderator);
+
+    Ok(Some(if is_moderator {
+        ModerationActor::Moderator
+    } else {
+        ModerationActor::Seller
+    }))
+}
+
+/// Checks the change against moderation state machine, returns side effects which have to be applied with it
+pub fn check_moderation_transition(
+    entity_type: ModerationEntityType,
+    entity_id: i32,
+    from_status: ModerationStatus,
+    change: &ModerationStatusChange,
+) -> Result<Vec<ModerationSideEffect>, FailureError> {
+    if !is_moderation_transition_allowed(from_status, change) {
+        let errors = match entity_type {
+            ModerationEntityType::Store => validation_errors!({"stores": ["stores" => "Store can not be sent to new status"]}),
+            ModerationEntityType::BaseProduct => {
+                validation_errors!({"base_products": ["base_products" => "Base product can not be sent to new status"]})
+            }
+        };
+        return Err(format_err!(
+            "{:?} with id: {} cannot be sent from {} to {}",
+            entity_type,
+            entity_id,
+            from_status,
+            change.status
+        )
+        .context(Error::Validate(errors))
+        .into());
+    }
+
+    Ok(moderation_side_effects(entity_type, from_status, change.status))
+}
+
+/// Seller has to fix feedback of the last moderator decision or failed pre-moderation checks
+/// until entity is moderated again
+fn find_last_decline_feedback(
+    transitions_repo: &ModerationTransitionsRepo,
+    feedback_repo: &ModerationFeedbackRepo,
+    entity_type: ModerationEntityType,
+    entity_id: i32,
+) -> Result<Vec<ModerationFeedback>, FailureError> {
+    let last_decision = transitions_repo
+        .list_by_entity(entity_type, entity_id)?
+        .into_iter()
+        .filter(|transition| {
+            transition.reason == ModerationReason::ModeratorDecision || transition.reason == ModerationReason::PreModerationCheck
+        })
+        .last();
+
+    match last_decision {
+        Some(ref transition) if transition.to_status == ModerationStatus::Decline => feedback_repo.list_by_transition(transition.id),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Records changes of moderation status made by user and keeps moderation queue in sync with them,
+/// meant to be used inside the transaction of the change
+pub struct ModerationRecorder<'a> {
+    queue_repo: Box<ModerationQueueRepo + 'a>,
+    transitions_repo: Box<ModerationTransitionsRepo + 'a>,
+    decline_reasons_repo: Box<DeclineReasonsRepo + 'a>,
+    feedback_repo: Box<ModerationFeedbackRepo + 'a>,
+    user_id: Option<UserId>,
+}
+
+impl<'a> ModerationRecorder<'a> {
+    pub fn new<C, F>(repo_factory: &F, db_conn: &'a C, user_id: Option<UserId>) -> Self
+    where
+        C: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        F: ReposFactory<C>,
+    {
+        Self {
+            queue_repo: repo_factory.create_moderation_queue_repo_with_sys_acl(db_conn),
+            transitions_repo: repo_factory.create_moderation_transitions_repo_with_sys_acl(db_conn),
+            decline_reasons_repo: repo_factory.create_decline_reasons_repo_with_sys_acl(db_conn),
+            feedback_repo: repo_factory.create_moderation_feedback_repo_with_sys_acl(db_conn),
+            user_id,
+        }
+    }
+
+    /// Records change of status of the entity from `from_status`. Entity entering `Moderation` is put into the queue,
+    /// entity leaving it is removed from the queue and its waiting time is saved with the change
+    pub fn record(
+        &self,
+        entity_type: ModerationEntityType,
+        entity_id: i32,
+        from_status: ModerationStatus,
+        change: &ModerationStatusChange,
+    ) -> Result<Option<ModerationTransition>, FailureError> {
+        self.validate_feedback(entity_type, change)?;
+
+        if from_status == change.status {
+            return Ok(None);
+        }
+
+        let mut time_in_queue_sec = None;
+        for side_effect in moderation_side_effects(entity_type, from_status, change.status) {
+            match side_effect {
+                ModerationSideEffect::Dequeue => {
+                    time_in_queue_sec = self
+                        .queue_repo
+                        .delete_by_entity(entity_type, entity_id)?
+                        .map(|item| item.time_in_queue(SystemTime::now()).as_secs() as i64);
+                }
+                ModerationSideEffect::Enqueue => {
+                    if self.queue_repo.find_by_entity(entity_type, entity_id)?.is_none() {
+                        self.queue_repo.enqueue(NewModerationQueueItem {
+                            entity_type,
+                            entity_id,
+                            needs_review: !change.review_notes.is_empty(),
+                            review_notes: serde_json::to_value(&change.review_notes)?,
+                        })?;
+                    }
+                }
+                // Applied by the caller changing the status
+                ModerationSideEffect::CascadeToBaseProducts | ModerationSideEffect::Reindex => {}
+            }
+        }
+
+        let transition = self.transitions_repo.create(NewModerationTransition {
+            entity_type,
+            entity_id,
+            user_id: self.user_id,
+            from_status,
+            to_status: change.status,
+            reason: change.reason,
+            comment: change.comment.clone(),
+            time_in_queue_sec,
+        })?;
+
+        for payload in change.feedback.iter().cloned() {
+            self.feedback_repo.create(NewModerationFeedback::new(&transition, payload))?;
+        }
+
+        Ok(Some(transition))
+    }
+
+    /// Feedback is given on decline only, with active reasons and fields known to the entity editor
+    fn validate_feedback(&self, entity_type: ModerationEntityType, change: &ModerationStatusChange) -> Result<(), FailureError> {
+        if change.feedback.is_empty() {
+            return Ok(());
+        }
+
+        if change.status != ModerationStatus::Decline {
+            return Err(format_err!("Feedback can not be given with status {}", change.status)
+                .context(Error::Validate(
+                    validation_errors!({"feedback": ["feedback" => "Feedback can be given on decline only"]}),
+                ))
+                .into());
+        }
+
+        let fields = entity_type.feedback_fields();
+        if let Some(field) = change
+            .feedback
+            .iter()
+            .filter_map(|item| item.field.as_ref())
+            .find(|field| !fields.contains(&field.as_str()))
+        {
+            return Err(format_err!("Feedback field {} is unknown for {:?}", field, entity_type)
+                .context(Error::Validate(
+                    validation_errors!({"feedback": ["field" => "Feedback field is unknown"]}),
+                ))
+                .into());
+        }
+
+        let codes = change.feedback.iter().map(|item| item.reason_code.clone()).collect::<Vec<_>>();
+        let active_codes = self
+            .decline_reasons_repo
+            .find_by_codes(codes.clone())?
+            .into_iter()
+            .filter(|reason| reason.is_active)
+            .map(|reason| reason.code)
+            .collect::<Vec<_>>();
+        if let Some(code) = codes.iter().find(|code| !active_codes.contains(code)) {
+            return Err(format_err!("Decline reason {} not found or inactive", code)
+                .context(Error::Validate(
+                    validation_errors!({"feedback": ["reason_code" => "Decline reason not found or inactive"]}),
+                ))
+                .into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+
+    use tokio_core::reactor::Core;
+
+    use stq_static_resources::ModerationStatus;
+    use stq_types::*;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    #[test]
+    fn test_search_moderation_queue() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.search_moderation_queue(ModerationQueueSearch::default());
+        let result = core.run(work).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].sla_breached, false);
+    }
+
+    #[test]
+    fn test_claim_moderation_queue_item() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.claim_moderation_queue_item(1);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.assignee_id, Some(MOCK_USER_ID));
+
+        let work = service.claim_moderation_queue_item(MOCK_CLAIMED_QUEUE_ITEM_ID);
+        assert!(core.run(work).is_err());
+    }
+
+    #[test]
+    fn test_release_moderation_queue_item() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_MODERATOR_ID), handle.clone());
+        let work = service.release_moderation_queue_item(MOCK_CLAIMED_QUEUE_ITEM_ID);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.assignee_id, None);
+
+        let service = create_service(Some(UserId(3)), handle.clone());
+        let work = service.release_moderation_queue_item(MOCK_CLAIMED_QUEUE_ITEM_ID);
+        assert!(core.run(work).is_err());
+
+        // Superuser releases items of any moderator
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.release_moderation_queue_item(MOCK_CLAIMED_QUEUE_ITEM_ID);
+        assert!(core.run(work).is_ok());
+    }
+
+    #[test]
+    fn test_get_base_product_moderation_history() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_base_product_moderation_history(BaseProductId(1));
+        let result = core.run(work).unwrap();
+        assert_eq!(result[0].entity_type, ModerationEntityType::BaseProduct);
+        assert_eq!(result[0].to_status, ModerationStatus::Moderation);
+    }
+
+    #[test]
+    fn test_get_base_product_moderation_feedback() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_base_product_moderation_feedback(BaseProductId(1));
+        let result = core.run(work).unwrap();
+        assert_eq!(result[0].transition_id, 2);
+        assert_eq!(result[0].field, Some("photo_main".to_string()));
+    }
+
+    #[test]
+    fn test_get_decline_reasons_report() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_decline_reasons_report(ModerationFeedbackSearch::default());
+        let result = core.run(work).unwrap();
+        assert_eq!(result[0].reason_code, "bad_photo");
+        assert_eq!(result[0].declines_count, 2);
+    }
+
+    #[test]
+    fn test_get_store_moderation_transitions() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_store_moderation_transitions(MOCK_STORE_ID);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.actor, Some(ModerationActor::Moderator));
+        assert_eq!(result.next_statuses, vec![ModerationStatus::Moderation, ModerationStatus::Draft]);
+    }
+
+    #[test]
+    fn test_get_base_product_moderation_transitions_for_seller() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_STAFF_USER_ID), handle);
+        let work = service.get_base_product_moderation_transitions(BaseProductId(1));
+        let result = core.run(work).unwrap();
+        assert_eq!(result.actor, Some(ModerationActor::Seller));
+        assert_eq!(result.editable, true);
+        assert_eq!(result.next_statuses, vec![ModerationStatus::Moderation, ModerationStatus::Draft]);
+    }
+
+    #[test]
+    fn test_check_moderation_transition() {
+        let publish = ModerationStatusChange::new(ModerationStatus::Published, ModerationReason::ModeratorDecision);
+        let side_effects = check_moderation_transition(ModerationEntityType::Store, 1, ModerationStatus::Moderation, &publish).unwrap();
+        assert!(side_effects.contains(&ModerationSideEffect::CascadeToBaseProducts));
+        assert!(check_moderation_transition(ModerationEntityType::Store, 1, ModerationStatus::Draft, &publish).is_err());
+
+        let send = ModerationStatusChange::new(ModerationStatus::Moderation, ModerationReason::SentToModeration);
+        assert!(check_moderation_transition(ModerationEntityType::BaseProduct, 1, ModerationStatus::Blocked, &send).is_err());
+    }
+
+    fn create_feedback_payload(reason_code: &str, field: &str) -> ModerationFeedbackPayload {
+        ModerationFeedbackPayload {
+            reason_code: reason_code.to_string(),
+            field: Some(field.to_string()),
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn test_record_decline_with_feedback() {
+        let conn = MockConnection::default();
+        let recorder = ModerationRecorder::new(&ReposFactoryMock, &conn, Some(MOCK_USER_ID));
+        let change = ModerationStatusChange::new(ModerationStatus::Decline, ModerationReason::ModeratorDecision)
+            .with_feedback(vec![create_feedback_payload("bad_photo", "photo_main")]);
+        let result = recorder.record(ModerationEntityType::BaseProduct, 1, ModerationStatus::Moderation, &change);
+        assert_eq!(result.unwrap().unwrap().to_status, ModerationStatus::Decline);
+    }
+
+    #[test]
+    fn test_record_feedback_with_unknown_field() {
+        let conn = MockConnection::default();
+        let recorder = ModerationRecorder::new(&ReposFactoryMock, &conn, Some(MOCK_USER_ID));
+        let change = ModerationStatusChange::new(ModerationStatus::Decline, ModerationReason::ModeratorDecision)
+            .with_feedback(vec![create_feedback_payload("bad_photo", "logo")]);
+        let result = recorder.record(ModerationEntityType::BaseProduct, 1, ModerationStatus::Moderation, &change);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_record_feedback_with_inactive_reason() {
+        let conn = MockConnection::default();
+        let recorder = ModerationRecorder::new(&ReposFactoryMock, &conn, Some(MOCK_USER_ID));
+        let change = ModerationStatusChange::new(ModerationStatus::Decline, ModerationReason::ModeratorDecision)
+            .with_feedback(vec![create_feedback_payload("outdated_reason", "name")]);
+        let result = recorder.record(ModerationEntityType::Store, 1, ModerationStatus::Moderation, &change);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_record_feedback_on_publish() {
+        let conn = MockConnection::default();
+        let recorder = ModerationRecorder::new(&ReposFactoryMock, &conn, Some(MOCK_USER_ID));
+        let change = ModerationStatusChange::new(ModerationStatus::Published, ModerationReason::ModeratorDecision)
+            .with_feedback(vec![create_feedback_payload("bad_photo", "photo_main")]);
+        let result = recorder.record(ModerationEntityType::BaseProduct, 1, ModerationStatus::Moderation, &change);
+        assert!(result.is_err());
+    }
+
+    fn create_bulk_moderation<Id>(ids: Vec<Id>, action: BulkModerationAction) -> BulkModeration<Id> {
+        BulkModeration {
+            ids,
+            action,
+            comment: Some("Checked by batch".to_string()),
+            feedback: vec![],
+        }
+    }
+
+    #[test]
+    fn test_bulk_moderate_base_products() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = create_bulk_moderation(
+            vec![BaseProductId(1), BaseProductId(2), BaseProductId(1)],
+            BulkModerationAction::SendToDraft,
+        );
+        let work = service.bulk_moderate_base_products(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.succeeded_count, 2);
+        assert_eq!(result.failed_count, 0);
+        let ids = result.items.iter().map(|item| item.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![BaseProductId(1), BaseProductId(2)]);
+        assert_eq!(result.items[0].status, Some(ModerationStatus::Draft));
+    }
+
+    #[test]
+    fn test_bulk_moderate_base_products_partially() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = create_bulk_moderation(
+            vec![BaseProductId(1), MOCK_DRAFT_BASE_PRODUCT_ID, BaseProductId(2)],
+            BulkModerationAction::SendToDraft,
+        );
+        let work = service.bulk_moderate_base_products(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.succeeded_count, 2);
+        assert_eq!(result.failed_count, 1);
+        assert_eq!(result.items[0].status, Some(ModerationStatus::Draft));
+        assert_eq!(result.items[1].id, MOCK_DRAFT_BASE_PRODUCT_ID);
+        assert_eq!(result.items[1].status, None);
+        assert!(result.items[1].validation_errors.is_some());
+        assert_eq!(result.items[2].status, Some(ModerationStatus::Draft));
+    }
+
+    #[test]
+    fn test_bulk_moderate_stores_reports_not_allowed_changes() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = create_bulk_moderation(vec![StoreId(1), StoreId(2)], BulkModerationAction::Approve);
+        let work = service.bulk_moderate_stores(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.succeeded_count, 0);
+        assert_eq!(result.failed_count, 2);
+        assert_eq!(result.items[1].id, StoreId(2));
+        assert_eq!(result.items[1].status, None);
+        assert!(result.items[1].error.is_some());
+        assert!(result.items[1].validation_errors.is_some());
+    }
+
+    #[test]
+    fn test_bulk_moderate_with_feedback_on_approve() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = BulkModeration {
+            feedback: vec![create_feedback_payload("bad_photo", "photo_main")],
+            ..create_bulk_moderation(vec![BaseProductId(1)], BulkModerationAction::Approve)
+        };
+        let work = service.bulk_moderate_base_products(payload);
+        let result = core.run(work);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Helpers writing catalog change events to outbox, meant to be called inside the transaction of the change
+use failure::Error as FailureError;
+
+use stq_types::StoreId;
+
+use models::{BaseProduct, NewOutboxEvent, OutboxAggregateType, OutboxEventType, RawProduct, Store};
+use repos::OutboxEventsRepo;
+
+pub fn write_store_event(outbox_repo: &OutboxEventsRepo, event_type: OutboxEventType, store: &Store) -> Result<(), FailureError> {
+    outbox_repo.create(NewOutboxEvent::store(event_type, store)?)?;
+
+    Ok(())
+}
+
+pub fn write_store_deleted_event(outbox_repo: &OutboxEventsRepo, store_id: StoreId) -> Result<(), FailureError> {
+    let event = NewOutboxEvent::new(OutboxAggregateType::Store, store_id.0, OutboxEventType::Deleted, None, &())?;
+    outbox_repo.create(event)?;
+
+    Ok(())
+}
+
+pub fn write_base_product_event(
+    outbox_repo: &OutboxEventsRepo,
+    event_type: OutboxEventType,
+    base_product: &BaseProduct,
+) -> Result<(), FailureError> {
+    outbox_repo.create(NewOutboxEvent::base_product(event_type, base_product)?)?;
+
+    Ok(())
+}
+
+pub fn write_base_product_events(
+    outbox_repo: &OutboxEventsRepo,
+    event_type: OutboxEventType,
+    base_products: &[BaseProduct],
+) -> Result<(), FailureError> {
+    for base_product in base_products {
+        write_base_product_event(outbox_repo, event_type, base_product)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_product_event(outbox_repo: &OutboxEventsRepo, event_type: OutboxEventType, product: &RawProduct) -> Result<(), FailureError> {
+    outbox_repo.create(NewOutboxEvent::product(event_type, product)?)?;
+
+    Ok(())
+}
+
+pub fn write_product_events(
+    outbox_repo: &OutboxEventsRepo,
+    event_type: OutboxEventType,
+    products: &[RawProduct],
+) -> Result<(), FailureError> {
+    for product in products {
+        write_product_event(outbox_repo, event_type, product)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use stq_types::*;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::outbox::*;
+
+    #[test]
+    fn test_product_event() {
+        let product = create_product(MOCK_PRODUCT_ID, MOCK_BASE_PRODUCT_ID);
+        let event = NewOutboxEvent::product(OutboxEventType::Updated, &product).unwrap();
+        assert_eq!(event.aggregate_type, OutboxAggregateType::Product);
+        assert_eq!(event.aggregate_id, MOCK_PRODUCT_ID.0);
+        assert_eq!(event.kafka_update_no, Some(product.kafka_update_no));
+        assert_eq!(event.payload["id"], json!(MOCK_PRODUCT_ID.0));
+    }
+
+    #[test]
+    fn test_write_store_deleted_event() {
+        let outbox_repo = OutboxEventsRepoMock::default();
+        let result = write_store_deleted_event(&outbox_repo, StoreId(1));
+        assert!(result.is_ok());
+
+        let created = outbox_repo.created.borrow();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].aggregate_type, OutboxAggregateType::Store);
+        assert_eq!(created[0].aggregate_id, 1);
+        assert_eq!(created[0].event_type, OutboxEventType::Deleted);
+        assert_eq!(created[0].kafka_update_no, None);
+        assert_eq!(created[0].payload, json!({ "id": 1 }));
+    }
+}
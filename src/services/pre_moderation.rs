@@ -0,0 +1,411 @@
+//! Pre-moderation checks of base products sent to moderation. Checks are pluggable rules, each of them
+//! can decline base product without moderator or mark it as needing moderator review
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde_json;
+
+use stq_static_resources::{Language, Translation};
+
+use config::PreModeration;
+use models::*;
+
+/// Automated check of base product sent to moderation
+pub trait PreModerationRule: Send + Sync {
+    /// Name of the check saved with its findings
+    fn name(&self) -> &'static str;
+
+    /// Returns issues found in base product, empty if the check is passed
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding>;
+}
+
+/// Runs pre-moderation rules one by one and collects their findings
+#[derive(Default)]
+pub struct PreModerationEngine {
+    rules: Vec<Box<PreModerationRule>>,
+}
+
+impl PreModerationEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, rule: Box<PreModerationRule>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Engine with built-in rules set up by config, no rules are run if checks are disabled
+    pub fn from_config(config: &PreModeration) -> Self {
+        if !config.enabled {
+            return Self::new();
+        }
+
+        Self::new()
+            .with_rule(Box::new(RequiredTranslationsRule))
+            .with_rule(Box::new(MainPhotoRule))
+            .with_rule(Box::new(DimensionsRule {
+                max_weight_g: config.max_weight_g,
+                max_dimension_cm: config.max_dimension_cm,
+            }))
+            .with_rule(Box::new(ForbiddenWordsRule::new(&config.forbidden_words)))
+            .with_rule(Box::new(DuplicateVendorCodesRule))
+    }
+
+    pub fn check(&self, submission: &BaseProductSubmission) -> PreModerationReport {
+        PreModerationReport {
+            findings: self.rules.iter().flat_map(|rule| rule.check(submission)).collect(),
+        }
+    }
+}
+
+fn get_translations(text: &serde_json::Value) -> Vec<Translation> {
+    serde_json::from_value::<Vec<Translation>>(text.clone()).unwrap_or_default()
+}
+
+/// Texts of base product with the fields they are kept in
+fn base_product_texts(base_product: &BaseProduct) -> Vec<(&'static str, &serde_json::Value)> {
+    let mut texts = vec![("name", &base_product.name), ("short_description", &base_product.short_description)];
+    if let Some(ref long_description) = base_product.long_description {
+        texts.push(("long_description", long_description));
+    }
+    if let Some(ref seo_title) = base_product.seo_title {
+        texts.push(("seo_title", seo_title));
+    }
+    if let Some(ref seo_description) = base_product.seo_description {
+        texts.push(("seo_description", seo_description));
+    }
+    texts
+}
+
+/// Name, descriptions and SEO texts set have to be translated to default language of the store
+pub struct RequiredTranslationsRule;
+
+impl PreModerationRule for RequiredTranslationsRule {
+    fn name(&self) -> &'static str {
+        "required_translations"
+    }
+
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding> {
+        let default_language = &submission.store.default_language;
+        let lang = match Language::from_639_1(default_language) {
+            Some(lang) => lang,
+            None => {
+                warn!("Store {} has unknown default language {}", submission.store.id, default_language);
+                return vec![];
+            }
+        };
+
+        base_product_texts(&submission.base_product)
+            .into_iter()
+            .filter(|(_, text)| {
+                !get_translations(text)
+                    .iter()
+                    .any(|translation| translation.lang == lang && !translation.text.trim().is_empty())
+            })
+            .map(|(field, _)| {
+                PreModerationFinding::decline(
+                    self.name(),
+                    "missing_translation",
+                    Some(field),
+                    format!("{} has no translation to default language of the store {}", field, default_language),
+                )
+            })
+            .collect()
+    }
+}
+
+/// At least one variant has to have main photo
+pub struct MainPhotoRule;
+
+impl PreModerationRule for MainPhotoRule {
+    fn name(&self) -> &'static str {
+        "main_photo"
+    }
+
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding> {
+        let has_photo = submission
+            .variants
+            .iter()
+            .any(|variant| variant.photo_main.as_ref().map(|photo| !photo.trim().is_empty()).unwrap_or(false));
+        if has_photo {
+            return vec![];
+        }
+
+        vec![PreModerationFinding::decline(
+            self.name(),
+            "bad_photo",
+            Some("photo_main"),
+            "None of variants has main photo".to_string(),
+        )]
+    }
+}
+
+/// Weight and dimensions set have to be positive and not exceed limits, otherwise moderator has to review them
+pub struct DimensionsRule {
+    pub max_weight_g: i32,
+    pub max_dimension_cm: i32,
+}
+
+impl PreModerationRule for DimensionsRule {
+    fn name(&self) -> &'static str {
+        "dimensions"
+    }
+
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding> {
+        let base_product = &submission.base_product;
+        let values = [
+            ("weight_g", base_product.weight_g, self.max_weight_g),
+            ("length_cm", base_product.length_cm, self.max_dimension_cm),
+            ("width_cm", base_product.width_cm, self.max_dimension_cm),
+            ("height_cm", base_product.height_cm, self.max_dimension_cm),
+        ];
+
+        values
+            .iter()
+            .filter_map(|&(field, value, max)| value.map(|value| (field, value, max)))
+            .filter(|&(_, value, max)| value <= 0 || value > max)
+            .map(|(field, value, max)| {
+                PreModerationFinding::review(self.name(), Some(field), format!("{} {} is out of range 1..{}", field, value, max))
+            })
+            .collect()
+    }
+}
+
+/// Texts of base product in any language must not contain forbidden words, compared case insensitive as whole words
+pub struct ForbiddenWordsRule {
+    words: HashSet<String>,
+}
+
+impl ForbiddenWordsRule {
+    pub fn new(words: &[String]) -> Self {
+        Self {
+            words: words
+                .iter()
+                .map(|word| word.trim().to_lowercase())
+                .filter(|word| !word.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl PreModerationRule for ForbiddenWordsRule {
+    fn name(&self) -> &'static str {
+        "forbidden_words"
+    }
+
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding> {
+        if self.words.is_empty() {
+            return vec![];
+        }
+
+        let mut findings = vec![];
+        for (field, text) in base_product_texts(&submission.base_product) {
+            let found = get_translations(text)
+                .iter()
+                .flat_map(|translation| {
+                    translation
+                        .text
+                        .split(|c: char| !c.is_alphanumeric())
+                        .map(|word| word.to_lowercase())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|word| self.words.contains(word))
+                .collect::<BTreeSet<_>>();
+            if !found.is_empty() {
+                let found = found.into_iter().collect::<Vec<_>>().join(", ");
+                findings.push(PreModerationFinding::decline(
+                    self.name(),
+                    "prohibited_item",
+                    Some(field),
+                    format!("{} contains forbidden words: {}", field, found),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+/// Vendor codes of variants have to be unique within the store, moderator has to review duplicates
+pub struct DuplicateVendorCodesRule;
+
+impl PreModerationRule for DuplicateVendorCodesRule {
+    fn name(&self) -> &'static str {
+        "duplicate_vendor_codes"
+    }
+
+    fn check(&self, submission: &BaseProductSubmission) -> Vec<PreModerationFinding> {
+        let mut variants_count = BTreeMap::<&str, usize>::new();
+        for variant in submission.variants.iter().filter(|variant| !variant.vendor_code.is_empty()) {
+            *variants_count.entry(&variant.vendor_code).or_insert(0) += 1;
+        }
+
+        let mut findings = variants_count
+            .iter()
+            .filter(|&(_, count)| *count > 1)
+            .map(|(vendor_code, count)| {
+                PreModerationFinding::review(
+                    self.name(),
+                    Some("vendor_code"),
+                    format!("Vendor code {} is used by {} variants", vendor_code, count),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let base_product_id = submission.base_product.id;
+        let mut other_base_products = BTreeMap::<&str, BTreeSet<i32>>::new();
+        for variant in submission
+            .store_variants
+            .iter()
+            .filter(|variant| variant.base_product_id != base_product_id && variants_count.contains_key(variant.vendor_code.as_str()))
+        {
+            other_base_products
+                .entry(&variant.vendor_code)
+                .or_default()
+                .insert(variant.base_product_id.0);
+        }
+
+        findings.extend(other_base_products.into_iter().map(|(vendor_code, base_product_ids)| {
+            let base_product_ids = base_product_ids.into_iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+            PreModerationFinding::review(
+                self.name(),
+                Some("vendor_code"),
+                format!(
+                    "Vendor code {} is used by base products {} of the store",
+                    vendor_code, base_product_ids
+                ),
+            )
+        }));
+        findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+
+    use stq_types::*;
+
+    use config::PreModeration;
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    fn create_submission() -> BaseProductSubmission {
+        let mut base_product = create_base_product(MOCK_BASE_PRODUCT_ID);
+        base_product.name = serde_json::from_str(r#"[{"lang": "en", "text": "Wooden chair"}]"#).unwrap();
+        base_product.short_description = serde_json::from_str(r#"[{"lang": "en", "text": "Handmade chair"}]"#).unwrap();
+
+        let mut variant = create_product(MOCK_PRODUCT_ID, MOCK_BASE_PRODUCT_ID);
+        variant.photo_main = Some("https://example.com/chair.png".to_string());
+
+        BaseProductSubmission {
+            base_product,
+            store: create_store(MOCK_STORE_ID, serde_json::from_str(MOCK_STORE_NAME_JSON).unwrap()),
+            variants: vec![variant],
+            store_variants: vec![],
+        }
+    }
+
+    fn create_engine(forbidden_words: &[&str]) -> PreModerationEngine {
+        PreModerationEngine::from_config(&PreModeration {
+            forbidden_words: forbidden_words.iter().map(|word| word.to_string()).collect(),
+            ..PreModeration::default()
+        })
+    }
+
+    fn fields(findings: &[PreModerationFinding]) -> Vec<&str> {
+        findings
+            .iter()
+            .filter_map(|finding| finding.field.as_ref().map(|field| field.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plausible_submission_passes() {
+        let report = create_engine(&["weapon"]).check(&create_submission());
+        assert!(report.findings.is_empty(), "{:?}", report.findings);
+    }
+
+    #[test]
+    fn test_missing_default_language_translation_declines() {
+        let mut submission = create_submission();
+        submission.base_product.short_description = serde_json::from_str(r#"[{"lang": "de", "text": "Stuhl"}]"#).unwrap();
+        submission.base_product.long_description = Some(serde_json::from_str(r#"[{"lang": "en", "text": " "}]"#).unwrap());
+
+        let report = create_engine(&[]).check(&submission);
+
+        assert!(report.is_declined());
+        assert_eq!(fields(&report.findings), vec!["short_description", "long_description"]);
+        let codes = report.feedback().into_iter().map(|item| item.reason_code).collect::<Vec<_>>();
+        assert_eq!(codes, vec!["missing_translation", "missing_translation"]);
+    }
+
+    #[test]
+    fn test_variants_without_main_photo_decline() {
+        let mut submission = create_submission();
+        submission.variants[0].photo_main = None;
+
+        let report = create_engine(&[]).check(&submission);
+
+        assert!(report.is_declined());
+        assert_eq!(report.feedback()[0].reason_code, "bad_photo");
+        assert_eq!(report.feedback()[0].field, Some("photo_main".to_string()));
+    }
+
+    #[test]
+    fn test_insane_dimensions_need_review() {
+        let mut submission = create_submission();
+        submission.base_product.weight_g = Some(0);
+        submission.base_product.height_cm = Some(100_000);
+        submission.base_product.width_cm = None;
+
+        let report = create_engine(&[]).check(&submission);
+
+        assert!(!report.is_declined());
+        assert_eq!(fields(&report.review_notes()), vec!["weight_g", "height_cm"]);
+    }
+
+    #[test]
+    fn test_forbidden_words_are_matched_as_whole_words() {
+        let mut submission = create_submission();
+        submission.base_product.name = serde_json::from_str(r#"[{"lang": "en", "text": "Chair, not a WEAPON!"}]"#).unwrap();
+        submission.base_product.short_description = serde_json::from_str(r#"[{"lang": "en", "text": "Weaponless chair"}]"#).unwrap();
+
+        let report = create_engine(&["Weapon"]).check(&submission);
+
+        assert!(report.is_declined());
+        assert_eq!(fields(&report.findings), vec!["name"]);
+        assert_eq!(report.findings[0].reason_code, Some("prohibited_item".to_string()));
+    }
+
+    #[test]
+    fn test_duplicate_vendor_codes_need_review() {
+        let mut submission = create_submission();
+        let variant = submission.variants[0].clone();
+        submission.variants.push(variant.clone());
+        submission.store_variants = vec![variant, create_product(ProductId(2), BaseProductId(2))];
+
+        let report = create_engine(&[]).check(&submission);
+
+        assert!(!report.is_declined());
+        let notes = report.review_notes();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].message, "Vendor code vendor_code is used by 2 variants");
+        assert_eq!(notes[1].message, "Vendor code vendor_code is used by base products 2 of the store");
+    }
+
+    #[test]
+    fn test_disabled_checks_and_custom_rules() {
+        let mut submission = create_submission();
+        submission.variants.clear();
+
+        let disabled = PreModerationEngine::from_config(&PreModeration {
+            enabled: false,
+            ..PreModeration::default()
+        });
+        assert!(disabled.check(&submission).findings.is_empty());
+
+        let custom = PreModerationEngine::new().with_rule(Box::new(MainPhotoRule));
+        assert_eq!(custom.check(&submission).findings.len(), 1);
+    }
+}
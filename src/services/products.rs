@@ -1,5 +1,6 @@
 //! Products Services, presents CRUD operations with product
 use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
 
 use diesel::connection::AnsiTransactionManager;
 use diesel::pg::Pg;
@@ -9,16 +10,19 @@ use r2d2::ManageConnection;
 
 use stq_static_resources::currency_type::CurrencyType;
 use stq_static_resources::Currency;
-use stq_types::{AttributeId, AttributeValueCode, BaseProductId, ExchangeRate, ProductId, ProductPrice, ProductSellerPrice, StoreId};
+use stq_types::{
+    AttributeId, AttributeValueCode, BaseProductId, ExchangeRate, ProductId, ProductPrice, ProductSellerPrice, StoreId, UserId,
+};
 
 use super::types::ServiceFuture;
 use errors::Error;
 use models::*;
 use repos::{
-    AttributeValuesRepo, AttributesRepo, BaseProductsSearchTerms, CurrencyExchangeRepo, CustomAttributesRepo, ProductAttrsRepo,
-    ProductFilters, ProductsRepo, RepoResult, ReposFactory, StoresRepo,
+    AttributeValuesRepo, AttributesRepo, BaseProductsSearchTerms, CurrencyExchangeRepo, CustomAttributesRepo, OutboxEventsRepo,
+    ProductAttrsRepo, ProductFilters, ProductPriceHistoryRepo, ProductsRepo, RepoResult, ReposFactory, ScheduledPriceChangesRepo,
+    StoresRepo,
 };
-use services::check_can_update_by_status;
+use services::outbox::write_product_event;
 use services::Service;
 
 pub trait ProductsService {
@@ -48,6 +52,18 @@ pub trait ProductsService {
     fn find_products_attributes(&self, product_id: ProductId) -> ServiceFuture<Vec<AttrValue>>;
     /// Check that you can update product
     fn validate_update_product(&self, product_id: ProductId) -> ServiceFuture<bool>;
+    /// Returns price history of product
+    fn get_product_price_history(&self, product_id: ProductId) -> ServiceFuture<Vec<ProductPriceHistoryRecord>>;
+    /// Returns scheduled price changes of product
+    fn get_scheduled_price_changes(&self, product_id: ProductId) -> ServiceFuture<Vec<ScheduledPriceChange>>;
+    /// Schedules price change of product
+    fn create_scheduled_price_change(
+        &self,
+        product_id: ProductId,
+        payload: NewScheduledPriceChangePayload,
+    ) -> ServiceFuture<ScheduledPriceChange>;
+    /// Deletes scheduled price change of product, reverting it if it is already applied
+    fn delete_scheduled_price_change(&self, product_id: ProductId, scheduled_price_change_id: i32) -> ServiceFuture<ScheduledPriceChange>;
 }
 
 impl<
@@ -195,9 +211,11 @@ impl<
         self.spawn_on_pool(move |conn| {
             let products_repo = repo_factory.create_product_repo(&*conn, user_id);
             let prod_attr_repo = repo_factory.create_product_attrs_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
             conn.transaction::<Product, FailureError, _>(move || {
                 let result_product = products_repo.deactivate(product_id)?;
                 prod_attr_repo.delete_all_attributes(result_product.id)?;
+                write_product_event(&*outbox_repo, OutboxEventType::Deactivated, &result_product)?;
 
                 Ok(result_product.into())
             })
@@ -245,6 +263,8 @@ impl<
             let attribute_values_repo = repo_factory.create_attribute_values_repo(&*conn, user_id);
             let custom_attributes_repo = repo_factory.create_custom_attributes_repo(&*conn, user_id);
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
 
             let NewProductWithAttributes { mut product, attributes } = payload;
 
@@ -261,9 +281,12 @@ impl<
                 product.base_product_id = Some(base_product_id);
 
                 check_vendor_code(&*stores_repo, base_product.store_id, &product.vendor_code)?;
+                check_stock_status(product.stock_status, product.pre_order.unwrap_or(false))?;
 
                 let result_product: Product = products_repo.create((product, base_product.currency).into())?.into();
 
+                record_price_history(&*price_history_repo, None, &result_product.product, user_id)?;
+
                 create_product_attributes_values(
                     &*products_repo,
                     &*prod_attr_repo,
@@ -274,6 +297,7 @@ impl<
                     base_product.id,
                     attributes,
                 )?;
+                write_product_event(&*outbox_repo, OutboxEventType::Created, &result_product.product)?;
 
                 Ok(result_product)
             })
@@ -294,6 +318,8 @@ impl<
             let attribute_values_repo = repo_factory.create_attribute_values_repo(&*conn, user_id);
             let custom_attributes_repo = repo_factory.create_custom_attributes_repo(&*conn, user_id);
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
 
             conn.transaction::<Product, FailureError, _>(move || {
                 let original_product = products_repo
@@ -313,7 +339,15 @@ impl<
                         }
                     };
 
-                    products_repo.update(product_id, product)?
+                    check_stock_status(
+                        product.stock_status.or(original_product.stock_status),
+                        product.pre_order.unwrap_or(original_product.pre_order),
+                    )?;
+
+                    let updated_product = products_repo.update(product_id, product)?;
+                    record_price_history(&*price_history_repo, Some(&original_product), &updated_product, user_id)?;
+
+                    updated_product
                 } else {
                     original_product
                 };
@@ -332,6 +366,7 @@ impl<
                         attributes,
                     )?;
                 }
+                write_product_event(&*outbox_repo, OutboxEventType::Updated, &result_product.product)?;
 
                 Ok(result_product)
             })
@@ -453,7 +488,102 @@ impl<
                 None => return Err(Error::NotFound.into()),
             };
 
-            Ok(check_can_update_by_status(current_status))
+            Ok(is_editable_status(current_status))
+        })
+    }
+
+    /// Returns price history of product
+    fn get_product_price_history(&self, product_id: ProductId) -> ServiceFuture<Vec<ProductPriceHistoryRecord>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            price_history_repo
+                .find_by_product(product_id)
+                .map_err(|e| e.context("Service Product, get_product_price_history endpoint error occurred.").into())
+        })
+    }
+
+    /// Returns scheduled price changes of product
+    fn get_scheduled_price_changes(&self, product_id: ProductId) -> ServiceFuture<Vec<ScheduledPriceChange>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let scheduled_price_changes_repo = repo_factory.create_scheduled_price_changes_repo(&*conn, user_id);
+            scheduled_price_changes_repo
+                .find_by_product(product_id)
+                .map_err(|e| e.context("Service Product, get_scheduled_price_changes endpoint error occurred.").into())
+        })
+    }
+
+    /// Schedules price change of product
+    fn create_scheduled_price_change(
+        &self,
+        product_id: ProductId,
+        payload: NewScheduledPriceChangePayload,
+    ) -> ServiceFuture<ScheduledPriceChange> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let products_repo = repo_factory.create_product_repo(&*conn, user_id);
+            let scheduled_price_changes_repo = repo_factory.create_scheduled_price_changes_repo(&*conn, user_id);
+
+            conn.transaction::<ScheduledPriceChange, FailureError, _>(move || {
+                products_repo
+                    .find(product_id)?
+                    .ok_or(format_err!("Not found such product id: {}", product_id).context(Error::NotFound))?;
+
+                let payload = NewScheduledPriceChange::from((product_id, payload));
+                let scheduled = scheduled_price_changes_repo.find_by_product(product_id)?;
+                check_scheduled_price_change(&payload, &scheduled, SystemTime::now())?;
+
+                scheduled_price_changes_repo.create(payload)
+            })
+            .map_err(|e| e.context("Service Product, create_scheduled_price_change endpoint error occurred.").into())
+        })
+    }
+
+    /// Deletes scheduled price change of product, reverting it if it is already applied
+    fn delete_scheduled_price_change(&self, product_id: ProductId, scheduled_price_change_id: i32) -> ServiceFuture<ScheduledPriceChange> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let products_repo = repo_factory.create_product_repo(&*conn, user_id);
+            let price_history_repo = repo_factory.create_product_price_history_repo(&*conn, user_id);
+            let scheduled_price_changes_repo = repo_factory.create_scheduled_price_changes_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+
+            conn.transaction::<ScheduledPriceChange, FailureError, _>(move || {
+                let scheduled_price_change = scheduled_price_changes_repo
+                    .find(scheduled_price_change_id)?
+                    .and_then(|value| if value.product_id == product_id { Some(value) } else { None })
+                    .ok_or(
+                        format_err!(
+                            "Not found scheduled price change with id {} for product {}",
+                            scheduled_price_change_id,
+                            product_id
+                        )
+                        .context(Error::NotFound),
+                    )?;
+
+                if scheduled_price_change.status == ScheduledPriceChangeStatus::Active {
+                    revert_scheduled_price_change(
+                        &*products_repo,
+                        &*price_history_repo,
+                        &*scheduled_price_changes_repo,
+                        &*outbox_repo,
+                        scheduled_price_change,
+                        user_id,
+                    )?;
+                }
+
+                scheduled_price_changes_repo.delete(scheduled_price_change_id)
+            })
+            .map_err(|e| e.context("Service Product, delete_scheduled_price_change endpoint error occurred.").into())
         })
     }
 }
@@ -643,10 +773,165 @@ pub fn check_vendor_code(stores_repo: &StoresRepo, store_id: StoreId, vendor_cod
     }
 }
 
+/// Checks that pre-order stock status is set only for variants available for pre-order
+pub fn check_stock_status(stock_status: Option<StockStatus>, pre_order: bool) -> RepoResult<()> {
+    if stock_status == Some(StockStatus::PreOrder) && !pre_order {
+        Err(format_err!("Stock status {:?} requires pre-order to be enabled.", StockStatus::PreOrder)
+            .context(Error::Validate(
+                validation_errors!({"stock_status": ["stock_status" => "Pre-order stock status requires pre-order to be enabled"]}),
+            ))
+            .into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes product prices to price history if they differ from `original` prices
+pub fn record_price_history(
+    price_history_repo: &ProductPriceHistoryRepo,
+    original: Option<&RawProduct>,
+    product: &RawProduct,
+    user_id: Option<UserId>,
+) -> RepoResult<()> {
+    let changed = match original {
+        Some(original) => {
+            UpdateProductPrices::from(original) != UpdateProductPrices::from(product) || original.currency != product.currency
+        }
+        None => true,
+    };
+
+    if changed {
+        price_history_repo.create(NewProductPriceHistoryRecord::new(product, user_id))?;
+    }
+
+    Ok(())
+}
+
+/// Checks that scheduled price change changes something and does not overlap with other changes of the product
+pub fn check_scheduled_price_change(
+    payload: &NewScheduledPriceChange,
+    scheduled: &[ScheduledPriceChange],
+    now: SystemTime,
+) -> RepoResult<()> {
+    if payload.price.is_none() && payload.discount.is_none() && payload.cashback.is_none() {
+        return Err(format_err!("Scheduled price change of product {} changes nothing.", payload.product_id)
+            .context(Error::Validate(
+                validation_errors!({"price": ["price" => "At least one of price, discount or cashback must be set"]}),
+            ))
+            .into());
+    }
+
+    if payload.starts_at >= payload.ends_at {
+        return Err(format_err!("Scheduled price change of product {} ends before it starts.", payload.product_id)
+            .context(Error::Validate(
+                validation_errors!({"starts_at": ["starts_at" => "Price change must start before it ends"]}),
+            ))
+            .into());
+    }
+
+    if payload.ends_at <= now {
+        return Err(format_err!("Scheduled price change of product {} ends in the past.", payload.product_id)
+            .context(Error::Validate(
+                validation_errors!({"ends_at": ["ends_at" => "Price change must end in the future"]}),
+            ))
+            .into());
+    }
+
+    let overlaps = scheduled
+        .iter()
+        .filter(|value| value.status != ScheduledPriceChangeStatus::Finished)
+        .any(|value| value.overlaps(payload.starts_at, payload.ends_at));
+
+    if overlaps {
+        return Err(
+            format_err!("Scheduled price change of product {} overlaps with another one.", payload.product_id)
+                .context(Error::Validate(
+                    validation_errors!({"starts_at": ["starts_at" => "Price change overlaps with another scheduled price change"]}),
+                ))
+                .into(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Applies scheduled price change to its product, remembering previous product prices.
+/// Changes that ended before they were applied and changes of inactive products are finished without applying.
+pub fn apply_scheduled_price_change(
+    products_repo: &ProductsRepo,
+    price_history_repo: &ProductPriceHistoryRepo,
+    scheduled_price_changes_repo: &ScheduledPriceChangesRepo,
+    outbox_repo: &OutboxEventsRepo,
+    scheduled_price_change: ScheduledPriceChange,
+    now: SystemTime,
+) -> RepoResult<ScheduledPriceChange> {
+    let product = match products_repo.find(scheduled_price_change.product_id)? {
+        Some(product) if scheduled_price_change.ends_at > now => product,
+        _ => {
+            return scheduled_price_changes_repo.update(
+                scheduled_price_change.id,
+                UpdateScheduledPriceChange {
+                    status: Some(ScheduledPriceChangeStatus::Finished),
+                    ..Default::default()
+                },
+            );
+        }
+    };
+
+    let previous = UpdateProductPrices::from(&product);
+    let updated_product = products_repo.update_prices(product.id, scheduled_price_change.apply_to(&previous))?;
+    record_price_history(price_history_repo, Some(&product), &updated_product, None)?;
+    write_product_event(outbox_repo, OutboxEventType::Updated, &updated_product)?;
+
+    scheduled_price_changes_repo.update(
+        scheduled_price_change.id,
+        UpdateScheduledPriceChange {
+            status: Some(ScheduledPriceChangeStatus::Active),
+            previous_price: Some(previous.price),
+            previous_discount: previous.discount,
+            previous_cashback: previous.cashback,
+        },
+    )
+}
+
+/// Restores product prices remembered by scheduled price change.
+/// Prices that were changed after the change had been applied are kept as is.
+pub fn revert_scheduled_price_change(
+    products_repo: &ProductsRepo,
+    price_history_repo: &ProductPriceHistoryRepo,
+    scheduled_price_changes_repo: &ScheduledPriceChangesRepo,
+    outbox_repo: &OutboxEventsRepo,
+    scheduled_price_change: ScheduledPriceChange,
+    user_id: Option<UserId>,
+) -> RepoResult<ScheduledPriceChange> {
+    let product = products_repo.find(scheduled_price_change.product_id)?;
+
+    if let (Some(product), Some(previous)) = (product, scheduled_price_change.previous_prices()) {
+        if UpdateProductPrices::from(&product) == scheduled_price_change.apply_to(&previous) {
+            let updated_product = products_repo.update_prices(product.id, previous)?;
+            record_price_history(price_history_repo, Some(&product), &updated_product, user_id)?;
+            write_product_event(outbox_repo, OutboxEventType::Updated, &updated_product)?;
+        } else {
+            info!(
+                "Prices of product {} were changed after scheduled price change {} had been applied, skipping revert",
+                product.id, scheduled_price_change.id
+            );
+        }
+    }
+
+    scheduled_price_changes_repo.update(
+        scheduled_price_change.id,
+        UpdateScheduledPriceChange {
+            status: Some(ScheduledPriceChangeStatus::Finished),
+            ..Default::default()
+        },
+    )
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::sync::Arc;
-    use std::time::SystemTime;
+    use std::time::{Duration, SystemTime};
 
     use stq_static_resources::Currency;
     use stq_types::*;
@@ -676,6 +961,7 @@ pub mod tests {
             pre_order_days: 0,
             kafka_update_no: 0,
             uuid: Uuid::new_v4(),
+            stock_status: None,
         }
     }
 
@@ -703,6 +989,7 @@ pub mod tests {
             pre_order: Some(false),
             pre_order_days: Some(0),
             uuid: Uuid::new_v4(),
+            stock_status: None,
         }
     }
 
@@ -717,6 +1004,7 @@ pub mod tests {
             currency: None,
             pre_order: None,
             pre_order_days: None,
+            stock_status: None,
         }
     }
 
@@ -781,4 +1069,178 @@ pub mod tests {
         assert_eq!(result.product.is_active, false);
     }
 
+    pub fn create_new_scheduled_price_change_payload(starts_in: Duration, lasts: Duration) -> NewScheduledPriceChangePayload {
+        let starts_at = SystemTime::now() + starts_in;
+
+        NewScheduledPriceChangePayload {
+            price: Some(ProductPrice(5f64)),
+            discount: Some(0.1),
+            cashback: None,
+            starts_at,
+            ends_at: starts_at + lasts,
+        }
+    }
+
+    #[test]
+    fn test_get_product_price_history() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.get_product_price_history(MOCK_PRODUCT_ID);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].product_id, MOCK_PRODUCT_ID);
+    }
+
+    #[test]
+    fn test_create_scheduled_price_change() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = create_new_scheduled_price_change_payload(Duration::from_secs(3 * 3600), Duration::from_secs(3600));
+        let work = service.create_scheduled_price_change(MOCK_PRODUCT_ID, payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.product_id, MOCK_PRODUCT_ID);
+        assert_eq!(result.price, Some(ProductPrice(5f64)));
+        assert_eq!(result.status, ScheduledPriceChangeStatus::Pending);
+    }
+
+    #[test]
+    fn test_create_overlapping_scheduled_price_change() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = create_new_scheduled_price_change_payload(Duration::from_secs(1800), Duration::from_secs(3600));
+        let work = service.create_scheduled_price_change(MOCK_PRODUCT_ID, payload);
+        let result = core.run(work);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_check_scheduled_price_change_without_changes() {
+        let mut payload = NewScheduledPriceChange::from((
+            MOCK_PRODUCT_ID,
+            create_new_scheduled_price_change_payload(Duration::from_secs(3600), Duration::from_secs(3600)),
+        ));
+        payload.price = None;
+        payload.discount = None;
+        assert!(check_scheduled_price_change(&payload, &[], SystemTime::now()).is_err());
+    }
+
+    #[test]
+    fn test_check_scheduled_price_change_in_the_past() {
+        let now = SystemTime::now();
+        let payload = NewScheduledPriceChange::from((
+            MOCK_PRODUCT_ID,
+            create_new_scheduled_price_change_payload(Duration::from_secs(0), Duration::from_secs(3600)),
+        ));
+        assert!(check_scheduled_price_change(&payload, &[], now).is_ok());
+        assert!(check_scheduled_price_change(&payload, &[], now + Duration::from_secs(2 * 3600)).is_err());
+    }
+
+    #[test]
+    fn test_delete_scheduled_price_change() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.delete_scheduled_price_change(MOCK_PRODUCT_ID, MOCK_SCHEDULED_PRICE_CHANGE_ID);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.id, MOCK_SCHEDULED_PRICE_CHANGE_ID);
+    }
+
+    #[test]
+    fn test_delete_scheduled_price_change_of_other_product() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.delete_scheduled_price_change(ProductId(2), MOCK_SCHEDULED_PRICE_CHANGE_ID);
+        let result = core.run(work);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_apply_scheduled_price_change() {
+        let scheduled_price_change = create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, MOCK_PRODUCT_ID);
+        let now = scheduled_price_change.starts_at;
+        let result = apply_scheduled_price_change(
+            &ProductsRepoMock::default(),
+            &ProductPriceHistoryRepoMock::default(),
+            &ScheduledPriceChangesRepoMock::default(),
+            &OutboxEventsRepoMock::default(),
+            scheduled_price_change,
+            now,
+        )
+        .unwrap();
+        assert_eq!(result.status, ScheduledPriceChangeStatus::Active);
+        assert_eq!(result.previous_price, Some(ProductPrice(0f64)));
+    }
+
+    #[test]
+    fn test_apply_expired_scheduled_price_change() {
+        let scheduled_price_change = create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, MOCK_PRODUCT_ID);
+        let now = scheduled_price_change.ends_at;
+        let result = apply_scheduled_price_change(
+            &ProductsRepoMock::default(),
+            &ProductPriceHistoryRepoMock::default(),
+            &ScheduledPriceChangesRepoMock::default(),
+            &OutboxEventsRepoMock::default(),
+            scheduled_price_change,
+            now,
+        )
+        .unwrap();
+        assert_eq!(result.status, ScheduledPriceChangeStatus::Finished);
+        assert_eq!(result.previous_price, None);
+    }
+
+    #[test]
+    fn test_revert_scheduled_price_change() {
+        let mut scheduled_price_change = create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, MOCK_PRODUCT_ID);
+        scheduled_price_change.status = ScheduledPriceChangeStatus::Active;
+        scheduled_price_change.previous_price = Some(ProductPrice(10f64));
+        let result = revert_scheduled_price_change(
+            &ProductsRepoMock::default(),
+            &ProductPriceHistoryRepoMock::default(),
+            &ScheduledPriceChangesRepoMock::default(),
+            &OutboxEventsRepoMock::default(),
+            scheduled_price_change,
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.status, ScheduledPriceChangeStatus::Finished);
+    }
+
+    #[test]
+    fn test_check_stock_status() {
+        assert!(check_stock_status(None, false).is_ok());
+        assert!(check_stock_status(Some(StockStatus::OutOfStock), true).is_ok());
+        assert!(check_stock_status(Some(StockStatus::PreOrder), true).is_ok());
+        assert!(check_stock_status(Some(StockStatus::PreOrder), false).is_err());
+    }
+
+    #[test]
+    fn test_effective_stock_status() {
+        let mut product = create_product(MOCK_PRODUCT_ID, MOCK_BASE_PRODUCT_ID);
+        assert_eq!(product.effective_stock_status(), StockStatus::InStock);
+        product.pre_order = true;
+        assert_eq!(product.effective_stock_status(), StockStatus::PreOrder);
+        product.stock_status = Some(StockStatus::OutOfStock);
+        assert_eq!(product.effective_stock_status(), StockStatus::OutOfStock);
+    }
+
+    #[test]
+    fn test_scheduled_price_change_apply_to() {
+        let mut scheduled_price_change = create_scheduled_price_change(MOCK_SCHEDULED_PRICE_CHANGE_ID, MOCK_PRODUCT_ID);
+        scheduled_price_change.cashback = Some(0.2);
+        let prices = UpdateProductPrices {
+            price: ProductPrice(20f64),
+            discount: Some(0.5),
+            cashback: None,
+        };
+        let expected = UpdateProductPrices {
+            price: ProductPrice(10f64),
+            discount: Some(0.5),
+            cashback: Some(0.2),
+        };
+        assert_eq!(scheduled_price_change.apply_to(&prices), expected);
+    }
 }
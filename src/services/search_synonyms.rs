@@ -0,0 +1,144 @@
+//! SearchSynonyms Services, presents CRUD operations with dictionary of search synonyms
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use futures::future;
+use r2d2::ManageConnection;
+
+use super::types::ServiceFuture;
+use models::{expand_search_text, normalize_synonym_terms, search_text_phrases, NewSearchSynonym, SearchSynonym, UpdateSearchSynonym};
+use repos::ReposFactory;
+use services::Service;
+
+pub trait SearchSynonymsService {
+    /// Returns all search synonyms
+    fn list_search_synonyms(&self) -> ServiceFuture<Vec<SearchSynonym>>;
+    /// Creates new search synonym
+    fn create_search_synonym(&self, payload: NewSearchSynonym) -> ServiceFuture<SearchSynonym>;
+    /// Updates specific search synonym
+    fn update_search_synonym(&self, id: i32, payload: UpdateSearchSynonym) -> ServiceFuture<SearchSynonym>;
+    /// Deletes specific search synonym
+    fn delete_search_synonym(&self, id: i32) -> ServiceFuture<SearchSynonym>;
+    /// Returns variants of search text with synonyms applied
+    fn find_search_text_synonyms(&self, text: String) -> ServiceFuture<Vec<String>>;
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > SearchSynonymsService for Service<T, M, F>
+{
+    /// Returns all search synonyms
+    fn list_search_synonyms(&self) -> ServiceFuture<Vec<SearchSynonym>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let search_synonyms_repo = repo_factory.create_search_synonyms_repo(&*conn, user_id);
+            search_synonyms_repo
+                .list()
+                .map_err(|e| e.context("Service SearchSynonyms, list endpoint error occurred.").into())
+        })
+    }
+
+    /// Creates new search synonym
+    fn create_search_synonym(&self, payload: NewSearchSynonym) -> ServiceFuture<SearchSynonym> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let payload = NewSearchSynonym {
+            terms: normalize_synonym_terms(payload.terms),
+        };
+
+        self.spawn_on_pool(move |conn| {
+            let search_synonyms_repo = repo_factory.create_search_synonyms_repo(&*conn, user_id);
+            search_synonyms_repo
+                .create(payload)
+                .map_err(|e| e.context("Service SearchSynonyms, create endpoint error occurred.").into())
+        })
+    }
+
+    /// Updates specific search synonym
+    fn update_search_synonym(&self, id: i32, payload: UpdateSearchSynonym) -> ServiceFuture<SearchSynonym> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let payload = UpdateSearchSynonym {
+            terms: normalize_synonym_terms(payload.terms),
+        };
+
+        self.spawn_on_pool(move |conn| {
+            let search_synonyms_repo = repo_factory.create_search_synonyms_repo(&*conn, user_id);
+            search_synonyms_repo
+                .update(id, payload)
+                .map_err(|e| e.context("Service SearchSynonyms, update endpoint error occurred.").into())
+        })
+    }
+
+    /// Deletes specific search synonym
+    fn delete_search_synonym(&self, id: i32) -> ServiceFuture<SearchSynonym> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let search_synonyms_repo = repo_factory.create_search_synonyms_repo(&*conn, user_id);
+            search_synonyms_repo
+                .delete(id)
+                .map_err(|e| e.context("Service SearchSynonyms, delete endpoint error occurred.").into())
+        })
+    }
+
+    /// Returns variants of search text with synonyms applied
+    fn find_search_text_synonyms(&self, text: String) -> ServiceFuture<Vec<String>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        let phrases = search_text_phrases(&text);
+        if phrases.is_empty() {
+            return Box::new(future::ok(vec![]));
+        }
+
+        self.spawn_on_pool(move |conn| {
+            let search_synonyms_repo = repo_factory.create_search_synonyms_repo(&*conn, user_id);
+            search_synonyms_repo
+                .find_by_terms(phrases)
+                .map(|synonyms| expand_search_text(&text, &synonyms))
+                .map_err(|e| {
+                    e.context("Service SearchSynonyms, find_search_text_synonyms endpoint error occurred.")
+                        .into()
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use tokio_core::reactor::Core;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    #[test]
+    fn test_create_search_synonym() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = NewSearchSynonym {
+            terms: vec!["TV".to_string(), "Television ".to_string(), "tv".to_string()],
+        };
+        let work = service.create_search_synonym(payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.terms, vec!["tv".to_string(), "television".to_string()]);
+    }
+
+    #[test]
+    fn test_find_search_text_synonyms() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let work = service.find_search_text_synonyms("red TV".to_string());
+        let result = core.run(work).unwrap();
+        assert_eq!(result, vec!["red television".to_string()]);
+    }
+}
@@ -0,0 +1,131 @@
+//! StoreStaff Services, presents operations with staff members of stores
+use diesel::connection::AnsiTransactionManager;
+use diesel::pg::Pg;
+use diesel::Connection;
+use failure::Error as FailureError;
+use r2d2::ManageConnection;
+
+use stq_types::{StoreId, UserId};
+
+use super::types::ServiceFuture;
+use errors::Error;
+use models::{NewStoreStaffMember, NewStoreStaffMemberPayload, StoreStaffMember, Visibility};
+use repos::ReposFactory;
+use services::Service;
+
+pub trait StoreStaffService {
+    /// Returns staff members of the store
+    fn get_store_staff(&self, store_id: StoreId) -> ServiceFuture<Vec<StoreStaffMember>>;
+    /// Adds user to store staff, role of user already being staff member is changed
+    fn invite_store_staff_member(&self, store_id: StoreId, payload: NewStoreStaffMemberPayload) -> ServiceFuture<StoreStaffMember>;
+    /// Removes user from store staff
+    fn remove_store_staff_member(&self, store_id: StoreId, staff_user_id: UserId) -> ServiceFuture<StoreStaffMember>;
+}
+
+impl<
+        T: Connection<Backend = Pg, TransactionManager = AnsiTransactionManager> + 'static,
+        M: ManageConnection<Connection = T>,
+        F: ReposFactory<T>,
+    > StoreStaffService for Service<T, M, F>
+{
+    /// Returns staff members of the store
+    fn get_store_staff(&self, store_id: StoreId) -> ServiceFuture<Vec<StoreStaffMember>> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let store_staff_repo = repo_factory.create_store_staff_repo(&*conn, user_id);
+            store_staff_repo
+                .list_by_store(store_id)
+                .map_err(|e| e.context("Service StoreStaff, get_store_staff endpoint error occurred.").into())
+        })
+    }
+
+    /// Adds user to store staff, role of user already being staff member is changed
+    fn invite_store_staff_member(&self, store_id: StoreId, payload: NewStoreStaffMemberPayload) -> ServiceFuture<StoreStaffMember> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+        let payload = NewStoreStaffMember::from((store_id, payload));
+
+        self.spawn_on_pool(move |conn| {
+            let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let store_staff_repo = repo_factory.create_store_staff_repo(&*conn, user_id);
+
+            conn.transaction::<StoreStaffMember, FailureError, _>(move || {
+                let store = stores_repo
+                    .find(store_id, Visibility::Active)?
+                    .ok_or(format_err!("Store with id {} not found.", store_id).context(Error::NotFound))?;
+
+                if store.user_id == payload.user_id {
+                    return Err(format_err!("User {} already owns store {}.", payload.user_id, store_id)
+                        .context(Error::Validate(
+                            validation_errors!({"user_id": ["user_id" => "User already owns the store"]}),
+                        ))
+                        .into());
+                }
+
+                match store_staff_repo.get(store_id, payload.user_id)? {
+                    Some(_) => store_staff_repo.update_role(store_id, payload.user_id, payload.role),
+                    None => store_staff_repo.create(payload),
+                }
+            })
+            .map_err(|e| {
+                e.context("Service StoreStaff, invite_store_staff_member endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+
+    /// Removes user from store staff
+    fn remove_store_staff_member(&self, store_id: StoreId, staff_user_id: UserId) -> ServiceFuture<StoreStaffMember> {
+        let user_id = self.dynamic_context.user_id;
+        let repo_factory = self.static_context.repo_factory.clone();
+
+        self.spawn_on_pool(move |conn| {
+            let store_staff_repo = repo_factory.create_store_staff_repo(&*conn, user_id);
+            store_staff_repo.delete(store_id, staff_user_id).map_err(|e| {
+                e.context("Service StoreStaff, remove_store_staff_member endpoint error occurred.")
+                    .into()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::sync::Arc;
+    use tokio_core::reactor::Core;
+
+    use models::*;
+    use repos::repo_factory::tests::*;
+    use services::*;
+
+    #[test]
+    fn test_invite_store_staff_member() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = NewStoreStaffMemberPayload {
+            user_id: MOCK_STAFF_USER_ID,
+            role: StoreStaffRole::Manager,
+        };
+        let work = service.invite_store_staff_member(MOCK_STORE_ID, payload);
+        let result = core.run(work).unwrap();
+        assert_eq!(result.user_id, MOCK_STAFF_USER_ID);
+        assert_eq!(result.role, StoreStaffRole::Manager);
+    }
+
+    #[test]
+    fn test_invite_store_owner_to_staff() {
+        let mut core = Core::new().unwrap();
+        let handle = Arc::new(core.handle());
+        let service = create_service(Some(MOCK_USER_ID), handle);
+        let payload = NewStoreStaffMemberPayload {
+            user_id: MOCK_USER_ID,
+            role: StoreStaffRole::Viewer,
+        };
+        let work = service.invite_store_staff_member(MOCK_STORE_ID, payload);
+        let result = core.run(work);
+        assert!(result.is_err());
+    }
+}
@@ -11,14 +11,19 @@ use stq_static_resources::ModerationStatus;
 use stq_types::{SagaId, StoreId, StoreSlug, UserId};
 
 use super::types::ServiceFuture;
-use elastic::{StoresElastic, StoresElasticImpl};
+use elastic::StoresElastic;
 use errors::Error;
 use models::{
-    Category, Direction, ModeratorStoreSearchResults, ModeratorStoreSearchTerms, NewStore, Ordering, PaginationParams, SearchStore,
+    find_moderation_transition, is_editable_status, moderation_status_after_edit, AutoCompleteSuggestion, Category, CountryLocalities,
+    Direction, ModerationEntityType, ModerationFeedbackPayload, ModerationReason, ModerationSideEffect, ModerationStatusChange,
+    ModeratorStoreSearchResults, ModeratorStoreSearchTerms, NewStore, Ordering, OutboxEventType, PaginationParams, SearchStore,
     ServiceUpdateBaseProduct, Store, UpdateStore, Visibility,
 };
 use repos::remove_unused_categories;
-use repos::{BaseProductsRepo, BaseProductsSearchTerms, ReposFactory, StoresRepo};
+use repos::{BaseProductsRepo, BaseProductsSearchTerms, OutboxEventsRepo, ReposFactory, StoresRepo};
+use services::moderation::{check_moderation_transition, ModerationRecorder};
+use services::outbox::{write_base_product_events, write_product_events, write_store_deleted_event, write_store_event};
+use services::search_synonyms::SearchSynonymsService;
 use services::Service;
 
 pub trait StoresService {
@@ -30,10 +35,12 @@ pub trait StoresService {
     fn search_store_filters_count(&self, search_store: SearchStore) -> ServiceFuture<i32>;
     /// search filters country
     fn search_store_filters_country(&self, search_store: SearchStore) -> ServiceFuture<Vec<String>>;
+    /// search filters locality, grouped by country
+    fn search_store_filters_locality(&self, search_store: SearchStore) -> ServiceFuture<Vec<CountryLocalities>>;
     /// search filters category
     fn search_store_filters_category(self, search_store: SearchStore) -> ServiceFuture<Category>;
     /// Find stores auto complete limited by `count` parameters
-    fn store_auto_complete(&self, name: String, count: i32, offset: i32) -> ServiceFuture<Vec<String>>;
+    fn store_auto_complete(&self, name: String, count: i32, offset: i32) -> ServiceFuture<Vec<AutoCompleteSuggestion>>;
     /// Returns store by ID
     fn get_store(&self, store_id: StoreId, visibility: Option<Visibility>) -> ServiceFuture<Option<Store>>;
     /// Returns store by slug
@@ -65,7 +72,13 @@ pub trait StoresService {
         term: ModeratorStoreSearchTerms,
     ) -> ServiceFuture<ModeratorStoreSearchResults>;
     /// Set moderation status for specific store. For moderator
-    fn set_store_moderation_status(&self, store_id: StoreId, status: ModerationStatus) -> ServiceFuture<Store>;
+    fn set_store_moderation_status(
+        &self,
+        store_id: StoreId,
+        status: ModerationStatus,
+        comment: Option<String>,
+        feedback: Vec<ModerationFeedbackPayload>,
+    ) -> ServiceFuture<Store>;
 
     /// Send store to moderation from store manager
     fn send_store_to_moderation(&self, store_id: StoreId) -> ServiceFuture<Store>;
@@ -105,11 +118,9 @@ impl<
         })
     }
 
-    fn store_auto_complete(&self, name: String, count: i32, offset: i32) -> ServiceFuture<Vec<String>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
+    fn store_auto_complete(&self, name: String, count: i32, offset: i32) -> ServiceFuture<Vec<AutoCompleteSuggestion>> {
         let stores_names = {
-            let stores_el = StoresElasticImpl::new(client_handle, address);
+            let stores_el = self.stores_search();
             stores_el.auto_complete(name, count, offset)
         };
 
@@ -117,14 +128,16 @@ impl<
     }
 
     /// Find stores by name
-    fn find_store_by_name(self, search_store: SearchStore, count: i32, offset: i32) -> ServiceFuture<Vec<Store>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
+    fn find_store_by_name(self, mut search_store: SearchStore, count: i32, offset: i32) -> ServiceFuture<Vec<Store>> {
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
         let stores = {
-            let stores_el = StoresElasticImpl::new(client_handle, address);
-            stores_el.find_by_name(search_store, count, offset)
+            let stores_el = self.stores_search();
+            self.find_search_text_synonyms(search_store.name.clone())
+                .and_then(move |name_synonyms| {
+                    search_store.name_synonyms = name_synonyms;
+                    stores_el.find_by_name(search_store, count, offset)
+                })
         };
 
         Box::new(
@@ -150,40 +163,60 @@ impl<
     }
 
     /// search filters count
-    fn search_store_filters_count(&self, search_store: SearchStore) -> ServiceFuture<i32> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
+    fn search_store_filters_count(&self, mut search_store: SearchStore) -> ServiceFuture<i32> {
         let search_filters = {
-            let stores_el = StoresElasticImpl::new(client_handle, address);
-            stores_el.search_count(search_store)
+            let stores_el = self.stores_search();
+            self.find_search_text_synonyms(search_store.name.clone())
+                .and_then(move |name_synonyms| {
+                    search_store.name_synonyms = name_synonyms;
+                    stores_el.search_count(search_store)
+                })
         };
 
         Box::new(search_filters.map_err(|e| e.context("Service Stores, search_filters_count endpoint error occurred.").into()))
     }
 
     /// search filters country
-    fn search_store_filters_country(&self, search_store: SearchStore) -> ServiceFuture<Vec<String>> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
+    fn search_store_filters_country(&self, mut search_store: SearchStore) -> ServiceFuture<Vec<String>> {
         let search_filters = {
-            let stores_el = StoresElasticImpl::new(client_handle, address);
-            stores_el.aggregate_countries(search_store)
+            let stores_el = self.stores_search();
+            self.find_search_text_synonyms(search_store.name.clone())
+                .and_then(move |name_synonyms| {
+                    search_store.name_synonyms = name_synonyms;
+                    stores_el.aggregate_countries(search_store)
+                })
+                .map(|countries| countries.into_iter().map(|country| country.country).collect())
         };
 
         Box::new(search_filters.map_err(|e| e.context("Service Stores, search_filters_country endpoint error occurred.").into()))
     }
 
+    /// search filters locality, grouped by country
+    fn search_store_filters_locality(&self, mut search_store: SearchStore) -> ServiceFuture<Vec<CountryLocalities>> {
+        let search_filters = {
+            let stores_el = self.stores_search();
+            self.find_search_text_synonyms(search_store.name.clone())
+                .and_then(move |name_synonyms| {
+                    search_store.name_synonyms = name_synonyms;
+                    stores_el.aggregate_countries(search_store)
+                })
+        };
+
+        Box::new(search_filters.map_err(|e| e.context("Service Stores, search_filters_locality endpoint error occurred.").into()))
+    }
+
     /// search filters category
-    fn search_store_filters_category(self, search_store: SearchStore) -> ServiceFuture<Category> {
-        let client_handle = self.static_context.client_handle.clone();
-        let address = self.static_context.config.server.elastic.clone();
-        let stores_el = StoresElasticImpl::new(client_handle, address);
+    fn search_store_filters_category(self, mut search_store: SearchStore) -> ServiceFuture<Category> {
+        let stores_el = self.stores_search();
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
 
         Box::new(
-            stores_el
-                .aggregate_categories(search_store)
+            self.find_search_text_synonyms(search_store.name.clone())
+                .and_then(move |name_synonyms| {
+                    search_store.name_synonyms = name_synonyms;
+                    stores_el.aggregate_categories(search_store)
+                })
                 .and_then(move |categories_ids| {
                     self.spawn_on_pool(move |conn| {
                         let categories_repo = repo_factory.create_categories_repo(&*conn, user_id);
@@ -251,13 +284,21 @@ impl<
                 let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
                 let products_repo = repo_factory.create_product_repo(&*conn, user_id);
                 let wizard_stores_repo = repo_factory.create_wizard_stores_repo(&*conn, user_id);
+                let moderation_queue_repo = repo_factory.create_moderation_queue_repo_with_sys_acl(&*conn);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
                 conn.transaction::<Store, FailureError, _>(move || {
                     let deactive_store = stores_repo.deactivate(store_id)?;
+                    write_store_event(&*outbox_repo, OutboxEventType::Deactivated, &deactive_store)?;
+
+                    moderation_queue_repo.delete_by_entity(ModerationEntityType::Store, store_id.0)?;
 
                     let base_products = base_products_repo.deactivate_by_store(store_id)?;
+                    write_base_product_events(&*outbox_repo, OutboxEventType::Deactivated, &base_products)?;
 
                     for base_product in &base_products {
-                        products_repo.deactivate_by_base_product(base_product.id)?;
+                        moderation_queue_repo.delete_by_entity(ModerationEntityType::BaseProduct, base_product.id.0)?;
+                        let products = products_repo.deactivate_by_base_product(base_product.id)?;
+                        write_product_events(&*outbox_repo, OutboxEventType::Deactivated, &products)?;
                     }
 
                     let _wizard_store = wizard_stores_repo.delete(deactive_store.user_id);
@@ -279,13 +320,21 @@ impl<
                 let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
                 let products_repo = repo_factory.create_product_repo(&*conn, user_id);
                 let wizard_stores_repo = repo_factory.create_wizard_stores_repo(&*conn, user_id);
+                let moderation_queue_repo = repo_factory.create_moderation_queue_repo_with_sys_acl(&*conn);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
                 conn.transaction::<Store, FailureError, _>(move || {
                     let store = stores_repo.deactivate_by_saga_id(saga_id_arg)?;
+                    write_store_event(&*outbox_repo, OutboxEventType::Deactivated, &store)?;
+
+                    moderation_queue_repo.delete_by_entity(ModerationEntityType::Store, store.id.0)?;
 
                     let base_products = base_products_repo.deactivate_by_store(store.id)?;
+                    write_base_product_events(&*outbox_repo, OutboxEventType::Deactivated, &base_products)?;
 
                     for base_product in &base_products {
-                        products_repo.deactivate_by_base_product(base_product.id)?;
+                        moderation_queue_repo.delete_by_entity(ModerationEntityType::BaseProduct, base_product.id.0)?;
+                        let products = products_repo.deactivate_by_base_product(base_product.id)?;
+                        write_product_events(&*outbox_repo, OutboxEventType::Deactivated, &products)?;
                     }
 
                     let _wizard_store = wizard_stores_repo.delete(store.user_id);
@@ -304,9 +353,16 @@ impl<
 
         self.spawn_on_pool(move |conn| {
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
-            stores_repo
-                .delete_by_user(user_id_arg)
-                .map_err(|e| e.context("Service Stores, delete_by_user endpoint error occurred.").into())
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+            conn.transaction::<Option<Store>, FailureError, _>(move || {
+                let store = stores_repo.delete_by_user(user_id_arg)?;
+                if let Some(ref store) = store {
+                    write_store_event(&*outbox_repo, OutboxEventType::Deactivated, store)?;
+                }
+
+                Ok(store)
+            })
+            .map_err(|e| e.context("Service Stores, delete_by_user endpoint error occurred.").into())
         })
     }
 
@@ -343,6 +399,7 @@ impl<
         let repo_factory = self.static_context.repo_factory.clone();
         self.spawn_on_pool(move |conn| {
             let stores_repo = repo_factory.create_stores_repo(&*conn, user_id);
+            let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
             conn.transaction::<Store, FailureError, _>(move || {
                 let store = stores_repo.get_by_user(payload.user_id)?;
                 if store.is_some() {
@@ -360,7 +417,10 @@ impl<
                             ))
                             .into())
                     } else {
-                        stores_repo.create(payload)
+                        let store = stores_repo.create(payload)?;
+                        write_store_event(&*outbox_repo, OutboxEventType::Created, &store)?;
+
+                        Ok(store)
                     }
                 }
             })
@@ -391,12 +451,22 @@ impl<
                     }
                 }
 
+                let base_products_repo = repo_factory.create_base_product_repo(&*conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&*conn);
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
                 conn.transaction::<Store, FailureError, _>(move || {
                     let store = stores_repo.update(store_id, payload)?;
 
-                    match store.status {
-                        ModerationStatus::Decline => stores_repo.set_moderation_status(store_id, ModerationStatus::Draft),
-                        _ => Ok(store),
+                    match moderation_status_after_edit(store.status) {
+                        Some(status) => {
+                            let change = ModerationStatusChange::new(status, ModerationReason::EditedAfterDecline);
+                            change_store_status(&*stores_repo, &*base_products_repo, &*outbox_repo, &recorder, store_id, change)
+                        }
+                        None => {
+                            write_store_event(&*outbox_repo, OutboxEventType::Updated, &store)?;
+
+                            Ok(store)
+                        }
                     }
                 })
             }
@@ -449,7 +519,13 @@ impl<
     }
 
     /// Set moderation status for specific store
-    fn set_store_moderation_status(&self, store_id: StoreId, status: ModerationStatus) -> ServiceFuture<Store> {
+    fn set_store_moderation_status(
+        &self,
+        store_id: StoreId,
+        status: ModerationStatus,
+        comment: Option<String>,
+        feedback: Vec<ModerationFeedbackPayload>,
+    ) -> ServiceFuture<Store> {
         let user_id = self.dynamic_context.user_id;
         let repo_factory = self.static_context.repo_factory.clone();
         debug!("Set moderation status {} for store {}", status, store_id);
@@ -458,9 +534,15 @@ impl<
             {
                 let stores_repo = repo_factory.create_stores_repo(&conn, user_id);
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
 
                 conn.transaction::<Store, FailureError, _>(move || {
-                    change_store_status(&*stores_repo, &*base_products_repo, store_id, status)
+                    let change = ModerationStatusChange::new(status, ModerationReason::ModeratorDecision)
+                        .with_comment(comment)
+                        .with_feedback(feedback);
+                    change_store_status(&*stores_repo, &*base_products_repo, &*outbox_repo, &recorder, store_id, change)
                 })
             }
             .map_err(|e: FailureError| e.context("Service stores, set_moderation_status endpoint error occurred.").into())
@@ -477,9 +559,13 @@ impl<
             {
                 let stores_repo = repo_factory.create_stores_repo(&conn, user_id);
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
 
                 conn.transaction::<Store, FailureError, _>(move || {
-                    change_store_status(&*stores_repo, &*base_products_repo, store_id, ModerationStatus::Moderation)
+                    let change = ModerationStatusChange::new(ModerationStatus::Moderation, ModerationReason::SentToModeration);
+                    change_store_status(&*stores_repo, &*base_products_repo, &*outbox_repo, &recorder, store_id, change)
                 })
             }
             .map_err(|e: FailureError| {
@@ -499,9 +585,13 @@ impl<
             {
                 let stores_repo = repo_factory.create_stores_repo(&conn, user_id);
                 let base_products_repo = repo_factory.create_base_product_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
+
+                let recorder = ModerationRecorder::new(&repo_factory, &*conn, user_id);
 
                 conn.transaction::<Store, FailureError, _>(move || {
-                    change_store_status(&*stores_repo, &*base_products_repo, store_id, ModerationStatus::Draft)
+                    let change = ModerationStatusChange::new(ModerationStatus::Draft, ModerationReason::HiddenBySeller);
+                    change_store_status(&*stores_repo, &*base_products_repo, &*outbox_repo, &recorder, store_id, change)
                 })
             }
             .map_err(|e: FailureError| {
@@ -527,7 +617,7 @@ impl<
                     None => return Err(Error::NotFound.into()),
                 };
 
-                Ok(check_change_status(current_status, status))
+                Ok(find_moderation_transition(current_status, status).is_some())
             }
             .map_err(|e: FailureError| {
                 e.context("Service stores, validate_change_moderation_status_store endpoint error occurred.")
@@ -552,10 +642,12 @@ impl<
             {
                 let stores_repo = repo_factory.create_stores_repo(&conn, user_id);
                 let wizard_stores_repo = repo_factory.create_wizard_stores_repo(&conn, user_id);
+                let outbox_repo = repo_factory.create_outbox_events_repo_with_sys_acl(&conn);
                 conn.transaction::<(), FailureError, _>(move || {
                     let _ = wizard_stores_repo.delete_by_store(store_id)?;
 
-                    stores_repo.delete(store_id)
+                    stores_repo.delete(store_id)?;
+                    write_store_deleted_event(&*outbox_repo, store_id)
                 })
             }
             .map_err(|e: FailureError| e.context("Service stores, delete endpoint error occurred.").into())
@@ -577,17 +669,21 @@ impl<
                 None => return Err(Error::NotFound.into()),
             };
 
-            Ok(check_can_update_by_status(current_status))
+            Ok(is_editable_status(current_status))
         })
     }
 }
 
+/// Changes moderation status of the store according to moderation state machine
 pub fn change_store_status(
     stores_repo: &StoresRepo,
     base_products_repo: &BaseProductsRepo,
+    outbox_repo: &OutboxEventsRepo,
+    recorder: &ModerationRecorder,
     store_id: StoreId,
-    new_status: ModerationStatus,
+    change: ModerationStatusChange,
 ) -> Result<Store, FailureError> {
+    let new_status = change.status;
     let store = stores_repo.find(store_id, Visibility::Active)?;
 
     let status = match store {
@@ -595,56 +691,31 @@ pub fn change_store_status(
         None => return Err(Error::NotFound.into()),
     };
 
-    if !check_change_status(status, new_status) {
-        return Err(format_err!("Store with id: {} cannot be sent to {}", store_id, new_status)
-            .context(Error::Validate(
-                validation_errors!({"stores": ["stores" => "Store can not be sent to new status"]}),
-            ))
-            .into());
-    }
-
-    let _ = base_products_repo.update_service_fields(
-        BaseProductsSearchTerms {
-            store_id: Some(store_id),
-            ..Default::default()
-        },
-        ServiceUpdateBaseProduct {
-            store_status: Some(new_status),
-        },
-    )?;
-
-    stores_repo.set_moderation_status(store_id, new_status)
-}
-
-pub fn check_change_status(current_status: ModerationStatus, new_status: ModerationStatus) -> bool {
-    match (current_status, new_status) {
-        (ModerationStatus::Draft, ModerationStatus::Moderation)
-        | (ModerationStatus::Moderation, ModerationStatus::Published)
-        | (ModerationStatus::Published, ModerationStatus::Moderation)
-        | (ModerationStatus::Moderation, ModerationStatus::Blocked)
-        | (ModerationStatus::Blocked, ModerationStatus::Moderation)
-        | (ModerationStatus::Moderation, ModerationStatus::Decline)
-        | (ModerationStatus::Decline, ModerationStatus::Moderation)
-        | (ModerationStatus::Published, ModerationStatus::Draft)
-        | (ModerationStatus::Decline, ModerationStatus::Draft) => true,
-        (_, _) => {
-            debug!("change status from {} to {} unreachable.", current_status, new_status);
-
-            false
+    let side_effects = check_moderation_transition(ModerationEntityType::Store, store_id.0, status, &change)?;
+
+    let store = stores_repo.set_moderation_status(store_id, new_status)?;
+    for side_effect in side_effects {
+        match side_effect {
+            ModerationSideEffect::CascadeToBaseProducts => {
+                let base_products = base_products_repo.update_service_fields(
+                    BaseProductsSearchTerms {
+                        store_id: Some(store_id),
+                        ..Default::default()
+                    },
+                    ServiceUpdateBaseProduct {
+                        store_status: Some(new_status),
+                    },
+                )?;
+                write_base_product_events(outbox_repo, OutboxEventType::Updated, &base_products)?;
+            }
+            ModerationSideEffect::Reindex => write_store_event(outbox_repo, OutboxEventType::Updated, &store)?,
+            // Moderation queue is kept in sync by recorder
+            ModerationSideEffect::Enqueue | ModerationSideEffect::Dequeue => {}
         }
     }
-}
-
-/// Validate update object for store manager
-pub fn check_can_update_by_status(current_status: ModerationStatus) -> bool {
-    match current_status {
-        ModerationStatus::Draft | ModerationStatus::Decline | ModerationStatus::Published => true,
-        _ => {
-            debug!("update object in status {} unreachable.", current_status);
+    recorder.record(ModerationEntityType::Store, store_id.0, status, &change)?;
 
-            false
-        }
-    }
+    Ok(store)
 }
 
 #[cfg(test)]
@@ -689,6 +760,8 @@ pub mod tests {
             saga_id: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
             uuid: Uuid::new_v4(),
         }
     }
@@ -719,6 +792,8 @@ pub mod tests {
             route: None,
             street_number: None,
             place_id: None,
+            latitude: None,
+            longitude: None,
         }
     }
 
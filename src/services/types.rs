@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use diesel::connection::AnsiTransactionManager;
 use diesel::pg::Pg;
 use diesel::Connection;
@@ -6,9 +8,13 @@ use failure::Fail;
 use futures::Future;
 use r2d2::{ManageConnection, PooledConnection};
 
+use config::SearchBackend;
 use controller::context::{DynamicContext, StaticContext};
+use elastic::postgres::{ProductsPostgresImpl, StoresPostgresImpl};
+use elastic::{ProductsElastic, ProductsElasticImpl, ProductsSearchWithFallback, StoresElastic, StoresElasticImpl, StoresSearchWithFallback};
 use errors::Error;
 use repos::repo_factory::*;
+use services::pre_moderation::PreModerationEngine;
 
 /// Service layer Future
 pub type ServiceFuture<T> = Box<Future<Item = T, Error = FailureError>>;
@@ -47,6 +53,61 @@ impl<
         let cpu_pool = self.static_context.cpu_pool.clone();
         Box::new(cpu_pool.spawn_fn(move || db_pool.get().map_err(|e| e.context(Error::Connection).into()).and_then(f)))
     }
+
+    /// Products search of backend selected in config
+    pub fn products_search(&self) -> Box<ProductsElastic> {
+        let config = &self.static_context.config;
+        let postgres = ProductsPostgresImpl::new(
+            self.static_context.db_pool.clone(),
+            self.static_context.cpu_pool.clone(),
+            config.search.postgres_candidates_limit,
+            config.search_ranking.clone(),
+        );
+        if config.search.backend == SearchBackend::Postgres {
+            return Box::new(postgres);
+        }
+
+        let elastic = ProductsElasticImpl::new(
+            self.static_context.client_handle.clone(),
+            config.server.elastic.clone(),
+            config.search_ranking.clone(),
+        );
+        if config.search.fallback_to_postgres {
+            Box::new(ProductsSearchWithFallback::new(elastic, postgres))
+        } else {
+            Box::new(elastic)
+        }
+    }
+
+    /// Time by which moderator decision is expected since entity was sent to moderation
+    pub fn moderation_sla(&self) -> Duration {
+        Duration::from_secs(self.static_context.config.moderation.sla_hours * 60 * 60)
+    }
+
+    /// Pre-moderation checks with rules configured in config
+    pub fn pre_moderation_engine(&self) -> PreModerationEngine {
+        PreModerationEngine::from_config(&self.static_context.config.pre_moderation)
+    }
+
+    /// Stores search of backend selected in config
+    pub fn stores_search(&self) -> Box<StoresElastic> {
+        let config = &self.static_context.config;
+        let postgres = StoresPostgresImpl::new(
+            self.static_context.db_pool.clone(),
+            self.static_context.cpu_pool.clone(),
+            config.search.postgres_candidates_limit,
+        );
+        if config.search.backend == SearchBackend::Postgres {
+            return Box::new(postgres);
+        }
+
+        let elastic = StoresElasticImpl::new(self.static_context.client_handle.clone(), config.server.elastic.clone());
+        if config.search.fallback_to_postgres {
+            Box::new(StoresSearchWithFallback::new(elastic, postgres))
+        } else {
+            Box::new(elastic)
+        }
+    }
 }
 
 impl<
@@ -40,6 +40,8 @@ fn create_new_store(name: &str, short_description: &str) -> NewStore {
         saga_id: None,
         street_number: None,
         place_id: None,
+        latitude: None,
+        longitude: None,
         uuid: uuid::Uuid::new_v4(),
     }
 }
@@ -70,6 +72,8 @@ pub fn create_update_store(name: &str, short_description: &str) -> UpdateStore {
         route: None,
         street_number: None,
         place_id: None,
+        latitude: None,
+        longitude: None,
     }
 }
 